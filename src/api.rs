@@ -0,0 +1,485 @@
+use crate::config::Config;
+use crate::job::{self, JobSource, JobSpec, JobState};
+use crate::logs::{LogEvent, LogFilter, LogLevel, Stage};
+use crate::profile::ProfileManager;
+use crate::settings::Settings;
+use crate::utils::parse_vendor_codes;
+use anyhow::Result;
+use chrono::Local;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+const DEFAULT_LOG_LIMIT: usize = 500;
+
+#[derive(Deserialize)]
+struct StartRequest {
+    profile: Option<String>,
+    vendor_codes: Vec<String>,
+    source: JobSource,
+    #[serde(default)]
+    only_new: bool,
+    #[serde(default)]
+    force_reupload: bool,
+}
+
+// Локальный HTTP API управления: слушает только 127.0.0.1 и пускает только запросы с токеном,
+// чтобы его не могли вызвать открытые в браузере страницы
+pub fn spawn(port: u16, token: &str, state: JobState) -> Result<()> {
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        anyhow::bail!("Не задан токен API управления: укажите его в настройках");
+    }
+    let address = format!("127.0.0.1:{}", port);
+    let server = Server::http(&address)
+        .map_err(|e| anyhow::anyhow!("Не удалось запустить API на {}: {}", address, e))?;
+    state.log(LogEvent::info(
+        Stage::General,
+        format!("API управления доступен на http://{}", address),
+    ));
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            if let Err((status, message)) = check_request(&request, port, &token) {
+                let path = request
+                    .url()
+                    .split('?')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                log::warn!(
+                    "API: отклонён запрос {} {}: {}",
+                    request.method(),
+                    path,
+                    message
+                );
+                respond_json(request, error(status, message));
+                continue;
+            }
+            handle(request, &state);
+        }
+    });
+    Ok(())
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().trim())
+}
+
+// Host защищает от DNS rebinding, Origin — от запросов со сторонних страниц,
+// токен — от всех остальных локальных клиентов
+fn check_request(request: &Request, port: u16, token: &str) -> Result<(), (u16, String)> {
+    let allowed_hosts = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    match header(request, "Host") {
+        Some(host) if allowed_hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) => {}
+        host => {
+            return Err((
+                403,
+                format!("Недопустимый заголовок Host: {}", host.unwrap_or("нет")),
+            ));
+        }
+    }
+    if let Some(origin) = header(request, "Origin")
+        && !allowed_hosts
+            .iter()
+            .any(|h| origin.eq_ignore_ascii_case(&format!("http://{}", h)))
+    {
+        return Err((403, format!("Запросы с {} запрещены", origin)));
+    }
+    let url = request.url();
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    // Браузерный WebSocket не умеет задавать заголовки, поэтому для /ws/logs токен можно передать в запросе
+    let query_token = (path == "/ws/logs")
+        .then(|| parse_query(query).remove("token"))
+        .flatten();
+    let provided = header(request, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|value| value.trim().to_string())
+        .or_else(|| header(request, "X-Api-Token").map(str::to_string))
+        .or(query_token);
+    match provided {
+        Some(provided) if tokens_equal(&provided, token) => {}
+        Some(_) => return Err((401, "Неверный токен API".to_string())),
+        None => {
+            return Err((
+                401,
+                "Нужен токен API: заголовок Authorization: Bearer <токен>".to_string(),
+            ));
+        }
+    }
+    if *request.method() == Method::Post {
+        let json = header(request, "Content-Type").is_some_and(|value| {
+            value
+                .split(';')
+                .next()
+                .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"))
+        });
+        if !json {
+            return Err((415, "Тело запроса должно быть application/json".to_string()));
+        }
+    }
+    Ok(())
+}
+
+// Сравнение без раннего выхода, чтобы токен нельзя было подобрать по времени ответа
+fn tokens_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+fn respond_json(request: Request, (status, body): (u16, serde_json::Value)) {
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(
+            Header::from_bytes("Content-Type", "application/json; charset=utf-8").unwrap(),
+        );
+    if let Err(e) = request.respond(response) {
+        log::warn!("API: не удалось отправить ответ: {}", e);
+    }
+}
+
+fn handle(mut request: Request, state: &JobState) {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let query = parse_query(query);
+    log::debug!("API: {} {}", request.method(), path);
+    let result = match (request.method(), path) {
+        (Method::Get, "/status") => (200, status(state)),
+        (Method::Get, "/logs") => (200, logs(state, &query)),
+        (Method::Get, "/profiles") => profiles(),
+        (Method::Get, "/ws/logs") => return stream_logs(request, state, &query),
+        (Method::Get, "/metrics") => return respond_metrics(request, state),
+        (Method::Post, "/jobs") => {
+            let mut body = String::new();
+            match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => start_job(state, &body),
+                Err(e) => error(400, format!("Не удалось прочитать тело запроса: {}", e)),
+            }
+        }
+        _ => error(
+            404,
+            format!("Неизвестный метод: {} {}", request.method(), path),
+        ),
+    };
+    respond_json(request, result);
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| {
+            let v = v.replace('+', " ");
+            (
+                k.to_string(),
+                urlencoding::decode(&v).map(|v| v.into_owned()).unwrap_or(v),
+            )
+        })
+        .collect()
+}
+
+fn error(status: u16, message: String) -> (u16, serde_json::Value) {
+    (status, serde_json::json!({ "error": message }))
+}
+
+fn status(state: &JobState) -> serde_json::Value {
+    let is_processing = *state.is_processing.lock().unwrap();
+    let progress = state.progress.lock().unwrap();
+    let results = state.results.lock().unwrap();
+    let batch = state.batch.lock().unwrap();
+    let queue = state.queue.lock().unwrap();
+    serde_json::json!({
+        "is_processing": is_processing,
+        "total": progress.total,
+        "processed": progress.processed,
+        "scanned": progress.scanned,
+        "resolved": progress.resolved,
+        "uploaded": progress.uploaded,
+        "failed": progress.failed,
+        "eta_seconds": progress.estimate_remaining().map(|(expected, _, _)| expected),
+        "results": results
+            .iter()
+            .map(|r| serde_json::json!({
+                "vendor_code": r.vendor_code,
+                "nm_id": r.nm_id,
+                "status": r.status.code(),
+                "files_found": r.files_found(),
+                "files_uploaded": r.files_uploaded,
+                "error": r.error,
+                "error_class": r.failure.as_ref().map(|e| e.label()),
+            }))
+            .collect::<Vec<_>>(),
+        "batch": batch
+            .iter()
+            .map(|item| serde_json::json!({
+                "profile": item.profile,
+                "vendor_codes": item.vendor_codes,
+                "status": item.status.code(),
+            }))
+            .collect::<Vec<_>>(),
+        "queue": queue
+            .iter()
+            .map(|queued| serde_json::json!({
+                "id": queued.id,
+                "profile": queued.spec.profile,
+                "source": queued.spec.source.describe(),
+                "vendor_codes": queued.spec.vendor_codes.len(),
+                "status": queued.status.code(),
+                "urgent": queued.urgent,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn log_filter(query: &HashMap<String, String>) -> LogFilter {
+    LogFilter {
+        min_level: query.get("level").and_then(|level| {
+            LogLevel::ALL
+                .into_iter()
+                .find(|l| l.label().eq_ignore_ascii_case(level))
+        }),
+        vendor_code: query.get("vendor_code").cloned().unwrap_or_default(),
+    }
+}
+
+// Фильтры: level=INFO|WARN|..., vendor_code=..., limit=N (последние N записей)
+fn logs(state: &JobState, query: &HashMap<String, String>) -> serde_json::Value {
+    let filter = log_filter(query);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(DEFAULT_LOG_LIMIT);
+    let events = state.logs.lock().unwrap().filtered(&filter);
+    let skip = events.len().saturating_sub(limit);
+    serde_json::to_value(&events[skip..]).unwrap_or_default()
+}
+
+// API-ключи наружу не отдаются
+fn profiles() -> (u16, serde_json::Value) {
+    match ProfileManager::new() {
+        Ok(manager) => (
+            200,
+            serde_json::json!(
+                manager
+                    .profiles
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .collect::<Vec<_>>()
+            ),
+        ),
+        Err(e) => error(500, format!("Ошибка чтения профилей: {}", e)),
+    }
+}
+
+fn start_job(state: &JobState, body: &str) -> (u16, serde_json::Value) {
+    let request: StartRequest = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return error(400, format!("Неверный формат запроса: {}", e)),
+    };
+    if *state.is_processing.lock().unwrap() {
+        return error(409, "Обработка уже выполняется".to_string());
+    }
+    let manager = match ProfileManager::new() {
+        Ok(manager) => manager,
+        Err(e) => return error(500, format!("Ошибка чтения профилей: {}", e)),
+    };
+    let profile = match &request.profile {
+        Some(name) => match manager.profiles.iter().find(|p| &p.name == name) {
+            Some(profile) => profile.clone(),
+            None => return error(404, format!("Профиль не найден: {}", name)),
+        },
+        None => manager.current_profile().clone(),
+    };
+    if profile.api_key.is_empty() {
+        return error(
+            400,
+            format!("API ключ не указан для профиля {}", profile.name),
+        );
+    }
+    let config: &Config = &manager.config;
+    let settings = Settings::load(config);
+    let vendor_codes =
+        parse_vendor_codes(&request.vendor_codes.join("\n"), &settings.vendor_codes).codes;
+    if vendor_codes.is_empty() && !matches!(request.source, JobSource::SingleFile { .. }) {
+        return error(400, "Не указаны vendor codes".to_string());
+    }
+
+    state.log(LogEvent::info(
+        Stage::General,
+        format!(
+            "Запуск через API: профиль {}, vendor codes: {}",
+            profile.name,
+            vendor_codes.len()
+        ),
+    ));
+    state.results.lock().unwrap().clear();
+    job::start(
+        JobSpec {
+            profile: profile.name.clone(),
+            api_key: profile.api_key,
+            wb_base_url: profile.wb_base_url,
+            timeouts: profile.timeouts,
+            vendor_codes,
+            source: request.source,
+            settings,
+            report_dir: config.get_reports_dir(),
+            started_at: Local::now(),
+            only_new: request.only_new,
+            confirm_validation: false,
+            force_reupload: request.force_reupload,
+        },
+        state.clone(),
+    );
+    (
+        202,
+        serde_json::json!({ "status": "started", "profile": profile.name }),
+    )
+}
+
+// WebSocket с новыми событиями логов в JSON; поддерживает те же фильтры level и vendor_code
+fn stream_logs(request: Request, state: &JobState, query: &HashMap<String, String>) {
+    let key = header(&request, "Sec-WebSocket-Key").map(str::to_string);
+    let Some(key) = key else {
+        let response =
+            Response::from_string("Ожидается WebSocket-подключение").with_status_code(400);
+        if let Err(e) = request.respond(response) {
+            log::warn!("API: не удалось отправить ответ: {}", e);
+        }
+        return;
+    };
+    let response = Response::empty(StatusCode(101))
+        .with_header(Header::from_bytes("Upgrade", "websocket").unwrap())
+        .with_header(Header::from_bytes("Connection", "Upgrade").unwrap())
+        .with_header(
+            Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes())).unwrap(),
+        );
+    let stream = request.upgrade("websocket", response);
+    let filter = log_filter(query);
+    let events = state.logs.lock().unwrap().subscribe();
+    std::thread::spawn(move || {
+        let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+        log::info!("API: подключён WebSocket-клиент логов");
+        for event in events {
+            if !filter.matches(&event) {
+                continue;
+            }
+            let Ok(text) = serde_json::to_string(&event) else {
+                continue;
+            };
+            if socket.send(Message::text(text)).is_err() {
+                break;
+            }
+        }
+        log::info!("API: WebSocket-клиент логов отключён");
+    });
+}
+
+fn respond_metrics(request: Request, state: &JobState) {
+    let response = Response::from_string(state.metrics.render()).with_header(
+        Header::from_bytes("Content-Type", "text/plain; version=0.0.4; charset=utf-8").unwrap(),
+    );
+    if let Err(e) = request.respond(response) {
+        log::warn!("API: не удалось отправить ответ: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::TestRequest;
+
+    const PORT: u16 = 8765;
+    const TOKEN: &str = "secret";
+
+    type Headers<'a> = Vec<(&'a str, &'a str)>;
+
+    fn request(method: Method, path: &str, headers: &[(&str, &str)]) -> Request {
+        headers
+            .iter()
+            .fold(
+                TestRequest::new().with_method(method).with_path(path),
+                |request, (name, value)| {
+                    request.with_header(Header::from_bytes(*name, *value).unwrap())
+                },
+            )
+            .into()
+    }
+
+    fn status(request: &Request) -> u16 {
+        check_request(request, PORT, TOKEN)
+            .err()
+            .map_or(200, |(status, _)| status)
+    }
+
+    #[test]
+    fn checks_host_origin_token_and_content_type() {
+        let auth = ("Authorization", "Bearer secret");
+        let host = ("Host", "127.0.0.1:8765");
+        let json = ("Content-Type", "application/json; charset=utf-8");
+        let cases: &[(Method, &str, Headers, u16)] = &[
+            (Method::Get, "/status", vec![host, auth], 200),
+            (
+                Method::Get,
+                "/status",
+                vec![("Host", "localhost:8765"), ("X-Api-Token", TOKEN)],
+                200,
+            ),
+            (Method::Get, "/status", vec![host], 401),
+            (
+                Method::Get,
+                "/status",
+                vec![host, ("Authorization", "Bearer wrong")],
+                401,
+            ),
+            (
+                Method::Get,
+                "/status",
+                vec![("Host", "evil.example:8765"), auth],
+                403,
+            ),
+            (Method::Get, "/status", vec![auth], 403),
+            (
+                Method::Post,
+                "/jobs",
+                vec![host, auth, json, ("Origin", "https://evil.example")],
+                403,
+            ),
+            (
+                Method::Post,
+                "/jobs",
+                vec![host, auth, json, ("Origin", "http://127.0.0.1:8765")],
+                200,
+            ),
+            (Method::Post, "/jobs", vec![host, auth, json], 200),
+            (
+                Method::Post,
+                "/jobs",
+                vec![host, auth, ("Content-Type", "text/plain")],
+                415,
+            ),
+            (Method::Post, "/jobs", vec![host, auth], 415),
+            (Method::Get, "/ws/logs?token=secret", vec![host], 200),
+            (Method::Get, "/status?token=secret", vec![host], 401),
+        ];
+        for (method, path, headers, expected) in cases {
+            let request = request(method.clone(), path, headers);
+            assert_eq!(
+                status(&request),
+                *expected,
+                "{} {} {:?}",
+                method,
+                path,
+                headers
+            );
+        }
+    }
+}
@@ -1,66 +1,320 @@
-use crate::config::Config;
-use crate::downloader::{Downloader, FileInfo};
-use crate::profile::{Profile, ProfileManager};
-use crate::uploader::WbUploader;
 use arboard::Clipboard;
+use chrono::{Duration as ChronoDuration, Local, NaiveDate};
 use eframe::egui;
 use eframe::App;
+use egui_extras::{Column, TableBuilder};
 use rfd::FileDialog;
-use std::path::Path;
+use crate::toast::Toasts;
+use crate::tray::Tray;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use wb_upload_core::api;
+use wb_upload_core::audit::{self, AuditState};
+use wb_upload_core::batch::{self, BatchMode, BatchStatus};
+use wb_upload_core::cache::ScanCache;
+use wb_upload_core::card_create;
+use wb_upload_core::card_update;
+use wb_upload_core::cards::{self, CardSearchState};
+use wb_upload_core::checkpoint::{self, Checkpoint};
+use wb_upload_core::compare::{self, CompareState};
+use wb_upload_core::config::Config;
+use wb_upload_core::crash;
+use wb_upload_core::downloader::Matching;
+use wb_upload_core::fields;
+use wb_upload_core::history::{HistoryEntry, HistoryFilter, ProfileStats};
+use wb_upload_core::import;
+use wb_upload_core::job::{self, ItemResult, ItemStatus, JobSource, JobSpec, JobState};
+use wb_upload_core::links::{is_valid_public_link, mask_password, parse_link_list};
+use wb_upload_core::logs::{LogEvent, LogFilter, LogLevel, MIN_LOG_CAPACITY, Stage};
+use wb_upload_core::network;
+use wb_upload_core::preview::{self, PreviewItem, PreviewState};
+use wb_upload_core::profile::{MAX_CONNECT_SECS, MAX_REQUEST_SECS, Profile, ProfileManager};
+use wb_upload_core::queue::{self, QueuedJob};
+use wb_upload_core::reference::{self, ReferenceCache, ReferenceState};
+use wb_upload_core::report;
+use wb_upload_core::scheduler::{self, SavedJob};
+use wb_upload_core::settings::{DEFAULT_ACCENT_COLOR, Settings, Theme};
+use wb_upload_core::ui_state::UiState;
+use wb_upload_core::update::{self, UpdateState};
+use wb_upload_core::uploader::{CardQuery, Subject, WB_API_URL, WB_SANDBOX_API_URL};
+use wb_upload_core::utils::{format_bytes, format_duration, parse_vendor_codes};
+use wb_upload_core::validation::{self, ValidationDecision};
+use wb_upload_core::watcher::FolderWatcher;
 
 pub struct DownloaderApp {
     urls: String,
     file_names: String,
     profile_manager: ProfileManager,
     new_profile_name: String,
-    is_processing: Arc<Mutex<bool>>,
-    total_files: Arc<Mutex<Option<usize>>>,
-    processed_files: Arc<Mutex<usize>>,
     use_local_path: bool,
     local_source_path: String,
     single_file_path: String,
-    failed_vendor_codes: Arc<Mutex<Vec<String>>>,
-    logs: Arc<Mutex<Vec<String>>>,
+    // Несколько файлов, перетащенных в окно
+    dropped_files: Vec<String>,
+    preview: PreviewState,
+    compare: CompareState,
+    audit: AuditState,
+    audit_min_photos: usize,
+    card_query: CardQuery,
+    card_brand: String,
+    card_search: CardSearchState,
+    reference: ReferenceState,
+    reference_filter: String,
+    reference_requested: bool,
+    force_reupload: bool,
+    confirm_undo: bool,
+    // Запуск, ожидающий подтверждения замены фото
+    confirm_start: Option<PendingStart>,
+    confirm_delete_profile: bool,
+    // Флажок «Больше не спрашивать» в открытом диалоге подтверждения
+    dont_ask_again: bool,
+    job_state: JobState,
     show_logs: bool,
-    start_time: Arc<Mutex<Option<Instant>>>,
+    // «Копировать ошибочные» добавляет текст ошибки через табуляцию
+    copy_failed_with_errors: bool,
+    log_filter: LogFilter,
+    group_logs_by_vendor_code: bool,
+    results_sort: ResultColumn,
+    results_sort_ascending: bool,
+    settings: Settings,
+    tab: Tab,
+    history_filter: HistoryFilter,
+    history_from: String,
+    history_to: String,
+    history_profiles: Vec<String>,
+    history_entries: Vec<HistoryEntry>,
+    history_stats: Vec<ProfileStats>,
+    webhook_urls_text: String,
+    host_overrides_text: String,
+    exclude_patterns_text: String,
+    scan_folders_text: String,
+    watcher: Option<FolderWatcher>,
+    saved_jobs: Arc<Mutex<Vec<SavedJob>>>,
+    new_job_name: String,
+    new_job_schedule: String,
+    new_job_only_new: bool,
+    vendor_import: Option<VendorImport>,
+    update: UpdateState,
+    new_favorite_name: String,
+    new_favorite_link: String,
+    // Отчёт о сбое прошлого запуска, пока диалог о нём не закрыт
+    crash_report: Option<PathBuf>,
+    // Прерванный запуск прошлого сеанса, пока диалог о нём не закрыт
+    resume: Option<Checkpoint>,
+    // vendorCode, для которого открыта панель подробностей ошибки
+    item_details: Option<String>,
+    // Пакет профилей: отмеченные профили и их списки vendor codes по имени профиля
+    batch_selected: BTreeSet<String>,
+    batch_codes: BTreeMap<String, String>,
+    batch_mode: BatchMode,
+    // Следующее задание ставится в очередь срочным
+    enqueue_urgent: bool,
+    toasts: Toasts,
+    // Для уведомлений о завершении запуска и новых ошибках
+    was_processing: bool,
+    toasted_failures: HashSet<String>,
+    tray: Tray,
+    // Окно закрывают во время обработки: выбор между работой в трее и остановкой
+    confirm_close: bool,
+    // Выход после остановки: окно закроется, когда текущий vendorCode доработается
+    quit_after_stop: bool,
+    // Закрытие подтверждено, запрос на закрытие больше не перехватывается
+    quitting: bool,
+}
+
+// Запуск, который заменит фото карточек и ждёт подтверждения
+enum PendingStart {
+    // vendor codes запуска по текущему профилю
+    Run(Vec<String>),
+    // Подготовленные задания пакета профилей
+    Batch(Vec<JobSpec>),
+    // Ожидающие задания очереди; список читается из очереди в момент запуска
+    Queue,
+}
+
+// Файл, из которого импортируются vendor codes, до выбора столбца
+struct VendorImport {
+    path: String,
+    rows: Vec<Vec<String>>,
+    column: usize,
+    skip_header: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Upload,
+    History,
+    Statistics,
+    Schedule,
+    Cards,
+    Reference,
+}
+
+#[derive(Clone, Copy)]
+enum ReportFormat {
+    Csv,
+    Xlsx,
+    Html,
+}
+
+impl ReportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Csv => "csv",
+            ReportFormat::Xlsx => "xlsx",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ResultColumn {
+    VendorCode,
+    NmId,
+    FilesFound,
+    FilesUploaded,
+    Status,
+    Error,
 }
 
 impl Default for DownloaderApp {
     fn default() -> Self {
         log::info!("Создание default DownloaderApp");
+        let mut profile_manager = ProfileManager::new().unwrap_or_else(|e| {
+            log::error!("Ошибка создания ProfileManager: {}", e);
+            ProfileManager {
+                profiles: vec![Profile {
+                    name: "Добавить".to_string(),
+                    ..Default::default()
+                }],
+                selected_index: 0,
+                config: Config::new().unwrap(),
+            }
+        });
+        let settings = Settings::load(&profile_manager.config);
+        network::set_options(&settings.network);
+        let webhook_urls_text = settings.webhook_urls.join("\n");
+        let host_overrides_text = settings.network.host_overrides.join("\n");
+        let exclude_patterns_text = settings.exclude_patterns.join("\n");
+        let scan_folders_text = settings.scan_folders.join("\n");
+        let job_state = JobState::open(&profile_manager.config, &settings);
+        crash::install(&profile_manager.config, job_state.logs.clone());
+        let crash_report = crash::take_report(&profile_manager.config);
+        let resume = checkpoint::load(&profile_manager.config.get_checkpoint_file_path())
+            .filter(|checkpoint| !checkpoint.remaining().is_empty());
+        if settings.api_enabled
+            && let Err(e) = api::spawn(settings.api_port, &settings.api_token, job_state.clone())
+        {
+            job_state.log(LogEvent::error(Stage::General, e.to_string()));
+        }
+        let update = UpdateState::default();
+        if settings.check_updates && !wb_upload_core::demo::is_enabled() {
+            update::spawn(update.clone());
+        }
+        let saved_jobs = Arc::new(Mutex::new(scheduler::load_jobs(&profile_manager.config)));
+        scheduler::spawn(profile_manager.config.clone(), saved_jobs.clone(), job_state.clone());
+        // Поля прошлого сеанса; в демо-режиме они изначально заполнены, чтобы сразу пройти весь сценарий
+        let ui_state = UiState::load(&profile_manager.config).unwrap_or_else(|| {
+            if wb_upload_core::demo::is_enabled() {
+                UiState {
+                    urls: wb_upload_core::demo::PUBLIC_LINK.to_string(),
+                    file_names: wb_upload_core::demo::VENDOR_CODES.join("\n"),
+                    ..Default::default()
+                }
+            } else {
+                UiState::default()
+            }
+        });
+        if let Some(index) = profile_manager
+            .profiles
+            .iter()
+            .position(|p| p.name == ui_state.profile)
+        {
+            profile_manager.selected_index = index;
+        }
         Self {
-            urls: String::new(),
-            file_names: String::new(),
-            profile_manager: ProfileManager::new().unwrap_or_else(|e| {
-                log::error!("Ошибка создания ProfileManager: {}", e);
-                ProfileManager {
-                    profiles: vec![Profile {
-                        name: "Добавить".to_string(),
-                        api_key: String::new(),
-                    }],
-                    selected_index: 0,
-                    config: Config::new().unwrap(),
-                }
-            }),
+            urls: ui_state.urls,
+            file_names: ui_state.file_names,
+            profile_manager,
             new_profile_name: String::new(),
-            is_processing: Arc::new(Mutex::new(false)),
-            total_files: Arc::new(Mutex::new(None)),
-            processed_files: Arc::new(Mutex::new(0)),
-            use_local_path: false,
-            local_source_path: String::new(),
-            single_file_path: String::new(),
-            failed_vendor_codes: Arc::new(Mutex::new(Vec::new())),
-            logs: Arc::new(Mutex::new(Vec::new())),
+            use_local_path: ui_state.use_local_path,
+            local_source_path: ui_state.local_source_path,
+            single_file_path: ui_state.single_file_path,
+            dropped_files: Vec::new(),
+            preview: PreviewState::default(),
+            compare: CompareState::default(),
+            audit: AuditState::default(),
+            audit_min_photos: 1,
+            card_query: CardQuery::default(),
+            card_brand: String::new(),
+            card_search: CardSearchState::default(),
+            reference: ReferenceState::default(),
+            reference_filter: String::new(),
+            reference_requested: false,
+            force_reupload: ui_state.force_reupload,
+            confirm_undo: false,
+            confirm_start: None,
+            confirm_delete_profile: false,
+            dont_ask_again: false,
+            job_state,
             show_logs: false,
-            start_time: Arc::new(Mutex::new(None)),
+            copy_failed_with_errors: false,
+            log_filter: LogFilter::default(),
+            group_logs_by_vendor_code: false,
+            results_sort: ResultColumn::VendorCode,
+            results_sort_ascending: true,
+            settings,
+            tab: Tab::Upload,
+            history_filter: HistoryFilter::default(),
+            history_from: String::new(),
+            history_to: String::new(),
+            history_profiles: Vec::new(),
+            history_entries: Vec::new(),
+            history_stats: Vec::new(),
+            webhook_urls_text,
+            host_overrides_text,
+            exclude_patterns_text,
+            scan_folders_text,
+            watcher: None,
+            saved_jobs,
+            new_job_name: String::new(),
+            new_job_schedule: "0 3 * * *".to_string(),
+            new_job_only_new: true,
+            vendor_import: None,
+            update,
+            new_favorite_name: String::new(),
+            new_favorite_link: String::new(),
+            crash_report,
+            resume,
+            item_details: None,
+            batch_selected: BTreeSet::new(),
+            batch_codes: BTreeMap::new(),
+            batch_mode: BatchMode::default(),
+            enqueue_urgent: false,
+            toasts: Toasts::default(),
+            was_processing: false,
+            toasted_failures: HashSet::new(),
+            tray: Tray::default(),
+            confirm_close: false,
+            quit_after_stop: false,
+            quitting: false,
         }
     }
 }
 
 impl App for DownloaderApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let theme = match self.settings.theme {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Light => egui::ThemePreference::Light,
+            Theme::Dark => egui::ThemePreference::Dark,
+        };
+        if ctx.options(|o| o.theme_preference) != theme {
+            ctx.set_theme(theme);
+        }
+        let [r, g, b] = self.settings.accent_color;
+        let accent = egui::Color32::from_rgb(r, g, b);
         let visuals = if ctx.style().visuals.dark_mode {
             let mut visuals = egui::Visuals::dark();
             visuals.widgets.noninteractive.bg_fill = egui::Color32::from_rgb(30, 30, 30);
@@ -79,19 +333,22 @@ impl App for DownloaderApp {
             visuals.widgets.inactive.fg_stroke = egui::Stroke::new(1.0, egui::Color32::BLACK);
             visuals.widgets.active.bg_fill = egui::Color32::from_rgb(200, 200, 200);
             visuals.widgets.active.fg_stroke = egui::Stroke::new(1.0, egui::Color32::BLACK);
-            visuals.selection.bg_fill = egui::Color32::from_rgb(180, 200, 255);
+            visuals.selection.bg_fill = lighten(accent);
             visuals.selection.stroke = egui::Stroke::new(1.0, egui::Color32::BLACK);
             visuals
         };
         ctx.set_visuals(visuals);
+        self.handle_dropped_files(ctx);
+        self.handle_shortcuts(ctx);
+        self.handle_close_request(ctx);
 
         egui::CentralPanel::default()
             .frame(egui::Frame {
                 inner_margin: egui::Margin::same(20.0),
                 fill: if ctx.style().visuals.dark_mode {
-                    egui::Color32::from_rgb(60, 80, 180)
+                    accent
                 } else {
-                    egui::Color32::from_rgb(180, 200, 255)
+                    lighten(accent)
                 },
                 rounding: egui::Rounding::same(8.0),
                 ..Default::default()
@@ -99,848 +356,3755 @@ impl App for DownloaderApp {
             .show(ctx, |ui| {
                 ui.add_space(20.0);
                 ui.heading(egui::RichText::new("🔥 Менеджер контента Wildberries").strong().size(32.0));
+                self.show_update_banner(ctx, ui);
                 ui.add_space(30.0);
 
-                // Profile Management
-                ui.group(|ui| {
-                    ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
-                    ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
-                        egui::Color32::from_rgb(70, 70, 70)
-                    } else {
-                        egui::Color32::from_rgb(220, 220, 220)
-                    };
-                    ui.label(egui::RichText::new("👤 Управление профилями").strong().size(22.0));
-                    ui.add_space(10.0);
-                    ui.horizontal(|ui| {
-                        egui::ComboBox::from_label("Профиль")
-                            .selected_text(&self.profile_manager.current_profile().name)
-                            .width(200.0)
-                            .show_ui(ui, |ui| {
-                                for (i, profile) in self.profile_manager.profiles.iter().enumerate() {
-                                    if ui
-                                        .selectable_label(self.profile_manager.selected_index == i, &profile.name)
-                                        .clicked()
-                                    {
-                                        self.profile_manager.selected_index = i;
-                                    }
-                                }
-                            });
-                        ui.add(
-                            egui::TextEdit::singleline(&mut self.new_profile_name)
-                                .hint_text("Новый профиль")
-                                .desired_width(150.0),
-                        );
-                        if ui.button("➕ Добавить").clicked() && !self.new_profile_name.is_empty() {
-                            self.profile_manager.add_profile(self.new_profile_name.clone());
-                            self.new_profile_name.clear();
-                            if let Err(e) = self.profile_manager.save() {
-                                log::error!("Ошибка сохранения профилей: {}", e);
-                            }
-                        }
-                        if ui.button("🗑 Удалить").clicked() && self.profile_manager.profiles.len() > 1 {
-                            self.profile_manager.delete_profile(self.profile_manager.selected_index);
-                            if let Err(e) = self.profile_manager.save() {
-                                log::error!("Ошибка сохранения профилей после удаления: {}", e);
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tab, Tab::Upload, egui::RichText::new("📤 Загрузка").size(18.0));
+                    if ui
+                        .selectable_value(&mut self.tab, Tab::History, egui::RichText::new("📜 История").size(18.0))
+                        .clicked()
+                    {
+                        self.refresh_history();
+                    }
+                    if ui
+                        .selectable_value(&mut self.tab, Tab::Statistics, egui::RichText::new("📊 Статистика").size(18.0))
+                        .clicked()
+                    {
+                        self.refresh_stats();
+                    }
+                    ui.selectable_value(&mut self.tab, Tab::Schedule, egui::RichText::new("🗓 Расписание").size(18.0));
+                    ui.selectable_value(&mut self.tab, Tab::Cards, egui::RichText::new("🗂 Карточки WB").size(18.0));
+                    ui.selectable_value(&mut self.tab, Tab::Reference, egui::RichText::new("📚 Справочник WB").size(18.0));
+                });
+                ui.add_space(10.0);
+
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| match self.tab {
+                    Tab::Upload => self.show_upload_tab(ctx, ui),
+                    Tab::History => self.show_history_tab(ui),
+                    Tab::Statistics => self.show_statistics_tab(ui),
+                    Tab::Schedule => self.show_schedule_tab(ui),
+                    Tab::Cards => self.show_cards_tab(ui),
+                    Tab::Reference => self.show_reference_tab(ui),
+                });
+
+                ctx.request_repaint();
+            });
+        self.show_validation_dialog(ctx);
+        self.show_undo_dialog(ctx);
+        self.show_start_dialog(ctx);
+        self.show_delete_profile_dialog(ctx);
+        self.show_crash_dialog(ctx);
+        self.show_resume_dialog(ctx);
+        self.show_item_details(ctx);
+        self.show_close_dialog(ctx);
+        self.toast_job_events();
+        self.toasts.show(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        let ui_state = UiState {
+            urls: self.urls.clone(),
+            file_names: self.file_names.clone(),
+            local_source_path: self.local_source_path.clone(),
+            single_file_path: self.single_file_path.clone(),
+            use_local_path: self.use_local_path,
+            force_reupload: self.force_reupload,
+            profile: self.profile_manager.current_profile().name.clone(),
+        };
+        if let Err(e) = ui_state.save(&self.profile_manager.config) {
+            log::error!("{}", e);
+        }
+    }
+}
+
+impl DownloaderApp {
+    fn show_update_banner(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        if *self.update.dismissed.lock().unwrap() {
+            return;
+        }
+        let Some(release) = self.update.available.lock().unwrap().clone() else {
+            return;
+        };
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new(format!(
+                    "⬆ Доступна новая версия {} (установлена {})",
+                    release.version,
+                    update::CURRENT_VERSION
+                ))
+                .strong()
+                .color(egui::Color32::from_rgb(255, 200, 0)),
+            );
+            if ui.button("⬇ Скачать").clicked() {
+                ctx.open_url(egui::OpenUrl::new_tab(&release.download_url));
+            }
+            if ui.button("✖").on_hover_text("Скрыть до следующего запуска").clicked() {
+                *self.update.dismissed.lock().unwrap() = true;
+            }
+        });
+    }
+
+    fn show_upload_tab(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        // Profile Management
+        ui.group(|ui| {
+            ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
+            ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
+                egui::Color32::from_rgb(70, 70, 70)
+            } else {
+                egui::Color32::from_rgb(220, 220, 220)
+            };
+            ui.label(egui::RichText::new("👤 Управление профилями").strong().size(22.0));
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label("Профиль (Ctrl+1…9)")
+                    .selected_text(&self.profile_manager.current_profile().name)
+                    .width(200.0)
+                    .show_ui(ui, |ui| {
+                        for (i, profile) in self.profile_manager.profiles.iter().enumerate() {
+                            if ui
+                                .selectable_label(self.profile_manager.selected_index == i, &profile.name)
+                                .clicked()
+                            {
+                                self.profile_manager.selected_index = i;
                             }
                         }
                     });
-                    ui.add_space(10.0);
-                    ui.vertical(|ui| {
-                        ui.label(egui::RichText::new("🔑 WB API ключ:").strong());
-                        ui.add_space(5.0);
-                        ui.add(
-                            egui::TextEdit::multiline(&mut self.profile_manager.current_profile_mut().api_key)
-                                .desired_width(400.0)
-                                .desired_rows(3),
-                        );
-                        if ui.button("💾 Сохранить").clicked() {
-                            let api_key = self.profile_manager.current_profile().api_key.trim();
-                            if api_key.is_empty() {
-                                log::error!("API ключ не может быть пустым");
-                                let mut logs = self.logs.lock().unwrap();
-                                logs.push("Ошибка: API ключ не может быть пустым".to_string());
-                            } else {
-                                match self.profile_manager.save() {
-                                    Ok(()) => {
-                                        log::info!("API ключ успешно сохранен");
-                                        let mut logs = self.logs.lock().unwrap();
-                                        logs.push("API ключ успешно сохранен".to_string());
-                                    }
-                                    Err(e) => {
-                                        log::error!("Ошибка сохранения API ключа: {}", e);
-                                        let mut logs = self.logs.lock().unwrap();
-                                        logs.push(format!("Ошибка сохранения API ключа: {}", e));
-                                    }
-                                }
-                            }
-                            ctx.request_repaint();
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_profile_name)
+                        .hint_text("Новый профиль")
+                        .desired_width(150.0),
+                );
+                if ui.button("➕ Добавить").clicked() && !self.new_profile_name.is_empty() {
+                    self.profile_manager.add_profile(self.new_profile_name.clone());
+                    match self.profile_manager.save() {
+                        Ok(()) => self.toasts.success(format!("Профиль «{}» добавлен", self.new_profile_name)),
+                        Err(e) => {
+                            self.toasts.error("Не удалось сохранить профили");
+                            self.job_state.log(LogEvent::error(
+                                Stage::General,
+                                format!("Ошибка сохранения профилей: {}", e),
+                            ));
                         }
-                    });
+                    }
+                    self.new_profile_name.clear();
+                }
+                if ui.button("🗑 Удалить").clicked() && self.profile_manager.profiles.len() > 1 {
+                    if self.settings.confirm_profile_delete {
+                        self.dont_ask_again = false;
+                        self.confirm_delete_profile = true;
+                    } else {
+                        self.delete_profile();
+                    }
+                }
+            });
+            ui.add_space(10.0);
+            ui.vertical(|ui| {
+                ui.label(egui::RichText::new("🔑 WB API ключ:").strong());
+                ui.add_space(5.0);
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut self.profile_manager.current_profile_mut().api_key)
+                        .desired_width(400.0)
+                        .desired_rows(3),
+                );
+                let error = fields::check_api_key(&self.profile_manager.current_profile().api_key);
+                mark_invalid(ui, response.rect, response, error);
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new("🌐 Адрес API WB:").strong());
+                ui.horizontal(|ui| {
+                    let wb_base_url = &mut self.profile_manager.current_profile_mut().wb_base_url;
+                    ui.add(
+                        egui::TextEdit::singleline(wb_base_url)
+                            .hint_text(WB_API_URL)
+                            .desired_width(300.0),
+                    )
+                    .on_hover_text("Песочница WB или адрес корпоративного прокси; пусто — основной API");
+                    if ui.button("Основной").clicked() {
+                        wb_base_url.clear();
+                    }
+                    if ui.button("Песочница").clicked() {
+                        *wb_base_url = WB_SANDBOX_API_URL.to_string();
+                    }
                 });
-
-                ui.add_space(30.0);
-                ui.group(|ui| {
-                    ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
-                    ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
-                        egui::Color32::from_rgb(70, 70, 70)
+                ui.horizontal(|ui| {
+                    let timeouts = &mut self.profile_manager.current_profile_mut().timeouts;
+                    ui.label("⏱ Таймауты, с: WB — соединение");
+                    ui.add(egui::DragValue::new(&mut timeouts.wb_connect_secs).range(1..=MAX_CONNECT_SECS));
+                    ui.label("запрос");
+                    ui.add(egui::DragValue::new(&mut timeouts.wb_request_secs).range(1..=MAX_REQUEST_SECS));
+                    ui.label("Яндекс.Диск — соединение");
+                    ui.add(egui::DragValue::new(&mut timeouts.yandex_connect_secs).range(1..=MAX_CONNECT_SECS));
+                    ui.label("запрос");
+                    ui.add(egui::DragValue::new(&mut timeouts.yandex_request_secs).range(1..=MAX_REQUEST_SECS));
+                })
+                .response
+                .on_hover_text("Таймаут запроса включает передачу файла целиком: на медленном канале для видео на 100 МБ нужны минуты. Действует со следующего запуска");
+                if ui.button("💾 Сохранить").clicked() {
+                    let api_key = self.profile_manager.current_profile().api_key.trim();
+                    if api_key.is_empty() {
+                        self.toasts.error("API ключ не может быть пустым");
+                        self.job_state.log(LogEvent::error(
+                            Stage::General,
+                            "Ошибка: API ключ не может быть пустым",
+                        ));
+                    } else if let Err(e) = self.profile_manager.current_profile().timeouts.validate() {
+                        self.toasts.error("Не удалось сохранить профиль");
+                        self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
                     } else {
-                        egui::Color32::from_rgb(220, 220, 220)
-                    };
-                    ui.label(egui::RichText::new("📥 Источник файлов").strong().size(22.0));
-                    ui.add_space(10.0);
-                    ui.horizontal(|ui| {
-                        ui.checkbox(&mut self.use_local_path, "Использовать локальный путь");
-                    });
-                    ui.add_space(10.0);
-                    if !self.use_local_path {
-                        ui.horizontal(|ui| {
-                            ui.label(egui::RichText::new("🔗 Ссылки на Яндекс.Диск (через запятую):").strong());
+                        match self.profile_manager.save() {
+                            Ok(()) => {
+                                self.toasts.success(format!(
+                                    "Профиль «{}» сохранён",
+                                    self.profile_manager.current_profile().name
+                                ));
+                                self.job_state
+                                    .log(LogEvent::info(Stage::General, "API ключ успешно сохранен"));
+                            }
+                            Err(e) => {
+                                self.toasts.error("Не удалось сохранить профиль");
+                                self.job_state.log(LogEvent::error(
+                                    Stage::General,
+                                    format!("Ошибка сохранения API ключа: {}", e),
+                                ));
+                            }
+                        }
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        });
+
+        ui.add_space(30.0);
+        ui.group(|ui| {
+            ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
+            ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
+                egui::Color32::from_rgb(70, 70, 70)
+            } else {
+                egui::Color32::from_rgb(220, 220, 220)
+            };
+            ui.label(egui::RichText::new("📥 Источник файлов").strong().size(22.0));
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.use_local_path, "Использовать локальный путь");
+            });
+            ui.add_space(10.0);
+            if !self.use_local_path {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("🔗 Ссылки на Яндекс.Диск (по одной на строке):").strong());
+                    ui.vertical(|ui| {
+                        let output = egui::ScrollArea::vertical().id_salt("urls").max_height(100.0).show(ui, |ui| {
                             text_edit_with_context_menu(
                                 ui,
                                 &mut self.urls,
                                 400.0,
-                                "https://disk.yandex.ru/d/link1,https://disk.yandex.ru/d/link2,etc",
-                            );
+                                "https://disk.yandex.ru/d/link1|/Май/Новинки\nhttps://disk.yandex.ru/d/link2\nEtc",
+                            )
                         });
-                    } else {
-                        ui.horizontal(|ui| {
-                            ui.label(egui::RichText::new("📂 Локальная папка:").strong());
-                            ui.add(egui::TextEdit::singleline(&mut self.local_source_path).desired_width(300.0));
-                            if ui.button("📁 Выбрать").clicked() {
-                                if let Some(path) = FileDialog::new().pick_folder() {
-                                    self.local_source_path = path.to_string_lossy().to_string();
+                        mark_invalid(ui, output.inner_rect, output.inner, fields::check_links(&self.urls));
+                        let parsed = parse_link_list(&self.urls);
+                        ui.label(format!("Распознано ссылок: {}", parsed.links.len()));
+                        if parsed.duplicates > 0 {
+                            ui.label(format!("Дубликатов пропущено: {}", parsed.duplicates));
+                        }
+                        for link in &parsed.invalid {
+                            ui.colored_label(egui::Color32::RED, format!("Не ссылка на Яндекс.Диск: {}", link));
+                        }
+                    });
+                    let recent = self.profile_manager.current_profile().recent_links.clone();
+                    ui.add_enabled_ui(!recent.is_empty(), |ui| {
+                        ui.menu_button("🕘 Недавние", |ui| {
+                            for link in recent {
+                                if ui.button(mask_password(&link)).clicked() {
+                                    add_link(&mut self.urls, &link);
+                                    ui.close_menu();
                                 }
                             }
-                        });
-                        ui.add_space(10.0);
-                        ui.horizontal(|ui| {
-                            ui.label(egui::RichText::new("📄 Путь к файлу:").strong());
-                            ui.add(egui::TextEdit::singleline(&mut self.single_file_path).desired_width(300.0));
-                            if ui.button("📄 Выбрать").clicked() {
-                                if let Some(path) = FileDialog::new()
-                                    .add_filter("Media", &["png", "jpg", "jpeg", "gif", "bmp", "webp", "mov", "mp4"])
-                                    .pick_file()
-                                {
-                                    self.single_file_path = path.to_string_lossy().to_string();
+                        })
+                        .response
+                        .on_hover_text("Ссылки последних запусков профиля; выбранная добавляется в список");
+                    });
+                    ui.menu_button("⭐ Избранное", |ui| self.show_favorites_menu(ui));
+                });
+            } else {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("📂 Локальная папка:").strong());
+                    let response = ui.add(egui::TextEdit::singleline(&mut self.local_source_path).desired_width(300.0));
+                    mark_invalid(ui, response.rect, response, fields::check_folder(&self.local_source_path));
+                    if ui.button("📁 Выбрать").clicked()
+                        && let Some(path) = FileDialog::new().pick_folder()
+                    {
+                        self.local_source_path = path.to_string_lossy().to_string();
+                    }
+                    let recent = self.profile_manager.current_profile().recent_folders.clone();
+                    ui.add_enabled_ui(!recent.is_empty(), |ui| {
+                        ui.menu_button("🕘 Недавние", |ui| {
+                            for folder in recent {
+                                if ui.button(&folder).clicked() {
+                                    self.local_source_path = folder;
+                                    ui.close_menu();
                                 }
                             }
-                        });
+                        })
+                        .response
+                        .on_hover_text("Папки последних запусков профиля");
+                    });
+                });
+                ui.horizontal(|ui| {
+                    let mut stop = false;
+                    if let Some(watcher) = &self.watcher {
+                        ui.label(format!("👁 Отслеживается папка: {}", watcher.folder()));
+                        stop = ui.button("⏹ Остановить").clicked();
+                    } else if ui
+                        .button("👁 Отслеживать папку")
+                        .on_hover_text("Новые файлы указанных vendor codes будут загружаться автоматически")
+                        .clicked()
+                    {
+                        self.start_watching();
+                    }
+                    if stop {
+                        self.watcher = None;
                     }
                 });
-
-                ui.add_space(30.0);
-                ui.group(|ui| {
-                    ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
-                    ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
-                        egui::Color32::from_rgb(70, 70, 70)
-                    } else {
-                        egui::Color32::from_rgb(220, 220, 220)
-                    };
-                    ui.label(egui::RichText::new("📋 Vendor Codes").strong().size(22.0));
-                    ui.add_space(10.0);
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("📄 Путь к файлу:").strong());
+                    let response = ui.add(egui::TextEdit::singleline(&mut self.single_file_path).desired_width(300.0));
+                    mark_invalid(ui, response.rect, response, fields::check_media_file(&self.single_file_path));
+                    if ui.button("📄 Выбрать").clicked()
+                        && let Some(path) = FileDialog::new()
+                            .add_filter("Media", &["png", "jpg", "jpeg", "gif", "bmp", "webp", "mov", "mp4"])
+                            .pick_file()
+                    {
+                        self.single_file_path = path.to_string_lossy().to_string();
+                    }
+                });
+                if !self.dropped_files.is_empty() {
                     ui.horizontal(|ui| {
-                        ui.label(egui::RichText::new("🔢 Список vendor codes (по одному на строке):").strong());
-                        ui.vertical(|ui| {
-                            egui::ScrollArea::vertical().max_height(100.0).show(ui, |ui| {
-                                text_edit_with_context_menu(
-                                    ui,
-                                    &mut self.file_names,
-                                    400.0,
-                                    "VendorCode001\nVendorCode002\nEtc",
-                                );
-                            });
-                        });
+                        ui.label(format!("📥 Перетащено файлов: {}", self.dropped_files.len()));
+                        if ui.button("✖ Очистить").clicked() {
+                            self.dropped_files.clear();
+                        }
                     });
-                });
+                }
+            }
+        });
 
-                ui.add_space(30.0);
-                let is_processing = *self.is_processing.lock().unwrap();
-                ui.add_enabled_ui(!is_processing, |ui| {
-                    let button = ui.add(egui::Button::new("🚀 Запуск").rounding(8.0));
-                    if button.clicked() {
-                        let urls = self.urls.clone();
-                        let local_source_path = self.local_source_path.clone();
-                        let single_file_path = self.single_file_path.clone();
-                        let vendor_codes: Vec<String> = self
-                            .file_names
-                            .trim()
-                            .lines()
-                            .map(|s| s.trim().to_string())
-                            .filter(|s| !s.is_empty())
-                            .collect();
-                        let api_key = self.profile_manager.current_profile().api_key.clone();
-                        let is_processing = Arc::clone(&self.is_processing);
-                        let total_files = Arc::clone(&self.total_files);
-                        let processed_files = Arc::clone(&self.processed_files);
-                        let logs = Arc::clone(&self.logs);
-                        let failed_vendor_codes = Arc::clone(&self.failed_vendor_codes);
-                        let start_time = Arc::clone(&self.start_time);
-                        if !self.use_local_path && !urls.split(',').all(|s| s.trim().contains("disk.yandex.ru/d/")) {
-                            log::error!("Все ссылки должны быть на Яндекс.Диск");
-                            let mut logs = logs.lock().unwrap();
-                            logs.push("Ошибка: Все ссылки должны быть на Яндекс.Диск".to_string());
-                            return;
-                        }
-                        if self.use_local_path
-                            && !single_file_path.is_empty()
-                            && !Path::new(&single_file_path).is_file()
-                        {
-                            log::error!("Указанный путь к файлу недействителен");
-                            let mut logs = logs.lock().unwrap();
-                            logs.push("Ошибка: Указанный путь к файлу недействителен".to_string());
-                            return;
-                        }
-                        if self.use_local_path
-                            && !single_file_path.is_empty()
-                            && !crate::utils::is_media_file(&single_file_path)
-                        {
-                            log::error!("Указанный файл не является медиафайлом");
-                            let mut logs = logs.lock().unwrap();
-                            logs.push("Ошибка: Указанный файл не является медиафайлом".to_string());
-                            return;
+        ui.add_space(30.0);
+        ui.group(|ui| {
+            ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
+            ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
+                egui::Color32::from_rgb(70, 70, 70)
+            } else {
+                egui::Color32::from_rgb(220, 220, 220)
+            };
+            ui.label(egui::RichText::new("📋 Vendor Codes").strong().size(22.0));
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("🔢 Список vendor codes (по одному на строке):").strong());
+                ui.vertical(|ui| {
+                    let output = egui::ScrollArea::vertical().max_height(100.0).show(ui, |ui| {
+                        text_edit_with_context_menu(
+                            ui,
+                            &mut self.file_names,
+                            400.0,
+                            "VendorCode001\nVendorCode002\nEtc",
+                        )
+                    });
+                    let error = fields::check_vendor_codes(&self.file_names, &self.settings.vendor_codes);
+                    if error.is_some() {
+                        ui.colored_label(egui::Color32::RED, "⚠ Есть строки, которые WB не примет как vendor code — наведите на поле");
+                    }
+                    mark_invalid(ui, output.inner_rect, output.inner, error);
+                    ui.horizontal(|ui| {
+                        if ui.button("📥 Импорт из файла").clicked() {
+                            self.open_vendor_import();
                         }
-                        if self.use_local_path
-                            && single_file_path.is_empty()
-                            && !Path::new(&local_source_path).is_dir()
+                        let is_processing = *self.job_state.is_processing.lock().unwrap();
+                        if ui
+                            .add_enabled(!is_processing, egui::Button::new("📤 Выгрузить артикулы из WB"))
+                            .on_hover_text("Сохранить vendorCode и nmId всех карточек продавца в CSV")
+                            .clicked()
                         {
-                            log::error!("Локальный путь должен быть директорией");
-                            let mut logs = logs.lock().unwrap();
-                            logs.push("Ошибка: Локальный путь должен быть директорией".to_string());
-                            return;
+                            self.export_wb_vendor_codes();
                         }
-                        if api_key.is_empty() {
-                            log::error!("API ключ не указан");
-                            let mut logs = logs.lock().unwrap();
-                            logs.push("Ошибка: API ключ не указан".to_string());
-                            return;
-                        }
-
-                        let file_names = Arc::new(Mutex::new(self.file_names.clone()));
-                        let public_keys: Vec<String> = urls
-                            .split(',')
-                            .map(|s| s.trim().to_string())
-                            .filter(|s| !s.is_empty())
-                            .collect();
-                        let use_local_path = self.use_local_path;
-
-                        log::info!("Начало обработки...");
-                        {
-                            let mut logs = logs.lock().unwrap();
-                            logs.push("Начало обработки...".to_string());
-                        }
-                        *is_processing.lock().unwrap() = true;
-                        *processed_files.lock().unwrap() = 0;
-                        *total_files.lock().unwrap() = Some(vendor_codes.len());
-                        *start_time.lock().unwrap() = Some(Instant::now());
-                        failed_vendor_codes.lock().unwrap().clear();
-
-                        let public_keys_for_thread = public_keys.clone();
-                        std::thread::spawn(move || {
-                            log::info!("Запущен фоновый поток");
-                            {
-                                let mut logs = logs.lock().unwrap();
-                                logs.push("Запущен фоновый поток".to_string());
-                            }
-
-                            log::info!("Инициализация WbUploader");
-                            let uploader = match WbUploader::new(api_key) {
-                                Ok(u) => u,
-                                Err(e) => {
-                                    log::error!("Ошибка инициализации WB: {}", e);
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push(format!("Ошибка инициализации WB: {}", e));
-                                    *is_processing.lock().unwrap() = false;
-                                    return;
-                                }
-                            };
-                            {
-                                let mut logs = logs.lock().unwrap();
-                                logs.push("WbUploader успешно инициализирован".to_string());
-                            }
-
-                            log::info!("Начало обработки vendor codes");
-                            {
-                                let mut logs = logs.lock().unwrap();
-                                logs.push(format!("Обработка {} vendor codes", vendor_codes.len()));
-                            }
-                            if use_local_path && !single_file_path.is_empty() {
-                                // Single file upload mode
-                                log::info!("Режим загрузки одного файла: {}", single_file_path);
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push(format!("Режим загрузки одного файла: {}", single_file_path));
-                                }
-                                let path = Path::new(&single_file_path);
-                                let name = path.file_name().unwrap().to_string_lossy().to_string();
-                                let base_name = name.to_lowercase();
-                                let vendor_codes_set: std::collections::HashSet<String> =
-                                    vendor_codes.iter().cloned().collect();
-                                let downloader = match Downloader::new(Vec::new(), vendor_codes.clone()) {
-                                    Ok(d) => d,
-                                    Err(e) => {
-                                        log::error!("Ошибка инициализации Downloader: {}", e);
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!("Ошибка инициализации Downloader: {}", e));
-                                        *is_processing.lock().unwrap() = false;
-                                        return;
-                                    }
-                                };
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push("Downloader успешно инициализирован для одиночного файла".to_string());
-                                }
-                                let matched_prefix = downloader
-                                    .prefixes
-                                    .iter()
-                                    .find(|p| base_name.starts_with(&p.to_lowercase()));
-                                let file_info = if let Some(prefix) = matched_prefix {
-                                    let articul = prefix.to_string();
-                                    if !vendor_codes_set.contains(&articul) {
-                                        log::error!(
-                                            "Файл {} не соответствует ни одному vendorCode: {:?}",
-                                            name,
-                                            vendor_codes
-                                        );
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!(
-                                            "Ошибка: Файл {} не соответствует ни одному vendorCode: {:?}",
-                                            name, vendor_codes
-                                        ));
-                                        *is_processing.lock().unwrap() = false;
-                                        return;
-                                    }
-                                    let remaining = &base_name[prefix.len()..];
-                                    let photo_number = if let Some(caps) =
-                                        regex::Regex::new(r"^[_-](\d+)\.\w+$")
-                                            .unwrap()
-                                            .captures(remaining)
-                                    {
-                                        caps[1].parse::<u32>().unwrap_or(1)
-                                    } else if remaining.starts_with('.') {
-                                        1
-                                    } else {
-                                        log::error!(
-                                            "Файл {} не соответствует шаблону для vendorCode {}",
-                                            name,
-                                            prefix
-                                        );
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!(
-                                            "Ошибка: Файл {} не соответствует шаблону для vendorCode {}",
-                                            name, prefix
-                                        ));
-                                        *is_processing.lock().unwrap() = false;
-                                        return;
-                                    };
-                                    FileInfo {
-                                        name: name.clone(),
-                                        path: single_file_path.clone(),
-                                        articul,
-                                        photo_number,
-                                    }
-                                } else {
-                                    log::error!(
-                                        "Файл {} не начинается ни с одного vendorCode: {:?}",
-                                        name,
-                                        vendor_codes
-                                    );
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push(format!(
-                                        "Ошибка: Файл {} не начинается ни с одного vendorCode: {:?}",
-                                        name, vendor_codes
-                                    ));
-                                    *is_processing.lock().unwrap() = false;
-                                    return;
-                                };
-
-                                match uploader.get_nm_id_by_vendor_code(&file_info.articul) {
-                                    Ok(nm_id) => {
-                                        {
-                                            let mut logs = logs.lock().unwrap();
-                                            logs.push(format!(
-                                                "Найден nmId {} для vendorCode {}",
-                                                nm_id, file_info.articul
-                                            ));
-                                        }
-                                        match uploader.upload_local_file(
-                                            nm_id,
-                                            &file_info.path,
-                                            file_info.photo_number,
-                                            &processed_files,
-                                        ) {
-                                            Ok(()) => {
-                                                log::info!(
-                                                    "Файл {} успешно загружен для nmId {} с номером фото {}",
-                                                    file_info.path,
-                                                    nm_id,
-                                                    file_info.photo_number
-                                                );
-                                                let mut logs = logs.lock().unwrap();
-                                                logs.push(format!(
-                                                    "Файл {} успешно загружен для nmId {} с номером фото {}",
-                                                    file_info.path, nm_id, file_info.photo_number
-                                                ));
-                                            }
-                                            Err(e) => {
-                                                log::error!(
-                                                    "Ошибка загрузки файла {} для nmId {}: {}",
-                                                    file_info.path,
-                                                    nm_id,
-                                                    e
-                                                );
-                                                let mut logs = logs.lock().unwrap();
-                                                logs.push(format!(
-                                                    "Ошибка загрузки файла {} для nmId {}: {}",
-                                                    file_info.path, nm_id, e
-                                                ));
-                                                let mut failed_vendor_codes =
-                                                    failed_vendor_codes.lock().unwrap();
-                                                failed_vendor_codes.push(file_info.articul.clone());
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        log::error!(
-                                            "Ошибка получения nmId для vendorCode {}: {}",
-                                            file_info.articul,
-                                            e
-                                        );
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!(
-                                            "Ошибка получения nmId для vendorCode {}: {}",
-                                            file_info.articul, e
-                                        ));
-                                        let mut failed_vendor_codes = failed_vendor_codes.lock().unwrap();
-                                        failed_vendor_codes.push(file_info.articul.clone());
-                                    }
-                                }
-                                {
-                                    let mut processed = processed_files.lock().unwrap();
-                                    *processed += 1;
-                                }
-                            } else if use_local_path {
-                                // Local folder mode
-                                log::info!("Инициализация Downloader для локального режима");
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push("Инициализация Downloader для локального режима".to_string());
-                                }
-                                let downloader = match Downloader::new(Vec::new(), vendor_codes.clone()) {
-                                    Ok(d) => d,
-                                    Err(e) => {
-                                        log::error!("Ошибка инициализации: {}", e);
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!("Ошибка инициализации Downloader: {}", e));
-                                        *is_processing.lock().unwrap() = false;
-                                        return;
-                                    }
-                                };
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push("Downloader успешно инициализирован для локального режима".to_string());
-                                }
-                                log::info!("Начало сканирования локальной папки: {}", local_source_path);
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push(format!("Начало сканирования локальной папки: {}", local_source_path));
-                                }
-                                let files = match downloader.find_local_files(&local_source_path) {
-                                    Ok(files) => {
-                                        log::info!("Найдено файлов: {}", files.len());
-                                        {
-                                            let mut logs = logs.lock().unwrap();
-                                            logs.push(format!("Найдено файлов: {}", files.len()));
-                                        }
-                                        files
-                                    }
-                                    Err(e) => {
-                                        log::error!("Ошибка сканирования локальной папки: {}", e);
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!("Ошибка сканирования локальной папки: {}", e));
-                                        *is_processing.lock().unwrap() = false;
-                                        return;
-                                    }
-                                };
-
-                                for vendor_code in vendor_codes {
-                                    log::info!("Обработка vendorCode: {}", vendor_code);
-                                    {
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!("Обработка vendorCode: {}", vendor_code));
-                                    }
-                                    match uploader.get_nm_id_by_vendor_code(&vendor_code) {
-                                        Ok(nm_id) => {
-                                            {
-                                                let mut logs = logs.lock().unwrap();
-                                                logs.push(format!("Найден nmId {} для vendorCode {}", nm_id, vendor_code));
-                                            }
-                                            let relevant_files: Vec<FileInfo> = files
-                                                .iter()
-                                                .filter(|f| f.articul == vendor_code)
-                                                .cloned()
-                                                .collect();
-                                            if relevant_files.is_empty() {
-                                                log::error!("Не найдено файлов для vendorCode: {}", vendor_code);
-                                                let mut logs = logs.lock().unwrap();
-                                                logs.push(format!(
-                                                    "Ошибка: Не найдено файлов для vendorCode: {}",
-                                                    vendor_code
-                                                ));
-                                                let mut failed_vendor_codes = failed_vendor_codes.lock().unwrap();
-                                                failed_vendor_codes.push(vendor_code.clone());
-                                                continue;
-                                            }
-                                            for file in relevant_files {
-                                                {
-                                                    let mut logs = logs.lock().unwrap();
-                                                    logs.push(format!("Загрузка файла {} для nmId {}", file.path, nm_id));
-                                                }
-                                                match uploader.upload_local_file(
-                                                    nm_id,
-                                                    &file.path,
-                                                    file.photo_number,
-                                                    &processed_files,
-                                                ) {
-                                                    Ok(()) => {
-                                                        log::info!(
-                                                            "Файл {} успешно загружен для nmId {} с номером фото {}",
-                                                            file.path,
-                                                            nm_id,
-                                                            file.photo_number
-                                                        );
-                                                        let mut logs = logs.lock().unwrap();
-                                                        logs.push(format!(
-                                                            "Файл {} успешно загружен для nmId {} с номером фото {}",
-                                                            file.path, nm_id, file.photo_number
-                                                        ));
-                                                    }
-                                                    Err(e) => {
-                                                        log::error!(
-                                                            "Ошибка загрузки файла {} для nmId {}: {}",
-                                                            file.path,
-                                                            nm_id,
-                                                            e
-                                                        );
-                                                        let mut logs = logs.lock().unwrap();
-                                                        logs.push(format!(
-                                                            "Ошибка загрузки файла {} для nmId {}: {}",
-                                                            file.path, nm_id, e
-                                                        ));
-                                                        let mut failed_vendor_codes =
-                                                            failed_vendor_codes.lock().unwrap();
-                                                        failed_vendor_codes.push(vendor_code.clone());
-                                                    }
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            log::error!(
-                                                "Ошибка получения nmId для vendorCode {}: {}",
-                                                vendor_code,
-                                                e
-                                            );
-                                            let mut logs = logs.lock().unwrap();
-                                            logs.push(format!(
-                                                "Ошибка получения nmId для vendorCode {}: {}",
-                                                vendor_code, e
-                                            ));
-                                            let mut failed_vendor_codes = failed_vendor_codes.lock().unwrap();
-                                            failed_vendor_codes.push(vendor_code.clone());
-                                        }
-                                    }
-                                    {
-                                        let mut processed = processed_files.lock().unwrap();
-                                        *processed += 1;
-                                        let processed_count = *processed;
-                                        let total = total_files.lock().unwrap().unwrap_or(0);
-                                        if processed_count < total {
-                                            let elapsed = start_time.lock().unwrap().map(|t| t.elapsed().as_secs()).unwrap_or(0);
-                                            let avg_time_per_item = elapsed as f64 / processed_count as f64;
-                                            let remaining_items = total - processed_count;
-                                            let estimated_remaining = (remaining_items as f64 * avg_time_per_item) as u64;
-                                            let mut logs = logs.lock().unwrap();
-                                            logs.push(format!(
-                                                "Прогресс: Обработано {}/{} vendor codes. Примерное время до завершения: {} сек",
-                                                processed_count, total, estimated_remaining
-                                            ));
-                                        }
-                                    }
-                                }
-                            } else {
-                                // Yandex Disk mode
-                                log::info!("Инициализация Downloader для Яндекс.Диска");
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push("Инициализация Downloader для Яндекс.Диска".to_string());
-                                }
-                                let downloader = match Downloader::new(public_keys_for_thread.clone(), vendor_codes.clone())
-                                {
-                                    Ok(d) => d,
-                                    Err(e) => {
-                                        log::error!("Ошибка инициализации: {}", e);
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!("Ошибка инициализации Downloader: {}", e));
-                                        *is_processing.lock().unwrap() = false;
-                                        return;
-                                    }
-                                };
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push("Downloader успешно инициализирован для Яндекс.Диска".to_string());
-                                }
-                                log::info!("Начало поиска файлов с URL: {:?}", public_keys_for_thread);
-                                {
-                                    let mut logs = logs.lock().unwrap();
-                                    logs.push(format!(
-                                        "Начало поиска файлов с URL: {:?}",
-                                        public_keys_for_thread
-                                    ));
-                                }
-                                let files = match downloader.find_files("/") {
-                                    Ok(files) => {
-                                        log::info!("Найдено файлов: {}", files.len());
-                                        {
-                                            let mut logs = logs.lock().unwrap();
-                                            logs.push(format!("Найдено файлов: {}", files.len()));
-                                        }
-                                        files
-                                    }
-                                    Err(e) => {
-                                        log::error!("Ошибка поиска файлов: {}", e);
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!("Ошибка поиска файлов: {}", e));
-                                        *is_processing.lock().unwrap() = false;
-                                        return;
-                                    }
-                                };
-
-                                for vendor_code in vendor_codes {
-                                    log::info!("Обработка vendorCode: {}", vendor_code);
-                                    {
-                                        let mut logs = logs.lock().unwrap();
-                                        logs.push(format!("Обработка vendorCode: {}", vendor_code));
-                                    }
-                                    match uploader.get_nm_id_by_vendor_code(&vendor_code) {
-                                        Ok(nm_id) => {
-                                            {
-                                                let mut logs = logs.lock().unwrap();
-                                                logs.push(format!(
-                                                    "Найден nmId {} для vendorCode {}",
-                                                    nm_id, vendor_code
-                                                ));
-                                            }
-                                            let relevant_files: Vec<FileInfo> = files
-                                                .iter()
-                                                .filter(|f| f.articul == vendor_code)
-                                                .cloned()
-                                                .collect();
-                                            if relevant_files.is_empty() {
-                                                log::error!("Не найдено файлов для vendorCode: {}", vendor_code);
-                                                let mut logs = logs.lock().unwrap();
-                                                logs.push(format!(
-                                                    "Ошибка: Не найдено файлов для vendorCode: {}",
-                                                    vendor_code
-                                                ));
-                                                let mut failed_vendor_codes = failed_vendor_codes.lock().unwrap();
-                                                failed_vendor_codes.push(vendor_code.clone());
-                                                continue;
-                                            }
-                                            let downloader = match Downloader::new(
-                                                public_keys_for_thread.clone(),
-                                                vec![vendor_code.clone()],
-                                            ) {
-                                                Ok(d) => d,
-                                                Err(e) => {
-                                                    log::error!(
-                                                        "Ошибка инициализации Downloader для публикации: {}",
-                                                        e
-                                                    );
-                                                    let mut logs = logs.lock().unwrap();
-                                                    logs.push(format!(
-                                                        "Ошибка инициализации Downloader для публикации: {}",
-                                                        e
-                                                    ));
-                                                    let mut failed_vendor_codes = failed_vendor_codes.lock().unwrap();
-                                                    failed_vendor_codes.push(vendor_code.clone());
-                                                    continue;
-                                                }
-                                            };
-                                            match downloader.generate_media_json(nm_id, &relevant_files, None) {
-                                                Ok(media) => {
-                                                    let json_output = serde_json::to_string_pretty(&media)
-                                                        .unwrap_or_else(|e| format!("Ошибка сериализации JSON: {}", e));
-                                                    log::info!("JSON Output для nmId {}:\n{}", nm_id, json_output);
-                                                    {
-                                                        let mut logs = logs.lock().unwrap();
-                                                        logs.push(format!("JSON Output для nmId {}:\n{}", nm_id, json_output));
-                                                    }
-                                                    if let Err(e) =
-                                                        uploader.upload_links(nm_id, &media.data, &processed_files)
-                                                    {
-                                                        log::error!(
-                                                            "Ошибка загрузки ссылок на WB для nmId {}: {}",
-                                                            nm_id,
-                                                            e
-                                                        );
-                                                        let mut logs = logs.lock().unwrap();
-                                                        logs.push(format!(
-                                                            "Ошибка загрузки ссылок на WB для nmId {}: {}",
-                                                            nm_id, e
-                                                        ));
-                                                        let mut failed_vendor_codes =
-                                                            failed_vendor_codes.lock().unwrap();
-                                                        failed_vendor_codes.push(vendor_code.clone());
-                                                    } else {
-                                                        log::info!("Ссылки для nmId {} загружены успешно", nm_id);
-                                                        let mut logs = logs.lock().unwrap();
-                                                        logs.push(format!(
-                                                            "Ссылки для nmId {} загружены успешно",
-                                                            nm_id
-                                                        ));
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    log::error!("Ошибка генерации JSON для nmId {}: {}", nm_id, e);
-                                                    let mut logs = logs.lock().unwrap();
-                                                    logs.push(format!(
-                                                        "Ошибка генерации JSON для nmId {}: {}",
-                                                        nm_id, e
-                                                    ));
-                                                    let mut failed_vendor_codes = failed_vendor_codes.lock().unwrap();
-                                                    failed_vendor_codes.push(vendor_code.clone());
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            log::error!(
-                                                "Ошибка получения nmId для vendorCode {}: {}",
-                                                vendor_code,
-                                                e
-                                            );
-                                            let mut logs = logs.lock().unwrap();
-                                            logs.push(format!(
-                                                "Ошибка получения nmId для vendorCode {}: {}",
-                                                vendor_code, e
-                                            ));
-                                            let mut failed_vendor_codes = failed_vendor_codes.lock().unwrap();
-                                            failed_vendor_codes.push(vendor_code.clone());
-                                        }
-                                    }
-                                    {
-                                        let mut processed = processed_files.lock().unwrap();
-                                        *processed += 1;
-                                        let processed_count = *processed;
-                                        let total = total_files.lock().unwrap().unwrap_or(0);
-                                        if processed_count < total {
-                                            let elapsed = start_time.lock().unwrap().map(|t| t.elapsed().as_secs()).unwrap_or(0);
-                                            let avg_time_per_item = elapsed as f64 / processed_count as f64;
-                                            let remaining_items = total - processed_count;
-                                            let estimated_remaining = (remaining_items as f64 * avg_time_per_item) as u64;
-                                            let mut logs = logs.lock().unwrap();
-                                            logs.push(format!(
-                                                "Прогресс: Обработано {}/{} vendor codes. Примерное время до завершения: {} сек",
-                                                processed_count, total, estimated_remaining
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
-
-                            let failed = failed_vendor_codes.lock().unwrap();
-                            if !failed.is_empty() {
-                                log::warn!("Ошибочные vendor codes для повторного запуска: {}", failed.join(", "));
-                                let mut logs = logs.lock().unwrap();
-                                logs.push(format!(
-                                    "Ошибочные vendor codes для повторного запуска: {}",
-                                    failed.join(", ")
-                                ));
-                                let mut file_names = file_names.lock().unwrap();
-                                *file_names = failed.join("\n");
-                            } else {
-                                log::info!("Все vendor codes обработаны успешно.");
-                                let mut logs = logs.lock().unwrap();
-                                logs.push("Все vendor codes обработаны успешно.".to_string());
-                            }
-
-                            log::info!("Процесс завершен.");
-                            {
-                                let mut logs = logs.lock().unwrap();
-                                logs.push("Процесс завершен.".to_string());
-                            }
-                            *is_processing.lock().unwrap() = false;
-                        });
+                    });
+                    let parsed = parse_vendor_codes(&self.file_names, &self.settings.vendor_codes);
+                    ui.label(format!("Распознано уникальных vendor codes: {}", parsed.codes.len()));
+                    if parsed.duplicates > 0 {
+                        ui.label(format!("Дубликатов пропущено: {}", parsed.duplicates));
+                    }
+                    if ui.button("🧹 Нормализовать список").clicked() {
+                        self.file_names = parsed.codes.join("\n");
                     }
                 });
+            });
+            self.show_vendor_import(ui);
+        });
 
-                ui.add_space(20.0);
-                ui.group(|ui| {
-                    ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
-                    ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
-                        egui::Color32::from_rgb(70, 70, 70)
-                    } else {
-                        egui::Color32::from_rgb(220, 220, 220)
-                    };
-                    ui.label(egui::RichText::new("📊 Статус обработки").strong().size(22.0));
-                    ui.add_space(10.0);
-                    ui.horizontal(|ui| {
-                        let processed = *self.processed_files.lock().unwrap();
-                        let total = self.total_files.lock().unwrap().unwrap_or(0);
-                        ui.label(egui::RichText::new(format!("Прогресс: {}/{}", processed, total)).size(16.0));
-                        if is_processing {
-                            if let Some(start) = *self.start_time.lock().unwrap() {
-                                let elapsed = start.elapsed().as_secs();
-                                let avg_time_per_item = if processed > 0 { elapsed as f64 / processed as f64 } else { 0.0 };
-                                let remaining_items = total - processed;
-                                let estimated_remaining = (remaining_items as f64 * avg_time_per_item) as u64;
-                                ui.label(egui::RichText::new(format!(
-                                    "Примерное время до завершения: {} сек",
-                                    estimated_remaining
-                                )).size(16.0));
-                            }
-                        }
-                    });
-                    ui.add_space(10.0);
-                    if ui.button("📜 Показать/Скрыть логи").clicked() {
-                        self.show_logs = !self.show_logs;
-                    }
-                    if self.show_logs {
-                        ui.add_space(10.0);
-                        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
-                            let logs = self.logs.lock().unwrap();
-                            for log in logs.iter().rev().take(50) {
-                                ui.label(egui::RichText::new(log).size(14.0));
+        ui.add_space(30.0);
+        let is_processing = *self.job_state.is_processing.lock().unwrap();
+        let preview_running = *self.preview.running.lock().unwrap();
+        let compare_running = *self.compare.running.lock().unwrap();
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!is_processing, |ui| {
+                let button = ui.add(egui::Button::new("🚀 Запуск").rounding(8.0)).on_hover_text("Ctrl+Enter");
+                if button.clicked() {
+                    self.start_processing();
+                }
+            });
+            if ui
+                .add(egui::Button::new("➕ В очередь").rounding(8.0))
+                .on_hover_text("Подготовить задание с текущими полями и выполнить его позже в очереди")
+                .clicked()
+            {
+                self.enqueue();
+            }
+            ui.checkbox(&mut self.enqueue_urgent, "⚡ срочно")
+                .on_hover_text("Выполнить раньше остальных: идущее задание очереди приостановится после текущего vendor code");
+            if is_processing
+                && ui
+                    .add(egui::Button::new("⏹ Остановить").rounding(8.0))
+                    .on_hover_text("Esc: текущий vendor code будет доработан, остальные пропущены")
+                    .clicked()
+            {
+                self.job_state.cancel();
+            }
+            ui.add_enabled_ui(!is_processing && !preview_running, |ui| {
+                if ui
+                    .add(egui::Button::new("🖼 Предпросмотр").rounding(8.0))
+                    .on_hover_text("Найти файлы без загрузки и показать миниатюры по vendor codes")
+                    .clicked()
+                {
+                    self.start_preview();
+                }
+            });
+            ui.add_enabled_ui(!is_processing && !compare_running, |ui| {
+                if ui
+                    .add(egui::Button::new("⚖ Сравнить с WB").rounding(8.0))
+                    .on_hover_text("Сравнить число фото в источнике и в карточках WB до загрузки")
+                    .clicked()
+                {
+                    self.start_compare();
+                }
+            });
+            ui.add_enabled_ui(!is_processing, |ui| {
+                if ui
+                    .add(egui::Button::new("↩ Откатить последнюю загрузку").rounding(8.0))
+                    .on_hover_text("Вернуть карточкам последнего запуска фото, которые были до загрузки")
+                    .clicked()
+                {
+                    self.confirm_undo = true;
+                }
+                if ui
+                    .add(egui::Button::new("💾 Резервная копия фото").rounding(8.0))
+                    .on_hover_text("Скачать текущие фото карточек WB для vendor codes из списка в выбранную папку")
+                    .clicked()
+                {
+                    self.start_backup();
+                }
+                if ui
+                    .add(egui::Button::new("📝 Изменения карточек из таблицы").rounding(8.0))
+                    .on_hover_text("Обновить названия, описания и характеристики карточек из CSV/Excel: столбцы vendorCode, название, описание и названия характеристик WB")
+                    .clicked()
+                {
+                    self.start_card_updates();
+                }
+                if ui
+                    .add(egui::Button::new("🆕 Создать карточки из таблицы").rounding(8.0))
+                    .on_hover_text("Создать карточки из CSV/Excel: столбцы vendorCode, subjectID, название, описание, бренд, фото (ссылка Яндекс.Диска или папка) и характеристики WB; фото загружаются сразу после создания")
+                    .clicked()
+                {
+                    self.start_new_cards();
+                }
+            });
+            if self.settings.skip_uploaded {
+                ui.checkbox(&mut self.force_reupload, "♻ Загрузить заново уже загруженные файлы");
+            }
+            let mut http_debug = self.job_state.http_debug();
+            if ui
+                .checkbox(&mut http_debug, "🐞 Подробный журнал HTTP на один запуск")
+                .on_hover_text("Запросы к WB и Яндекс.Диску с заголовками и телами ответов попадают в журнал (уровень DEBUG). Можно включить и во время запуска; после его окончания флажок снимается сам")
+                .changed()
+            {
+                self.job_state.set_http_debug(http_debug);
+            }
+        });
+        self.show_preview(ctx, ui);
+        self.show_compare(ctx, ui);
+        self.show_audit(ctx, ui);
+        self.show_queue(ui, is_processing);
+        self.show_batch(ui, is_processing);
+
+        ui.add_space(20.0);
+        egui::CollapsingHeader::new(egui::RichText::new("⚙ Настройки").strong().size(18.0))
+            .id_salt("settings")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Тема:");
+                    egui::ComboBox::from_id_salt("theme")
+                        .selected_text(self.settings.theme.label())
+                        .show_ui(ui, |ui| {
+                            for theme in [Theme::System, Theme::Light, Theme::Dark] {
+                                ui.selectable_value(&mut self.settings.theme, theme, theme.label());
                             }
                         });
+                    ui.label("Цвет рамки:");
+                    ui.color_edit_button_srgb(&mut self.settings.accent_color);
+                    if ui.button("Сбросить").clicked() {
+                        self.settings.accent_color = DEFAULT_ACCENT_COLOR;
                     }
                 });
-
-                ui.add_space(20.0);
                 ui.horizontal(|ui| {
-                    let failed = self.failed_vendor_codes.lock().unwrap();
-                    ui.add_enabled_ui(!failed.is_empty() && !is_processing, |ui| {
-                        if ui.button("🔄 Повторить для ошибочных").clicked() {
-                            self.file_names = failed.join("\n");
-                            log::info!("Повторная обработка vendor codes: {}", failed.join(", "));
-                            let mut logs = self.logs.lock().unwrap();
-                            logs.push(format!("Повторная обработка vendor codes: {}", failed.join(", ")));
-                        }
-                    });
+                    ui.label("Размер буфера логов (записей):");
+                    ui.add(
+                        egui::DragValue::new(&mut self.settings.log_capacity)
+                            .range(MIN_LOG_CAPACITY..=1_000_000)
+                            .speed(100),
+                    );
                 });
-
-                ctx.request_repaint();
-            });
-    }
-}
-
-fn text_edit_with_context_menu(ui: &mut egui::Ui, text: &mut String, width: f32, hint_text: &str) {
-    let text_edit = egui::TextEdit::multiline(text).desired_width(width).hint_text(hint_text);
-    let response = ui.add(text_edit);
-    response.context_menu(|ui| {
-        if ui.button("📋 Вставить").clicked() {
-            if let Ok(mut clipboard) = Clipboard::new() {
-                if let Ok(clipboard_text) = clipboard.get_text() {
-                    *text = clipboard_text;
+                ui.horizontal(|ui| {
+                    ui.label("Макс. скорость загрузки файлов (КБ/с, 0 — без ограничения):");
+                    ui.add(egui::DragValue::new(&mut self.settings.max_upload_kbps).speed(10));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Параллельных загрузок файлов (1 — по одному):");
+                    ui.add(egui::DragValue::new(&mut self.settings.upload_concurrency).range(1..=8));
+                })
+                .response
+                .on_hover_text("Локальные файлы одной карточки загружаются в WB одновременно. При повторных ответах 429 число потоков уменьшается вдвое и постепенно возвращается к заданному");
+                ui.horizontal(|ui| {
+                    ui.label("Минимум свободного места на диске (МБ, 0 — не проверять):");
+                    ui.add(egui::DragValue::new(&mut self.settings.min_free_disk_mb).speed(10));
+                })
+                .response
+                .on_hover_text("Запуск и резервное копирование не начнутся и остановятся, если на диске с отчётами или копией фото останется меньше");
+                ui.horizontal(|ui| {
+                    ui.label("Предел памяти на файл (МБ, 0 — без ограничения):");
+                    ui.add(egui::DragValue::new(&mut self.settings.max_file_memory_mb).speed(10));
+                })
+                .response
+                .on_hover_text("Локальный файл перед загрузкой в WB читается в память целиком; файлы больше предела отклоняются с ошибкой до чтения");
+                ui.horizontal(|ui| {
+                    ui.label("Папка временных файлов: до (МБ, 0 — без ограничения)");
+                    ui.add(egui::DragValue::new(&mut self.settings.staging_max_mb).speed(10));
+                    ui.label("хранить (дней, 0 — без срока)");
+                    ui.add(egui::DragValue::new(&mut self.settings.staging_max_age_days).range(0..=365));
+                })
+                .response
+                .on_hover_text("Сюда скачиваются файлы Яндекс.Диска перед загрузкой в WB файлами и для архива загруженных файлов; старые файлы и превышение размера убираются при запуске приложения и после каждого запуска");
+                ui.checkbox(
+                    &mut self.settings.download_yandex_files,
+                    "Скачивать файлы Яндекс.Диска и загружать в WB файлами",
+                )
+                .on_hover_text("Резервный режим, если WB не принимает ссылки Диска: файлы скачиваются во временную папку и загружаются через media/file в фото со своими номерами, а не заменяют всю галерею");
+                ui.checkbox(
+                    &mut self.settings.delete_after_upload,
+                    "Удалять скачанные файлы после загрузки",
+                )
+                .on_hover_text("Без флажка загруженные файлы остаются в папке временных файлов до очистки по сроку и размеру; удаляются только файлы этой папки, локальные файлы пользователя — никогда");
+                ui.horizontal(|ui| {
+                    let staging = self.job_state.staging();
+                    ui.label(format!(
+                        "{} — {}",
+                        staging.dir().display(),
+                        staging.usage().describe()
+                    ));
+                    let processing = *self.job_state.is_processing.lock().unwrap();
+                    if ui
+                        .add_enabled(!processing, egui::Button::new("🗑 Очистить кэш"))
+                        .on_disabled_hover_text("Идёт обработка")
+                        .clicked()
+                    {
+                        let removed = staging.clear();
+                        self.job_state.log(LogEvent::info(
+                            Stage::General,
+                            format!("Папка временных файлов очищена: {}", removed.describe()),
+                        ));
+                    }
+                });
+                ui.checkbox(
+                    &mut self.settings.capture_http,
+                    "Записывать HTTP-запросы запуска (для поддержки WB)",
+                )
+                .on_hover_text(format!(
+                    "Каждый запрос к WB и Яндекс.Диску с ответом записывается в JSONL-файл запуска в {}; заголовки авторизации и cookie заменяются на ***",
+                    self.profile_manager.config.get_captures_dir().display()
+                ));
+                ui.horizontal(|ui| {
+                    ui.label("Проверять ошибки обработки карточек WB через (сек, 0 — не проверять):");
+                    ui.add(egui::DragValue::new(&mut self.settings.card_errors_delay_secs).range(0..=600));
+                })
+                .response
+                .on_hover_text("После загрузки запрашивает у WB список ошибок карточек (например, фото отклонено по качеству) и добавляет их в отчёт");
+                ui.horizontal(|ui| {
+                    ui.label("Автоповтор ошибочных (раз, 0 — не повторять):");
+                    ui.add(egui::DragValue::new(&mut self.settings.auto_retry_count).range(0..=10));
+                    ui.label("пауза перед повтором (сек):");
+                    ui.add(egui::DragValue::new(&mut self.settings.auto_retry_delay_secs).range(0..=3600));
+                })
+                .response
+                .on_hover_text("После основного прохода ошибочные vendor codes запускаются заново, пока не пройдут или не кончатся повторы; создание карточек из таблицы не повторяется");
+                ui.horizontal(|ui| {
+                    ui.label("Папка для JSON-отчётов (пусто — не сохранять):");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.json_report_dir).desired_width(250.0));
+                    if ui.button("📁 Выбрать").clicked()
+                        && let Some(path) = FileDialog::new().pick_folder()
+                    {
+                        self.settings.json_report_dir = path.to_string_lossy().to_string();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Папка архива загруженных файлов (пусто — не архивировать):");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.archive_dir).desired_width(250.0));
+                    if ui.button("📁 Выбрать").clicked()
+                        && let Some(path) = FileDialog::new().pick_folder()
+                    {
+                        self.settings.archive_dir = path.to_string_lossy().to_string();
+                    }
+                })
+                .response
+                .on_hover_text("После запуска файлы, загруженные в WB, сохраняются в ZIP «run_<номер запуска>.zip» по папкам vendor codes — для проверки и повторной загрузки");
+                ui.horizontal(|ui| {
+                    ui.label("Telegram: токен бота");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.telegram_token)
+                            .password(true)
+                            .desired_width(250.0),
+                    );
+                    ui.label("ID чата");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.telegram_chat_id).desired_width(120.0));
+                });
+                ui.label("Итог запуска отправляется в Telegram, если указаны токен и ID чата.");
+                ui.horizontal(|ui| {
+                    ui.label("Файл соответствий vendor code → файл/папка (CSV, пусто — не использовать):");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.mapping_file).desired_width(250.0));
+                    if ui.button("📁 Выбрать").clicked()
+                        && let Some(path) = FileDialog::new().add_filter("Таблицы", &["csv", "txt", "xlsx", "xls", "ods"]).pick_file()
+                    {
+                        self.settings.mapping_file = path.to_string_lossy().to_string();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Рич-контент: шаблон JSON или папка шаблонов (пусто — не загружать):");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.rich_content_template).desired_width(250.0));
+                    if ui.button("📄 Файл").clicked()
+                        && let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).pick_file()
+                    {
+                        self.settings.rich_content_template = path.to_string_lossy().to_string();
+                    }
+                    if ui.button("📁 Папка").clicked()
+                        && let Some(path) = FileDialog::new().pick_folder()
+                    {
+                        self.settings.rich_content_template = path.to_string_lossy().to_string();
+                    }
+                });
+                ui.label("В шаблоне подставляются {vendorCode} и {nmId}; в папке берётся «<vendorCode>.json», иначе «default.json».");
+                ui.label("Исключить при сканировании (glob-шаблоны, по одному в строке):");
+                if ui
+                    .add(
+                        egui::TextEdit::multiline(&mut self.exclude_patterns_text)
+                            .hint_text("**/Исходники/**\n*_raw.*\n*.psd")
+                            .desired_rows(2)
+                            .desired_width(400.0),
+                    )
+                    .changed()
+                {
+                    self.settings.exclude_patterns = self
+                        .exclude_patterns_text
+                        .lines()
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                }
+                ui.label("Сканировать только подпапки (пути от корня источника, по одному в строке; пусто — все):");
+                if ui
+                    .add(
+                        egui::TextEdit::multiline(&mut self.scan_folders_text)
+                            .hint_text("Фото/2024\nГотовое")
+                            .desired_rows(2)
+                            .desired_width(400.0),
+                    )
+                    .changed()
+                {
+                    self.settings.scan_folders = self
+                        .scan_folders_text
+                        .lines()
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Макс. глубина вложенности папок (0 — без ограничения):");
+                    ui.add(egui::DragValue::new(&mut self.settings.max_scan_depth).range(0..=100));
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.cache_scans, "Кэшировать списки файлов публичных папок Яндекс.Диска");
+                    if ui.button("🗑 Очистить кэш").clicked()
+                        && let Err(e) = ScanCache::new(self.profile_manager.config.get_scan_cache_path()).clear()
+                    {
+                        self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
+                    }
+                });
+                ui.checkbox(
+                    &mut self.settings.validate_before_upload,
+                    "Проверять vendor codes и файлы перед загрузкой",
+                )
+                .on_hover_text("Перед загрузкой показывается список vendor codes без файлов или карточек WB, файлов без vendor code, слишком больших файлов и ошибок в номерах фото");
+                ui.checkbox(
+                    &mut self.settings.skip_uploaded,
+                    "Пропускать файлы, уже загруженные в карточку (по истории загрузок)",
+                );
+                ui.checkbox(
+                    &mut self.settings.snapshot_media,
+                    "Сохранять текущие фото карточек перед загрузкой (для отката)",
+                )
+                .on_hover_text("Перед заменой медиа запрашивает у WB текущие фото карточки — один дополнительный запрос на vendor code");
+                ui.checkbox(
+                    &mut self.settings.restore_trashed_cards,
+                    "Восстанавливать карточки из корзины WB",
+                )
+                .on_hover_text("Если карточка найдена только в корзине, она восстанавливается перед загрузкой; иначе vendor code пропускается с ошибкой");
+                ui.checkbox(
+                    &mut self.settings.auto_renumber_photos,
+                    "Автоматически перенумеровывать фото подряд (1, 2, 3…)",
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Сопоставление файлов:");
+                    ui.checkbox(&mut self.settings.matching.case_sensitive, "Учитывать регистр");
+                    ui.checkbox(&mut self.settings.matching.ignore_separators, "Не различать «-» и «_»");
+                });
+                ui.horizontal(|ui| {
+                    let options = &mut self.settings.vendor_codes;
+                    ui.label("Разбор vendor codes:");
+                    ui.checkbox(&mut options.trim, "Обрезать пробелы");
+                    ui.checkbox(&mut options.strip_bom, "Удалять BOM");
+                    ui.checkbox(&mut options.dedupe, "Убирать дубликаты");
+                    ui.checkbox(&mut options.strip_comments, "Удалять комментарии (#)");
+                    ui.checkbox(&mut options.uppercase, "Верхний регистр");
+                });
+                ui.checkbox(&mut self.settings.desktop_notifications, "Системное уведомление по завершении обработки");
+                ui.add_space(5.0);
+                ui.label("Отправка отчёта по почте (SMTP) после каждого запуска:");
+                egui::Grid::new("smtp_settings").num_columns(2).show(ui, |ui| {
+                    ui.label("Сервер:");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.settings.smtp_host).hint_text("smtp.example.com").desired_width(200.0));
+                        ui.label("Порт:");
+                        ui.add(egui::DragValue::new(&mut self.settings.smtp_port));
+                    });
+                    ui.end_row();
+                    ui.label("Логин:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.smtp_username).desired_width(200.0));
+                    ui.end_row();
+                    ui.label("Пароль:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.smtp_password).password(true).desired_width(200.0));
+                    ui.end_row();
+                    ui.label("Отправитель:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.smtp_from).hint_text("по умолчанию — логин").desired_width(200.0));
+                    ui.end_row();
+                    ui.label("Получатели:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.smtp_to).hint_text("через запятую").desired_width(300.0));
+                    ui.end_row();
+                });
+                ui.add_space(5.0);
+                ui.label("Webhook URL для событий задания (по одному в строке):");
+                if ui
+                    .add(
+                        egui::TextEdit::multiline(&mut self.webhook_urls_text)
+                            .hint_text("https://example.com/hook")
+                            .desired_rows(2)
+                            .desired_width(400.0),
+                    )
+                    .changed()
+                {
+                    self.settings.webhook_urls = self
+                        .webhook_urls_text
+                        .lines()
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                }
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.api_enabled, "Локальный API управления на порту");
+                    ui.add(egui::DragValue::new(&mut self.settings.api_port).range(1024..=65535));
+                    ui.label("(применяется после перезапуска)");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Токен API:");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.api_token).password(true).desired_width(260.0));
+                    if ui.button("Сгенерировать").clicked() {
+                        self.settings.api_token = format!("{:032x}", rand::random::<u128>());
+                    }
+                    if ui.button("📋").on_hover_text("Скопировать токен").clicked() {
+                        ui.ctx().copy_text(self.settings.api_token.clone());
+                    }
+                })
+                .response
+                .on_hover_text("Передаётся в заголовке Authorization: Bearer <токен>; для /ws/logs — также параметром token");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.file_server_enabled, "Отдавать локальные файлы ссылками на порту");
+                    ui.add(egui::DragValue::new(&mut self.settings.file_server_port).range(1024..=65535));
+                    ui.label("Внешний адрес:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.file_server_url)
+                            .hint_text("https://….trycloudflare.com")
+                            .desired_width(220.0),
+                    );
+                })
+                .response
+                .on_hover_text("Локальные файлы загружаются через media/save по ссылкам встроенного HTTP-сервера, а не файлами через media/file. WB должен видеть адрес: укажите туннель (например, https://….trycloudflare.com) на этот порт. Адрес в локальной сети не принимается");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.settings.network.force_ipv4, "Только IPv4");
+                    ui.label("Локальный адрес:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.settings.network.local_address)
+                            .hint_text("пусто — выбирает система")
+                            .desired_width(140.0),
+                    );
+                })
+                .response
+                .on_hover_text("Для сетей, где соединения по IPv6 обрываются, и для компьютеров с несколькими сетевыми картами. Действует на запросы к WB и Яндекс.Диску со следующего запуска");
+                ui.label("Адреса хостов в обход DNS (host=IP, по одному в строке):");
+                if ui
+                    .add(
+                        egui::TextEdit::multiline(&mut self.host_overrides_text)
+                            .hint_text("content-api.wildberries.ru=203.0.113.10")
+                            .desired_rows(2)
+                            .desired_width(400.0),
+                    )
+                    .changed()
+                {
+                    self.settings.network.host_overrides = self
+                        .host_overrides_text
+                        .lines()
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Корневой сертификат (PEM, пусто — только системные):");
+                    ui.add(egui::TextEdit::singleline(&mut self.settings.network.ca_certificate).desired_width(250.0));
+                    if ui.button("📄 Выбрать").clicked()
+                        && let Some(path) = FileDialog::new()
+                            .add_filter("PEM", &["pem", "crt", "cer"])
+                            .pick_file()
+                    {
+                        self.settings.network.ca_certificate = path.to_string_lossy().to_string();
+                    }
+                })
+                .response
+                .on_hover_text("Сертификат корпоративного прокси, который проверяет HTTPS-трафик: без него запросы к WB и Яндекс.Диску завершаются ошибкой TLS. Добавляется к системным сертификатам");
+                ui.checkbox(&mut self.settings.confirm_media_replace, "Подтверждать запуск, заменяющий фото карточек");
+                ui.checkbox(&mut self.settings.confirm_profile_delete, "Подтверждать удаление профиля");
+                ui.checkbox(&mut self.settings.check_updates, "Проверять обновления при запуске")
+                    .on_hover_text(format!("Текущая версия {}; о новой версии на GitHub сообщит баннер вверху окна", update::CURRENT_VERSION));
+                ui.add_space(5.0);
+                if ui.button("💾 Сохранить настройки").clicked() {
+                    self.apply_settings();
+                }
+            });
+
+        ui.add_space(20.0);
+        ui.group(|ui| {
+            ui.visuals_mut().widgets.noninteractive.rounding = egui::Rounding::same(8.0);
+            ui.visuals_mut().widgets.noninteractive.bg_fill = if ctx.style().visuals.dark_mode {
+                egui::Color32::from_rgb(70, 70, 70)
+            } else {
+                egui::Color32::from_rgb(220, 220, 220)
+            };
+            ui.label(egui::RichText::new("📊 Статус обработки").strong().size(22.0));
+            ui.add_space(10.0);
+            let progress = self.job_state.progress.lock().unwrap().clone();
+            let (processed, total) = (progress.processed, progress.total);
+            ui.add(
+                egui::ProgressBar::new(progress.fraction())
+                    .text(format!("{}/{} vendor codes", processed, total))
+                    .animate(is_processing),
+            );
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                ui.label(format!("🔍 Найдено файлов: {}", progress.scanned));
+                ui.separator();
+                ui.label(format!("🔗 nmId получено: {}", progress.resolved));
+                ui.separator();
+                ui.label(format!("⬆ Загружено файлов: {}", progress.uploaded));
+                ui.separator();
+                ui.label(format!("❌ Ошибок: {}", progress.failed));
+            });
+            for (name, transfer) in &progress.current_files {
+                let (sent, total) = (transfer.sent(), transfer.total());
+                ui.horizontal(|ui| {
+                    ui.label(format!("📄 {}", name));
+                    ui.add(
+                        egui::ProgressBar::new(if total > 0 { sent as f32 / total as f32 } else { 0.0 })
+                            .desired_width(200.0)
+                            .text(format!("{} / {}", format_bytes(sent), format_bytes(total))),
+                    );
+                });
+            }
+            if !progress.current_files.is_empty() || progress.bytes_uploaded > 0 {
+                let current = progress.current_bytes_per_second();
+                ui.label(format!(
+                    "⚡ Скорость: текущая {}, средняя {}/с",
+                    current.map_or("—".to_string(), |v| format!("{}/с", format_bytes(v as u64))),
+                    format_bytes(progress.average_bytes_per_second() as u64),
+                ));
+            }
+            ui.horizontal(|ui| {
+                if is_processing {
+                    let eta = progress.estimate_remaining();
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Примерное время до завершения: {}",
+                            eta.map_or("оценивается…".to_string(), job::format_eta)
+                        ))
+                        .size(16.0),
+                    );
+                }
+            });
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if ui.button("📜 Показать/Скрыть логи").on_hover_text("Ctrl+L").clicked() {
+                    self.show_logs = !self.show_logs;
+                }
+                if ui.button("💾 Сохранить логи").clicked() {
+                    self.save_logs_to_file();
+                }
+                if ui.button("📋 Копировать логи").clicked() {
+                    self.copy_logs_to_clipboard();
+                }
+            });
+            if self.show_logs {
+                ui.add_space(10.0);
+                self.show_log_panel(ui);
+            }
+        });
+
+        let has_results = !self.job_state.results.lock().unwrap().is_empty();
+        if has_results {
+            ui.add_space(20.0);
+            ui.group(|ui| {
+                ui.label(egui::RichText::new("📑 Результаты").strong().size(22.0));
+                ui.add_space(10.0);
+                self.show_results_table(ui, is_processing);
+                ui.add_space(10.0);
+                ui.add_enabled_ui(!is_processing, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("📄 Сохранить отчёт CSV").clicked() {
+                            self.export_report(ReportFormat::Csv);
+                        }
+                        if ui.button("📊 Сохранить отчёт XLSX").clicked() {
+                            self.export_report(ReportFormat::Xlsx);
+                        }
+                        if ui.button("🖼 Сохранить отчёт HTML").clicked() {
+                            self.export_report(ReportFormat::Html);
+                        }
+                        if let Some(path) = self.job_state.last_report.lock().unwrap().as_ref() {
+                            ui.label(format!("Автоотчёт: {}", path.display()));
+                        }
+                    });
+                });
+            });
+        }
+
+        ui.add_space(20.0);
+        ui.horizontal(|ui| {
+            let failed = self.job_state.failed_vendor_codes.lock().unwrap().clone();
+            ui.add_enabled_ui(!failed.is_empty() && !is_processing, |ui| {
+                if ui.button("🔄 Повторить для ошибочных").clicked() {
+                    self.file_names = failed.join("\n");
+                    self.job_state.log(LogEvent::info(
+                        Stage::General,
+                        format!("Повторная обработка vendor codes: {}", failed.join(", ")),
+                    ));
+                }
+            });
+            ui.add_enabled_ui(!failed.is_empty(), |ui| {
+                if ui
+                    .button("📋 Копировать ошибочные")
+                    .on_hover_text("По одному vendor code на строку; с ошибками — через табуляцию, для вставки в Excel")
+                    .clicked()
+                {
+                    self.copy_failed_to_clipboard(&failed);
+                }
+                ui.checkbox(&mut self.copy_failed_with_errors, "с текстом ошибок");
+            });
+        });
+    }
+
+    fn copy_failed_to_clipboard(&mut self, failed: &[String]) {
+        let mut vendor_codes: Vec<&String> = Vec::new();
+        for vendor_code in failed {
+            if !vendor_codes.contains(&vendor_code) {
+                vendor_codes.push(vendor_code);
+            }
+        }
+        let text = if self.copy_failed_with_errors {
+            let results = self.job_state.results.lock().unwrap();
+            vendor_codes
+                .iter()
+                .map(|vendor_code| {
+                    let errors: Vec<String> = results
+                        .iter()
+                        .find(|r| &&r.vendor_code == vendor_code)
+                        .map(|r| r.error.iter().chain(&r.wb_errors).cloned().collect())
+                        .unwrap_or_default();
+                    // Табуляции и переводы строк внутри ошибки сломали бы столбцы
+                    let errors = errors.join("; ").replace(['\t', '\n', '\r'], " ");
+                    format!("{}\t{}", vendor_code, errors)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            vendor_codes.iter().map(|s| s.as_str()).collect::<Vec<_>>().join("\n")
+        };
+        let mut logs = self.job_state.logs.lock().unwrap();
+        match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+            Ok(()) => logs.push(LogEvent::info(
+                Stage::General,
+                format!("Ошибочные vendor codes ({}) скопированы в буфер обмена", vendor_codes.len()),
+            )),
+            Err(e) => logs.push(LogEvent::error(
+                Stage::General,
+                format!("Ошибка копирования ошибочных vendor codes: {}", e),
+            )),
+        }
+    }
+
+    fn vendor_codes(&self) -> Vec<String> {
+        parse_vendor_codes(&self.file_names, &self.settings.vendor_codes).codes
+    }
+
+    fn start_processing(&mut self) {
+        let vendor_codes = self.vendor_codes();
+        // Подтверждение нужно, только если запуск действительно начнётся
+        let api_key = self.profile_manager.current_profile().api_key.clone();
+        if self.settings.confirm_media_replace
+            && !vendor_codes.is_empty()
+            && self.validate_input(&api_key).is_ok()
+        {
+            self.dont_ask_again = false;
+            self.confirm_start = Some(PendingStart::Run(vendor_codes));
+            return;
+        }
+        self.launch(vendor_codes);
+    }
+
+    fn launch(&mut self, vendor_codes: Vec<String>) {
+        self.job_state.results.lock().unwrap().clear();
+        self.run_job(vendor_codes);
+    }
+
+    fn delete_profile(&mut self) {
+        let name = self.profile_manager.current_profile().name.clone();
+        self.profile_manager.delete_profile(self.profile_manager.selected_index);
+        match self.profile_manager.save() {
+            Ok(()) => self.toasts.info(format!("Профиль «{}» удалён", name)),
+            Err(e) => {
+                self.toasts.error("Не удалось сохранить профили");
+                self.job_state.log(LogEvent::error(
+                    Stage::General,
+                    format!("Ошибка сохранения профилей после удаления: {}", e),
+                ));
+            }
+        }
+    }
+
+    // Повторный запуск отдельных vendor codes; остальные строки таблицы результатов сохраняются
+    fn retry_vendor_codes(&mut self, vendor_codes: Vec<String>) {
+        self.job_state.log(LogEvent::info(
+            Stage::General,
+            format!("Повторная обработка vendor codes: {}", vendor_codes.join(", ")),
+        ));
+        self.run_job(vendor_codes);
+    }
+
+    fn run_job(&mut self, vendor_codes: Vec<String>) {
+        let api_key = self.profile_manager.current_profile().api_key.clone();
+        if let Err(message) = self.validate_input(&api_key) {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return;
+        }
+        self.remember_source();
+        job::start(self.current_spec(vendor_codes, true), self.job_state.clone());
+    }
+
+    // Задание по полям вкладки и выбранному профилю
+    fn current_spec(&self, vendor_codes: Vec<String>, confirm_validation: bool) -> JobSpec {
+        let profile = self.profile_manager.current_profile();
+        JobSpec {
+            profile: profile.name.clone(),
+            api_key: profile.api_key.clone(),
+            wb_base_url: profile.wb_base_url.clone(),
+            timeouts: profile.timeouts,
+            vendor_codes,
+            source: self.current_source(),
+            settings: self.settings.clone(),
+            report_dir: self.profile_manager.config.get_reports_dir(),
+            started_at: Local::now(),
+            only_new: false,
+            confirm_validation,
+            force_reupload: self.force_reupload,
+        }
+    }
+
+    // Задание ставится в очередь с текущими полями; поля можно сразу менять под следующее
+    fn enqueue(&mut self) {
+        let vendor_codes = self.vendor_codes();
+        let api_key = self.profile_manager.current_profile().api_key.clone();
+        if let Err(message) = self.validate_input(&api_key) {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return;
+        }
+        if vendor_codes.is_empty() {
+            self.job_state
+                .log(LogEvent::error(Stage::General, "Ошибка: не указаны vendor codes"));
+            return;
+        }
+        self.remember_source();
+        // Подтверждать проверку некому, пока очередь идёт без присмотра
+        queue::add(&self.job_state, self.current_spec(vendor_codes, false), self.enqueue_urgent);
+        self.toasts.info(if self.enqueue_urgent {
+            "Срочное задание добавлено в очередь"
+        } else {
+            "Задание добавлено в очередь"
+        });
+        self.enqueue_urgent = false;
+    }
+
+    fn current_source(&self) -> JobSource {
+        if !self.use_local_path {
+            JobSource::Yandex {
+                public_keys: parse_link_list(&self.urls).links,
+            }
+        } else if !self.dropped_files.is_empty() {
+            JobSource::Files {
+                paths: self.dropped_files.clone(),
+            }
+        } else if !self.single_file_path.is_empty() {
+            JobSource::SingleFile {
+                path: self.single_file_path.clone(),
+            }
+        } else {
+            JobSource::LocalFolder {
+                path: self.local_source_path.clone(),
+            }
+        }
+    }
+
+    // Закреплённые ссылки профиля: выбор добавляет ссылку в список, ниже — форма закрепления
+    fn show_favorites_menu(&mut self, ui: &mut egui::Ui) {
+        let favorites = self.profile_manager.current_profile().favorite_links.clone();
+        let mut remove = None;
+        for (index, favorite) in favorites.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(&favorite.name)
+                    .on_hover_text(mask_password(&favorite.link))
+                    .clicked()
+                {
+                    add_link(&mut self.urls, &favorite.link);
+                    ui.close_menu();
+                }
+                if ui.small_button("🗑").on_hover_text("Открепить").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if favorites.is_empty() {
+            ui.label("Закреплённых ссылок пока нет");
+        }
+        ui.separator();
+        // Без ссылки в форме закрепляется первая ссылка из поля
+        let first_link = parse_link_list(&self.urls).links.into_iter().next().unwrap_or_default();
+        ui.add(egui::TextEdit::singleline(&mut self.new_favorite_name).hint_text("Название, например «Фотостудия май»"));
+        ui.add(egui::TextEdit::singleline(&mut self.new_favorite_link).hint_text(if first_link.is_empty() {
+            "Ссылка на Яндекс.Диск".to_string()
+        } else {
+            mask_password(&first_link)
+        }));
+        let mut pinned = false;
+        if ui.button("📌 Закрепить").clicked() {
+            let link = if self.new_favorite_link.trim().is_empty() {
+                first_link
+            } else {
+                self.new_favorite_link.trim().to_string()
+            };
+            if self.new_favorite_name.trim().is_empty() {
+                self.job_state.log(LogEvent::error(Stage::General, "Ошибка: укажите название ссылки"));
+            } else if !is_valid_public_link(&link) {
+                self.job_state.log(LogEvent::error(Stage::General, "Ошибка: это не ссылка на Яндекс.Диск"));
+            } else {
+                self.profile_manager
+                    .current_profile_mut()
+                    .add_favorite(&self.new_favorite_name, &link);
+                self.new_favorite_name.clear();
+                self.new_favorite_link.clear();
+                pinned = true;
+            }
+        }
+        if let Some(index) = remove {
+            self.profile_manager.current_profile_mut().favorite_links.remove(index);
+        }
+        if (pinned || remove.is_some())
+            && let Err(e) = self.profile_manager.save()
+        {
+            self.job_state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка сохранения профилей: {}", e),
+            ));
+        }
+    }
+
+    // Папка или ссылки запуска попадают в список недавних у профиля
+    fn remember_source(&mut self) {
+        let source = self.current_source();
+        let profile = self.profile_manager.current_profile_mut();
+        match source {
+            JobSource::Yandex { public_keys } => profile.remember_links(&public_keys),
+            JobSource::LocalFolder { path } => profile.remember_folder(&path),
+            _ => return,
+        }
+        if let Err(e) = self.profile_manager.save() {
+            self.job_state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка сохранения профилей: {}", e),
+            ));
+        }
+    }
+
+    fn start_watching(&mut self) {
+        let vendor_codes = self.vendor_codes();
+        let api_key = self.profile_manager.current_profile().api_key.clone();
+        let error = if api_key.is_empty() {
+            Some("API ключ не указан")
+        } else if !Path::new(&self.local_source_path).is_dir() {
+            Some("Локальный путь должен быть директорией")
+        } else if vendor_codes.is_empty() {
+            Some("Укажите vendor codes, файлы которых нужно отслеживать")
+        } else {
+            None
+        };
+        if let Some(message) = error {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return;
+        }
+
+        let profile = self.profile_manager.current_profile().name.clone();
+        let wb_base_url = self.profile_manager.current_profile().wb_base_url.clone();
+        let timeouts = self.profile_manager.current_profile().timeouts;
+        let settings = self.settings.clone();
+        let report_dir = self.profile_manager.config.get_reports_dir();
+        let make_spec = move |vendor_codes, source| JobSpec {
+            profile: profile.clone(),
+            api_key: api_key.clone(),
+            wb_base_url: wb_base_url.clone(),
+            timeouts,
+            vendor_codes,
+            source,
+            settings: settings.clone(),
+            report_dir: report_dir.clone(),
+            started_at: Local::now(),
+            only_new: false,
+            confirm_validation: false,
+            force_reupload: false,
+        };
+        let watcher = Matching::from_settings(&self.settings).and_then(|matching| {
+            FolderWatcher::start(&self.local_source_path, vendor_codes, matching, self.job_state.clone(), make_spec)
+        });
+        match watcher {
+            Ok(watcher) => self.watcher = Some(watcher),
+            Err(e) => self
+                .job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка запуска отслеживания: {}", e))),
+        }
+    }
+
+    fn validate_input(&self, api_key: &str) -> Result<(), &'static str> {
+        self.validate_source()?;
+        if api_key.is_empty() {
+            return Err("API ключ не указан");
+        }
+        Ok(())
+    }
+
+    fn validate_source(&self) -> Result<(), &'static str> {
+        if !self.use_local_path {
+            let parsed = parse_link_list(&self.urls);
+            if !parsed.invalid.is_empty() {
+                return Err("Все ссылки должны быть на Яндекс.Диск");
+            }
+            if parsed.links.is_empty() {
+                return Err("Укажите хотя бы одну ссылку на Яндекс.Диск");
+            }
+        }
+        if self.use_local_path && !self.dropped_files.is_empty() {
+            return Ok(());
+        }
+        if self.use_local_path && !self.single_file_path.is_empty() {
+            if !Path::new(&self.single_file_path).is_file() {
+                return Err("Указанный путь к файлу недействителен");
+            }
+            if !wb_upload_core::utils::is_media_file(&self.single_file_path) {
+                return Err("Указанный файл не является медиафайлом");
+            }
+        }
+        if self.use_local_path && self.single_file_path.is_empty() && !Path::new(&self.local_source_path).is_dir() {
+            return Err("Локальный путь должен быть директорией");
+        }
+        Ok(())
+    }
+
+    fn show_validation_dialog(&mut self, ctx: &egui::Context) {
+        let Some(report) = self.job_state.pending_validation.lock().unwrap().clone() else {
+            return;
+        };
+        let sections = [
+            ("📭 Vendor codes без файлов", report.codes_without_files.clone()),
+            ("❓ Файлы без vendor code", report.unmatched_files.clone()),
+            ("🔍 Vendor codes, не найденные в WB", report.codes_not_in_wb.clone()),
+            (
+                "📦 Слишком большие файлы",
+                report
+                    .oversized_files
+                    .iter()
+                    .map(|(path, size)| format!("{} ({})", path, format_bytes(*size)))
+                    .collect(),
+            ),
+            (
+                "🎬 Несколько видео у карточки",
+                report
+                    .extra_videos
+                    .iter()
+                    .map(|(code, names)| format!("{}: {}", code, names.join(", ")))
+                    .collect(),
+            ),
+            (
+                "🔢 Пропуски в номерах фото",
+                report
+                    .number_gaps
+                    .iter()
+                    .map(|(code, numbers)| format!("{}: {}", code, validation::join_numbers(numbers)))
+                    .collect(),
+            ),
+            (
+                "⚠ Повторы номеров фото",
+                report
+                    .number_conflicts
+                    .iter()
+                    .map(|(code, number, names)| format!("{} №{}: {}", code, number, names.join(", ")))
+                    .collect(),
+            ),
+        ];
+        egui::Window::new("Проверка перед загрузкой")
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                for (title, items) in sections.iter().filter(|(_, items)| !items.is_empty()) {
+                    egui::CollapsingHeader::new(format!("{}: {}", title, items.len()))
+                        .id_salt(*title)
+                        .show(ui, |ui| {
+                            egui::ScrollArea::vertical().id_salt(*title).max_height(150.0).show(ui, |ui| {
+                                for item in items {
+                                    ui.label(item);
+                                }
+                            });
+                        });
+                }
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("▶ Продолжить").clicked() {
+                        self.job_state.decide_validation(ValidationDecision::Continue);
+                    }
+                    if report.has_number_issues()
+                        && ui
+                            .button("🔢 Перенумеровать и продолжить")
+                            .on_hover_text("Номера фото каждого vendor code станут 1, 2, 3… в порядке прежних номеров")
+                            .clicked()
+                    {
+                        self.job_state.decide_validation(ValidationDecision::Renumber);
+                    }
+                    if ui.button("✏ Отменить и исправить").clicked() {
+                        self.job_state.decide_validation(ValidationDecision::Cancel);
+                    }
+                    if ui.button("💾 Экспорт списка").clicked()
+                        && let Some(path) = FileDialog::new()
+                            .add_filter("CSV", &["csv"])
+                            .set_file_name("validation.csv")
+                            .save_file()
+                        && let Err(e) = report.save_csv(&path)
+                    {
+                        self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
+                    }
+                });
+            });
+    }
+
+    fn start_backup(&mut self) {
+        let vendor_codes = self.vendor_codes();
+        let profile = self.profile_manager.current_profile().clone();
+        let error = if profile.api_key.is_empty() {
+            Some("API ключ не указан")
+        } else if vendor_codes.is_empty() {
+            Some("Укажите vendor codes")
+        } else {
+            None
+        };
+        if let Some(message) = error {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return;
+        }
+        if let Some(folder) = FileDialog::new().pick_folder() {
+            job::start_backup(
+                profile,
+                vendor_codes,
+                folder,
+                self.settings.min_free_disk_mb,
+                self.job_state.clone(),
+            );
+        }
+    }
+
+    fn start_card_updates(&mut self) {
+        self.start_table_job(
+            |path| Ok(card_update::load(path)?.into_iter().map(|u| u.vendor_code).collect()),
+            |path| JobSource::CardUpdates { path },
+        );
+    }
+
+    fn start_new_cards(&mut self) {
+        self.start_table_job(
+            |path| Ok(card_create::load(path)?.into_iter().map(|c| c.vendor_code).collect()),
+            |path| JobSource::NewCards { path },
+        );
+    }
+
+    // Запуск по таблице карточек: load возвращает vendor codes из файла
+    fn start_table_job(
+        &mut self,
+        load: impl Fn(&Path) -> anyhow::Result<Vec<String>>,
+        source: impl Fn(String) -> JobSource,
+    ) {
+        let api_key = self.profile_manager.current_profile().api_key.clone();
+        if api_key.is_empty() {
+            self.job_state.log(LogEvent::error(Stage::General, "Ошибка: API ключ не указан"));
+            return;
+        }
+        let Some(path) = FileDialog::new().add_filter("Таблицы", &["csv", "txt", "xlsx", "xls", "ods"]).pick_file() else {
+            return;
+        };
+        let mut vendor_codes = match load(&path) {
+            Ok(vendor_codes) => vendor_codes,
+            Err(e) => {
+                self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
+                return;
+            }
+        };
+        let mut seen = std::collections::HashSet::new();
+        vendor_codes.retain(|code| seen.insert(code.clone()));
+        self.job_state.results.lock().unwrap().clear();
+        job::start(
+            JobSpec {
+                profile: self.profile_manager.current_profile().name.clone(),
+                api_key,
+                wb_base_url: self.profile_manager.current_profile().wb_base_url.clone(),
+                timeouts: self.profile_manager.current_profile().timeouts,
+                vendor_codes,
+                source: source(path.to_string_lossy().to_string()),
+                settings: self.settings.clone(),
+                report_dir: self.profile_manager.config.get_reports_dir(),
+                started_at: Local::now(),
+                only_new: false,
+                confirm_validation: false,
+                force_reupload: false,
+            },
+            self.job_state.clone(),
+        );
+    }
+
+    fn show_undo_dialog(&mut self, ctx: &egui::Context) {
+        if !self.confirm_undo {
+            return;
+        }
+        egui::Window::new("Откат последней загрузки")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Фото карточек последнего запуска профиля {} будут заменены сохранёнными перед загрузкой.",
+                    self.profile_manager.current_profile().name
+                ));
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("↩ Откатить").clicked() {
+                        self.confirm_undo = false;
+                        let profile = self.profile_manager.current_profile();
+                        if profile.api_key.is_empty() {
+                            self.job_state.log(LogEvent::error(Stage::General, "Ошибка: API ключ не указан"));
+                        } else {
+                            job::start_undo(profile.clone(), self.job_state.clone());
+                        }
+                    }
+                    if ui.button("Отмена").clicked() {
+                        self.confirm_undo = false;
+                    }
+                });
+            });
+    }
+
+    fn show_start_dialog(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.confirm_start else {
+            return;
+        };
+        let cards = |count: usize| {
+            if count % 10 == 1 && count % 100 != 11 { "карточки" } else { "карточек" }
+        };
+        let question = match pending {
+            PendingStart::Run(vendor_codes) => format!(
+                "Заменить фото у {} {} профиля «{}»?",
+                vendor_codes.len(),
+                cards(vendor_codes.len()),
+                self.profile_manager.current_profile().name
+            ),
+            PendingStart::Batch(specs) => {
+                let count: usize = specs.iter().map(|spec| spec.vendor_codes.len()).sum();
+                format!(
+                    "Заменить фото у {} {} в профилях {}?",
+                    count,
+                    cards(count),
+                    specs
+                        .iter()
+                        .map(|spec| format!("«{}» ({})", spec.profile, spec.vendor_codes.len()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            PendingStart::Queue => {
+                let jobs: Vec<QueuedJob> = self
+                    .job_state
+                    .queue
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|queued| queued.status == BatchStatus::Pending)
+                    .cloned()
+                    .collect();
+                let count: usize = jobs.iter().map(|queued| queued.spec.vendor_codes.len()).sum();
+                format!(
+                    "Заменить фото у {} {} в заданиях очереди: {}?",
+                    count,
+                    cards(count),
+                    jobs.iter().map(|queued| queued.describe()).collect::<Vec<_>>().join("; ")
+                )
+            }
+        };
+        let replaced = match (pending, self.current_source()) {
+            (PendingStart::Queue, _) => "Фото карточек будут заменены файлами из источника каждого задания.",
+            (_, JobSource::Yandex { .. }) => {
+                "Галерея каждой карточки будет целиком заменена фото по ссылкам Яндекс.Диска."
+            }
+            _ => "Фото с теми же номерами в карточках будут заменены локальными файлами.",
+        };
+        let mut decision = None;
+        egui::Window::new("Подтверждение запуска")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(question).strong());
+                ui.label(replaced);
+                ui.add_space(10.0);
+                ui.checkbox(&mut self.dont_ask_again, "Больше не спрашивать")
+                    .on_hover_text("Подтверждение можно вернуть в «Настройках»");
+                ui.horizontal(|ui| {
+                    if ui.button("🚀 Запустить").clicked() {
+                        decision = Some(true);
+                    }
+                    if ui.button("Отмена").clicked() {
+                        decision = Some(false);
+                    }
+                });
+            });
+        let Some(confirmed) = decision else {
+            return;
+        };
+        let Some(pending) = self.confirm_start.take() else {
+            return;
+        };
+        if !confirmed {
+            return;
+        }
+        if self.dont_ask_again {
+            self.settings.confirm_media_replace = false;
+            self.apply_settings();
+        }
+        match pending {
+            PendingStart::Run(vendor_codes) => self.launch(vendor_codes),
+            PendingStart::Batch(specs) => self.launch_batch(specs),
+            PendingStart::Queue => queue::start(self.job_state.clone()),
+        }
+    }
+
+    fn show_delete_profile_dialog(&mut self, ctx: &egui::Context) {
+        if !self.confirm_delete_profile {
+            return;
+        }
+        egui::Window::new("Удаление профиля")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Удалить профиль «{}»?",
+                        self.profile_manager.current_profile().name
+                    ))
+                    .strong(),
+                );
+                ui.label("API ключ, адрес API, недавние и избранные ссылки профиля будут удалены. История загрузок сохранится.");
+                ui.add_space(10.0);
+                ui.checkbox(&mut self.dont_ask_again, "Больше не спрашивать")
+                    .on_hover_text("Подтверждение можно вернуть в «Настройках»");
+                ui.horizontal(|ui| {
+                    if ui.button("🗑 Удалить").clicked() {
+                        self.confirm_delete_profile = false;
+                        if self.dont_ask_again {
+                            self.settings.confirm_profile_delete = false;
+                            self.apply_settings();
+                        }
+                        self.delete_profile();
+                    }
+                    if ui.button("Отмена").clicked() {
+                        self.confirm_delete_profile = false;
+                    }
+                });
+            });
+    }
+
+    fn show_close_dialog(&mut self, ctx: &egui::Context) {
+        if self.quit_after_stop {
+            self.show_stopping_dialog(ctx);
+            return;
+        }
+        if !self.confirm_close {
+            return;
+        }
+        egui::Window::new("Обработка ещё идёт")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                let progress = self.job_state.progress.lock().unwrap().clone();
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Обработано {} из {} vendor codes. Закрыть окно?",
+                        progress.processed, progress.total
+                    ))
+                    .strong(),
+                );
+                if self.tray.is_available() {
+                    ui.label("Запуск может продолжиться в фоне: окно откроется щелчком по значку в трее.");
+                } else {
+                    ui.label("Запуск может продолжиться в свёрнутом окне.");
+                }
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let hide = if self.tray.is_available() {
+                        "📥 Свернуть в трей"
+                    } else {
+                        "📥 Свернуть окно"
+                    };
+                    if ui.button(hide).clicked() {
+                        self.confirm_close = false;
+                        self.tray.hide_window(ctx);
+                    }
+                    if ui
+                        .button("⏹ Остановить и выйти")
+                        .on_hover_text("Текущий vendor code будет доработан, остальные сохранятся в поле vendor codes")
+                        .clicked()
+                    {
+                        self.stop_and_quit();
+                    }
+                    if ui.button("Отмена").clicked() {
+                        self.confirm_close = false;
+                    }
+                });
+            });
+    }
+
+    fn show_stopping_dialog(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Остановка перед выходом")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                let current = !self.job_state.progress.lock().unwrap().current_files.is_empty();
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Дорабатывается текущий vendor code, после этого окно закроется.");
+                });
+                ui.label("Необработанные vendor codes останутся в поле и будут на месте при следующем запуске.");
+                ui.add_space(10.0);
+                let quit_now = ui
+                    .button("Выйти сразу")
+                    .on_hover_text(if current {
+                        "Загрузка текущего файла оборвётся, vendor code останется необработанным"
+                    } else {
+                        "Текущий vendor code останется необработанным"
+                    })
+                    .clicked();
+                if quit_now {
+                    self.quit(ctx);
+                }
+            });
+    }
+
+    fn show_crash_dialog(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.crash_report.clone() else {
+            return;
+        };
+        egui::Window::new("Прошлый запуск завершился сбоем")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label("Приложение было аварийно закрыто. Отчёт с описанием ошибки и последними событиями журнала сохранён:");
+                ui.monospace(path.display().to_string());
+                ui.label("Приложите его к сообщению об ошибке.");
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button("📄 Открыть отчёт").clicked() {
+                        ctx.open_url(egui::OpenUrl::new_tab(format!("file://{}", path.display())));
+                        self.crash_report = None;
+                    }
+                    if ui.button("Закрыть").clicked() {
+                        self.crash_report = None;
+                    }
+                });
+            });
+    }
+
+    // Показывается после диалога о сбое: оба бывают после одного аварийного закрытия
+    fn show_resume_dialog(&mut self, ctx: &egui::Context) {
+        if self.crash_report.is_some() {
+            return;
+        }
+        let Some(checkpoint) = self.resume.clone() else {
+            return;
+        };
+        let remaining = checkpoint.remaining();
+        egui::Window::new("Прошлый запуск не завершён")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Запуск профиля «{}» от {} был прерван: обработано {} из {} vendor codes.",
+                        checkpoint.profile,
+                        checkpoint.started_at.format("%d.%m.%Y %H:%M"),
+                        checkpoint.vendor_codes.len() - remaining.len(),
+                        checkpoint.vendor_codes.len()
+                    ))
+                    .strong(),
+                );
+                ui.label(checkpoint.source.describe());
+                ui.label(format!("Продолжить с оставшихся {} vendor codes?", remaining.len()));
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let processing = *self.job_state.is_processing.lock().unwrap();
+                    if ui.add_enabled(!processing, egui::Button::new("▶ Продолжить")).clicked() {
+                        self.resume = None;
+                        self.resume_run(checkpoint.clone(), remaining.clone());
+                    }
+                    if ui
+                        .button("📝 В поле vendor codes")
+                        .on_hover_text("Подставить оставшиеся vendor codes и проверить поля перед запуском")
+                        .clicked()
+                    {
+                        self.resume = None;
+                        self.file_names = remaining.join("\n");
+                        self.select_profile(&checkpoint.profile);
+                        checkpoint::clear(&self.profile_manager.config.get_checkpoint_file_path());
+                    }
+                    if ui.button("Отклонить").clicked() {
+                        self.resume = None;
+                        checkpoint::clear(&self.profile_manager.config.get_checkpoint_file_path());
+                    }
+                });
+            });
+    }
+
+    // Прерванный запуск продолжается с тем же профилем и источником, настройки — текущие
+    fn resume_run(&mut self, checkpoint: Checkpoint, vendor_codes: Vec<String>) {
+        let Some(profile) = self
+            .profile_manager
+            .profiles
+            .iter()
+            .find(|profile| profile.name == checkpoint.profile)
+            .cloned()
+        else {
+            self.toasts.error(format!("Профиль «{}» не найден", checkpoint.profile));
+            return;
+        };
+        if profile.api_key.is_empty() {
+            self.toasts.error(format!("API ключ не указан для профиля {}", profile.name));
+            return;
+        }
+        self.select_profile(&profile.name);
+        self.job_state.log(LogEvent::info(
+            Stage::General,
+            format!("Продолжение прерванного запуска: осталось vendor codes: {}", vendor_codes.len()),
+        ));
+        self.job_state.results.lock().unwrap().clear();
+        job::start(
+            JobSpec {
+                profile: profile.name.clone(),
+                api_key: profile.api_key.clone(),
+                wb_base_url: profile.wb_base_url.clone(),
+                timeouts: profile.timeouts,
+                vendor_codes,
+                source: checkpoint.source,
+                settings: self.settings.clone(),
+                report_dir: self.profile_manager.config.get_reports_dir(),
+                started_at: Local::now(),
+                only_new: false,
+                confirm_validation: true,
+                force_reupload: checkpoint.force_reupload,
+            },
+            self.job_state.clone(),
+        );
+    }
+
+    fn select_profile(&mut self, name: &str) {
+        if let Some(index) = self.profile_manager.profiles.iter().position(|p| p.name == name) {
+            self.profile_manager.selected_index = index;
+        }
+    }
+
+    // Уведомления о новых ошибочных vendor codes и завершении запуска; подробности — в логе
+    fn toast_job_events(&mut self) {
+        let processing = *self.job_state.is_processing.lock().unwrap();
+        if processing && !self.was_processing {
+            self.toasted_failures.clear();
+        }
+        let failed: Vec<String> = self
+            .job_state
+            .failed_vendor_codes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|vendor_code| !self.toasted_failures.contains(*vendor_code))
+            .cloned()
+            .collect();
+        // Пачка ошибок сразу (например, WB недоступен) — одно уведомление вместо десятка
+        if failed.len() > 3 {
+            self.toasts.error(format!("Ошибки у {} vendor codes", failed.len()));
+        } else {
+            let results = self.job_state.results.lock().unwrap();
+            for vendor_code in &failed {
+                let error = results
+                    .iter()
+                    .find(|r| &r.vendor_code == vendor_code)
+                    .and_then(|r| r.error.clone())
+                    .unwrap_or_default();
+                let error: String = error.chars().take(120).collect();
+                self.toasts.error(format!("{}: {}", vendor_code, error));
+            }
+        }
+        self.toasted_failures.extend(failed);
+        if self.was_processing && !processing {
+            if self.toasted_failures.is_empty() {
+                self.toasts.success("Обработка завершена");
+            } else {
+                self.toasts.error(format!(
+                    "Обработка завершена, ошибочных vendor codes: {}",
+                    self.toasted_failures.len()
+                ));
+            }
+        }
+        self.was_processing = processing;
+    }
+
+    // Ответ WB, отправленный запрос и сопоставленные файлы одного vendorCode —
+    // то, что в логе перемешано с остальными карточками
+    fn show_item_details(&mut self, ctx: &egui::Context) {
+        let Some(vendor_code) = self.item_details.clone() else {
+            return;
+        };
+        let result = self
+            .job_state
+            .results
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|r| r.vendor_code == vendor_code)
+            .cloned();
+        let Some(result) = result else {
+            self.item_details = None;
+            return;
+        };
+        let mut open = true;
+        egui::Window::new(format!("Подробности: {}", vendor_code))
+            .open(&mut open)
+            .default_size([700.0, 500.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("item_details_summary").num_columns(2).show(ui, |ui| {
+                    ui.label("nmId:");
+                    ui.label(result.nm_id.map_or("—".to_string(), |id| id.to_string()));
+                    ui.end_row();
+                    ui.label("Статус:");
+                    ui.label(result.status.label());
+                    ui.end_row();
+                    if let Some(error) = &result.error {
+                        ui.label("Ошибка:");
+                        ui.colored_label(egui::Color32::from_rgb(255, 120, 120), error);
+                        ui.end_row();
+                    }
+                    if let Some(failure) = &result.failure {
+                        ui.label("Тип ошибки:");
+                        ui.label(failure.label());
+                        ui.end_row();
+                    }
+                    if !result.wb_errors.is_empty() {
+                        ui.label("Ошибки WB:");
+                        ui.label(result.wb_errors.join("; "));
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::CollapsingHeader::new(format!("Файлы ({})", result.files.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            if result.files.is_empty() {
+                                ui.label("Файлы для vendorCode не найдены");
+                            }
+                            for file in &result.files {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("№{}", file.photo_number));
+                                    ui.monospace(&file.path);
+                                });
+                            }
+                        });
+                    egui::CollapsingHeader::new(format!("Запросы к WB ({})", result.exchanges.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            if result.exchanges.is_empty() {
+                                ui.label("Запросов к WB по vendorCode не было");
+                            }
+                            for (i, exchange) in result.exchanges.iter().enumerate() {
+                                let failed = exchange.status.is_none_or(|status| status >= 400);
+                                let status = exchange
+                                    .status
+                                    .map_or("нет ответа".to_string(), |status| status.to_string());
+                                let title = format!(
+                                    "{} {} → {}",
+                                    exchange.timestamp.format("%H:%M:%S"),
+                                    exchange.request.lines().next().unwrap_or_default(),
+                                    status
+                                );
+                                let title = if failed {
+                                    egui::RichText::new(title).color(egui::Color32::from_rgb(255, 120, 120))
+                                } else {
+                                    egui::RichText::new(title)
+                                };
+                                egui::CollapsingHeader::new(title)
+                                    .id_salt(("item_exchange", i))
+                                    .default_open(failed)
+                                    .show(ui, |ui| {
+                                        ui.horizontal(|ui| {
+                                            ui.strong("Запрос");
+                                            if ui.small_button("📋").on_hover_text("Копировать").clicked() {
+                                                ui.ctx().copy_text(exchange.request.clone());
+                                            }
+                                        });
+                                        ui.monospace(&exchange.request);
+                                        ui.horizontal(|ui| {
+                                            ui.strong("Ответ");
+                                            if ui.small_button("📋").on_hover_text("Копировать").clicked() {
+                                                ui.ctx().copy_text(exchange.response.clone());
+                                            }
+                                        });
+                                        ui.monospace(&exchange.response);
+                                    });
+                            }
+                        });
+                });
+            });
+        if !open {
+            self.item_details = None;
+        }
+    }
+
+    fn start_preview(&mut self) {
+        let vendor_codes = self.vendor_codes();
+        let error = match self.validate_source() {
+            Err(message) => Some(message.to_string()),
+            Ok(()) if vendor_codes.is_empty() => Some("Укажите vendor codes".to_string()),
+            Ok(()) => None,
+        };
+        if let Some(message) = error {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return;
+        }
+        match Matching::from_settings(&self.settings) {
+            Ok(matching) => preview::spawn(
+                self.current_source(),
+                vendor_codes,
+                matching,
+                self.job_state.scan_cache(&self.settings),
+                self.profile_manager.current_profile().timeouts,
+                self.preview.clone(),
+            ),
+            Err(e) => self.job_state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка настройки сопоставления файлов: {}", e),
+            )),
+        }
+    }
+
+    fn show_preview(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let running = *self.preview.running.lock().unwrap();
+        let items = self.preview.items.lock().unwrap().clone();
+        let error = self.preview.error.lock().unwrap().clone();
+        if !running && items.is_empty() && error.is_none() {
+            return;
+        }
+        if running {
+            ctx.request_repaint_after(std::time::Duration::from_millis(300));
+        }
+        ui.add_space(10.0);
+        egui::CollapsingHeader::new(egui::RichText::new("🖼 Предпросмотр файлов").strong())
+            .id_salt("preview")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if running {
+                        ui.spinner();
+                        ui.label(format!("Поиск файлов и загрузка миниатюр… ({})", items.len()));
+                    } else {
+                        ui.label(format!("Найдено файлов: {}", items.len()));
+                    }
+                    if !running && ui.button("✖ Закрыть").clicked() {
+                        self.preview.items.lock().unwrap().clear();
+                        *self.preview.error.lock().unwrap() = None;
+                    }
+                });
+                if let Some(error) = &error {
+                    ui.colored_label(egui::Color32::RED, format!("Ошибка: {}", error));
+                }
+                let mut groups: BTreeMap<&str, Vec<&PreviewItem>> = BTreeMap::new();
+                for item in &items {
+                    groups.entry(item.file.articul.as_str()).or_default().push(item);
+                }
+                let missing: Vec<String> = self
+                    .vendor_codes()
+                    .into_iter()
+                    .filter(|code| !running && error.is_none() && !groups.contains_key(code.as_str()))
+                    .collect();
+                if !missing.is_empty() {
+                    ui.colored_label(egui::Color32::YELLOW, format!("Нет файлов для: {}", missing.join(", ")));
+                }
+                let size = egui::vec2(preview::THUMBNAIL_SIZE as f32, preview::THUMBNAIL_SIZE as f32);
+                egui::ScrollArea::vertical().id_salt("preview_scroll").max_height(400.0).show(ui, |ui| {
+                    for (vendor_code, files) in groups {
+                        ui.label(egui::RichText::new(format!("{} — файлов: {}", vendor_code, files.len())).strong());
+                        ui.horizontal_wrapped(|ui| {
+                            for item in files {
+                                let hover = format!("{}\nФото {}", item.file.path, item.file.photo_number);
+                                ui.vertical(|ui| {
+                                    match &item.thumbnail {
+                                        Some(bytes) => {
+                                            ui.add(
+                                                egui::Image::from_bytes(format!("bytes://preview{}", item.file.path), bytes.clone())
+                                                    .fit_to_exact_size(size),
+                                            )
+                                            .on_hover_text(&hover);
+                                        }
+                                        None => {
+                                            let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+                                            ui.painter().rect_filled(rect, 4.0, egui::Color32::from_gray(90));
+                                            let text = if item.error.is_some() { "🎞" } else { "…" };
+                                            ui.painter().text(
+                                                rect.center(),
+                                                egui::Align2::CENTER_CENTER,
+                                                text,
+                                                egui::FontId::proportional(24.0),
+                                                egui::Color32::WHITE,
+                                            );
+                                            response.on_hover_text(match &item.error {
+                                                Some(e) => format!("{}\n{}", hover, e),
+                                                None => hover.clone(),
+                                            });
+                                        }
+                                    }
+                                    ui.label(format!("№{}", item.file.photo_number));
+                                });
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+            });
+    }
+
+    fn start_compare(&mut self) {
+        let vendor_codes = self.vendor_codes();
+        let profile = self.profile_manager.current_profile().clone();
+        let error = match self.validate_input(&profile.api_key) {
+            Err(message) => Some(message.to_string()),
+            Ok(()) if vendor_codes.is_empty() => Some("Укажите vendor codes".to_string()),
+            Ok(()) => None,
+        };
+        if let Some(message) = error {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return;
+        }
+        match Matching::from_settings(&self.settings) {
+            Ok(matching) => compare::spawn(
+                self.current_source(),
+                vendor_codes,
+                matching,
+                self.job_state.scan_cache(&self.settings),
+                profile,
+                self.settings.download_yandex_files,
+                self.compare.clone(),
+            ),
+            Err(e) => self.job_state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка настройки сопоставления файлов: {}", e),
+            )),
+        }
+    }
+
+    fn show_compare(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let running = *self.compare.running.lock().unwrap();
+        let rows = self.compare.rows.lock().unwrap().clone();
+        let error = self.compare.error.lock().unwrap().clone();
+        if !running && rows.is_empty() && error.is_none() {
+            return;
+        }
+        if running {
+            ctx.request_repaint_after(std::time::Duration::from_millis(300));
+        }
+        ui.add_space(10.0);
+        egui::CollapsingHeader::new(egui::RichText::new("⚖ Сравнение источника и WB").strong())
+            .id_salt("compare")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    if running {
+                        ui.spinner();
+                        ui.label(format!("Сканирование источника и запрос карточек… ({})", rows.len()));
+                    } else {
+                        let losing = rows.iter().filter(|r| r.change().is_some_and(|c| c < 0)).count();
+                        let gaining = rows.iter().filter(|r| r.change().is_some_and(|c| c > 0)).count();
+                        ui.label(format!("Карточек: {}, получат фото: {}, потеряют фото: {}", rows.len(), gaining, losing));
+                    }
+                    if !running && ui.button("✖ Закрыть").clicked() {
+                        self.compare.rows.lock().unwrap().clear();
+                        *self.compare.error.lock().unwrap() = None;
+                    }
+                });
+                if let Some(error) = &error {
+                    ui.colored_label(egui::Color32::RED, format!("Ошибка: {}", error));
+                }
+                egui::ScrollArea::vertical().id_salt("compare_scroll").max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("compare_grid").striped(true).num_columns(6).show(ui, |ui| {
+                        for title in ["Vendor code", "nmId", "В источнике", "На WB", "После загрузки", "Изменение"] {
+                            ui.label(egui::RichText::new(title).strong());
+                        }
+                        ui.end_row();
+                        for row in &rows {
+                            ui.label(&row.vendor_code);
+                            ui.label(row.nm_id.map(|id| id.to_string()).unwrap_or_default());
+                            ui.label(row.source.to_string());
+                            ui.label(row.wb.map(|n| n.to_string()).unwrap_or_default());
+                            ui.label(row.after.map(|n| n.to_string()).unwrap_or_default());
+                            match (row.change(), &row.error) {
+                                (_, Some(error)) => {
+                                    ui.colored_label(egui::Color32::RED, "ошибка").on_hover_text(error);
+                                }
+                                (Some(change), _) if change > 0 => {
+                                    ui.colored_label(egui::Color32::GREEN, format!("+{}", change));
+                                }
+                                (Some(change), _) if change < 0 => {
+                                    ui.colored_label(egui::Color32::RED, change.to_string());
+                                }
+                                _ => {
+                                    ui.label("без изменений");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+
+    fn show_queue(&mut self, ui: &mut egui::Ui, is_processing: bool) {
+        let jobs = self.job_state.queue.lock().unwrap().clone();
+        ui.add_space(10.0);
+        egui::CollapsingHeader::new(egui::RichText::new(format!("📋 Очередь заданий ({})", jobs.len())).strong())
+            .id_salt("queue")
+            .show(ui, |ui| {
+                if jobs.is_empty() {
+                    ui.label("Заполните поля и нажмите «➕ В очередь», чтобы подготовить задание; затем поля можно менять под следующее.");
+                }
+                let pending: Vec<usize> = jobs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, queued)| queued.status == BatchStatus::Pending)
+                    .map(|(index, _)| index)
+                    .collect();
+                // Ожидающие задания перетаскиваются за «☰» на место другого ожидающего
+                let mut dropped = None;
+                for (index, queued) in jobs.iter().enumerate() {
+                    let is_pending = queued.status == BatchStatus::Pending;
+                    let row = ui.horizontal(|ui| {
+                        if is_pending {
+                            ui.dnd_drag_source(egui::Id::new(("queue_job", queued.id)), queued.id, |ui| {
+                                ui.label("☰");
+                            })
+                            .response
+                            .on_hover_text("Перетащите, чтобы изменить порядок");
+                        } else {
+                            ui.label(" ");
+                        }
+                        ui.label(format!("{}.", index + 1));
+                        if queued.urgent {
+                            ui.colored_label(egui::Color32::from_rgb(255, 170, 60), "⚡");
+                        }
+                        ui.label(queued.describe());
+                        ui.label(queued.status_label());
+                        if !is_pending {
+                            return;
+                        }
+                        let position = pending.iter().position(|i| *i == index).unwrap_or_default();
+                        if ui.add_enabled(position > 0, egui::Button::new("⬆")).on_hover_text("Выше").clicked() {
+                            queue::move_job(&self.job_state, queued.id, pending[position - 1]);
+                        }
+                        if ui
+                            .add_enabled(position + 1 < pending.len(), egui::Button::new("⬇"))
+                            .on_hover_text("Ниже")
+                            .clicked()
+                        {
+                            queue::move_job(&self.job_state, queued.id, pending[position + 1]);
+                        }
+                        if ui
+                            .selectable_label(queued.urgent, "⚡")
+                            .on_hover_text("Срочно: выполнить раньше остальных")
+                            .clicked()
+                        {
+                            queue::set_urgent(&self.job_state, queued.id, !queued.urgent);
+                        }
+                        if ui.button("✖").on_hover_text("Убрать из очереди").clicked() {
+                            queue::remove(&self.job_state, queued.id);
+                        }
+                    });
+                    if is_pending && row.response.dnd_hover_payload::<u64>().is_some() {
+                        let stroke = ui.visuals().selection.stroke;
+                        ui.painter().hline(row.response.rect.x_range(), row.response.rect.top(), stroke);
+                    }
+                    if let Some(id) = row.response.dnd_release_payload::<u64>() {
+                        dropped = Some((*id, index));
+                    }
+                }
+                if let Some((id, index)) = dropped {
+                    queue::move_job(&self.job_state, id, index);
+                }
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(!is_processing && !pending.is_empty(), |ui| {
+                        if ui.add(egui::Button::new("▶ Запустить очередь").rounding(8.0)).clicked() {
+                            if self.settings.confirm_media_replace {
+                                self.dont_ask_again = false;
+                                self.confirm_start = Some(PendingStart::Queue);
+                            } else {
+                                queue::start(self.job_state.clone());
+                            }
+                        }
+                    });
+                    if ui.button("🧹 Убрать завершённые").clicked() {
+                        queue::clear_finished(&self.job_state);
+                    }
+                });
+            });
+    }
+
+    // Источник файлов у пакета общий — выбранный выше; у каждого профиля свои vendor codes
+    fn show_batch(&mut self, ui: &mut egui::Ui, is_processing: bool) {
+        ui.add_space(10.0);
+        egui::CollapsingHeader::new(egui::RichText::new("👥 Пакет профилей").strong())
+            .id_salt("batch")
+            .show(ui, |ui| {
+                ui.label("Отметьте профили и укажите для каждого свои vendor codes. Файлы берутся из источника, выбранного выше.");
+                let names: Vec<String> = self
+                    .profile_manager
+                    .profiles
+                    .iter()
+                    .map(|profile| profile.name.clone())
+                    .collect();
+                for name in names {
+                    let mut selected = self.batch_selected.contains(&name);
+                    if ui.checkbox(&mut selected, &name).changed() {
+                        if selected {
+                            self.batch_selected.insert(name.clone());
+                        } else {
+                            self.batch_selected.remove(&name);
+                        }
+                    }
+                    if selected {
+                        let text = self.batch_codes.entry(name).or_default();
+                        ui.add(
+                            egui::TextEdit::multiline(text)
+                                .desired_rows(3)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("vendor codes, по одному в строке"),
+                        );
+                    }
+                }
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.batch_mode, BatchMode::Sequential, "По очереди");
+                    ui.radio_value(&mut self.batch_mode, BatchMode::Parallel, "Параллельно")
+                        .on_hover_text("Профили обрабатываются одновременно: у каждого продавца свои лимиты WB");
+                    ui.add_enabled_ui(!is_processing && !self.batch_selected.is_empty(), |ui| {
+                        if ui.add(egui::Button::new("🚀 Запустить пакет").rounding(8.0)).clicked() {
+                            self.start_batch();
+                        }
+                    });
+                });
+                let items = self.job_state.batch.lock().unwrap().clone();
+                if !items.is_empty() {
+                    ui.add_space(5.0);
+                    egui::Grid::new("batch_status").striped(true).show(ui, |ui| {
+                        ui.strong("Профиль");
+                        ui.strong("Vendor codes");
+                        ui.strong("Статус");
+                        ui.end_row();
+                        for item in &items {
+                            ui.label(&item.profile);
+                            ui.label(item.vendor_codes.to_string());
+                            ui.label(item.status.label());
+                            ui.end_row();
+                        }
+                    });
+                }
+            });
+    }
+
+    fn start_batch(&mut self) {
+        let Some(specs) = self.batch_specs() else {
+            return;
+        };
+        if self.settings.confirm_media_replace {
+            self.dont_ask_again = false;
+            self.confirm_start = Some(PendingStart::Batch(specs));
+            return;
+        }
+        self.launch_batch(specs);
+    }
+
+    fn launch_batch(&mut self, specs: Vec<JobSpec>) {
+        self.remember_source();
+        batch::start(specs, self.batch_mode, self.job_state.clone());
+    }
+
+    // Задания пакета по отмеченным профилям; None — ошибка уже записана в журнал
+    fn batch_specs(&self) -> Option<Vec<JobSpec>> {
+        if let Err(message) = self.validate_source() {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return None;
+        }
+        let mut specs = Vec::new();
+        for profile in &self.profile_manager.profiles {
+            if !self.batch_selected.contains(&profile.name) {
+                continue;
+            }
+            let text = self.batch_codes.get(&profile.name).map(String::as_str).unwrap_or_default();
+            let vendor_codes = parse_vendor_codes(text, &self.settings.vendor_codes).codes;
+            let error = if profile.api_key.is_empty() {
+                Some("API ключ не указан")
+            } else if vendor_codes.is_empty() {
+                Some("не указаны vendor codes")
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                self.job_state.log(LogEvent::error(
+                    Stage::General,
+                    format!("Ошибка: профиль {}: {}", profile.name, error),
+                ));
+                return None;
+            }
+            specs.push(JobSpec {
+                profile: profile.name.clone(),
+                api_key: profile.api_key.clone(),
+                wb_base_url: profile.wb_base_url.clone(),
+                timeouts: profile.timeouts,
+                vendor_codes,
+                source: self.current_source(),
+                settings: self.settings.clone(),
+                report_dir: self.profile_manager.config.get_reports_dir(),
+                started_at: Local::now(),
+                only_new: false,
+                // Проверку перед загрузкой некому подтверждать, пока профили идут параллельно
+                confirm_validation: false,
+                force_reupload: self.force_reupload,
+            });
+        }
+        Some(specs)
+    }
+
+    fn show_audit(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+        let running = *self.audit.running.lock().unwrap();
+        if running {
+            ctx.request_repaint_after(std::time::Duration::from_millis(300));
+        }
+        ui.add_space(10.0);
+        egui::CollapsingHeader::new(egui::RichText::new("🔎 Аудит карточек WB").strong())
+            .id_salt("audit")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Карточки, у которых фото меньше:");
+                    ui.add(egui::DragValue::new(&mut self.audit_min_photos).range(1..=30));
+                    ui.add_enabled_ui(!running, |ui| {
+                        if ui.button("🔎 Найти карточки").clicked() {
+                            let profile = self.profile_manager.current_profile().clone();
+                            if profile.api_key.is_empty() {
+                                self.job_state.log(LogEvent::error(Stage::General, "Ошибка: API ключ не указан"));
+                            } else {
+                                audit::spawn(profile, self.audit_min_photos, self.audit.clone());
+                            }
+                        }
+                    });
+                });
+                let cards = self.audit.cards.lock().unwrap().clone();
+                if running {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Получено карточек каталога: {}", self.audit.fetched.lock().unwrap()));
+                    });
+                } else if let Some(error) = self.audit.error.lock().unwrap().clone() {
+                    ui.colored_label(egui::Color32::RED, format!("Ошибка: {}", error));
+                } else if *self.audit.fetched.lock().unwrap() > 0 {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Найдено карточек: {}", cards.len()));
+                        if !cards.is_empty() && ui.button("📋 В список vendor codes").clicked() {
+                            let added = self.add_vendor_codes(cards.iter().map(|c| c.vendor_code.clone()).collect());
+                            self.job_state.log(LogEvent::info(
+                                Stage::General,
+                                format!("Добавлено vendor codes из аудита: {}", added.len()),
+                            ));
+                        }
+                        if !cards.is_empty()
+                            && ui.button("💾 Экспорт CSV").clicked()
+                            && let Some(path) = FileDialog::new()
+                                .add_filter("CSV", &["csv"])
+                                .set_file_name("cards_without_photos.csv")
+                                .save_file()
+                            && let Err(e) = audit::save_csv(&path, &cards)
+                        {
+                            self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
+                        }
+                    });
+                    egui::ScrollArea::vertical().id_salt("audit_scroll").max_height(200.0).show(ui, |ui| {
+                        egui::Grid::new("audit_grid").striped(true).num_columns(3).show(ui, |ui| {
+                            for title in ["Vendor code", "nmId", "Фото"] {
+                                ui.label(egui::RichText::new(title).strong());
+                            }
+                            ui.end_row();
+                            for card in &cards {
+                                ui.label(&card.vendor_code);
+                                ui.label(card.nm_id.to_string());
+                                ui.label(card.photos.to_string());
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+            });
+    }
+
+    fn show_results_table(&mut self, ui: &mut egui::Ui, is_processing: bool) {
+        let mut results = self.job_state.results.lock().unwrap().clone();
+        sort_results(&mut results, self.results_sort, self.results_sort_ascending);
+        // В таблице пакета строки разных профилей; повтор запустился бы с выбранным профилем
+        let batch_run = !self.job_state.batch.lock().unwrap().is_empty();
+        let mut retry = None;
+        let columns = [
+            (ResultColumn::VendorCode, "vendorCode"),
+            (ResultColumn::NmId, "nmId"),
+            (ResultColumn::FilesFound, "Найдено"),
+            (ResultColumn::FilesUploaded, "Загружено"),
+            (ResultColumn::Status, "Статус"),
+            (ResultColumn::Error, "Ошибка"),
+        ];
+        TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .max_scroll_height(300.0)
+            .column(Column::auto().at_least(120.0))
+            .column(Column::auto().at_least(80.0))
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::remainder().at_least(150.0).clip(true))
+            .column(Column::auto())
+            .header(24.0, |mut header| {
+                for (column, title) in columns {
+                    header.col(|ui| {
+                        let arrow = match (self.results_sort == column, self.results_sort_ascending) {
+                            (true, true) => " ⏶",
+                            (true, false) => " ⏷",
+                            _ => "",
+                        };
+                        if ui.button(egui::RichText::new(format!("{}{}", title, arrow)).strong()).clicked() {
+                            if self.results_sort == column {
+                                self.results_sort_ascending = !self.results_sort_ascending;
+                            } else {
+                                self.results_sort = column;
+                                self.results_sort_ascending = true;
+                            }
+                        }
+                    });
+                }
+                header.col(|_| {});
+            })
+            .body(|mut body| {
+                for result in &results {
+                    body.row(22.0, |mut row| {
+                        row.col(|ui| {
+                            if result.status == ItemStatus::Failed {
+                                if ui.link(&result.vendor_code).on_hover_text("Подробности ошибки").clicked() {
+                                    self.item_details = Some(result.vendor_code.clone());
+                                }
+                            } else {
+                                ui.label(&result.vendor_code);
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(result.nm_id.map_or("—".to_string(), |id| id.to_string()));
+                        });
+                        row.col(|ui| {
+                            ui.label(result.files_found().to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(result.files_uploaded.to_string());
+                        });
+                        row.col(|ui| {
+                            let color = match result.status {
+                                ItemStatus::Success => egui::Color32::from_rgb(120, 220, 120),
+                                ItemStatus::Failed => egui::Color32::from_rgb(255, 120, 120),
+                                ItemStatus::InProgress => egui::Color32::from_rgb(255, 200, 80),
+                            };
+                            ui.label(egui::RichText::new(result.status.label()).color(color));
+                        });
+                        row.col(|ui| {
+                            let error = result.error.as_deref().unwrap_or("");
+                            if error.is_empty() && !result.wb_errors.is_empty() {
+                                let wb_errors = format!("WB: {}", result.wb_errors.join("; "));
+                                ui.colored_label(egui::Color32::from_rgb(255, 200, 80), &wb_errors).on_hover_text(&wb_errors);
+                            } else {
+                                ui.label(error).on_hover_text(error);
+                            }
+                        });
+                        row.col(|ui| {
+                            if result.status == ItemStatus::Failed
+                                && ui.add_enabled(!is_processing && !batch_run, egui::Button::new("🔄")).on_hover_text("Повторить").clicked()
+                            {
+                                retry = Some(result.vendor_code.clone());
+                            }
+                        });
+                    });
                 }
+            });
+        if let Some(vendor_code) = retry {
+            self.retry_vendor_codes(vec![vendor_code]);
+        }
+    }
+
+    fn refresh_history(&mut self) {
+        let Some(history) = &self.job_state.history else {
+            return;
+        };
+        let history = history.lock().unwrap();
+        match history.profiles() {
+            Ok(profiles) => self.history_profiles = profiles,
+            Err(e) => log::error!("Ошибка чтения профилей из истории: {}", e),
+        }
+        match history.query(&self.history_filter) {
+            Ok(entries) => self.history_entries = entries,
+            Err(e) => log::error!("Ошибка чтения истории: {}", e),
+        }
+    }
+
+    fn show_history_tab(&mut self, ui: &mut egui::Ui) {
+        if self.job_state.history.is_none() {
+            ui.label("История загрузок недоступна: не удалось открыть базу данных.");
+            return;
+        }
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Профиль")
+                .selected_text(self.history_filter.profile.as_deref().unwrap_or("Все"))
+                .width(160.0)
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut self.history_filter.profile, None, "Все").clicked();
+                    for profile in &self.history_profiles {
+                        changed |= ui
+                            .selectable_value(&mut self.history_filter.profile, Some(profile.clone()), profile)
+                            .clicked();
+                    }
+                });
+            ui.label("vendorCode:");
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut self.history_filter.vendor_code).desired_width(120.0))
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            for (label, text, date) in [
+                ("С:", &mut self.history_from, &mut self.history_filter.from),
+                ("По:", &mut self.history_to, &mut self.history_filter.to),
+            ] {
+                ui.label(label);
+                let response = ui.add(egui::TextEdit::singleline(text).hint_text("ГГГГ-ММ-ДД").desired_width(90.0));
+                let parsed = NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok();
+                if !text.trim().is_empty() && parsed.is_none() {
+                    ui.colored_label(egui::Color32::from_rgb(255, 120, 120), "неверная дата");
+                }
+                if response.changed() && (parsed.is_some() || text.trim().is_empty()) {
+                    *date = parsed;
+                    changed = true;
+                }
+            }
+            if ui.button("🔄 Обновить").clicked() {
+                changed = true;
+            }
+        });
+        if changed {
+            self.refresh_history();
+        }
+        ui.add_space(10.0);
+
+        let failed: Vec<String> = self
+            .history_entries
+            .iter()
+            .filter(|e| !e.success)
+            .map(|e| e.vendor_code.clone())
+            .collect();
+        ui.horizontal(|ui| {
+            ui.label(format!("Записей: {}", self.history_entries.len()));
+            if ui
+                .add_enabled(!failed.is_empty(), egui::Button::new("📥 В очередь все ошибочные"))
+                .clicked()
+            {
+                self.requeue_vendor_codes(failed, None);
+            }
+        });
+
+        let mut requeue = None;
+        TableBuilder::new(ui)
+            .id_salt("history_table")
+            .striped(true)
+            .resizable(true)
+            .max_scroll_height(500.0)
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto())
+            .column(Column::auto().at_least(100.0))
+            .column(Column::auto())
+            .column(Column::auto().at_least(120.0).clip(true))
+            .column(Column::auto())
+            .column(Column::remainder().at_least(150.0).clip(true))
+            .column(Column::auto())
+            .header(24.0, |mut header| {
+                for title in ["Время", "Запуск", "Профиль", "vendorCode", "nmId", "Файл", "Результат", "Ошибка"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+                header.col(|_| {});
+            })
+            .body(|mut body| {
+                for entry in &self.history_entries {
+                    body.row(22.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(entry.timestamp.format("%Y-%m-%d %H:%M:%S").to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(format!("#{} от {}", entry.run_id, entry.run_started_at.format("%d.%m %H:%M")));
+                        });
+                        row.col(|ui| {
+                            ui.label(&entry.profile);
+                        });
+                        row.col(|ui| {
+                            ui.label(&entry.vendor_code);
+                        });
+                        row.col(|ui| {
+                            ui.label(entry.nm_id.map_or("—".to_string(), |id| id.to_string()));
+                        });
+                        row.col(|ui| {
+                            let file = match (&entry.file, entry.photo_number) {
+                                (Some(file), Some(number)) => format!("{} (№{})", file, number),
+                                (Some(file), None) => file.clone(),
+                                (None, _) => "—".to_string(),
+                            };
+                            ui.label(&file).on_hover_text(&file);
+                        });
+                        row.col(|ui| {
+                            if entry.success {
+                                ui.colored_label(egui::Color32::from_rgb(120, 220, 120), ItemStatus::Success.label());
+                            } else {
+                                ui.colored_label(egui::Color32::from_rgb(255, 120, 120), ItemStatus::Failed.label());
+                            }
+                        });
+                        row.col(|ui| {
+                            let error = entry.error.as_deref().unwrap_or("");
+                            ui.label(error).on_hover_text(error);
+                        });
+                        row.col(|ui| {
+                            if ui.button("📥").on_hover_text("Добавить в очередь").clicked() {
+                                requeue = Some((entry.vendor_code.clone(), entry.profile.clone()));
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some((vendor_code, profile)) = requeue {
+            self.requeue_vendor_codes(vec![vendor_code], Some(&profile));
+        }
+    }
+
+    fn refresh_stats(&mut self) {
+        let Some(history) = &self.job_state.history else {
+            return;
+        };
+        match history.lock().unwrap().stats(Local::now() - ChronoDuration::days(7)) {
+            Ok(stats) => self.history_stats = stats,
+            Err(e) => log::error!("Ошибка расчёта статистики: {}", e),
+        }
+    }
+
+    fn show_statistics_tab(&mut self, ui: &mut egui::Ui) {
+        if self.job_state.history.is_none() {
+            ui.label("Статистика недоступна: не удалось открыть базу данных.");
+            return;
+        }
+        if ui.button("🔄 Обновить").clicked() {
+            self.refresh_stats();
+        }
+        if self.history_stats.is_empty() {
+            ui.label("В истории пока нет данных.");
+            return;
+        }
+        for stats in &self.history_stats {
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label(egui::RichText::new(format!("👤 {}", stats.profile)).strong().size(20.0));
+                egui::Grid::new(("stats", &stats.profile)).num_columns(2).striped(true).show(ui, |ui| {
+                    ui.label("Загружено файлов за 7 дней:");
+                    ui.label(stats.uploads_since.to_string());
+                    ui.end_row();
+                    ui.label("Обработано карточек:");
+                    ui.label(stats.cards.to_string());
+                    ui.end_row();
+                    ui.label("Доля ошибок:");
+                    ui.label(stats.failure_rate().map_or("—".to_string(), |rate| {
+                        format!("{:.1}% ({} из {})", rate * 100.0, stats.failed_cards, stats.cards)
+                    }));
+                    ui.end_row();
+                    ui.label("Среднее время на карточку:");
+                    ui.label(stats.average_card_time.map_or("—".to_string(), |d| {
+                        if d.as_secs() == 0 {
+                            format!("{:.1} сек", d.as_secs_f64())
+                        } else {
+                            format_duration(d.as_secs())
+                        }
+                    }));
+                    ui.end_row();
+                });
+                if !stats.top_errors.is_empty() {
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new("Частые ошибки:").strong());
+                    for (error, count) in &stats.top_errors {
+                        ui.label(format!("{} × {}", count, error));
+                    }
+                }
+            });
+        }
+    }
+
+    fn show_cards_tab(&mut self, ui: &mut egui::Ui) {
+        let running = *self.card_search.running.lock().unwrap();
+        let mut search = false;
+        ui.horizontal(|ui| {
+            ui.label("Поиск:");
+            search |= ui
+                .add(
+                    egui::TextEdit::singleline(&mut self.card_query.text)
+                        .hint_text("vendorCode, nmId или название")
+                        .desired_width(220.0),
+                )
+                .lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.label("Бренд:");
+            ui.add(egui::TextEdit::singleline(&mut self.card_brand).hint_text("через запятую").desired_width(150.0));
+            let photo_label = match self.card_query.with_photo {
+                0 => "Без фото",
+                1 => "С фото",
+                _ => "Все",
+            };
+            egui::ComboBox::from_id_salt("card_with_photo")
+                .selected_text(photo_label)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.card_query.with_photo, -1, "Все");
+                    ui.selectable_value(&mut self.card_query.with_photo, 1, "С фото");
+                    ui.selectable_value(&mut self.card_query.with_photo, 0, "Без фото");
+                });
+            search |= ui.add_enabled(!running, egui::Button::new("🔍 Найти")).clicked();
+        });
+        if search && !running {
+            let profile = self.profile_manager.current_profile().clone();
+            if profile.api_key.is_empty() {
+                self.job_state.log(LogEvent::error(Stage::General, "Ошибка: API ключ не указан"));
+            } else {
+                self.card_query.brands = self
+                    .card_brand
+                    .split(',')
+                    .map(|b| b.trim().to_string())
+                    .filter(|b| !b.is_empty())
+                    .collect();
+                cards::spawn(profile, self.card_query.clone(), self.card_search.clone());
+            }
+        }
+        if running {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Поиск карточек…");
+            });
+            return;
+        }
+        if let Some(error) = self.card_search.error.lock().unwrap().clone() {
+            ui.colored_label(egui::Color32::RED, format!("Ошибка: {}", error));
+            return;
+        }
+        let cards = self.card_search.cards.lock().unwrap().clone();
+        let mut selected = self.card_search.selected.lock().unwrap().clone();
+        ui.add_space(10.0);
+        let mut push = false;
+        ui.horizontal(|ui| {
+            ui.label(format!("Найдено карточек: {}", cards.len()));
+            if cards.len() >= cards::SEARCH_LIMIT {
+                ui.label(format!("(показаны первые {}, уточните поиск)", cards::SEARCH_LIMIT));
+            }
+            if ui.button("☑ Выбрать все").clicked() {
+                selected = cards.iter().map(|c| c.nm_id).collect();
+            }
+            if ui.button("☐ Снять выбор").clicked() {
+                selected.clear();
+            }
+            push = ui
+                .add_enabled(
+                    !selected.is_empty(),
+                    egui::Button::new(format!("📥 В список vendor codes ({})", selected.len())),
+                )
+                .clicked();
+        });
+        TableBuilder::new(ui)
+            .id_salt("cards_table")
+            .striped(true)
+            .resizable(true)
+            .max_scroll_height(500.0)
+            .column(Column::auto())
+            .column(Column::auto().at_least(130.0))
+            .column(Column::auto().at_least(100.0))
+            .column(Column::auto().at_least(100.0))
+            .column(Column::auto())
+            .column(Column::remainder().at_least(150.0).clip(true))
+            .header(24.0, |mut header| {
+                for title in ["", "vendorCode", "nmId", "Бренд", "Фото", "Название"] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for card in &cards {
+                    body.row(22.0, |mut row| {
+                        row.col(|ui| {
+                            let mut checked = selected.contains(&card.nm_id);
+                            if ui.checkbox(&mut checked, "").changed() {
+                                if checked {
+                                    selected.insert(card.nm_id);
+                                } else {
+                                    selected.remove(&card.nm_id);
+                                }
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(&card.vendor_code);
+                        });
+                        row.col(|ui| {
+                            ui.label(card.nm_id.to_string());
+                        });
+                        row.col(|ui| {
+                            ui.label(&card.brand);
+                        });
+                        row.col(|ui| {
+                            if card.photos == 0 {
+                                ui.colored_label(egui::Color32::from_rgb(255, 120, 120), "0");
+                            } else {
+                                ui.label(card.photos.to_string());
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(&card.title).on_hover_text(&card.title);
+                        });
+                    });
+                }
+            });
+        if push {
+            let vendor_codes = cards
+                .iter()
+                .filter(|c| selected.contains(&c.nm_id))
+                .map(|c| c.vendor_code.clone())
+                .collect();
+            let added = self.add_vendor_codes(vendor_codes);
+            self.job_state.log(LogEvent::info(
+                Stage::General,
+                format!("Добавлено vendor codes из каталога WB: {}", added.len()),
+            ));
+            selected.clear();
+            self.tab = Tab::Upload;
+        }
+        *self.card_search.selected.lock().unwrap() = selected;
+    }
+
+    fn load_reference(&mut self, refresh: bool) {
+        let profile = self.profile_manager.current_profile().clone();
+        if profile.api_key.is_empty() {
+            self.job_state.log(LogEvent::error(Stage::General, "Ошибка: API ключ не указан"));
+            return;
+        }
+        let cache = ReferenceCache::new(self.profile_manager.config.get_reference_cache_path());
+        reference::spawn_subjects(profile.clone(), cache, refresh, self.reference.clone());
+        reference::spawn_limits(profile, self.reference.clone());
+    }
+
+    // Предметы WB и их характеристики; шаблоны таблиц для создания и изменения карточек
+    fn show_reference_tab(&mut self, ui: &mut egui::Ui) {
+        if !self.reference_requested {
+            self.reference_requested = true;
+            self.load_reference(false);
+        }
+        let running = *self.reference.running.lock().unwrap();
+        ui.horizontal(|ui| {
+            ui.label("Предмет:");
+            ui.add(egui::TextEdit::singleline(&mut self.reference_filter).hint_text("название или subjectID").desired_width(220.0));
+            if ui
+                .add_enabled(!running, egui::Button::new("🔄 Обновить с WB"))
+                .on_hover_text("Справочники хранятся локально и обновляются раз в неделю")
+                .clicked()
+            {
+                self.load_reference(true);
+            }
+            if running {
+                ui.spinner();
+                ui.label(format!("Загрузка справочника… {}", *self.reference.fetched.lock().unwrap()));
+            }
+        });
+        if let Some(error) = self.reference.error.lock().unwrap().clone() {
+            ui.colored_label(egui::Color32::RED, format!("Ошибка: {}", error));
+        }
+        match *self.reference.limits.lock().unwrap() {
+            Some(limits) if limits.total() == 0 => {
+                ui.colored_label(egui::Color32::from_rgb(255, 120, 120), "Лимит создания карточек исчерпан");
+            }
+            Some(limits) => {
+                ui.label(format!("Можно создать карточек: {}", limits.describe()));
+            }
+            None => {}
+        }
+        let filter = self.reference_filter.trim().to_lowercase();
+        let subjects: Vec<Subject> = self
+            .reference
+            .subjects
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|s| {
+                filter.is_empty()
+                    || s.id.to_string() == filter
+                    || s.name.to_lowercase().contains(&filter)
+                    || s.parent_name.to_lowercase().contains(&filter)
+            })
+            .cloned()
+            .collect();
+        let selected = self.reference.selected.lock().unwrap().clone();
+        let characteristics = self.reference.characteristics.lock().unwrap().clone();
+        let mut pick = None;
+        ui.add_space(10.0);
+        ui.columns(2, |columns| {
+            columns[0].label(format!("Предметов: {}", subjects.len()));
+            TableBuilder::new(&mut columns[0])
+                .id_salt("reference_subjects")
+                .striped(true)
+                .max_scroll_height(500.0)
+                .column(Column::auto().at_least(80.0))
+                .column(Column::auto().at_least(150.0).clip(true))
+                .column(Column::remainder().clip(true))
+                .header(24.0, |mut header| {
+                    for title in ["subjectID", "Предмет", "Категория"] {
+                        header.col(|ui| {
+                            ui.strong(title);
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(22.0, subjects.len(), |mut row| {
+                        let subject = &subjects[row.index()];
+                        let is_selected = selected.as_ref().is_some_and(|s| s.id == subject.id);
+                        row.col(|ui| {
+                            if ui.selectable_label(is_selected, subject.id.to_string()).clicked() {
+                                pick = Some(subject.clone());
+                            }
+                        });
+                        row.col(|ui| {
+                            if ui.selectable_label(is_selected, &subject.name).clicked() {
+                                pick = Some(subject.clone());
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(&subject.parent_name);
+                        });
+                    });
+                });
+            let ui = &mut columns[1];
+            let Some(subject) = &selected else {
+                ui.label("Выберите предмет, чтобы увидеть его характеристики");
+                return;
+            };
+            ui.horizontal(|ui| {
+                ui.strong(format!("{} ({})", subject.name, subject.id));
+                if ui.button("📋 subjectID").on_hover_text("Скопировать subjectID").clicked() {
+                    ui.ctx().copy_text(subject.id.to_string());
+                }
+            });
+            ui.horizontal(|ui| {
+                for (label, create) in [("💾 Шаблон для создания карточек", true), ("💾 Шаблон для изменения характеристик", false)] {
+                    if ui.add_enabled(!running, egui::Button::new(label)).clicked()
+                        && let Some(path) = FileDialog::new()
+                            .add_filter("CSV", &["csv"])
+                            .set_file_name(format!("{}.csv", subject.name))
+                            .save_file()
+                        && let Err(e) = reference::save_template(&path, subject, &characteristics, create)
+                    {
+                        self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
+                    }
+                }
+            });
+            TableBuilder::new(ui)
+                .id_salt("reference_characteristics")
+                .striped(true)
+                .max_scroll_height(470.0)
+                .column(Column::auto().at_least(180.0).clip(true))
+                .column(Column::auto().at_least(70.0))
+                .column(Column::auto())
+                .column(Column::remainder())
+                .header(24.0, |mut header| {
+                    for title in ["Характеристика", "Тип", "Значений", "Обязательная"] {
+                        header.col(|ui| {
+                            ui.strong(title);
+                        });
+                    }
+                })
+                .body(|mut body| {
+                    for charc in &characteristics {
+                        body.row(22.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(&charc.name).on_hover_text(format!("charcID {}", charc.id));
+                            });
+                            row.col(|ui| {
+                                ui.label(if charc.is_numeric() { "число" } else { "текст" });
+                            });
+                            row.col(|ui| {
+                                ui.label(if charc.max_count > 0 { charc.max_count.to_string() } else { "—".to_string() });
+                            });
+                            row.col(|ui| {
+                                if charc.required {
+                                    ui.label("✔");
+                                }
+                            });
+                        });
+                    }
+                });
+        });
+        if let Some(subject) = pick
+            && !running
+        {
+            let profile = self.profile_manager.current_profile().clone();
+            let cache = ReferenceCache::new(self.profile_manager.config.get_reference_cache_path());
+            reference::spawn_characteristics(profile, cache, subject, self.reference.clone());
+        }
+    }
+
+    fn save_current_job(&mut self) {
+        let name = self.new_job_name.trim().to_string();
+        if name.is_empty() {
+            self.job_state.log(LogEvent::error(Stage::General, "Ошибка: укажите название задания"));
+            return;
+        }
+        if let Err(e) = scheduler::parse_schedule(&self.new_job_schedule) {
+            self.job_state.log(LogEvent::error(Stage::General, format!("Ошибка: {}", e)));
+            return;
+        }
+        let api_key = self.profile_manager.current_profile().api_key.clone();
+        if let Err(message) = self.validate_input(&api_key) {
+            self.job_state
+                .log(LogEvent::error(Stage::General, format!("Ошибка: {}", message)));
+            return;
+        }
+        let saved = SavedJob {
+            name: name.clone(),
+            profile: self.profile_manager.current_profile().name.clone(),
+            vendor_codes: self.vendor_codes(),
+            source: self.current_source(),
+            schedule: self.new_job_schedule.trim().to_string(),
+            enabled: true,
+            only_new: self.new_job_only_new,
+            last_run: None,
+        };
+        let mut jobs = self.saved_jobs.lock().unwrap();
+        match jobs.iter_mut().find(|job| job.name == name) {
+            Some(existing) => *existing = saved,
+            None => jobs.push(saved),
+        }
+        if let Err(e) = scheduler::save_jobs(&self.profile_manager.config, &jobs) {
+            self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
+            return;
+        }
+        self.job_state
+            .log(LogEvent::info(Stage::General, format!("Задание сохранено: {}", name)));
+        self.new_job_name.clear();
+    }
+
+    fn show_schedule_tab(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.label(egui::RichText::new("💾 Сохранить текущее задание").strong().size(18.0));
+            ui.label("Профиль, источник и vendor codes берутся с вкладки загрузки.");
+            egui::Grid::new("new_job").num_columns(2).show(ui, |ui| {
+                ui.label("Название:");
+                ui.text_edit_singleline(&mut self.new_job_name);
+                ui.end_row();
+                ui.label("Расписание (cron):");
+                ui.text_edit_singleline(&mut self.new_job_schedule);
+                ui.end_row();
+                ui.label("");
+                match scheduler::parse_schedule(&self.new_job_schedule) {
+                    Ok(schedule) => ui.label(schedule.after(&Local::now()).next().map_or(
+                        "Расписание не даёт ни одного запуска".to_string(),
+                        |next| format!("Следующий запуск: {}", next.format("%Y-%m-%d %H:%M")),
+                    )),
+                    Err(e) => ui.colored_label(egui::Color32::RED, e.to_string()),
+                };
+                ui.end_row();
+            });
+            ui.label("Формат: минута час день месяц день_недели, например «0 3 * * *» — каждую ночь в 03:00.");
+            ui.checkbox(&mut self.new_job_only_new, "Загружать только новые файлы");
+            if ui.button("💾 Сохранить задание").clicked() {
+                self.save_current_job();
+            }
+        });
+        ui.add_space(10.0);
+
+        let mut jobs = self.saved_jobs.lock().unwrap();
+        if jobs.is_empty() {
+            ui.label("Сохранённых заданий пока нет.");
+            return;
+        }
+        let mut changed = false;
+        let mut run_now = None;
+        let mut remove = None;
+        egui::Grid::new("saved_jobs").num_columns(8).striped(true).show(ui, |ui| {
+            for title in ["Вкл.", "Название", "Профиль", "Источник", "Расписание", "Следующий запуск", "Последний запуск", ""] {
+                ui.label(egui::RichText::new(title).strong());
+            }
+            ui.end_row();
+            for (index, saved) in jobs.iter_mut().enumerate() {
+                changed |= ui.checkbox(&mut saved.enabled, "").changed();
+                ui.label(&saved.name);
+                ui.label(&saved.profile);
+                ui.label(format!("{} ({} vendor codes)", saved.source.describe(), saved.vendor_codes.len()));
+                ui.label(&saved.schedule);
+                ui.label(
+                    saved
+                        .next_run(Local::now())
+                        .filter(|_| saved.enabled)
+                        .map_or("—".to_string(), |next| next.format("%Y-%m-%d %H:%M").to_string()),
+                );
+                ui.label(saved.last_run.map_or("—".to_string(), |last| last.format("%Y-%m-%d %H:%M").to_string()));
+                ui.horizontal(|ui| {
+                    if ui.button("▶ Запустить сейчас").clicked() {
+                        run_now = Some(index);
+                    }
+                    if ui.button("🗑").clicked() {
+                        remove = Some(index);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if let Some(index) = run_now {
+            match scheduler::run_saved_job(&jobs[index], &self.job_state) {
+                Ok(()) => {
+                    jobs[index].last_run = Some(Local::now());
+                    changed = true;
+                }
+                Err(e) => self.job_state.log(LogEvent::error(Stage::General, format!("Ошибка: {}", e))),
+            }
+        }
+        if let Some(index) = remove {
+            let saved = jobs.remove(index);
+            self.job_state
+                .log(LogEvent::info(Stage::General, format!("Задание удалено: {}", saved.name)));
+            changed = true;
+        }
+        if changed && let Err(e) = scheduler::save_jobs(&self.profile_manager.config, &jobs) {
+            self.job_state.log(LogEvent::error(Stage::General, e.to_string()));
+        }
+    }
+
+    fn requeue_vendor_codes(&mut self, vendor_codes: Vec<String>, profile: Option<&str>) {
+        if let Some(profile) = profile
+            && let Some(index) = self.profile_manager.profiles.iter().position(|p| p.name == profile)
+        {
+            self.profile_manager.selected_index = index;
+        }
+        let added = self.add_vendor_codes(vendor_codes);
+        self.job_state.log(LogEvent::info(
+            Stage::General,
+            format!("Добавлено в очередь из истории: {}", added.join(", ")),
+        ));
+        self.tab = Tab::Upload;
+    }
+
+    // Ctrl+Enter — запуск, Esc — закрыть диалог или остановить обработку,
+    // Ctrl+L — показать/скрыть логи, Ctrl+1…9 — выбор профиля. Обрабатываются до полей ввода,
+    // чтобы многострочные поля не забирали Ctrl+Enter себе
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        const PROFILE_KEYS: [egui::Key; 9] = [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+            egui::Key::Num6,
+            egui::Key::Num7,
+            egui::Key::Num8,
+            egui::Key::Num9,
+        ];
+        let (start, escape, toggle_logs, profile) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::L),
+                PROFILE_KEYS
+                    .iter()
+                    .position(|key| i.consume_key(egui::Modifiers::COMMAND, *key)),
+            )
+        });
+        if start && !*self.job_state.is_processing.lock().unwrap() {
+            self.start_processing();
+        }
+        if escape {
+            if self.confirm_undo {
+                self.confirm_undo = false;
+            } else if self.confirm_start.is_some() {
+                self.confirm_start = None;
+            } else if self.confirm_delete_profile {
+                self.confirm_delete_profile = false;
+            } else if self.confirm_close {
+                self.confirm_close = false;
+            } else if self.crash_report.is_some() {
+                self.crash_report = None;
+            } else if self.resume.is_some() {
+                self.resume = None;
+            } else if self.item_details.is_some() {
+                self.item_details = None;
+            } else if self.vendor_import.is_some() {
+                self.vendor_import = None;
+            } else {
+                self.job_state.cancel();
+            }
+        }
+        if toggle_logs {
+            self.show_logs = !self.show_logs;
+        }
+        if let Some(index) = profile
+            && index < self.profile_manager.profiles.len()
+        {
+            self.profile_manager.selected_index = index;
+        }
+    }
+
+    // Подсказка значка в трее следует за запуском. Закрытие окна во время обработки
+    // не обрывает запуск молча: пользователь выбирает, оставить его в фоне или остановить
+    fn handle_close_request(&mut self, ctx: &egui::Context) {
+        let processing = *self.job_state.is_processing.lock().unwrap();
+        let tooltip = if processing {
+            let progress = self.job_state.progress.lock().unwrap();
+            format!(
+                "Менеджер контента WB: обработано {} из {}, ошибок {}",
+                progress.processed, progress.total, progress.failed
+            )
+        } else {
+            "Менеджер контента WB: ожидание".to_string()
+        };
+        self.tray.update(ctx, tooltip);
+        if self.tray.take_quit_request() {
+            if processing {
+                self.stop_and_quit();
+            } else {
+                self.quit(ctx);
+            }
+        }
+        if self.quit_after_stop && !processing {
+            self.quit(ctx);
+        }
+        if processing && !self.quitting && ctx.input(|i| i.viewport().close_requested()) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            if !self.quit_after_stop {
+                self.confirm_close = true;
+            }
+        }
+    }
+
+    // Обрывать загрузку на середине нельзя: ждём конца текущего vendorCode
+    fn stop_and_quit(&mut self) {
+        self.confirm_close = false;
+        self.quit_after_stop = true;
+        self.job_state.cancel();
+    }
+
+    // Необработанные vendor codes запуска заменяют поле ввода, которое сохраняется при выходе,
+    // чтобы после перезапуска продолжить с того же места
+    fn quit(&mut self, ctx: &egui::Context) {
+        let interrupted = self.quit_after_stop || *self.job_state.is_processing.lock().unwrap();
+        self.confirm_close = false;
+        self.quit_after_stop = false;
+        self.quitting = true;
+        self.job_state.cancel();
+        let unfinished = if interrupted {
+            self.job_state.unfinished_vendor_codes()
+        } else {
+            Vec::new()
+        };
+        if !unfinished.is_empty() {
+            self.job_state.log(LogEvent::warn(
+                Stage::General,
+                format!(
+                    "Выход до завершения запуска: необработанные vendor codes ({}) сохранены для следующего запуска",
+                    unfinished.len()
+                ),
+            ));
+            self.file_names = unfinished.join("\n");
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    // Папка становится локальным источником, один файл — путём к файлу, несколько — списком файлов.
+    // Пустой список vendor codes заполняется по именам файлов
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_overlay")));
+            let rect = ctx.screen_rect();
+            painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Отпустите, чтобы загрузить файлы или папку",
+                egui::FontId::proportional(24.0),
+                egui::Color32::WHITE,
+            );
+        }
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if dropped.is_empty() || *self.job_state.is_processing.lock().unwrap() {
+            return;
+        }
+        self.use_local_path = true;
+        let files: Vec<PathBuf> = if let [folder] = dropped.as_slice()
+            && folder.is_dir()
+        {
+            self.local_source_path = folder.to_string_lossy().to_string();
+            self.single_file_path.clear();
+            self.dropped_files.clear();
+            media_files_in(folder)
+        } else {
+            let files: Vec<PathBuf> = dropped
+                .iter()
+                .flat_map(|path| if path.is_dir() { media_files_in(path) } else { vec![path.clone()] })
+                .filter(|path| wb_upload_core::utils::is_media_file(&path.to_string_lossy()))
+                .collect();
+            if let [file] = files.as_slice() {
+                self.single_file_path = file.to_string_lossy().to_string();
+                self.dropped_files.clear();
+            } else {
+                self.single_file_path.clear();
+                self.dropped_files = files.iter().map(|p| p.to_string_lossy().to_string()).collect();
+            }
+            files
+        };
+        self.job_state.log(LogEvent::info(
+            Stage::General,
+            format!("Перетащено медиафайлов: {}", files.len()),
+        ));
+        if self.vendor_codes().is_empty() {
+            let codes: Vec<String> = files
+                .iter()
+                .filter_map(|p| wb_upload_core::utils::guess_vendor_code(&p.file_name()?.to_string_lossy()))
+                .collect();
+            let added = self.add_vendor_codes(codes);
+            self.job_state.log(LogEvent::info(
+                Stage::General,
+                format!("Vendor codes по именам файлов: {}", added.len()),
+            ));
+        }
+    }
+
+    // Добавляет vendor codes в поле ввода вкладки загрузки, не дублируя уже указанные
+    fn add_vendor_codes(&mut self, vendor_codes: Vec<String>) -> Vec<String> {
+        let mut queued = self.vendor_codes();
+        let mut added = Vec::new();
+        for vendor_code in vendor_codes {
+            if !queued.contains(&vendor_code) {
+                queued.push(vendor_code.clone());
+                added.push(vendor_code);
+            }
+        }
+        self.file_names = queued.join("\n");
+        added
+    }
+
+    fn export_wb_vendor_codes(&mut self) {
+        let profile = self.profile_manager.current_profile().clone();
+        if profile.api_key.is_empty() {
+            self.job_state.log(LogEvent::error(Stage::General, "Ошибка: API ключ не указан"));
+            return;
+        }
+        if let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("wb_vendor_codes.csv")
+            .save_file()
+        {
+            job::start_export_cards(profile, path, self.job_state.clone());
+        }
+    }
+
+    fn open_vendor_import(&mut self) {
+        let Some(path) = FileDialog::new()
+            .add_filter("Таблицы", &["csv", "txt", "xlsx", "xlsm", "xls", "ods"])
+            .pick_file()
+        else {
+            return;
+        };
+        match import::read_table(&path) {
+            Ok(rows) if rows.is_empty() => self
+                .job_state
+                .log(LogEvent::warn(Stage::General, format!("Файл {} пуст", path.display()))),
+            Ok(rows) => {
+                let column = import::guess_column(&rows[0]);
+                self.vendor_import = Some(VendorImport {
+                    path: path.to_string_lossy().to_string(),
+                    rows,
+                    column,
+                    skip_header: true,
+                });
+            }
+            Err(e) => self.job_state.log(LogEvent::error(Stage::General, e.to_string())),
+        }
+    }
+
+    fn show_vendor_import(&mut self, ui: &mut egui::Ui) {
+        let Some(vendor_import) = &mut self.vendor_import else {
+            return;
+        };
+        let mut apply = false;
+        let mut cancel = false;
+        ui.add_space(10.0);
+        ui.group(|ui| {
+            ui.label(format!("📄 {}", vendor_import.path));
+            let columns = vendor_import.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+            let column_label = |column: usize| match vendor_import.rows[0].get(column) {
+                Some(header) if !header.is_empty() => format!("{}: {}", column + 1, header),
+                _ => format!("Столбец {}", column + 1),
+            };
+            ui.horizontal(|ui| {
+                ui.label("Столбец с vendor codes:");
+                egui::ComboBox::from_id_salt("vendor_import_column")
+                    .selected_text(column_label(vendor_import.column))
+                    .show_ui(ui, |ui| {
+                        for column in 0..columns {
+                            ui.selectable_value(&mut vendor_import.column, column, column_label(column));
+                        }
+                    });
+                ui.checkbox(&mut vendor_import.skip_header, "Первая строка — заголовок");
+            });
+            let values = import::column_values(&vendor_import.rows, vendor_import.column, vendor_import.skip_header);
+            let preview: Vec<&str> = values.iter().take(5).map(String::as_str).collect();
+            ui.label(format!("Найдено значений: {} ({}{})", values.len(), preview.join(", "), if values.len() > 5 { ", …" } else { "" }));
+            ui.horizontal(|ui| {
+                apply = ui.button("✅ Добавить в список").clicked();
+                cancel = ui.button("❌ Отмена").clicked();
+            });
+        });
+        if apply && let Some(vendor_import) = self.vendor_import.take() {
+            let values = import::column_values(&vendor_import.rows, vendor_import.column, vendor_import.skip_header);
+            let total = values.len();
+            let added = self.add_vendor_codes(values);
+            self.job_state.log(LogEvent::info(
+                Stage::General,
+                format!("Импортировано vendor codes из {}: {} (новых: {})", vendor_import.path, total, added.len()),
+            ));
+        } else if cancel {
+            self.vendor_import = None;
+        }
+    }
+
+    fn export_report(&mut self, format: ReportFormat) {
+        let extension = format.extension();
+        let file_name = format!("report_{}.{}", Local::now().format("%Y-%m-%d_%H-%M-%S"), extension);
+        let Some(path) = FileDialog::new()
+            .set_file_name(&file_name)
+            .add_filter(extension.to_uppercase(), &[extension])
+            .save_file()
+        else {
+            return;
+        };
+        let results = self.job_state.results.lock().unwrap().clone();
+        let result = match format {
+            ReportFormat::Csv => report::save_csv(&path, &results),
+            ReportFormat::Xlsx => report::save_xlsx(&path, &results),
+            ReportFormat::Html => report::save_html(&path, &results),
+        };
+        match result {
+            Ok(()) => self.job_state.log(LogEvent::info(
+                Stage::General,
+                format!("Отчёт сохранён: {}", path.display()),
+            )),
+            Err(e) => self.job_state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка сохранения отчёта: {}", e),
+            )),
+        }
+    }
+
+    fn show_log_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Уровень")
+                .selected_text(self.log_filter.min_level.map_or("Все", |l| l.label()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.log_filter.min_level, None, "Все");
+                    for level in LogLevel::ALL {
+                        ui.selectable_value(&mut self.log_filter.min_level, Some(level), level.label());
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.log_filter.vendor_code)
+                    .hint_text("vendorCode")
+                    .desired_width(150.0),
+            );
+            ui.checkbox(&mut self.group_logs_by_vendor_code, "Группировать по vendorCode");
+        });
+        ui.add_space(5.0);
+        let events = self.job_state.logs.lock().unwrap().filtered(&self.log_filter);
+        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            if self.group_logs_by_vendor_code {
+                let mut groups: BTreeMap<&str, Vec<&LogEvent>> = BTreeMap::new();
+                for event in &events {
+                    groups
+                        .entry(event.vendor_code.as_deref().unwrap_or("—"))
+                        .or_default()
+                        .push(event);
+                }
+                for (vendor_code, group) in groups {
+                    let has_errors = group.iter().any(|e| e.level == LogLevel::Error);
+                    let title = format!(
+                        "{}{} ({})",
+                        if has_errors { "❌ " } else { "" },
+                        vendor_code,
+                        group.len()
+                    );
+                    egui::CollapsingHeader::new(title)
+                        .id_salt(("log_group", vendor_code))
+                        .show(ui, |ui| {
+                            for event in group.iter().rev().take(50) {
+                                log_event_label(ui, event);
+                            }
+                        });
+                }
+            } else {
+                for event in events.iter().rev().take(50) {
+                    log_event_label(ui, event);
+                }
+            }
+        });
+    }
+
+    fn apply_settings(&mut self) {
+        if let Err(e) = network::validate(&self.settings.network) {
+            self.toasts.error("Не удалось сохранить настройки");
+            self.job_state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка в параметрах сети: {}", e),
+            ));
+            return;
+        }
+        network::set_options(&self.settings.network);
+        let mut logs = self.job_state.logs.lock().unwrap();
+        logs.set_capacity(self.settings.log_capacity);
+        self.settings.log_capacity = logs.capacity();
+        match self.settings.save(&self.profile_manager.config) {
+            Ok(()) => {
+                self.toasts.success("Настройки сохранены");
+                logs.push(LogEvent::info(Stage::General, "Настройки сохранены"));
+            }
+            Err(e) => {
+                self.toasts.error("Не удалось сохранить настройки");
+                logs.push(LogEvent::error(
+                    Stage::General,
+                    format!("Ошибка сохранения настроек: {}", e),
+                ));
+            }
+        }
+    }
+
+    fn save_logs_to_file(&mut self) {
+        let file_name = format!("wb_logs_{}.txt", Local::now().format("%Y-%m-%d_%H-%M-%S"));
+        let Some(path) = FileDialog::new()
+            .set_file_name(&file_name)
+            .add_filter("Text", &["txt"])
+            .add_filter("JSON Lines", &["jsonl"])
+            .save_file()
+        else {
+            return;
+        };
+        let mut logs = self.job_state.logs.lock().unwrap();
+        let data = if path.extension().is_some_and(|ext| ext == "jsonl") {
+            logs.to_json_lines(&self.log_filter)
+        } else {
+            logs.to_text(&self.log_filter)
+        };
+        match std::fs::write(&path, data) {
+            Ok(()) => logs.push(LogEvent::info(
+                Stage::General,
+                format!("Логи сохранены в {}", path.display()),
+            )),
+            Err(e) => logs.push(LogEvent::error(
+                Stage::General,
+                format!("Ошибка сохранения логов в {}: {}", path.display(), e),
+            )),
+        }
+    }
+
+    fn copy_logs_to_clipboard(&mut self) {
+        let mut logs = self.job_state.logs.lock().unwrap();
+        let text = logs.to_text(&self.log_filter);
+        match Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+            Ok(()) => logs.push(LogEvent::info(Stage::General, "Логи скопированы в буфер обмена")),
+            Err(e) => logs.push(LogEvent::error(
+                Stage::General,
+                format!("Ошибка копирования логов: {}", e),
+            )),
+        }
+    }
+}
+
+fn sort_results(results: &mut [ItemResult], column: ResultColumn, ascending: bool) {
+    results.sort_by(|a, b| {
+        let ordering = match column {
+            ResultColumn::VendorCode => a.vendor_code.cmp(&b.vendor_code),
+            ResultColumn::NmId => a.nm_id.cmp(&b.nm_id),
+            ResultColumn::FilesFound => a.files_found().cmp(&b.files_found()),
+            ResultColumn::FilesUploaded => a.files_uploaded.cmp(&b.files_uploaded),
+            ResultColumn::Status => a.status.cmp(&b.status),
+            ResultColumn::Error => a.error.cmp(&b.error),
+        };
+        if ascending { ordering } else { ordering.reverse() }
+    });
+}
+
+fn log_event_label(ui: &mut egui::Ui, event: &LogEvent) {
+    let text = egui::RichText::new(event.to_string()).size(14.0);
+    let text = match event.level {
+        LogLevel::Error => text.color(egui::Color32::from_rgb(255, 120, 120)),
+        LogLevel::Warn => text.color(egui::Color32::from_rgb(255, 200, 80)),
+        _ => text,
+    };
+    ui.label(text);
+}
+
+fn media_files_in(folder: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(folder)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && wb_upload_core::utils::is_media_file(&e.file_name().to_string_lossy()))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+// Акцентный цвет для светлой темы: смешение с белым
+fn lighten(color: egui::Color32) -> egui::Color32 {
+    let mix = |channel: u8| channel + ((255 - channel) as f32 * 0.6) as u8;
+    egui::Color32::from_rgb(mix(color.r()), mix(color.g()), mix(color.b()))
+}
+
+// Ссылка дописывается новой строкой, если её ещё нет в списке
+fn add_link(urls: &mut String, link: &str) {
+    if urls.lines().any(|line| line.trim() == link) {
+        return;
+    }
+    if !urls.trim().is_empty() && !urls.ends_with('\n') {
+        urls.push('\n');
+    }
+    urls.push_str(link);
+}
+
+// Поле с ошибкой ввода обводится красным (rect — видимая область поля), текст ошибки — в подсказке
+fn mark_invalid(ui: &egui::Ui, rect: egui::Rect, response: egui::Response, error: Option<String>) {
+    let Some(error) = error else {
+        return;
+    };
+    ui.painter()
+        .rect_stroke(rect.expand(2.0), 4.0, egui::Stroke::new(1.5, egui::Color32::RED));
+    response.on_hover_text(egui::RichText::new(error).color(egui::Color32::RED));
+}
+
+fn text_edit_with_context_menu(
+    ui: &mut egui::Ui,
+    text: &mut String,
+    width: f32,
+    hint_text: &str,
+) -> egui::Response {
+    let text_edit = egui::TextEdit::multiline(text).desired_width(width).hint_text(hint_text);
+    let response = ui.add(text_edit);
+    response.context_menu(|ui| {
+        if ui.button("📋 Вставить").clicked() {
+            if let Ok(mut clipboard) = Clipboard::new()
+                && let Ok(clipboard_text) = clipboard.get_text()
+            {
+                *text = clipboard_text;
             }
             ui.close_menu();
         }
@@ -949,4 +4113,5 @@ fn text_edit_with_context_menu(ui: &mut egui::Ui, text: &mut String, width: f32,
             ui.close_menu();
         }
     });
+    response
 }
\ No newline at end of file
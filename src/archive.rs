@@ -0,0 +1,116 @@
+use crate::backup;
+use crate::downloader::{Downloader, FileInfo};
+use crate::profile::HttpTimeouts;
+use crate::staging::Staging;
+use anyhow::Result;
+use chrono::{Datelike, Local, Timelike};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+// Загруженный файл и публичные ссылки, из которых он взят; без ссылок — локальный файл
+#[derive(Clone)]
+pub struct ArchivedFile {
+    pub file: FileInfo,
+    pub public_keys: Vec<String>,
+}
+
+// ZIP ровно с теми файлами, которые запуск загрузил в WB. Внутри — «<vendorCode>/<имя файла>»,
+// как в резервной копии фото, поэтому распакованный архив можно загрузить заново в локальном
+// режиме. Файлы Яндекс.Диска скачиваются заново с проверкой размера и SHA-256
+pub fn save(
+    dir: &Path,
+    name: &str,
+    files: &[ArchivedFile],
+    staging: &Staging,
+    timeouts: &HttpTimeouts,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| anyhow::anyhow!("Не удалось создать папку {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.zip", name));
+    let file = File::create(&path)
+        .map_err(|e| anyhow::anyhow!("Не удалось создать архив {}: {}", path.display(), e))?;
+    // Недописанный архив для аудита бесполезен — удаляем его
+    if let Err(e) = write(ZipWriter::new(file), files, staging, timeouts) {
+        if let Err(remove_error) = std::fs::remove_file(&path) {
+            log::warn!(
+                "Не удалось удалить недописанный архив {}: {}",
+                path.display(),
+                remove_error
+            );
+        }
+        return Err(e);
+    }
+    Ok(path)
+}
+
+fn write(
+    mut zip: ZipWriter<File>,
+    files: &[ArchivedFile],
+    staging: &Staging,
+    timeouts: &HttpTimeouts,
+) -> Result<()> {
+    let mut names = HashSet::new();
+    // Без даты ZIP показывает у файлов 1980 год; ставим время архивирования
+    let now = Local::now();
+    let modified = zip::DateTime::from_date_and_time(
+        now.year() as u16,
+        now.month() as u8,
+        now.day() as u8,
+        now.hour() as u8,
+        now.minute() as u8,
+        now.second() as u8,
+    )
+    .unwrap_or_default();
+    let mut sources: HashMap<&[String], Downloader> = HashMap::new();
+    for ArchivedFile { file, public_keys } in files {
+        let temp = if public_keys.is_empty() {
+            None
+        } else {
+            let downloader = match sources.entry(public_keys) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(Downloader::new(public_keys.clone(), Vec::new(), timeouts)?)
+                }
+            };
+            Some(downloader.download_to_temp(file, staging)?)
+        };
+        let local = temp
+            .as_ref()
+            .map_or(Path::new(&file.path), |temp| temp.path());
+        let mut content = File::open(local)
+            .map_err(|e| anyhow::anyhow!("Не удалось открыть файл {}: {}", local.display(), e))?;
+        let size = content.metadata().map(|m| m.len()).unwrap_or_default();
+        // Фото и видео уже сжаты, повторное сжатие только тратит время
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .last_modified_time(modified)
+            .large_file(size >= u32::MAX as u64);
+        zip.start_file(entry_name(file, &mut names), options)
+            .map_err(|e| anyhow::anyhow!("Ошибка записи архива: {}", e))?;
+        std::io::copy(&mut content, &mut zip)
+            .map_err(|e| anyhow::anyhow!("Ошибка записи {} в архив: {}", file.path, e))?;
+    }
+    zip.finish()
+        .map_err(|e| anyhow::anyhow!("Ошибка записи архива: {}", e))?;
+    Ok(())
+}
+
+// Одноимённые файлы из разных папок одного vendorCode получают номер: «ABC_1 (2).jpg»
+fn entry_name(file: &FileInfo, names: &mut HashSet<String>) -> String {
+    let dir = backup::file_name(&file.articul);
+    let (stem, extension) = match file.name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (file.name.as_str(), String::new()),
+    };
+    let mut name = format!("{}/{}", dir, file.name);
+    let mut index = 2;
+    while !names.insert(name.clone()) {
+        name = format!("{}/{} ({}){}", dir, stem, index, extension);
+        index += 1;
+    }
+    name
+}
@@ -0,0 +1,68 @@
+use crate::profile::Profile;
+use crate::uploader::{CardSummary, WbUploader};
+use anyhow::Result;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Поиск карточек каталога, у которых меньше min_photos фото
+#[derive(Clone, Default)]
+pub struct AuditState {
+    pub running: Arc<Mutex<bool>>,
+    // Сколько карточек каталога уже получено
+    pub fetched: Arc<Mutex<usize>>,
+    pub cards: Arc<Mutex<Vec<CardSummary>>>,
+    pub error: Arc<Mutex<Option<String>>>,
+}
+
+pub fn spawn(profile: Profile, min_photos: usize, state: AuditState) {
+    *state.running.lock().unwrap() = true;
+    *state.fetched.lock().unwrap() = 0;
+    state.cards.lock().unwrap().clear();
+    *state.error.lock().unwrap() = None;
+    std::thread::spawn(move || {
+        // Для карточек совсем без фото WB фильтрует каталог сам
+        let with_photo = if min_photos <= 1 { 0 } else { -1 };
+        let fetched = Arc::clone(&state.fetched);
+        let cards = WbUploader::for_profile(&profile).and_then(|uploader| {
+            Ok(uploader.list_cards(with_photo, |count| *fetched.lock().unwrap() = count)?)
+        });
+        match cards {
+            Ok(cards) => {
+                let mut cards: Vec<CardSummary> = cards
+                    .into_iter()
+                    .filter(|c| c.photos < min_photos.max(1))
+                    .collect();
+                cards.sort_by(|a, b| a.vendor_code.cmp(&b.vendor_code));
+                log::info!(
+                    "Аудит каталога: карточек с фото меньше {}: {}",
+                    min_photos.max(1),
+                    cards.len()
+                );
+                *state.cards.lock().unwrap() = cards;
+            }
+            Err(e) => {
+                log::error!("Ошибка аудита каталога: {}", e);
+                *state.error.lock().unwrap() = Some(e.to_string());
+            }
+        }
+        *state.running.lock().unwrap() = false;
+    });
+}
+
+pub fn save_csv(path: &Path, cards: &[CardSummary]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось создать файл {}: {}", path.display(), e))?;
+    writer.write_record(["vendorCode", "nmId", "Фото"])?;
+    for card in cards {
+        writer.write_record([
+            card.vendor_code.as_str(),
+            &card.nm_id.to_string(),
+            &card.photos.to_string(),
+        ])?;
+    }
+    writer
+        .flush()
+        .map_err(|e| anyhow::anyhow!("Ошибка записи файла {}: {}", path.display(), e))?;
+    log::info!("Список карточек сохранён в {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use crate::http::{HttpClient, ReqwestClient, Request};
+use crate::uploader::CardMedia;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Резервная копия текущих фото карточек WB в локальную папку
+pub struct CardBackup {
+    client: Arc<dyn HttpClient>,
+    root: PathBuf,
+}
+
+impl CardBackup {
+    pub fn new(root: &Path) -> Result<Self> {
+        if let Some(client) = crate::demo::client() {
+            return Ok(Self::with_client(root, client));
+        }
+        let client = crate::network::client_builder()?
+            .timeout(Duration::from_secs(60))
+            .build()
+            .map_err(|e| anyhow::anyhow!("Не удалось создать HTTP-клиент: {}", e))?;
+        Ok(Self::with_client(root, Arc::new(ReqwestClient::new(client))))
+    }
+
+    pub fn with_client(root: &Path, client: Arc<dyn HttpClient>) -> Self {
+        Self {
+            client,
+            root: root.to_path_buf(),
+        }
+    }
+
+    // Фото сохраняются как «<vendorCode>/<vendorCode>_<номер>.<расширение>»,
+    // такую папку можно загрузить обратно в локальном режиме
+    pub fn save(&self, vendor_code: &str, card: &CardMedia) -> Result<PathBuf> {
+        let name = file_name(vendor_code);
+        let dir = self.root.join(&name);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("Не удалось создать папку {}: {}", dir.display(), e))?;
+        for (index, url) in card.photos.iter().enumerate() {
+            let response = self
+                .client
+                .send(Request::get(url))
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| anyhow::anyhow!("Ошибка скачивания фото {}: {}", index + 1, e))?;
+            let path = dir.join(format!("{}_{}.{}", name, index + 1, extension(url)));
+            std::fs::write(&path, &response.body).map_err(|e| {
+                anyhow::anyhow!("Не удалось записать файл {}: {}", path.display(), e)
+            })?;
+            log::debug!("Фото {} сохранено в {}", url, path.display());
+        }
+        Ok(dir)
+    }
+}
+
+// Символы, недопустимые в именах файлов Windows, заменяются на «_»
+pub(crate) fn file_name(vendor_code: &str) -> String {
+    vendor_code
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn extension(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .filter(|e| crate::utils::is_media_file(&format!("photo.{}", e)))
+        .unwrap_or_else(|| "jpg".to_string())
+}
@@ -0,0 +1,212 @@
+use crate::job::{self, ItemStatus, JobSpec, JobState, Progress};
+use crate::logs::{LogEvent, Stage};
+use std::time::{Duration, Instant};
+
+// Как часто общий прогресс пакета собирается из прогресса профилей
+const MIRROR_INTERVAL: Duration = Duration::from_millis(250);
+
+// Профили пакета обрабатываются по очереди или одновременно: лимиты WB у каждого
+// продавца свои, поэтому параллельный запуск не упирается в ограничения соседей
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchMode {
+    #[default]
+    Sequential,
+    Parallel,
+}
+
+impl BatchMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BatchMode::Sequential => "по очереди",
+            BatchMode::Parallel => "параллельно",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum BatchStatus {
+    Pending,
+    Running,
+    Done { succeeded: usize, failed: usize },
+    Cancelled,
+}
+
+impl BatchStatus {
+    pub fn label(&self) -> String {
+        match self {
+            BatchStatus::Pending => "ожидает".to_string(),
+            BatchStatus::Running => "в работе".to_string(),
+            BatchStatus::Done { succeeded, failed } => {
+                format!("готово: успешно {}, ошибок {}", succeeded, failed)
+            }
+            BatchStatus::Cancelled => "остановлен".to_string(),
+        }
+    }
+
+    // Стабильный идентификатор для API
+    pub fn code(&self) -> &'static str {
+        match self {
+            BatchStatus::Pending => "pending",
+            BatchStatus::Running => "running",
+            BatchStatus::Done { .. } => "done",
+            BatchStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+// Строка статуса профиля в пакете
+#[derive(Clone)]
+pub struct BatchItem {
+    pub profile: String,
+    pub vendor_codes: usize,
+    pub status: BatchStatus,
+}
+
+// Один сеанс на несколько профилей: для каждого — своё задание со своим списком vendor codes.
+// Общее состояние показывает сумму прогресса и все результаты, отчёты сохраняются по профилям
+pub fn start(specs: Vec<JobSpec>, mode: BatchMode, state: JobState) {
+    state.log(LogEvent::info(
+        Stage::General,
+        format!("Пакетный запуск: профилей {}, {}", specs.len(), mode.label()),
+    ));
+    *state.is_processing.lock().unwrap() = true;
+    *state.start_time.lock().unwrap() = Some(Instant::now());
+    state.results.lock().unwrap().clear();
+    state.failed_vendor_codes.lock().unwrap().clear();
+    // Необработанные vendor codes разных профилей в одно поле не сохранить
+    state.run_vendor_codes.lock().unwrap().clear();
+    let total = specs.iter().map(|spec| spec.vendor_codes.len()).sum();
+    let kind = specs
+        .first()
+        .map(|spec| spec.source.item_kind())
+        .unwrap_or_default();
+    *state.progress.lock().unwrap() = Progress::new(total, kind);
+    *state.batch.lock().unwrap() = specs
+        .iter()
+        .map(|spec| BatchItem {
+            profile: spec.profile.clone(),
+            vendor_codes: spec.vendor_codes.len(),
+            status: BatchStatus::Pending,
+        })
+        .collect();
+    std::thread::spawn(move || {
+        run(specs, mode, &state);
+        state.finish();
+    });
+}
+
+fn run(specs: Vec<JobSpec>, mode: BatchMode, state: &JobState) {
+    let forks: Vec<JobState> = specs.iter().map(|spec| state.fork(&spec.profile)).collect();
+    let jobs: Vec<(usize, JobSpec, JobState)> = specs
+        .into_iter()
+        .zip(forks.iter().cloned())
+        .enumerate()
+        .map(|(index, (spec, fork))| (index, spec, fork))
+        .collect();
+    let workers = match mode {
+        BatchMode::Parallel => jobs
+            .into_iter()
+            .map(|(index, spec, fork)| {
+                let state = state.clone();
+                std::thread::spawn(move || run_profile(index, spec, fork, &state))
+            })
+            .collect::<Vec<_>>(),
+        BatchMode::Sequential => {
+            let state = state.clone();
+            vec![std::thread::spawn(move || {
+                for (index, spec, fork) in jobs {
+                    if state.is_cancelled() {
+                        break;
+                    }
+                    run_profile(index, spec, fork, &state);
+                }
+            })]
+        }
+    };
+    while !workers.iter().all(|worker| worker.is_finished()) {
+        mirror(state, &forks);
+        std::thread::sleep(MIRROR_INTERVAL);
+    }
+    for worker in workers {
+        if worker.join().is_err() {
+            state.log(LogEvent::error(Stage::General, "Обработка профиля завершилась сбоем"));
+        }
+    }
+    mirror(state, &forks);
+
+    let mut batch = state.batch.lock().unwrap();
+    for item in batch.iter_mut().filter(|item| item.status == BatchStatus::Pending) {
+        item.status = BatchStatus::Cancelled;
+    }
+    let summary = batch
+        .iter()
+        .map(|item| format!("{} — {}", item.profile, item.status.label()))
+        .collect::<Vec<_>>()
+        .join("; ");
+    drop(batch);
+    state.log(LogEvent::info(
+        Stage::Summary,
+        format!("Пакетный запуск завершён: {}", summary),
+    ));
+}
+
+fn run_profile(index: usize, spec: JobSpec, fork: JobState, state: &JobState) {
+    set_status(state, index, BatchStatus::Running);
+    job::prepare(&spec, &fork);
+    job::execute(spec, fork.clone());
+    let status = if fork.is_cancelled() {
+        BatchStatus::Cancelled
+    } else {
+        let results = fork.results.lock().unwrap();
+        BatchStatus::Done {
+            succeeded: results.iter().filter(|r| r.status == ItemStatus::Success).count(),
+            failed: results.iter().filter(|r| r.status == ItemStatus::Failed).count(),
+        }
+    };
+    set_status(state, index, status);
+}
+
+fn set_status(state: &JobState, index: usize, status: BatchStatus) {
+    if let Some(item) = state.batch.lock().unwrap().get_mut(index) {
+        item.status = status;
+    }
+}
+
+// Прогресс, результаты и ошибочные vendor codes профилей собираются в общее состояние,
+// которое показывает интерфейс и отдаёт API
+fn mirror(state: &JobState, forks: &[JobState]) {
+    let batch = state.batch.lock().unwrap().clone();
+    let mut results = Vec::new();
+    let mut failed = Vec::new();
+    let mut progress = state.progress.lock().unwrap().clone();
+    progress.total = 0;
+    progress.processed = 0;
+    progress.scanned = 0;
+    progress.resolved = 0;
+    progress.uploaded = 0;
+    progress.failed = 0;
+    progress.bytes_uploaded = 0;
+    progress.upload_time = Duration::ZERO;
+    progress.current_files.clear();
+    for (fork, item) in forks.iter().zip(&batch) {
+        let fork_progress = fork.progress.lock().unwrap();
+        progress.total += fork_progress.total.max(item.vendor_codes);
+        progress.processed += fork_progress.processed;
+        progress.scanned += fork_progress.scanned;
+        progress.resolved += fork_progress.resolved;
+        progress.uploaded += fork_progress.uploaded;
+        progress.failed += fork_progress.failed;
+        progress.bytes_uploaded += fork_progress.bytes_uploaded;
+        // Профили загружаются одновременно, поэтому время передачи — по самому долгому
+        progress.upload_time = progress.upload_time.max(fork_progress.upload_wall_time());
+        progress
+            .current_files
+            .extend(fork_progress.current_files.iter().cloned());
+        drop(fork_progress);
+        results.extend(fork.results.lock().unwrap().iter().cloned());
+        failed.extend(fork.failed_vendor_codes.lock().unwrap().iter().cloned());
+    }
+    *state.progress.lock().unwrap() = progress;
+    *state.results.lock().unwrap() = results;
+    *state.failed_vendor_codes.lock().unwrap() = failed;
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Сколько публичных ссылок хранится в кэше; старые записи вытесняются
+const MAX_ENTRIES: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CachedItem {
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    public_key: String,
+    revision: String,
+    // Путь и настройки сканирования, при которых получен список
+    signature: String,
+    saved_at: DateTime<Local>,
+    items: Vec<CachedItem>,
+}
+
+// Кэш полного списка медиафайлов публичной папки Яндекс.Диска по ссылке и ревизии
+#[derive(Clone)]
+pub struct ScanCache {
+    path: PathBuf,
+}
+
+impl ScanCache {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn load_entries(&self) -> Vec<CacheEntry> {
+        let Ok(data) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            log::warn!("Ошибка парсинга кэша сканирования: {}", e);
+            Vec::new()
+        })
+    }
+
+    pub fn get(
+        &self,
+        public_key: &str,
+        revision: &str,
+        signature: &str,
+    ) -> Option<Vec<CachedItem>> {
+        self.load_entries()
+            .into_iter()
+            .find(|e| {
+                e.public_key == public_key && e.revision == revision && e.signature == signature
+            })
+            .map(|e| e.items)
+    }
+
+    pub fn put(
+        &self,
+        public_key: &str,
+        revision: &str,
+        signature: &str,
+        items: Vec<CachedItem>,
+    ) -> Result<()> {
+        let mut entries = self.load_entries();
+        entries.retain(|e| !(e.public_key == public_key && e.signature == signature));
+        entries.push(CacheEntry {
+            public_key: public_key.to_string(),
+            revision: revision.to_string(),
+            signature: signature.to_string(),
+            saved_at: Local::now(),
+            items,
+        });
+        entries.sort_by_key(|e| std::cmp::Reverse(e.saved_at));
+        entries.truncate(MAX_ENTRIES);
+        let data = serde_json::to_string(&entries)
+            .map_err(|e| anyhow::anyhow!("Ошибка сериализации кэша сканирования: {}", e))?;
+        std::fs::write(&self.path, data).map_err(|e| {
+            anyhow::anyhow!(
+                "Не удалось записать кэш сканирования {}: {}",
+                self.path.display(),
+                e
+            )
+        })
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+        std::fs::remove_file(&self.path).map_err(|e| {
+            anyhow::anyhow!(
+                "Не удалось удалить кэш сканирования {}: {}",
+                self.path.display(),
+                e
+            )
+        })?;
+        log::info!("Кэш сканирования очищен");
+        Ok(())
+    }
+}
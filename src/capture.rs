@@ -0,0 +1,171 @@
+use crate::http::{Body, HttpClient, Method, Request, Response};
+use anyhow::Result;
+use chrono::Local;
+use serde_json::{Value, json};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// Тело ответа длиннее обрезается: для обращения в поддержку WB важны JSON-ответы, а не файлы
+const MAX_BODY: usize = 64 * 1024;
+
+// Заголовки с ключами и сессиями в файл не попадают
+const SECRET_HEADERS: [&str; 4] = ["authorization", "cookie", "set-cookie", "proxy-authorization"];
+
+// Запись всех HTTP-запросов запуска с ответами в JSONL: одна строка — один обмен.
+// Файл можно приложить к обращению в поддержку WB, ключ API в него не записывается
+pub struct Capture {
+    path: PathBuf,
+    file: Mutex<BufWriter<File>>,
+}
+
+impl Capture {
+    pub fn create(dir: &Path, name: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| anyhow::anyhow!("Не удалось создать папку {}: {}", dir.display(), e))?;
+        let path = dir.join(format!("{}.jsonl", name));
+        let file = File::create(&path)
+            .map_err(|e| anyhow::anyhow!("Не удалось создать файл {}: {}", path.display(), e))?;
+        Ok(Self {
+            path,
+            file: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// Получатель записанных обменов: файл запуска или подробный журнал HTTP
+pub trait ExchangeSink: Send + Sync {
+    // Пока получатель выключен, обмены не собираются совсем
+    fn active(&self) -> bool {
+        true
+    }
+
+    fn record(&self, entry: &Value);
+}
+
+impl ExchangeSink for Capture {
+    fn record(&self, entry: &Value) {
+        let mut file = self.file.lock().unwrap();
+        let written = writeln!(file, "{}", entry).and_then(|_| file.flush());
+        if let Err(e) = written {
+            log::warn!("Не удалось записать запрос в {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+// Клиент-обёртка: отправляет запрос через inner и передаёт обмен получателю
+pub struct CapturingClient {
+    inner: Arc<dyn HttpClient>,
+    sink: Arc<dyn ExchangeSink>,
+}
+
+impl CapturingClient {
+    pub fn new(inner: Arc<dyn HttpClient>, sink: Arc<dyn ExchangeSink>) -> Self {
+        Self { inner, sink }
+    }
+}
+
+impl HttpClient for CapturingClient {
+    fn send(&self, request: Request) -> Result<Response> {
+        if !self.sink.active() {
+            return self.inner.send(request);
+        }
+        let started = Local::now();
+        let timer = Instant::now();
+        let captured = request_json(&request);
+        let response = self.inner.send(request);
+        let mut entry = json!({
+            "started": started.to_rfc3339(),
+            "time_ms": timer.elapsed().as_millis() as u64,
+            "request": captured,
+        });
+        match &response {
+            Ok(response) => {
+                entry["response"] = json!({
+                    "status": response.status().as_u16(),
+                    "url": response.url,
+                    "headers": headers_json(&response.headers),
+                    "body": body_text(&response.body),
+                })
+            }
+            Err(e) => entry["error"] = json!(e.to_string()),
+        }
+        self.sink.record(&entry);
+        response
+    }
+
+    // Скачанный файл не записывается, только запрос и число байт
+    fn download(&self, request: Request, out: &mut dyn Write) -> Result<u64> {
+        if !self.sink.active() {
+            return self.inner.download(request, out);
+        }
+        let started = Local::now();
+        let timer = Instant::now();
+        let captured = request_json(&request);
+        let result = self.inner.download(request, out);
+        let mut entry = json!({
+            "started": started.to_rfc3339(),
+            "time_ms": timer.elapsed().as_millis() as u64,
+            "request": captured,
+        });
+        match &result {
+            Ok(bytes) => entry["response"] = json!({ "downloaded_bytes": bytes }),
+            Err(e) => entry["error"] = json!(e.to_string()),
+        }
+        self.sink.record(&entry);
+        result
+    }
+}
+
+fn request_json(request: &Request) -> Value {
+    let body = match &request.body {
+        Body::Empty => Value::Null,
+        Body::Json(bytes) => serde_json::from_slice(bytes).unwrap_or_else(|_| body_text(bytes)),
+        Body::Multipart(part) => json!({
+            "field": part.field,
+            "file_name": part.file_name,
+            "mime": part.mime,
+            "length": part.length,
+        }),
+    };
+    json!({
+        "method": match request.method {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        },
+        "url": request.url,
+        "headers": headers_json(&request.headers),
+        "body": body,
+    })
+}
+
+fn headers_json(headers: &[(String, String)]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let secret = SECRET_HEADERS
+                .iter()
+                .any(|secret| name.eq_ignore_ascii_case(secret));
+            json!({ "name": name, "value": if secret { "***" } else { value.as_str() } })
+        })
+        .collect()
+}
+
+// Текст тела: JSON-ответ как есть, двоичные данные — только размер
+fn body_text(body: &[u8]) -> Value {
+    match std::str::from_utf8(body) {
+        Ok(text) if text.len() > MAX_BODY => json!(format!(
+            "{}… (обрезано, всего {} байт)",
+            &text[..text.floor_char_boundary(MAX_BODY)],
+            text.len()
+        )),
+        Ok(text) => json!(text),
+        Err(_) => json!(format!("<двоичные данные, {} байт>", body.len())),
+    }
+}
@@ -0,0 +1,119 @@
+use crate::card_update;
+use crate::import;
+use crate::uploader::Characteristic;
+use anyhow::Result;
+use std::path::Path;
+
+const VENDOR_CODE_COLUMNS: [&str; 3] = ["vendorcode", "vendor code", "артикул"];
+const SUBJECT_COLUMNS: [&str; 3] = ["subjectid", "предмет", "категория"];
+const TITLE_COLUMNS: [&str; 3] = ["title", "название", "наименование"];
+const DESCRIPTION_COLUMNS: [&str; 2] = ["description", "описание"];
+const BRAND_COLUMNS: [&str; 2] = ["brand", "бренд"];
+const PHOTOS_COLUMNS: [&str; 2] = ["photos", "фото"];
+
+// Новая карточка из строки таблицы. photos — публичная ссылка Яндекс.Диска
+// или локальная папка, где фото ищутся по vendorCode, как при обычной загрузке
+#[derive(Clone)]
+pub struct NewCard {
+    pub vendor_code: String,
+    pub subject_id: i64,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub brand: Option<String>,
+    pub photos: Option<String>,
+    pub characteristics: Vec<(String, String)>,
+}
+
+// Первая строка — заголовок: vendorCode, subjectID (ID предмета WB), название, описание,
+// бренд, фото; остальные столбцы — названия характеристик WB
+pub fn load(path: &Path) -> Result<Vec<NewCard>> {
+    let rows = import::read_table(path)?;
+    let Some(header) = rows.first() else {
+        return Err(anyhow::anyhow!("Файл {} пуст", path.display()));
+    };
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_string()).collect();
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.contains(&h.to_lowercase().as_str()))
+    };
+    let vendor_code_column = column(&VENDOR_CODE_COLUMNS)
+        .ok_or_else(|| anyhow::anyhow!("В файле {} нет столбца vendorCode", path.display()))?;
+    let subject_column = column(&SUBJECT_COLUMNS)
+        .ok_or_else(|| anyhow::anyhow!("В файле {} нет столбца subjectID", path.display()))?;
+    let known = [
+        Some(vendor_code_column),
+        Some(subject_column),
+        column(&TITLE_COLUMNS),
+        column(&DESCRIPTION_COLUMNS),
+        column(&BRAND_COLUMNS),
+        column(&PHOTOS_COLUMNS),
+    ];
+    let characteristic_columns: Vec<usize> = (0..header.len())
+        .filter(|c| !known.contains(&Some(*c)) && !header[*c].is_empty())
+        .collect();
+    let cell = |row: &[String], column: Option<usize>| {
+        column
+            .and_then(|c| row.get(c))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let mut cards = Vec::new();
+    for (index, row) in rows.iter().enumerate().skip(1) {
+        let Some(vendor_code) = cell(row, Some(vendor_code_column)) else {
+            continue;
+        };
+        let subject = cell(row, Some(subject_column)).unwrap_or_default();
+        let subject_id = subject.parse().map_err(|_| {
+            anyhow::anyhow!(
+                "Строка {}: subjectID для vendorCode {} должен быть числом, указано «{}»",
+                index + 1,
+                vendor_code,
+                subject
+            )
+        })?;
+        cards.push(NewCard {
+            vendor_code,
+            subject_id,
+            title: cell(row, column(&TITLE_COLUMNS)),
+            description: cell(row, column(&DESCRIPTION_COLUMNS)),
+            brand: cell(row, column(&BRAND_COLUMNS)),
+            photos: cell(row, column(&PHOTOS_COLUMNS)),
+            characteristics: characteristic_columns
+                .iter()
+                .filter_map(|c| Some((header[*c].clone(), cell(row, Some(*c))?)))
+                .collect(),
+        });
+    }
+    log::info!(
+        "Загружено новых карточек из {}: {}",
+        path.display(),
+        cards.len()
+    );
+    Ok(cards)
+}
+
+// Тело для cards/upload: одна карточка без склейки с другими
+pub fn body(card: &NewCard, reference: &[Characteristic]) -> Result<serde_json::Value> {
+    let characteristics = card_update::characteristic_entries(
+        &card.characteristics,
+        reference,
+        &card.subject_id.to_string(),
+    )?;
+    let mut variant = serde_json::Map::new();
+    variant.insert("vendorCode".to_string(), card.vendor_code.clone().into());
+    for (field, value) in [
+        ("title", &card.title),
+        ("description", &card.description),
+        ("brand", &card.brand),
+    ] {
+        if let Some(value) = value {
+            variant.insert(field.to_string(), value.clone().into());
+        }
+    }
+    variant.insert("characteristics".to_string(), characteristics.into());
+    Ok(serde_json::json!({
+        "subjectID": card.subject_id,
+        "variants": [variant],
+    }))
+}
@@ -0,0 +1,184 @@
+use crate::import;
+use crate::uploader::Characteristic;
+use anyhow::Result;
+use std::path::Path;
+
+// Поля карточки, которые принимает cards/update; остальное из cards/list не передаётся
+const UPDATE_FIELDS: [&str; 8] = [
+    "nmID",
+    "vendorCode",
+    "brand",
+    "title",
+    "description",
+    "dimensions",
+    "characteristics",
+    "sizes",
+];
+
+const VENDOR_CODE_COLUMNS: [&str; 3] = ["vendorcode", "vendor code", "артикул"];
+const TITLE_COLUMNS: [&str; 3] = ["title", "название", "наименование"];
+const DESCRIPTION_COLUMNS: [&str; 2] = ["description", "описание"];
+
+// Несколько значений характеристики в одной ячейке: «хлопок; полиэстер»
+const VALUE_SEPARATOR: char = ';';
+
+// Новые значения полей карточки из строки таблицы; пустая ячейка — поле не меняется
+#[derive(Clone)]
+pub struct CardUpdate {
+    pub vendor_code: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    // Название характеристики из заголовка и значение
+    pub characteristics: Vec<(String, String)>,
+}
+
+// Первая строка — заголовок: vendorCode (артикул), название (title), описание (description),
+// остальные столбцы — названия характеристик WB («Цвет», «Состав»)
+pub fn load(path: &Path) -> Result<Vec<CardUpdate>> {
+    let rows = import::read_table(path)?;
+    let Some(header) = rows.first() else {
+        return Err(anyhow::anyhow!("Файл {} пуст", path.display()));
+    };
+    let header: Vec<String> = header.iter().map(|h| h.trim().to_string()).collect();
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.contains(&h.to_lowercase().as_str()))
+    };
+    let vendor_code_column = column(&VENDOR_CODE_COLUMNS)
+        .ok_or_else(|| anyhow::anyhow!("В файле {} нет столбца vendorCode", path.display()))?;
+    let title_column = column(&TITLE_COLUMNS);
+    let description_column = column(&DESCRIPTION_COLUMNS);
+    let characteristic_columns: Vec<usize> = (0..header.len())
+        .filter(|c| {
+            ![Some(vendor_code_column), title_column, description_column].contains(&Some(*c))
+                && !header[*c].is_empty()
+        })
+        .collect();
+    if title_column.is_none() && description_column.is_none() && characteristic_columns.is_empty() {
+        return Err(anyhow::anyhow!(
+            "В файле {} нет столбцов с изменениями",
+            path.display()
+        ));
+    }
+    let cell = |row: &[String], column: Option<usize>| {
+        column
+            .and_then(|c| row.get(c))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let updates: Vec<CardUpdate> = rows[1..]
+        .iter()
+        .filter_map(|row| {
+            let vendor_code = cell(row, Some(vendor_code_column))?;
+            Some(CardUpdate {
+                vendor_code,
+                title: cell(row, title_column),
+                description: cell(row, description_column),
+                characteristics: characteristic_columns
+                    .iter()
+                    .filter_map(|c| Some((header[*c].clone(), cell(row, Some(*c))?)))
+                    .collect(),
+            })
+        })
+        .collect();
+    log::info!(
+        "Загружено изменений карточек из {}: {}",
+        path.display(),
+        updates.len()
+    );
+    Ok(updates)
+}
+
+// Тело для cards/update из текущей карточки с новыми значениями.
+// Названия характеристик проверяются по справочнику категории карточки
+pub fn apply(
+    card: &serde_json::Value,
+    update: &CardUpdate,
+    reference: &[Characteristic],
+) -> Result<serde_json::Value> {
+    let mut body = serde_json::Map::new();
+    for field in UPDATE_FIELDS {
+        if let Some(value) = card.get(field) {
+            body.insert(field.to_string(), value.clone());
+        }
+    }
+    if let Some(title) = &update.title {
+        body.insert("title".to_string(), title.clone().into());
+    }
+    if let Some(description) = &update.description {
+        body.insert("description".to_string(), description.clone().into());
+    }
+    if update.characteristics.is_empty() {
+        return Ok(serde_json::Value::Object(body));
+    }
+    let mut characteristics = card["characteristics"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let category = card["subjectName"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| card["subjectID"].to_string());
+    for entry in characteristic_entries(&update.characteristics, reference, &category)? {
+        match characteristics.iter_mut().find(|c| c["id"] == entry["id"]) {
+            Some(existing) => *existing = entry,
+            None => characteristics.push(entry),
+        }
+    }
+    body.insert("characteristics".to_string(), characteristics.into());
+    Ok(serde_json::Value::Object(body))
+}
+
+// Характеристики из таблицы в формате WB {"id", "value"}; category — для сообщений об ошибках
+pub fn characteristic_entries(
+    values: &[(String, String)],
+    reference: &[Characteristic],
+    category: &str,
+) -> Result<Vec<serde_json::Value>> {
+    values
+        .iter()
+        .map(|(name, value)| {
+            let Some(charc) = reference
+                .iter()
+                .find(|c| c.name.to_lowercase() == name.to_lowercase())
+            else {
+                return Err(anyhow::anyhow!(
+                    "Характеристика «{}» не найдена в справочнике категории «{}»",
+                    name,
+                    category
+                ));
+            };
+            let value = characteristic_value(charc, value)?;
+            Ok(serde_json::json!({ "id": charc.id, "value": value }))
+        })
+        .collect()
+}
+
+// Числовые характеристики передаются числом, остальные — списком строк
+fn characteristic_value(charc: &Characteristic, value: &str) -> Result<serde_json::Value> {
+    if charc.is_numeric() {
+        let number: f64 = value.replace(',', ".").parse().map_err(|_| {
+            anyhow::anyhow!(
+                "Характеристика «{}»: ожидается число, указано «{}»",
+                charc.name,
+                value
+            )
+        })?;
+        return Ok(serde_json::json!(number));
+    }
+    let values: Vec<String> = value
+        .split(VALUE_SEPARATOR)
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if charc.max_count > 0 && values.len() > charc.max_count {
+        return Err(anyhow::anyhow!(
+            "Характеристика «{}»: не больше {} значений, указано {}",
+            charc.name,
+            charc.max_count,
+            values.len()
+        ));
+    }
+    Ok(serde_json::json!(values))
+}
@@ -0,0 +1,45 @@
+use crate::profile::Profile;
+use crate::uploader::{CardQuery, CardSummary, WbUploader};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+// Больше карточек в таблице поиска не показывается
+pub const SEARCH_LIMIT: usize = 1000;
+
+// Результат поиска карточек для вкладки «Карточки WB»; выбранные строки — по nmId
+#[derive(Clone, Default)]
+pub struct CardSearchState {
+    pub running: Arc<Mutex<bool>>,
+    pub cards: Arc<Mutex<Vec<CardSummary>>>,
+    pub selected: Arc<Mutex<HashSet<i64>>>,
+    pub error: Arc<Mutex<Option<String>>>,
+}
+
+pub fn spawn(profile: Profile, query: CardQuery, state: CardSearchState) {
+    *state.running.lock().unwrap() = true;
+    state.cards.lock().unwrap().clear();
+    state.selected.lock().unwrap().clear();
+    *state.error.lock().unwrap() = None;
+    std::thread::spawn(move || {
+        let query = CardQuery {
+            limit: SEARCH_LIMIT,
+            ..query
+        };
+        let cards = WbUploader::for_profile(&profile).and_then(|uploader| {
+            Ok(uploader.search_cards(&query, |count| {
+                log::debug!("Поиск карточек: получено {}", count)
+            })?)
+        });
+        match cards {
+            Ok(cards) => {
+                log::info!("Поиск карточек: найдено {}", cards.len());
+                *state.cards.lock().unwrap() = cards;
+            }
+            Err(e) => {
+                log::error!("Ошибка поиска карточек: {}", e);
+                *state.error.lock().unwrap() = Some(e.to_string());
+            }
+        }
+        *state.running.lock().unwrap() = false;
+    });
+}
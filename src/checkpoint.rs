@@ -0,0 +1,86 @@
+use crate::job::{JobSource, JobSpec};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+// Файл незавершённого запуска: первая строка — задание, дальше по строке на каждый
+// обработанный vendorCode. Строки дописываются сразу после обработки, поэтому после сбоя
+// или отключения питания запуск продолжается с места остановки, а не заново
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Checkpoint {
+    pub profile: String,
+    pub vendor_codes: Vec<String>,
+    pub source: JobSource,
+    pub force_reupload: bool,
+    pub started_at: DateTime<Local>,
+    // Обработанные vendor codes — строки после заголовка
+    #[serde(skip)]
+    pub completed: Vec<String>,
+}
+
+impl Checkpoint {
+    // vendor codes, до которых прерванный запуск не дошёл, в исходном порядке
+    pub fn remaining(&self) -> Vec<String> {
+        self.vendor_codes
+            .iter()
+            .filter(|vendor_code| !self.completed.contains(vendor_code))
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn begin(path: &Path, spec: &JobSpec) -> Result<()> {
+    let checkpoint = Checkpoint {
+        profile: spec.profile.clone(),
+        vendor_codes: spec.vendor_codes.clone(),
+        source: spec.source.clone(),
+        force_reupload: spec.force_reupload,
+        started_at: spec.started_at,
+        completed: Vec::new(),
+    };
+    let header = serde_json::to_string(&checkpoint)
+        .map_err(|e| anyhow::anyhow!("Ошибка сериализации точки восстановления: {}", e))?;
+    std::fs::write(path, format!("{}\n", header)).map_err(|e| {
+        anyhow::anyhow!(
+            "Не удалось записать точку восстановления {}: {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+pub fn record(path: &Path, vendor_code: &str) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось открыть точку восстановления {}: {}", path.display(), e))?;
+    writeln!(file, "{}", vendor_code)
+        .and_then(|()| file.sync_data())
+        .map_err(|e| anyhow::anyhow!("Не удалось дописать точку восстановления {}: {}", path.display(), e))
+}
+
+// Запуск дошёл до конца или остановлен пользователем — продолжать нечего
+pub fn clear(path: &Path) {
+    if path.exists()
+        && let Err(e) = std::fs::remove_file(path)
+    {
+        log::warn!("Не удалось удалить точку восстановления {}: {}", path.display(), e);
+    }
+}
+
+// None, если прошлый запуск завершился штатно или файл повреждён
+pub fn load(path: &Path) -> Option<Checkpoint> {
+    let data = std::fs::read_to_string(path).ok()?;
+    let mut lines = data.lines();
+    let mut checkpoint: Checkpoint = serde_json::from_str(lines.next()?)
+        .map_err(|e| log::warn!("Ошибка парсинга точки восстановления: {}", e))
+        .ok()?;
+    checkpoint.completed = lines
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    Some(checkpoint)
+}
@@ -0,0 +1,107 @@
+use crate::cache::ScanCache;
+use crate::downloader::Matching;
+use crate::job::JobSource;
+use crate::preview;
+use crate::profile::Profile;
+use crate::uploader::WbUploader;
+use crate::utils::is_video_file;
+use std::sync::{Arc, Mutex};
+
+// Сравнение числа фото vendorCode в источнике и в карточке WB
+#[derive(Clone)]
+pub struct CompareRow {
+    pub vendor_code: String,
+    pub nm_id: Option<i64>,
+    pub source: usize,
+    pub wb: Option<usize>,
+    // Сколько фото будет в карточке после загрузки
+    pub after: Option<usize>,
+    pub error: Option<String>,
+}
+
+impl CompareRow {
+    // > 0 — карточка получит фото, < 0 — потеряет
+    pub fn change(&self) -> Option<i64> {
+        Some(self.after? as i64 - self.wb? as i64)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CompareState {
+    pub running: Arc<Mutex<bool>>,
+    pub rows: Arc<Mutex<Vec<CompareRow>>>,
+    pub error: Arc<Mutex<Option<String>>>,
+}
+
+// download_yandex_files — резервный режим, в котором файлы Яндекс.Диска идут через media/file
+pub fn spawn(
+    source: JobSource,
+    vendor_codes: Vec<String>,
+    matching: Matching,
+    scan_cache: Option<ScanCache>,
+    profile: Profile,
+    download_yandex_files: bool,
+    state: CompareState,
+) {
+    *state.running.lock().unwrap() = true;
+    state.rows.lock().unwrap().clear();
+    *state.error.lock().unwrap() = None;
+    std::thread::spawn(move || {
+        // Ссылки заменяют всю галерею, локальные файлы — только фото со своими номерами
+        let replaces_gallery = matches!(source, JobSource::Yandex { .. }) && !download_yandex_files;
+        let scanned = WbUploader::for_profile(&profile).and_then(|uploader| {
+            let (_, files) = preview::scan(
+                &source,
+                vendor_codes.clone(),
+                matching,
+                scan_cache,
+                &profile.timeouts,
+            )?;
+            Ok((uploader, files))
+        });
+        match scanned {
+            Ok((uploader, files)) => {
+                for vendor_code in &vendor_codes {
+                    let numbers: Vec<u32> = files
+                        .iter()
+                        .filter(|f| &f.articul == vendor_code && !is_video_file(&f.name))
+                        .map(|f| f.photo_number)
+                        .collect();
+                    let mut row = CompareRow {
+                        vendor_code: vendor_code.clone(),
+                        nm_id: None,
+                        source: numbers.len(),
+                        wb: None,
+                        after: None,
+                        error: None,
+                    };
+                    match uploader.get_card(vendor_code) {
+                        Ok(card) => {
+                            let wb = card.photos.len();
+                            row.nm_id = Some(card.nm_id);
+                            row.wb = Some(wb);
+                            row.after = Some(if numbers.is_empty() {
+                                wb
+                            } else if replaces_gallery {
+                                numbers.len()
+                            } else {
+                                wb.max(numbers.iter().copied().max().unwrap_or(0) as usize)
+                            });
+                        }
+                        Err(e) => row.error = Some(e.to_string()),
+                    }
+                    state.rows.lock().unwrap().push(row);
+                }
+                log::info!(
+                    "Сравнение с WB: обработано vendor codes {}",
+                    vendor_codes.len()
+                );
+            }
+            Err(e) => {
+                log::error!("Ошибка сравнения с WB: {}", e);
+                *state.error.lock().unwrap() = Some(e.to_string());
+            }
+        }
+        *state.running.lock().unwrap() = false;
+    });
+}
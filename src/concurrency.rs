@@ -0,0 +1,104 @@
+use std::sync::{Condvar, Mutex};
+
+// Сколько ответов 429 подряд (без серии успехов между ними) уменьшают параллельность вдвое
+const RATE_LIMITED_TO_DECREASE: usize = 2;
+// Сколько успешных ответов подряд добавляют один поток
+const SUCCESSES_TO_INCREASE: usize = 20;
+
+// Число одновременных загрузок в WB, подстраиваемое под ответы 429: после повторных 429
+// потоков становится вдвое меньше, после серии успешных ответов — на один больше, но не
+// больше заданного в настройках. Так загрузка держится у реального предела API без ручной настройки
+pub struct AdaptiveLimit {
+    max: usize,
+    state: Mutex<LimitState>,
+    released: Condvar,
+}
+
+struct LimitState {
+    limit: usize,
+    active: usize,
+    rate_limited: usize,
+    successes: usize,
+}
+
+// Разрешение на одну загрузку; место освобождается при удалении
+pub struct Permit<'a> {
+    limit: &'a AdaptiveLimit,
+}
+
+impl AdaptiveLimit {
+    pub fn new(max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            max,
+            state: Mutex::new(LimitState {
+                limit: max,
+                active: 0,
+                rate_limited: 0,
+                successes: 0,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    // Текущее число потоков
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    // Ждёт, пока число идущих загрузок станет меньше текущего предела
+    pub fn acquire(&self) -> Permit<'_> {
+        let mut state = self.state.lock().unwrap();
+        while state.active >= state.limit {
+            state = self.released.wait(state).unwrap();
+        }
+        state.active += 1;
+        Permit { limit: self }
+    }
+
+    pub fn on_rate_limited(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.successes = 0;
+        state.rate_limited += 1;
+        if state.rate_limited < RATE_LIMITED_TO_DECREASE {
+            return;
+        }
+        state.rate_limited = 0;
+        if state.limit > 1 {
+            state.limit = (state.limit / 2).max(1);
+            log::warn!(
+                "Повторные ответы 429 от WB: параллельных загрузок теперь {}",
+                state.limit
+            );
+        }
+    }
+
+    pub fn on_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.successes += 1;
+        if state.successes < SUCCESSES_TO_INCREASE {
+            return;
+        }
+        state.successes = 0;
+        state.rate_limited = 0;
+        if state.limit < self.max {
+            state.limit += 1;
+            log::info!(
+                "WB отвечает без 429: параллельных загрузок теперь {}",
+                state.limit
+            );
+            self.released.notify_all();
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.limit.state.lock().unwrap().active -= 1;
+        self.limit.released.notify_all();
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 use directories::ProjectDirs;
 use std::path::PathBuf;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Config {
     config_dir: PathBuf,
 }
@@ -12,7 +12,11 @@ impl Config {
         log::info!("Инициализация конфигурации");
         let proj_dirs = ProjectDirs::from("com", "yandex", "downloader")
             .ok_or_else(|| anyhow::anyhow!("Не удалось определить директорию конфигурации"))?;
-        let config_dir = proj_dirs.config_dir().to_path_buf();
+        let mut config_dir = proj_dirs.config_dir().to_path_buf();
+        // Демо-режим не трогает профили, настройки и историю рабочей конфигурации
+        if crate::demo::is_enabled() {
+            config_dir.push("demo");
+        }
         std::fs::create_dir_all(&config_dir).map_err(|e| {
             anyhow::anyhow!(
                 "Не удалось создать директорию конфигурации {}: {}",
@@ -28,8 +32,68 @@ impl Config {
         self.config_dir.join("profiles.json")
     }
 
+    pub fn get_settings_file_path(&self) -> PathBuf {
+        self.config_dir.join("settings.json")
+    }
+
+    pub fn get_log_file_path(&self) -> PathBuf {
+        self.config_dir.join("app.log")
+    }
+
+    pub fn get_jobs_file_path(&self) -> PathBuf {
+        self.config_dir.join("jobs.json")
+    }
+
+    pub fn get_history_db_path(&self) -> PathBuf {
+        self.config_dir.join("history.sqlite")
+    }
+
+    pub fn get_reports_dir(&self) -> PathBuf {
+        let dir = self.config_dir.join("reports");
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::error!(
+                "Не удалось создать директорию отчётов {}: {}",
+                dir.display(),
+                e
+            );
+        }
+        dir
+    }
+
+    pub fn get_scan_cache_path(&self) -> PathBuf {
+        self.config_dir.join("scan_cache.json")
+    }
+
+    pub fn get_reference_cache_path(&self) -> PathBuf {
+        self.config_dir.join("wb_reference.json")
+    }
+
+    pub fn get_ui_state_file_path(&self) -> PathBuf {
+        self.config_dir.join("ui_state.json")
+    }
+
+    pub fn get_crash_file_path(&self) -> PathBuf {
+        self.config_dir.join("crash.log")
+    }
+
+    pub fn get_last_crash_file_path(&self) -> PathBuf {
+        self.config_dir.join("crash_last.log")
+    }
+
+    pub fn get_checkpoint_file_path(&self) -> PathBuf {
+        self.config_dir.join("checkpoint.jsonl")
+    }
+
+    pub fn get_staging_dir(&self) -> PathBuf {
+        self.config_dir.join("staging")
+    }
+
+    pub fn get_captures_dir(&self) -> PathBuf {
+        self.config_dir.join("captures")
+    }
+
     #[allow(dead_code)]
     pub fn get_cookies_file_path(&self) -> PathBuf {
         self.config_dir.join("cookies.json")
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,71 @@
+use crate::config::Config;
+use crate::logs::LogBuffer;
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, TryLockError};
+
+// Сколько последних событий журнала попадает в отчёт о сбое
+const CRASH_LOG_ENTRIES: usize = 200;
+
+// При панике в любом потоке отчёт пишется в crash.log рядом с конфигурацией;
+// стандартный обработчик вызывается после записи
+pub fn install(config: &Config, logs: Arc<Mutex<LogBuffer>>) {
+    let path = config.get_crash_file_path();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let report = format_report(info, &logs);
+        match std::fs::write(&path, report) {
+            Ok(()) => log::error!("Отчёт о сбое сохранён в {}", path.display()),
+            Err(e) => log::error!("Не удалось записать отчёт о сбое {}: {}", path.display(), e),
+        }
+        default_hook(info);
+    }));
+}
+
+// Отчёт прошлого запуска, если он завершился сбоем; переносится в crash_last.log,
+// чтобы диалог показывался один раз
+pub fn take_report(config: &Config) -> Option<PathBuf> {
+    let path = config.get_crash_file_path();
+    if !path.exists() {
+        return None;
+    }
+    let last = config.get_last_crash_file_path();
+    match std::fs::rename(&path, &last) {
+        Ok(()) => Some(last),
+        Err(e) => {
+            log::error!("Не удалось перенести отчёт о сбое {}: {}", path.display(), e);
+            Some(path)
+        }
+    }
+}
+
+fn format_report(info: &PanicHookInfo, logs: &Mutex<LogBuffer>) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "без сообщения".to_string());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "неизвестно".to_string());
+    // Паника могла случиться, пока журнал захвачен этим же потоком: ждать его нельзя
+    let entries = match logs.try_lock() {
+        Ok(buffer) => buffer.last_text(CRASH_LOG_ENTRIES),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().last_text(CRASH_LOG_ENTRIES),
+        Err(TryLockError::WouldBlock) => "Журнал недоступен: он был захвачен в момент сбоя".to_string(),
+    };
+    format!(
+        "Сбой: {}\nВремя: {}\nВерсия: {}\nПоток: {}\nМесто: {}\n\nСтек вызовов:\n{}\n\nПоследние события журнала:\n{}\n",
+        message,
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        env!("CARGO_PKG_VERSION"),
+        std::thread::current().name().unwrap_or("без имени"),
+        location,
+        Backtrace::force_capture(),
+        entries
+    )
+}
@@ -0,0 +1,390 @@
+use crate::http::{Body, HttpClient, Request, Response};
+use anyhow::Result;
+use chrono::Local;
+use image::{ImageFormat, Rgb, RgbImage};
+use reqwest::{StatusCode, Url};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+// Демо-режим: запросы к WB и Яндекс.Диску обслуживает DemoClient внутри процесса,
+// поэтому весь сценарий можно пройти без API-ключа и сети
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CLIENT: LazyLock<Arc<DemoClient>> = LazyLock::new(|| Arc::new(DemoClient::new()));
+
+pub const PUBLIC_LINK: &str = "https://disk.yandex.ru/d/demo-wb";
+pub const API_KEY: &str = "demo";
+pub const VENDOR_CODES: [&str; 5] = ["DEMO-001", "DEMO-002", "DEMO-003", "DEMO-004", "DEMO-005"];
+
+const FILES_HOST: &str = "demo-files.local";
+
+// Лимит создания карточек демо-продавца
+const CARD_LIMIT: i64 = 10;
+
+// Содержимое демо-папки: путь и имя файла или папки (имя папки заканчивается на «/»)
+const FOLDER: [(&str, &str); 9] = [
+    ("/", "DEMO-001_1.jpg"),
+    ("/", "DEMO-001_2.jpg"),
+    ("/", "DEMO-001_3.jpg"),
+    ("/", "DEMO-002_1.jpg"),
+    ("/", "DEMO-002_2.jpg"),
+    ("/", "DEMO-002_4.jpg"),
+    ("/", "Съёмка/"),
+    ("/Съёмка", "DEMO-003_1.jpg"),
+    ("/Съёмка", "DEMO-004_1.jpg"),
+];
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+    log::info!("Демо-режим: запросы к WB и Яндекс.Диску обрабатываются без сети");
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Общий демо-клиент, если демо-режим включён: каталог WB меняется загрузками за весь сеанс
+pub fn client() -> Option<Arc<dyn HttpClient>> {
+    is_enabled().then(|| CLIENT.clone() as Arc<dyn HttpClient>)
+}
+
+// Каталог продавца и корзина WB; карточки хранятся в формате cards/list
+pub struct DemoClient {
+    cards: Mutex<Vec<Value>>,
+    trash: Mutex<Vec<Value>>,
+    next_nm_id: Mutex<i64>,
+}
+
+impl Default for DemoClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DemoClient {
+    pub fn new() -> Self {
+        let card = |nm_id: i64, vendor_code: &str, subject: (i64, &str), title: &str, photos: usize| {
+            json!({
+                "nmID": nm_id,
+                "vendorCode": vendor_code,
+                "brand": "Демо",
+                "title": title,
+                "description": format!("{} — карточка демо-режима", title),
+                "subjectID": subject.0,
+                "subjectName": subject.1,
+                "characteristics": [{ "id": 1, "name": "Цвет", "value": ["белый"] }],
+                "sizes": [],
+                "photos": (1..=photos).map(|n| json!({ "big": photo_url(nm_id, n) })).collect::<Vec<_>>(),
+            })
+        };
+        Self {
+            cards: Mutex::new(vec![
+                card(100001, "DEMO-001", (105, "Футболки"), "Футболка базовая", 2),
+                card(100002, "DEMO-002", (69, "Платья"), "Платье летнее", 0),
+                card(100003, "DEMO-003", (104, "Кроссовки"), "Кроссовки беговые", 1),
+            ]),
+            trash: Mutex::new(vec![card(100004, "DEMO-004", (105, "Футболки"), "Футболка удалённая", 3)]),
+            next_nm_id: Mutex::new(100100),
+        }
+    }
+
+    fn wb(&self, url: &Url, body: Option<Value>, request: &Request) -> Response {
+        match url.path() {
+            "/content/v2/get/cards/list" => cards_page(&self.cards.lock().unwrap(), body),
+            "/content/v2/get/cards/trash" => cards_page(&self.trash.lock().unwrap(), body),
+            "/content/v2/cards/recover" => {
+                let nm_ids = nm_ids(&body.unwrap_or_default());
+                let mut trash = self.trash.lock().unwrap();
+                let (recovered, rest): (Vec<Value>, Vec<Value>) = trash
+                    .drain(..)
+                    .partition(|card| nm_ids.contains(&card["nmID"].as_i64().unwrap_or_default()));
+                *trash = rest;
+                self.cards.lock().unwrap().extend(recovered);
+                ok()
+            }
+            "/content/v2/cards/update" => {
+                let mut cards = self.cards.lock().unwrap();
+                for update in body.and_then(|b| b.as_array().cloned()).unwrap_or_default() {
+                    if let Some(card) = cards.iter_mut().find(|c| c["nmID"] == update["nmID"]) {
+                        for field in ["title", "description", "characteristics"] {
+                            card[field] = update[field].clone();
+                        }
+                    }
+                }
+                ok()
+            }
+            "/content/v2/cards/upload" => {
+                let mut cards = self.cards.lock().unwrap();
+                let mut next_nm_id = self.next_nm_id.lock().unwrap();
+                for entry in body.and_then(|b| b.as_array().cloned()).unwrap_or_default() {
+                    for variant in entry["variants"].as_array().cloned().unwrap_or_default() {
+                        let mut card = variant.clone();
+                        card["nmID"] = json!(*next_nm_id);
+                        card["subjectID"] = entry["subjectID"].clone();
+                        card["photos"] = json!([]);
+                        *next_nm_id += 1;
+                        cards.push(card);
+                    }
+                }
+                ok()
+            }
+            // Лимит уменьшается с каждой созданной в демо карточкой
+            "/content/v2/cards/limits" => {
+                let created = *self.next_nm_id.lock().unwrap() - 100100;
+                respond(
+                    StatusCode::OK,
+                    json!({ "data": { "freeLimits": (CARD_LIMIT - created).max(0), "paidLimits": 0 } }),
+                )
+            }
+            "/content/v2/cards/error/list" => {
+                let data: Vec<Value> = self
+                    .cards
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|card| card["vendorCode"] == "DEMO-003")
+                    .map(|card| {
+                        json!({
+                            "vendorCode": card["vendorCode"],
+                            "updateAt": Local::now().to_rfc3339(),
+                            "errors": ["Фото 1: низкое разрешение (демо)"],
+                        })
+                    })
+                    .collect();
+                respond(StatusCode::OK, json!({ "data": data }))
+            }
+            "/content/v2/object/all" => {
+                let first_page = query(url, "offset").is_none_or(|offset| offset == "0");
+                let subjects = if first_page {
+                    json!([
+                        { "subjectID": 105, "subjectName": "Футболки", "parentName": "Одежда" },
+                        { "subjectID": 69, "subjectName": "Платья", "parentName": "Одежда" },
+                        { "subjectID": 104, "subjectName": "Кроссовки", "parentName": "Обувь" },
+                    ])
+                } else {
+                    json!([])
+                };
+                respond(StatusCode::OK, json!({ "data": subjects }))
+            }
+            path if path.starts_with("/content/v2/object/charcs/") => respond(
+                StatusCode::OK,
+                json!({ "data": [
+                    { "charcID": 1, "name": "Цвет", "charcType": 1, "maxCount": 3 },
+                    { "charcID": 2, "name": "Состав", "charcType": 1, "maxCount": 5 },
+                    { "charcID": 3, "name": "Вес товара без упаковки (г)", "charcType": 4, "maxCount": 1 },
+                ]}),
+            ),
+            "/content/v2/cards/rich-content" => ok(),
+            "/content/v3/media/save" => {
+                let body = body.unwrap_or_default();
+                let urls: Vec<Value> = body["data"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|url| json!({ "big": url }))
+                    .collect();
+                self.update_photos(body["nmId"].as_i64(), |photos| *photos = urls)
+            }
+            "/content/v3/media/file" => {
+                let header = |name: &str| {
+                    request
+                        .headers
+                        .iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                        .map(|(_, value)| value.clone())
+                };
+                let nm_id = header("X-Nm-Id").and_then(|v| v.parse().ok());
+                let number: usize = header("X-Photo-Number").and_then(|v| v.parse().ok()).unwrap_or(1);
+                // Видео принимается, но в галерею фото не попадает
+                let photo = matches!(&request.body, Body::Multipart(part) if !part.mime.starts_with("video/"));
+                if !photo {
+                    return self.update_photos(nm_id, |_| {});
+                }
+                self.update_photos(nm_id, |photos| {
+                    let nm_id = nm_id.unwrap_or_default();
+                    while photos.len() < number {
+                        photos.push(json!({ "big": photo_url(nm_id, photos.len() + 1) }));
+                    }
+                    photos[number - 1] = json!({ "big": photo_url(nm_id, number) });
+                })
+            }
+            _ if request.method == crate::http::Method::Post => ok(),
+            _ => not_found(url),
+        }
+    }
+
+    fn update_photos(&self, nm_id: Option<i64>, update: impl FnOnce(&mut Vec<Value>)) -> Response {
+        let mut cards = self.cards.lock().unwrap();
+        let Some(card) = cards.iter_mut().find(|c| c["nmID"].as_i64() == nm_id) else {
+            return respond(
+                StatusCode::BAD_REQUEST,
+                json!({ "error": true, "errorText": "Карточка не найдена" }),
+            );
+        };
+        let mut photos = card["photos"].as_array().cloned().unwrap_or_default();
+        update(&mut photos);
+        card["photos"] = Value::Array(photos);
+        ok()
+    }
+}
+
+impl HttpClient for DemoClient {
+    fn send(&self, mut request: Request) -> Result<Response> {
+        let url = Url::parse(&request.url)?;
+        log::debug!("Демо-режим: {:?} {}", request.method, url);
+        let body = match &mut request.body {
+            Body::Json(bytes) => serde_json::from_slice(bytes).ok(),
+            // Файл читается целиком, чтобы прогресс и ограничение скорости работали как обычно
+            Body::Multipart(part) => {
+                std::io::copy(&mut part.reader, &mut std::io::sink())?;
+                None
+            }
+            Body::Empty => None,
+        };
+        let response = match url.host_str().unwrap_or_default() {
+            "content-api.wildberries.ru" | "content-api-sandbox.wildberries.ru" => self.wb(&url, body, &request),
+            "cloud-api.yandex.net" => yandex(&url),
+            "disk.yandex.ru" if url.path() == "/public/api/check-password" => Response {
+                status: StatusCode::OK,
+                url: url.to_string(),
+                headers: vec![("Set-Cookie".to_string(), "demo=1; Path=/".to_string())],
+                body: b"{}".to_vec(),
+            },
+            FILES_HOST => Response {
+                status: StatusCode::OK,
+                url: url.to_string(),
+                headers: Vec::new(),
+                body: image_bytes(url.path())?,
+            },
+            _ => not_found(&url),
+        };
+        Ok(response)
+    }
+}
+
+fn yandex(url: &Url) -> Response {
+    if query(url, "public_key").as_deref() != Some(PUBLIC_LINK) {
+        return respond(
+            StatusCode::NOT_FOUND,
+            json!({ "error": "DiskNotFoundError", "description": "Демо-режим: доступна только папка демо-ссылки" }),
+        );
+    }
+    let path = query(url, "path").unwrap_or_else(|| "/".to_string());
+    if url.path() == "/v1/disk/public/resources/download" {
+        return respond(StatusCode::OK, json!({ "href": file_url(&path) }));
+    }
+    let fields = query(url, "fields").unwrap_or_default();
+    if fields.contains("revision") {
+        return respond(StatusCode::OK, json!({ "revision": 1 }));
+    }
+    if fields.contains("preview") {
+        return respond(StatusCode::OK, json!({ "preview": file_url(&path) }));
+    }
+    let first_page = query(url, "offset").is_none_or(|offset| offset == "0");
+    let items: Vec<Value> = FOLDER
+        .iter()
+        .filter(|(dir, _)| first_page && *dir == path)
+        .map(|(_, name)| match name.strip_suffix('/') {
+            Some(dir) => json!({ "name": dir, "type": "dir" }),
+            // Хэш и размер — настоящие, как у Яндекс.Диска: по ним проверяется скачанный файл
+            None => {
+                let file_path = format!("{}/{}", path.trim_end_matches('/'), name);
+                let bytes = Url::parse(&file_url(&file_path))
+                    .ok()
+                    .and_then(|url| image_bytes(url.path()).ok())
+                    .unwrap_or_default();
+                json!({
+                    "name": name,
+                    "type": "file",
+                    "sha256": format!("{:x}", Sha256::digest(&bytes)),
+                    "size": bytes.len(),
+                })
+            }
+        })
+        .collect();
+    respond(StatusCode::OK, json!({ "_embedded": { "items": items } }))
+}
+
+// cards/list и cards/trash: поиск по vendorCode, названию и nmID, точное совпадение — первым
+fn cards_page(cards: &[Value], body: Option<Value>) -> Response {
+    let settings = body.unwrap_or_default()["settings"].clone();
+    let text = settings["filter"]["textSearch"].as_str().unwrap_or_default().to_lowercase();
+    let with_photo = settings["filter"]["withPhoto"].as_i64().unwrap_or(-1);
+    let brands: Vec<String> = settings["filter"]["brands"]
+        .as_array()
+        .map(|b| b.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    let mut found: Vec<Value> = cards
+        .iter()
+        .filter(|card| {
+            let searchable = format!("{} {} {}", card["vendorCode"], card["title"], card["nmID"]).to_lowercase();
+            let photos = card["photos"].as_array().map_or(0, Vec::len);
+            searchable.contains(&text)
+                && match with_photo {
+                    0 => photos == 0,
+                    1 => photos > 0,
+                    _ => true,
+                }
+                && (brands.is_empty() || brands.iter().any(|b| card["brand"] == b.as_str()))
+        })
+        .cloned()
+        .collect();
+    found.sort_by_key(|card| card["vendorCode"].as_str().unwrap_or_default().to_lowercase() != text);
+    let total = found.len();
+    respond(StatusCode::OK, json!({ "cards": found, "cursor": { "total": total } }))
+}
+
+fn nm_ids(body: &Value) -> Vec<i64> {
+    body["nmIDs"]
+        .as_array()
+        .map(|ids| ids.iter().filter_map(Value::as_i64).collect())
+        .unwrap_or_default()
+}
+
+fn query(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+fn photo_url(nm_id: i64, number: usize) -> String {
+    format!("https://{}/wb/{}/{}.jpg", FILES_HOST, nm_id, number)
+}
+
+fn file_url(path: &str) -> String {
+    format!("https://{}/disk{}", FILES_HOST, urlencoding::encode(path).replace("%2F", "/"))
+}
+
+// Однотонная картинка, цвет которой зависит от пути: разные файлы различимы в предпросмотре
+fn image_bytes(path: &str) -> Result<Vec<u8>> {
+    let hash = Sha256::digest(path.as_bytes());
+    let image = RgbImage::from_pixel(256, 256, Rgb([hash[0], hash[1], hash[2]]));
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Jpeg)?;
+    Ok(bytes.into_inner())
+}
+
+fn respond(status: StatusCode, body: Value) -> Response {
+    Response {
+        status,
+        url: String::new(),
+        headers: Vec::new(),
+        body: body.to_string().into_bytes(),
+    }
+}
+
+fn ok() -> Response {
+    respond(StatusCode::OK, json!({ "data": null, "error": false, "errorText": "" }))
+}
+
+fn not_found(url: &Url) -> Response {
+    let mut response = respond(
+        StatusCode::NOT_FOUND,
+        json!({ "error": true, "errorText": format!("Демо-режим: адрес {} не поддерживается", url) }),
+    );
+    response.url = url.to_string();
+    response
+}
@@ -1,10 +1,24 @@
+use crate::cache::{CachedItem, ScanCache};
+use crate::capture::{CapturingClient, ExchangeSink};
+use crate::file_server::FileServer;
+use crate::filter::ScanFilter;
+use crate::http::{HttpClient, ReqwestClient, Request, Response};
+use crate::links::{self, PublicLink};
+use crate::mapping::{self, VendorMapping};
+use crate::profile::HttpTimeouts;
+use crate::settings::{MatchOptions, Settings};
+use crate::staging::Staging;
 use crate::utils::is_media_file;
 use anyhow::{Context, Result};
 use regex::Regex;
-use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Duration;
 use urlencoding::encode;
 use walkdir::WalkDir;
@@ -24,6 +38,10 @@ struct Item {
     name: String,
     #[serde(rename = "type")]
     item_type: String,
+    #[serde(default)]
+    sha256: Option<String>,
+    #[serde(default)]
+    size: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -31,12 +49,128 @@ struct DownloadLink {
     href: String,
 }
 
+// Сколько публичных ссылок сканируется одновременно
+const SCAN_WORKERS: usize = 4;
+
+// Попытки запроса к API Яндекс.Диска при ограничении частоты (429) и временных сбоях (5xx,
+// обрыв соединения). Без Retry-After пауза начинается с RETRY_DELAY и удваивается
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(120);
+
+// Ограничение частоты и сбои на стороне Яндекс.Диска проходят сами, их стоит повторить
+fn is_temporary(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Пауза перед повтором без Retry-After: 2, 4, 8… секунд
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_DELAY
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+// Понятное сообщение об ответе API с ошибкой: временное ограничение отличается от
+// постоянных ошибок ссылки, которые повтором не исправить
+fn status_error(public_key: &str, status: StatusCode, body: &str) -> anyhow::Error {
+    match status.as_u16() {
+        401 | 403 => anyhow::anyhow!(
+            "Доступ к {} запрещён: если папка защищена паролем, укажите его после ссылки: ссылка||пароль",
+            public_key
+        ),
+        404 => anyhow::anyhow!(
+            "Не найдено по ссылке {}: папка или файл удалены, либо доступ по ссылке закрыт",
+            public_key
+        ),
+        _ if is_temporary(status) => anyhow::anyhow!(
+            "Яндекс.Диск временно ограничивает запросы к {} (статус {}), повторите позже",
+            public_key,
+            status
+        ),
+        _ => anyhow::anyhow!("Ошибка API Яндекс.Диска: Статус {}, Тело: {}", status, body),
+    }
+}
+
+// Окончание имени после vendorCode: «_1.jpg», «-2.jpg», « 3.jpg», «(4).png», «_01_final.jpg»
+static SUFFIX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:[ _-]*\((\d+)\)|[ _-]+(\d+))(?:[ _-][^.]*)?\.\w+$").unwrap());
+
+// В карточке WB не больше 30 фото: число больше — не номер фото, а дата или код
+// («ABC123-2024-05.jpg», «ABC123_20240501.jpg»)
+const MAX_PHOTO_NUMBER: u32 = 30;
+
+// Номер фото по окончанию имени; файл без номера («ABC123.jpg») — первое фото
+fn photo_number_from_suffix(remaining: &str) -> Option<u32> {
+    if remaining.starts_with('.') {
+        return Some(1);
+    }
+    let caps = SUFFIX_RE.captures(remaining)?;
+    caps.get(1)
+        .or(caps.get(2))?
+        .as_str()
+        .parse()
+        .ok()
+        .filter(|number| (1..=MAX_PHOTO_NUMBER).contains(number))
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct FileInfo {
     pub name: String,
     pub path: String,
     pub articul: String,
     pub photo_number: u32,
+    // SHA-256 содержимого: у файлов Яндекс.Диска — из API, у локальных вычисляется перед загрузкой
+    #[serde(default)]
+    pub hash: Option<String>,
+    // Размер в байтах из списка Яндекс.Диска; у локальных файлов не заполняется
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+// Скачанный в папку staging файл; удаляется вместе со значением, если его не забрали
+// через keep, — в том числе когда загрузка в WB завершилась ошибкой
+pub struct TempDownload {
+    path: PathBuf,
+}
+
+impl TempDownload {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Файл загружен: дальше его судьбу решает вызывающий — удалить через cleanup_file
+    // или оставить в staging до очистки по сроку и размеру
+    pub fn keep(mut self) -> PathBuf {
+        std::mem::take(&mut self.path)
+    }
+}
+
+impl Drop for TempDownload {
+    fn drop(&mut self) {
+        if self.path.exists()
+            && let Err(e) = std::fs::remove_file(&self.path)
+        {
+            log::warn!("Не удалось удалить временный файл {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+// Считает SHA-256 по мере записи, чтобы не перечитывать скачанный файл
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -46,21 +180,85 @@ pub struct MediaOutput {
 }
 
 pub struct Downloader {
-    client: Client,
+    client: Arc<dyn HttpClient>,
+    // Ссылки как их ввёл пользователь, с паролями: по ним можно создать такой же Downloader
     public_keys: Vec<String>,
+    links: Vec<PublicLink>,
     pub(crate) prefixes: Vec<String>,
+    matching: Matching,
+    scan_cache: Option<ScanCache>,
+    // Медиафайлы, не сопоставленные ни с одним vendorCode
+    unmatched: Mutex<Vec<String>>,
+}
+
+// Правила поиска файлов и сопоставления их имён с vendorCode
+#[derive(Clone, Default)]
+pub struct Matching {
+    pub options: MatchOptions,
+    pub mapping: Option<VendorMapping>,
+    pub filter: ScanFilter,
+}
+
+impl Matching {
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        Ok(Self {
+            options: settings.matching.clone(),
+            mapping: VendorMapping::load_optional(&settings.mapping_file)?,
+            filter: ScanFilter::new(
+                &settings.exclude_patterns,
+                &settings.scan_folders,
+                settings.max_scan_depth,
+            )?,
+        })
+    }
+}
+
+// Остаток имени после vendorCode или None, если имя начинается не с него
+fn strip_vendor_code<'a>(
+    name: &'a str,
+    vendor_code: &str,
+    options: &MatchOptions,
+) -> Option<&'a str> {
+    let normalize = |c: char| {
+        if options.ignore_separators && c == '-' {
+            '_'
+        } else {
+            c
+        }
+    };
+    let mut chars = name.chars();
+    for expected in vendor_code.chars() {
+        let (actual, expected) = (normalize(chars.next()?), normalize(expected));
+        let equal = if options.case_sensitive {
+            actual == expected
+        } else {
+            actual.to_lowercase().eq(expected.to_lowercase())
+        };
+        if !equal {
+            return None;
+        }
+    }
+    Some(chars.as_str())
 }
 
 impl Downloader {
-    pub fn new(public_keys: Vec<String>, prefixes: Vec<String>) -> Result<Self> {
+    pub fn new(
+        public_keys: Vec<String>,
+        prefixes: Vec<String>,
+        timeouts: &HttpTimeouts,
+    ) -> Result<Self> {
         log::info!(
             "Инициализация Downloader с {} ключами и префиксами {:?}",
             public_keys.len(),
             prefixes
         );
-        let client = ClientBuilder::new()
-            .timeout(Duration::from_secs(20))
-            .connect_timeout(Duration::from_secs(5))
+        if let Some(client) = crate::demo::client() {
+            return Ok(Self::with_client(public_keys, prefixes, client));
+        }
+        let (connect_timeout, timeout) = timeouts.yandex();
+        let client = crate::network::client_builder()?
+            .timeout(timeout)
+            .connect_timeout(connect_timeout)
             .default_headers({
                 let mut headers = reqwest::header::HeaderMap::new();
                 headers.insert(
@@ -72,53 +270,290 @@ impl Downloader {
             })
             .build()
             .map_err(|e| anyhow::anyhow!("Не удалось создать HTTP-клиент: {}", e))?;
-        Ok(Self {
+        Ok(Self::with_client(
+            public_keys,
+            prefixes,
+            Arc::new(ReqwestClient::new(client)),
+        ))
+    }
+
+    // Сокращённые и защищённые паролем ссылки раскрываются через переданный клиент
+    pub fn with_client(
+        public_keys: Vec<String>,
+        prefixes: Vec<String>,
+        client: Arc<dyn HttpClient>,
+    ) -> Self {
+        let links = public_keys
+            .iter()
+            .map(|key| links::resolve_public_link(client.as_ref(), key))
+            .collect();
+        Self {
             client,
             public_keys,
+            links,
             prefixes,
+            matching: Matching::default(),
+            scan_cache: None,
+            unmatched: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn public_keys(&self) -> &[String] {
+        &self.public_keys
+    }
+
+    pub fn set_matching(&mut self, matching: Matching) {
+        self.matching = matching;
+    }
+
+    pub fn set_scan_cache(&mut self, scan_cache: Option<ScanCache>) {
+        self.scan_cache = scan_cache;
+    }
+
+    // Дальнейшие запросы с ответами передаются sink: в файл записи или подробный журнал
+    pub fn set_capture(&mut self, sink: Arc<dyn ExchangeSink>) {
+        self.client = Arc::new(CapturingClient::new(self.client.clone(), sink));
+    }
+
+    pub fn unmatched_files(&self) -> Vec<String> {
+        self.unmatched.lock().unwrap().clone()
+    }
+
+    // Запрос к API с cookie доступа, если ссылка защищена паролем
+    fn get(&self, public_key: &str, url: &str) -> Result<Response> {
+        let request = Request::get(url);
+        let request = match self
+            .links
+            .iter()
+            .find(|link| link.public_key == public_key)
+            .and_then(|link| link.cookie.as_ref())
+        {
+            Some(cookie) => request.header("Cookie", cookie.as_str()),
+            None => request,
+        };
+        self.client.send(request)
+    }
+
+    // GET с повторами временных ошибок; остальные ответы, в том числе 403 и 404 для ссылки,
+    // возвращаются сразу — повтор их не исправит. После последней попытки возвращается
+    // последний ответ или ошибка
+    fn get_with_retry(&self, public_key: &str, url: &str) -> Result<Response> {
+        let mut attempt = 1;
+        loop {
+            let (delay, problem) = match self.get(public_key, url) {
+                Ok(response) if is_temporary(response.status()) && attempt < MAX_ATTEMPTS => {
+                    let delay = response
+                        .retry_after()
+                        .map(|delay| delay.min(MAX_RETRY_DELAY))
+                        .unwrap_or_else(|| retry_delay(attempt));
+                    (delay, format!("статус {}", response.status()))
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if attempt < MAX_ATTEMPTS => (retry_delay(attempt), e.to_string()),
+                Err(e) => return Err(e),
+            };
+            log::warn!(
+                "Яндекс.Диск, {}: {}, повтор через {} с (попытка {}/{})",
+                public_key,
+                problem,
+                delay.as_secs(),
+                attempt + 1,
+                MAX_ATTEMPTS
+            );
+            std::thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    // Ревизия публичной папки меняется при любом изменении её содержимого
+    fn public_revision(&self, public_key: &str) -> Result<String> {
+        let url = format!(
+            "https://cloud-api.yandex.net/v1/disk/public/resources?public_key={}&fields=revision,modified&limit=0",
+            encode(public_key)
+        );
+        let response = self
+            .get_with_retry(public_key, &url)
+            .map_err(|e| anyhow::anyhow!("Ошибка HTTP запроса ревизии: {}", e))?;
+        let status = response.status();
+        let body: serde_json::Value = response
+            .json()
+            .map_err(|e| anyhow::anyhow!("Ошибка парсинга ревизии: {}", e))?;
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Ошибка API Яндекс.Диска: Статус {}",
+                status
+            ));
+        }
+        body.get("revision")
+            .or_else(|| body.get("modified"))
+            .map(|v| v.to_string())
+            .ok_or_else(|| anyhow::anyhow!("В ответе нет ревизии"))
+    }
+
+    // Файл, имя которого не начинается с vendorCode, ищется в файле соответствий
+    fn mapped_file(&self, name: &str, path: &str) -> Option<FileInfo> {
+        let articul = self
+            .matching
+            .mapping
+            .as_ref()?
+            .vendor_code_for(path, &self.prefixes)?
+            .clone();
+        log::info!(
+            "Файл {} сопоставлен с vendorCode {} по файлу соответствий",
+            name,
+            articul
+        );
+        Some(FileInfo {
+            name: name.to_string(),
+            path: path.to_string(),
+            articul,
+            photo_number: 0,
+            hash: None,
+            size: None,
         })
     }
 
-    pub fn find_files(&self, path: &str) -> Result<Vec<FileInfo>> {
-        let mut files: Vec<FileInfo> = Vec::new();
-        let mut found_prefixes: HashSet<String> = HashSet::new();
+    // Публичные ссылки сканируются параллельно; каждый поток выдерживает свои паузы между запросами
+    pub fn find_files(&self) -> Result<Vec<FileInfo>> {
         let target_prefixes: HashSet<String> = self.prefixes.iter().cloned().collect();
+        let found_prefixes: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+        let next_key = AtomicUsize::new(0);
+        let results: Mutex<Vec<(usize, Result<Vec<FileInfo>>)>> = Mutex::new(Vec::new());
 
-        for public_key in &self.public_keys {
-            log::info!(
-                "Сканирование директории на Яндекс.Диске: {} для URL: {}",
-                path,
-                public_key
-            );
-            let result =
-                self.find_files_for_url(public_key, path, &mut found_prefixes, &target_prefixes)?;
-            files.extend(result);
+        std::thread::scope(|scope| {
+            for _ in 0..SCAN_WORKERS.min(self.links.len()) {
+                scope.spawn(|| {
+                    let mut first = true;
+                    loop {
+                        let index = next_key.fetch_add(1, Ordering::SeqCst);
+                        let Some(link) = self.links.get(index) else {
+                            break;
+                        };
+                        if target_prefixes.is_subset(&found_prefixes.lock().unwrap()) {
+                            log::info!("Все указанные vendorCode найдены: {:?}", target_prefixes);
+                            break;
+                        }
+                        if !first {
+                            std::thread::sleep(Duration::from_secs(1));
+                        }
+                        first = false;
+                        let result =
+                            self.find_files_for_key(&link.public_key, &link.path, &target_prefixes);
+                        if let Ok(files) = &result {
+                            found_prefixes
+                                .lock()
+                                .unwrap()
+                                .extend(files.iter().map(|f| f.articul.clone()));
+                        }
+                        results.lock().unwrap().push((index, result));
+                    }
+                });
+            }
+        });
 
-            if target_prefixes.is_subset(&found_prefixes) {
-                log::info!("Все указанные vendorCode найдены: {:?}", target_prefixes);
-                break;
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(index, _)| *index);
+        let mut files: Vec<FileInfo> = Vec::new();
+        let mut first_error = None;
+        let mut scanned = 0;
+        for (index, result) in results {
+            match result {
+                Ok(result) => {
+                    scanned += 1;
+                    files.extend(result);
+                }
+                Err(e) => {
+                    log::error!(
+                        "Ошибка сканирования {}: {}",
+                        self.links[index].public_key,
+                        e
+                    );
+                    first_error.get_or_insert(e);
+                }
             }
-            std::thread::sleep(Duration::from_secs(1));
+        }
+        // Ошибка возвращается, только если не удалось просканировать ни одну ссылку
+        if scanned == 0
+            && let Some(e) = first_error
+        {
+            return Err(e);
         }
 
+        mapping::number_mapped_files(&mut files);
         if files.is_empty() {
-            log::warn!(
-                "Не найдено файлов с префиксами в {}: {:?}",
-                path,
-                self.prefixes
-            );
+            log::warn!("Не найдено файлов с префиксами: {:?}", self.prefixes);
         } else {
-            log::info!("Найдено {} файлов в {}", files.len(), path);
+            log::info!("Найдено {} файлов", files.len());
         }
         Ok(files)
     }
 
+    fn find_files_for_key(
+        &self,
+        public_key: &str,
+        path: &str,
+        target_prefixes: &HashSet<String>,
+    ) -> Result<Vec<FileInfo>> {
+        log::info!(
+            "Сканирование директории на Яндекс.Диске: {} для URL: {}",
+            path,
+            public_key
+        );
+        let mut found_prefixes: HashSet<String> = HashSet::new();
+        let signature = format!("{}|{}", path, self.matching.filter.signature());
+        let revision = self.scan_cache.as_ref().and_then(|_| {
+            self.public_revision(public_key)
+                .map_err(|e| log::warn!("Не удалось получить ревизию {}: {}", public_key, e))
+                .ok()
+        });
+        if let (Some(cache), Some(revision)) = (&self.scan_cache, &revision)
+            && let Some(items) = cache.get(public_key, revision, &signature)
+        {
+            log::info!(
+                "Используется кэш сканирования для {} (ревизия {}, файлов: {})",
+                public_key,
+                revision,
+                items.len()
+            );
+            return Ok(items
+                .iter()
+                .filter_map(|item| {
+                    let mut file = self.match_file(&item.name, &item.path)?;
+                    file.hash = item.sha256.clone();
+                    file.size = item.size;
+                    Some(file)
+                })
+                .collect());
+        }
+
+        let mut listing = revision.is_some().then(Vec::new);
+        let files = self.find_files_for_url(
+            public_key,
+            path,
+            path,
+            &mut found_prefixes,
+            target_prefixes,
+            &mut listing,
+        )?;
+        // Обход, остановленный досрочно, неполон и в кэш не попадает
+        if let (Some(cache), Some(revision), Some(listing)) = (&self.scan_cache, &revision, listing)
+            && !target_prefixes.is_subset(&found_prefixes)
+            && let Err(e) = cache.put(public_key, revision, &signature, listing)
+        {
+            log::warn!("{}", e);
+        }
+        Ok(files)
+    }
+
+    // Шаблоны исключений и ограничения сканирования проверяются относительно root
     fn find_files_for_url(
         &self,
         public_key: &str,
+        root: &str,
         path: &str,
         found_prefixes: &mut HashSet<String>,
         target_prefixes: &HashSet<String>,
+        listing: &mut Option<Vec<CachedItem>>,
     ) -> Result<Vec<FileInfo>> {
         let mut files: Vec<FileInfo> = Vec::new();
         let mut subdirs: Vec<String> = Vec::new();
@@ -135,42 +570,21 @@ impl Downloader {
             );
             log::debug!("HTTP Request: GET {}", url);
 
-            let mut attempts = 0;
-            let max_attempts = 3;
-            let response = loop {
-                log::debug!(
-                    "Отправка HTTP-запроса к Яндекс.Диске (попытка {}/{}, offset={})",
-                    attempts + 1,
-                    max_attempts,
-                    offset
+            let response = self.get_with_retry(public_key, &url).map_err(|e| {
+                log::error!(
+                    "Не удалось получить ответ для {} (offset={}) после {} попыток: {}",
+                    path,
+                    offset,
+                    MAX_ATTEMPTS,
+                    e
                 );
-                match self.client.get(&url).send() {
-                    Ok(response) => break response,
-                    Err(e) => {
-                        log::error!(
-                            "Ошибка HTTP запроса для {} (offset={}): {}",
-                            path,
-                            offset,
-                            e
-                        );
-                        attempts += 1;
-                        if attempts >= max_attempts {
-                            log::error!(
-                                "Не удалось получить ответ для {} (offset={}) после {} попыток",
-                                path,
-                                offset,
-                                max_attempts
-                            );
-                            return Err(anyhow::anyhow!(
-                                "Не удалось получить ответ для {} после {} попыток",
-                                path,
-                                max_attempts
-                            ));
-                        }
-                        std::thread::sleep(Duration::from_secs(5));
-                    }
-                }
-            };
+                anyhow::anyhow!(
+                    "Не удалось получить ответ для {} после {} попыток: {}",
+                    path,
+                    MAX_ATTEMPTS,
+                    e
+                )
+            })?;
 
             log::debug!(
                 "Ответ от API Яндекс.Диска получен для {} (offset={})",
@@ -178,9 +592,7 @@ impl Downloader {
                 offset
             );
             let status = response.status();
-            let body = response
-                .text()
-                .map_err(|e| anyhow::anyhow!("Не удалось прочитать ответ для {}: {}", path, e))?;
+            let body = response.text();
             log::trace!(
                 "HTTP Response: Status: {}, Body (preview): {}",
                 status,
@@ -195,11 +607,7 @@ impl Downloader {
                     status,
                     body
                 );
-                return Err(anyhow::anyhow!(
-                    "Ошибка API Яндекс.Диска: Статус {}, Тело: {}",
-                    status,
-                    body
-                ));
+                return Err(status_error(public_key, status, &body));
             }
 
             log::debug!("Парсинг JSON-ответа для {} (offset={})", path, offset);
@@ -225,51 +633,26 @@ impl Downloader {
                 } else {
                     format!("{}/{}", path, item.name)
                 };
-                if item.item_type == "file" && is_media_file(&item.name) {
-                    let base_name = item.name.to_lowercase();
-                    let matched_prefix = self
-                        .prefixes
-                        .iter()
-                        .filter(|p| base_name.starts_with(&p.to_lowercase()))
-                        .max_by_key(|p| p.len());
-                    if let Some(prefix) = matched_prefix {
-                        let articul = prefix.to_string();
-                        found_prefixes.insert(articul.clone());
-                        let remaining = &base_name[prefix.len()..];
-                        let photo_number = if let Some(caps) =
-                            Regex::new(r"^[_-](\d+)\.\w+$")?.captures(remaining)
-                        {
-                            caps.get(1).unwrap().as_str().parse::<u32>().unwrap_or(1)
-                        } else if remaining.starts_with('.') {
-                            1
-                        } else {
-                            log::warn!(
-                                "Файл {} содержит vendorCode {}, но не соответствует шаблону",
-                                item.name,
-                                prefix
-                            );
-                            continue;
-                        };
-                        files.push(FileInfo {
+                let relative = item_path.strip_prefix(root).unwrap_or(&item_path);
+                if item.item_type == "file"
+                    && is_media_file(&item.name)
+                    && self.matching.filter.allows_file(relative)
+                {
+                    if let Some(listing) = listing {
+                        listing.push(CachedItem {
                             name: item.name.clone(),
-                            path: item_path,
-                            articul: articul.clone(),
-                            photo_number,
+                            path: item_path.clone(),
+                            sha256: item.sha256.clone(),
+                            size: item.size,
                         });
-                        log::info!(
-                            "Найден файл: {} (vendorCode: {}, фото: {})",
-                            item.name,
-                            articul,
-                            photo_number
-                        );
-                    } else {
-                        log::debug!(
-                            "Файл {} не начинается ни с одного vendorCode: {:?}",
-                            item.name,
-                            self.prefixes
-                        );
                     }
-                } else if item.item_type == "dir" {
+                    if let Some(mut file) = self.match_file(&item.name, &item_path) {
+                        file.hash = item.sha256.clone();
+                        file.size = item.size;
+                        found_prefixes.insert(file.articul.clone());
+                        files.push(file);
+                    }
+                } else if item.item_type == "dir" && self.matching.filter.allows_dir(relative) {
                     subdirs.push(item_path);
                 }
             }
@@ -295,13 +678,21 @@ impl Downloader {
 
         for subdir in subdirs {
             log::info!("Переход к поддиректории: {}", subdir);
-            match self.find_files_for_url(public_key, &subdir, found_prefixes, target_prefixes) {
+            match self.find_files_for_url(
+                public_key,
+                root,
+                &subdir,
+                found_prefixes,
+                target_prefixes,
+                listing,
+            ) {
                 Ok(new_files) => {
                     files.extend(new_files);
                     log::info!("Завершено сканирование поддиректории: {}", subdir);
                 }
                 Err(e) => {
                     log::error!("Ошибка сканирования поддиректории {}: {}", subdir, e);
+                    *listing = None;
                 }
             }
             if target_prefixes.is_subset(found_prefixes) {
@@ -327,58 +718,101 @@ impl Downloader {
             ));
         }
 
-        for entry in WalkDir::new(source_path).into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            let name = path.file_name().unwrap().to_string_lossy().to_string();
-            if path.is_file() && is_media_file(&name) {
-                let base_name = name.to_lowercase();
-                if let Some(prefix) = self
-                    .prefixes
-                    .iter()
-                    .find(|p| base_name.starts_with(&p.to_lowercase()))
-                {
-                    let articul = prefix.to_string();
-                    let remaining = &base_name[prefix.len()..];
-                    let photo_number =
-                        if let Some(caps) = Regex::new(r"^[_-](\d+)\.\w+$")?.captures(remaining) {
-                            caps[1].parse::<u32>().unwrap_or(1)
-                        } else if remaining.starts_with('.') {
-                            1
-                        } else {
-                            log::warn!(
-                                "Файл {} содержит vendorCode {}, но не соответствует шаблону",
-                                name,
-                                prefix
-                            );
-                            continue;
-                        };
-                    files.push(FileInfo {
-                        name: name.clone(),
-                        path: path.to_string_lossy().to_string(),
-                        articul: articul.clone(),
-                        photo_number,
-                    });
-                    log::info!(
-                        "Найден локальный файл: {} (vendorCode: {}, фото: {})",
-                        name,
-                        articul,
-                        photo_number
-                    );
-                } else {
-                    log::debug!(
-                        "Файл {} не начинается ни с одного vendorCode: {:?}",
-                        name,
-                        self.prefixes
-                    );
-                }
+        let filter = &self.matching.filter;
+        let entries = WalkDir::new(source_path).into_iter().filter_entry(|entry| {
+            let relative = entry.path().strip_prefix(source_path);
+            let relative = relative.unwrap_or(entry.path()).to_string_lossy();
+            if entry.file_type().is_dir() {
+                filter.allows_dir(&relative)
+            } else {
+                filter.allows_file(&relative)
+            }
+        });
+        for entry in entries.filter_map(|e| e.ok()) {
+            if let Some(file) = self.match_local_file(entry.path()) {
+                files.push(file);
             }
         }
+        mapping::number_mapped_files(&mut files);
         log::info!("Найдено {} локальных файлов", files.len());
         Ok(files)
     }
 
+    // Сопоставление отдельных файлов, например новых файлов в отслеживаемой папке
+    pub fn match_local_paths(&self, paths: &[String]) -> Result<Vec<FileInfo>> {
+        let mut files: Vec<FileInfo> = paths
+            .iter()
+            .filter(|path| !self.matching.filter.is_excluded(path))
+            .filter_map(|path| self.match_local_file(Path::new(path)))
+            .collect();
+        mapping::number_mapped_files(&mut files);
+        Ok(files)
+    }
+
+    fn match_local_file(&self, path: &Path) -> Option<FileInfo> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        if !path.is_file() || !is_media_file(&name) {
+            return None;
+        }
+        self.match_file(&name, &path.to_string_lossy())
+    }
+
+    // Единое сопоставление для всех режимов: самый длинный vendorCode, после которого
+    // идёт допустимое окончание имени, иначе — правило из файла соответствий
+    pub fn match_file(&self, name: &str, path: &str) -> Option<FileInfo> {
+        let candidates: Vec<(&String, &str)> = self
+            .prefixes
+            .iter()
+            .filter_map(|p| {
+                strip_vendor_code(name, p, &self.matching.options).map(|remaining| (p, remaining))
+            })
+            .collect();
+        let matched = candidates
+            .iter()
+            .filter_map(|(p, remaining)| photo_number_from_suffix(remaining).map(|n| (*p, n)))
+            .max_by_key(|(p, _)| p.len());
+        if let Some((prefix, photo_number)) = matched {
+            log::info!(
+                "Найден файл: {} (vendorCode: {}, фото: {})",
+                name,
+                prefix,
+                photo_number
+            );
+            return Some(FileInfo {
+                name: name.to_string(),
+                path: path.to_string(),
+                articul: prefix.clone(),
+                photo_number,
+                hash: None,
+                size: None,
+            });
+        }
+        if let Some(file) = self.mapped_file(name, path) {
+            return Some(file);
+        }
+        self.unmatched.lock().unwrap().push(path.to_string());
+        if let Some((prefix, _)) = candidates.iter().max_by_key(|(p, _)| p.len()) {
+            log::warn!(
+                "Файл {} содержит vendorCode {}, но не соответствует шаблону",
+                name,
+                prefix
+            );
+        } else {
+            log::debug!(
+                "Файл {} не начинается ни с одного vendorCode: {:?}",
+                name,
+                self.prefixes
+            );
+        }
+        None
+    }
+
+    // Ссылки перебираются по очереди: временные ошибки повторяются для той же ссылки,
+    // а 404 (файла нет в этой папке) и 401/403 сразу переводят к следующей
     pub fn get_download_url(&self, file_path: &str) -> Result<String> {
-        for public_key in &self.public_keys {
+        let mut last_error = None;
+        for link in &self.links {
+            let public_key = &link.public_key;
             log::info!("Получение ссылки для: {} с URL: {}", file_path, public_key);
             let url = format!(
                 "https://cloud-api.yandex.net/v1/disk/public/resources/download?public_key={}&path={}",
@@ -387,65 +821,140 @@ impl Downloader {
             );
             log::debug!("HTTP Request: GET {}", url);
 
-            let mut attempts = 0;
-            let max_attempts = 3;
-            loop {
-                match self.client.get(&url).send() {
-                    Ok(response) => {
-                        let status = response.status();
-                        let body = response.text().map_err(|e| {
-                            anyhow::anyhow!("Не удалось прочитать ответ для {}: {}", file_path, e)
-                        })?;
-                        log::debug!("HTTP Response: Status: {}, Body: {}", status, body);
-                        if status.is_success() {
-                            let download_link: DownloadLink =
-                                serde_json::from_str(&body).map_err(|e| {
-                                    anyhow::anyhow!(
-                                        "Ошибка парсинга ссылки для {}: {}",
-                                        file_path,
-                                        e
-                                    )
-                                })?;
-                            return Ok(download_link.href);
-                        } else {
-                            log::warn!("Ошибка получения ссылки для {}: {}", file_path, body);
-                            if status.as_u16() == 401 {
-                                log::info!("Пропуск URL {} из-за ошибки 401", public_key);
-                                break;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("Ошибка HTTP запроса для {}: {}", file_path, e);
+            match self.get_with_retry(public_key, &url) {
+                Ok(response) => {
+                    let status = response.status();
+                    let body = response.text();
+                    log::debug!("HTTP Response: Status: {}, Body: {}", status, body);
+                    if status.is_success() {
+                        let download_link: DownloadLink =
+                            serde_json::from_str(&body).map_err(|e| {
+                                anyhow::anyhow!("Ошибка парсинга ссылки для {}: {}", file_path, e)
+                            })?;
+                        return Ok(download_link.href);
                     }
+                    let error = status_error(public_key, status, &body);
+                    log::warn!("Ошибка получения ссылки для {}: {}", file_path, error);
+                    last_error = Some(error);
                 }
-                attempts += 1;
-                if attempts >= max_attempts {
+                Err(e) => {
                     log::error!(
-                        "Не удалось получить ссылку для {} после {} попыток",
+                        "Не удалось получить ссылку для {} после {} попыток: {}",
                         file_path,
-                        max_attempts
+                        MAX_ATTEMPTS,
+                        e
                     );
-                    break;
+                    last_error = Some(e);
                 }
-                log::debug!(
-                    "Повторная попытка через 5 секунд ({}/{})",
-                    attempts,
-                    max_attempts
-                );
-                std::thread::sleep(Duration::from_secs(5));
             }
         }
-        Err(anyhow::anyhow!(
-            "Не удалось получить ссылку для {} ни с одного URL",
-            file_path
-        ))
+        Err(match last_error {
+            Some(e) => anyhow::anyhow!(
+                "Не удалось получить ссылку для {} ни с одного URL: {}",
+                file_path,
+                e
+            ),
+            None => anyhow::anyhow!("Не удалось получить ссылку для {} ни с одного URL", file_path),
+        })
+    }
+
+    // Уменьшенная копия файла, которую Яндекс.Диск формирует сам
+    pub fn download_preview(&self, file_path: &str) -> Result<Vec<u8>> {
+        for link in &self.links {
+            let url = format!(
+                "https://cloud-api.yandex.net/v1/disk/public/resources?public_key={}&path={}&fields=preview&preview_size=M",
+                encode(&link.public_key),
+                encode(file_path)
+            );
+            let response = match self.get(&link.public_key, &url) {
+                Ok(response) if response.status().is_success() => response,
+                Ok(response) => {
+                    log::debug!(
+                        "Нет предпросмотра {} в {}: Статус {}",
+                        file_path,
+                        link.public_key,
+                        response.status()
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Ошибка запроса предпросмотра {}: {}", file_path, e);
+                    continue;
+                }
+            };
+            let body: serde_json::Value = response
+                .json()
+                .map_err(|e| anyhow::anyhow!("Ошибка парсинга предпросмотра: {}", e))?;
+            let Some(preview_url) = body.get("preview").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let response = self
+                .get(&link.public_key, preview_url)
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| {
+                    anyhow::anyhow!("Не удалось скачать предпросмотр {}: {}", file_path, e)
+                })?;
+            return Ok(response.body);
+        }
+        Err(anyhow::anyhow!("Нет предпросмотра для {}", file_path))
+    }
+
+    // Файл публичной папки для загрузки в WB через media/file или для архива скачивается
+    // потоком во временный файл в папке staging. Размер и SHA-256 сверяются со списком
+    // папки, чтобы повреждённый при скачивании файл не попал в карточку; при любой ошибке
+    // временный файл удаляется, успешный — при освобождении TempDownload или через keep
+    pub fn download_to_temp(&self, file: &FileInfo, staging: &Staging) -> Result<TempDownload> {
+        let url = self.get_download_url(&file.path)?;
+        let temp = TempDownload {
+            path: staging.new_file_path()?,
+        };
+        let mut out = HashingWriter {
+            inner: std::fs::File::create(&temp.path).map_err(|e| {
+                anyhow::anyhow!(
+                    "Не удалось создать временный файл {}: {}",
+                    temp.path.display(),
+                    e
+                )
+            })?,
+            hasher: Sha256::new(),
+        };
+        let written = self
+            .client
+            .download(Request::get(&url), &mut out)
+            .and_then(|written| {
+                out.inner.sync_all()?;
+                Ok(written)
+            })
+            .map_err(|e| anyhow::anyhow!("Не удалось скачать {}: {}", file.path, e))?;
+        if let Some(expected) = file.size
+            && written != expected
+        {
+            return Err(anyhow::anyhow!(
+                "Файл {} скачан не полностью: получено {} байт из {} по данным Яндекс.Диска",
+                file.path,
+                written,
+                expected
+            ));
+        }
+        let actual = format!("{:x}", out.hasher.finalize());
+        if let Some(expected) = &file.hash
+            && !expected.eq_ignore_ascii_case(&actual)
+        {
+            return Err(anyhow::anyhow!(
+                "Файл {} повреждён при скачивании: SHA-256 {} не совпадает с {} по данным Яндекс.Диска",
+                file.path,
+                actual,
+                expected
+            ));
+        }
+        log::debug!("Файл {} скачан и проверен, SHA-256 {}", file.path, actual);
+        Ok(temp)
     }
 
     #[allow(dead_code)]
     pub fn download_all(&self) -> Result<Vec<FileInfo>> {
         log::info!("Начало поиска всех файлов");
-        let files = self.find_files("/")?;
+        let files = self.find_files()?;
         if files.is_empty() {
             log::warn!("Не найдено файлов с префиксами: {:?}", self.prefixes);
         }
@@ -456,13 +965,13 @@ impl Downloader {
         &self,
         nm_id: i64,
         files: &[FileInfo],
-        _server_port: Option<u16>,
+        server: Option<&FileServer>,
     ) -> Result<MediaOutput> {
         log::info!("Генерация JSON для nmId: {}", nm_id);
         let mut urls = vec![];
         for file in files {
             log::debug!("Обработка файла {} для nmId {}", file.name, nm_id);
-            if !self.public_keys.is_empty() {
+            if !self.links.is_empty() {
                 match self.get_download_url(&file.path) {
                     Ok(download_url) => {
                         urls.push(download_url.clone());
@@ -473,13 +982,16 @@ impl Downloader {
                         return Err(e);
                     }
                 }
+            } else if let Some(server) = server {
+                let url = server.publish(Path::new(&file.path));
+                log::info!("Добавлена ссылка сервера файлов для {}: {}", file.name, url);
+                urls.push(url);
             } else {
-                urls.push(format!("file://{}", file.path));
-                log::info!(
-                    "Добавлен локальный путь для {}: file://{}",
-                    file.name,
+                // Ссылку file:// сервер WB открыть не может — локальные файлы идут через media/file
+                return Err(anyhow::anyhow!(
+                    "Файл {} локальный, ссылки на него нет: загрузите его файлом",
                     file.path
-                );
+                ));
             }
         }
         if urls.is_empty() {
@@ -495,19 +1007,169 @@ impl Downloader {
         Ok(MediaOutput { nm_id, data: urls })
     }
 
-    #[allow(dead_code)]
-    pub fn cleanup_file(&self, file_path: &str) -> Result<()> {
-        if file_path.starts_with("file://") {
-            let local_path = file_path.strip_prefix("file://").unwrap_or(file_path);
-            log::info!("Удаление локального файла: {}", local_path);
-            // Uncomment the following lines if local file deletion is desired
-            // std::fs::remove_file(local_path).map_err(|e| {
-            //     anyhow::anyhow!("Не удалось удалить файл {}: {}", local_path, e)
-            // })?;
-            log::info!("Удаление локального файла {} пока не реализовано", local_path);
-        } else {
-            log::info!("Файлы, полученные по URL ({}), не удаляются", file_path);
+    // Удаляет скачанный файл после загрузки, но только внутри папки staging: файлы
+    // пользователя (локальные папки, пути file://) не удаляются ни при каких настройках
+    pub fn cleanup_file(&self, file_path: &str, staging: &Staging) -> Result<()> {
+        let local_path = Path::new(file_path.strip_prefix("file://").unwrap_or(file_path));
+        if !staging.contains(local_path) {
+            return Err(anyhow::anyhow!(
+                "Файл {} не из папки временных файлов {}, удаление запрещено",
+                local_path.display(),
+                staging.dir().display()
+            ));
         }
+        std::fs::remove_file(local_path).map_err(|e| {
+            anyhow::anyhow!("Не удалось удалить файл {}: {}", local_path.display(), e)
+        })?;
+        log::info!("Удалён временный файл {}", local_path.display());
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fake::{ScriptedClient, response};
+
+    const KEY: &str = "https://disk.yandex.ru/d/test";
+    const URL: &str = "https://cloud-api.yandex.net/v1/disk/public/resources";
+
+    fn downloader(client: &Arc<ScriptedClient>) -> Downloader {
+        Downloader::with_client(Vec::new(), Vec::new(), client.clone())
+    }
+
+    #[test]
+    fn temporary_errors_are_retried_after_retry_after() {
+        let client = ScriptedClient::new(vec![
+            response(503, &[("Retry-After", "0")], ""),
+            response(429, &[("Retry-After", "Wed, 21 Oct 2015 07:28:00 GMT")], ""),
+            response(200, &[], "{}"),
+        ]);
+        let result = downloader(&client).get_with_retry(KEY, URL).unwrap();
+        assert_eq!(result.status(), StatusCode::OK);
+        assert_eq!(client.requests().len(), 3);
+    }
+
+    #[test]
+    fn permanent_errors_are_not_retried() {
+        let client = ScriptedClient::new(vec![response(404, &[], "")]);
+        let result = downloader(&client).get_with_retry(KEY, URL).unwrap();
+        assert_eq!(result.status(), StatusCode::NOT_FOUND);
+        assert_eq!(client.requests().len(), 1);
+    }
+
+    #[test]
+    fn last_temporary_error_is_returned_after_all_attempts() {
+        let client = ScriptedClient::new(
+            (0..MAX_ATTEMPTS)
+                .map(|_| response(429, &[("Retry-After", "0")], ""))
+                .collect(),
+        );
+        let result = downloader(&client).get_with_retry(KEY, URL).unwrap();
+        assert_eq!(result.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(client.requests().len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_limit() {
+        assert_eq!(retry_delay(1), RETRY_DELAY);
+        assert_eq!(retry_delay(2), RETRY_DELAY * 2);
+        assert_eq!(retry_delay(3), RETRY_DELAY * 4);
+        assert_eq!(retry_delay(30), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn downloaded_file_is_checked_against_listing() {
+        let staging = Staging::new(
+            std::env::temp_dir().join(format!("wb_staging_test_{}", std::process::id())),
+        );
+        let content = "содержимое фото";
+        let file = |hash: &str, size: usize| FileInfo {
+            name: "ABC_1.jpg".to_string(),
+            path: "/ABC_1.jpg".to_string(),
+            articul: "ABC".to_string(),
+            photo_number: 1,
+            hash: Some(hash.to_string()),
+            size: Some(size as u64),
+        };
+        let download = |file: FileInfo| {
+            let client = ScriptedClient::new(vec![
+                response(200, &[], r#"{"href": "https://downloader.disk.yandex.ru/f"}"#),
+                response(200, &[], content),
+            ]);
+            Downloader::with_client(vec![KEY.to_string()], Vec::new(), client)
+                .download_to_temp(&file, &staging)
+        };
+        let sha256 = format!("{:x}", Sha256::digest(content));
+
+        let temp = download(file(&sha256.to_uppercase(), content.len())).unwrap();
+        assert_eq!(std::fs::read_to_string(temp.path()).unwrap(), content);
+        let path = temp.path().to_path_buf();
+        drop(temp);
+        assert!(!path.exists());
+
+        let Err(corrupted) = download(file(&"0".repeat(64), content.len())) else {
+            panic!("повреждённый файл принят");
+        };
+        assert!(corrupted.to_string().contains("SHA-256"), "{}", corrupted);
+        let Err(truncated) = download(file(&sha256, content.len() + 1)) else {
+            panic!("недокачанный файл принят");
+        };
+        assert!(truncated.to_string().contains("не полностью"), "{}", truncated);
+        // Файлы, не прошедшие проверку, не остаются в staging
+        assert_eq!(staging.usage().files, 0);
+        let _ = std::fs::remove_dir_all(staging.dir());
+    }
+
+    #[test]
+    fn cleanup_deletes_only_staging_files() {
+        let root = std::env::temp_dir().join(format!("wb_cleanup_test_{}", std::process::id()));
+        let staging = Staging::new(root.join("staging"));
+        let user_dir = root.join("Фото");
+        std::fs::create_dir_all(&user_dir).unwrap();
+        let user_file = user_dir.join("ABC_1.jpg");
+        std::fs::write(&user_file, "фото").unwrap();
+        let staged = staging.new_file_path().unwrap();
+        std::fs::write(&staged, "фото").unwrap();
+        let downloader = downloader(&ScriptedClient::new(Vec::new()));
+
+        let escaped = staging.dir().join("..").join("Фото").join("ABC_1.jpg");
+        for path in [&user_file, &escaped] {
+            let refused = downloader.cleanup_file(&path.to_string_lossy(), &staging);
+            assert!(refused.is_err(), "{}", path.display());
+            let refused = downloader.cleanup_file(&format!("file://{}", path.display()), &staging);
+            assert!(refused.is_err(), "{}", path.display());
+        }
+        assert!(user_file.exists());
+
+        downloader.cleanup_file(&staged.to_string_lossy(), &staging).unwrap();
+        assert!(!staged.exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn photo_number_from_name_suffix() {
+        let cases = [
+            (".jpg", Some(1)),
+            ("_1.jpg", Some(1)),
+            ("-2.jpg", Some(2)),
+            (" 3.jpg", Some(3)),
+            ("(4).png", Some(4)),
+            (" (5).png", Some(5)),
+            ("_01_final.jpg", Some(1)),
+            ("_30.jpg", Some(30)),
+            ("_31.jpg", None),
+            ("_0.jpg", None),
+            ("-2024-05.jpg", None),
+            ("_20240501.jpg", None),
+            ("_360_1.jpg", None),
+            ("_99999999999999999999.jpg", None),
+            ("7.jpg", None),
+            ("_final.jpg", None),
+            ("_1", None),
+        ];
+        for (suffix, expected) in cases {
+            assert_eq!(photo_number_from_suffix(suffix), expected, "{}", suffix);
+        }
+    }
+}
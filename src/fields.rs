@@ -0,0 +1,116 @@
+use crate::links::parse_link_list;
+use crate::settings::VendorCodeOptions;
+use crate::utils::is_media_file;
+use std::path::Path;
+
+// Длина vendorCode, которую принимает WB
+const MAX_VENDOR_CODE_LEN: usize = 72;
+// Сколько проблемных строк перечислять в подсказке
+const MAX_LISTED: usize = 5;
+
+// Ключ WB — JWT из трёх частей base64url через точку; в демо-режиме ключ любой
+pub fn check_api_key(key: &str) -> Option<String> {
+    let key = key.trim();
+    if key.is_empty() || crate::demo::is_enabled() {
+        return None;
+    }
+    if key.chars().any(char::is_whitespace) {
+        return Some("В ключе есть пробелы или переносы строк — скопируйте его заново".to_string());
+    }
+    let parts: Vec<&str> = key.split('.').collect();
+    let is_base64url = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '=')
+    };
+    if parts.len() != 3 || !parts.iter().all(|part| is_base64url(part)) {
+        return Some(
+            "Не похоже на ключ WB: он состоит из трёх частей через точку. Проверьте, что ключ скопирован целиком"
+                .to_string(),
+        );
+    }
+    None
+}
+
+pub fn check_links(text: &str) -> Option<String> {
+    let parsed = parse_link_list(text);
+    if parsed.invalid.is_empty() {
+        return None;
+    }
+    Some(format!("Не ссылки на Яндекс.Диск:\n{}", list(&parsed.invalid)))
+}
+
+// Строки, которые WB не примет как vendorCode или которые вставлены не в тот столбец
+pub fn check_vendor_codes(text: &str, options: &VendorCodeOptions) -> Option<String> {
+    let mut problems = Vec::new();
+    for line in text.lines() {
+        let mut code = line.trim_start_matches('\u{feff}');
+        if options.strip_comments
+            && let Some(index) = code.find('#')
+        {
+            code = &code[..index];
+        }
+        let code = code.trim();
+        if code.is_empty() {
+            continue;
+        }
+        if code.contains(['\t', ';']) {
+            problems.push(format!("«{}» — несколько значений в строке", code));
+        } else if code.contains("://") {
+            problems.push(format!("«{}» — ссылка, а не vendor code", code));
+        } else if code.chars().count() > MAX_VENDOR_CODE_LEN {
+            problems.push(format!(
+                "«{}…» — длиннее {} символов",
+                code.chars().take(20).collect::<String>(),
+                MAX_VENDOR_CODE_LEN
+            ));
+        }
+    }
+    if problems.is_empty() {
+        return None;
+    }
+    Some(list(&problems))
+}
+
+pub fn check_folder(path: &str) -> Option<String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return None;
+    }
+    let path = Path::new(path);
+    if path.is_file() {
+        Some("Указан файл, а нужна папка".to_string())
+    } else if !path.is_dir() {
+        Some("Папка не найдена".to_string())
+    } else {
+        None
+    }
+}
+
+pub fn check_media_file(path: &str) -> Option<String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return None;
+    }
+    if !Path::new(path).is_file() {
+        Some("Файл не найден".to_string())
+    } else if !is_media_file(path) {
+        Some("Поддерживаются фото (jpg, png, webp, gif, bmp) и видео (mp4, mov)".to_string())
+    } else {
+        None
+    }
+}
+
+fn list(items: &[String]) -> String {
+    let mut text = items
+        .iter()
+        .take(MAX_LISTED)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if items.len() > MAX_LISTED {
+        text.push_str(&format!("\n… и ещё {}", items.len() - MAX_LISTED));
+    }
+    text
+}
@@ -0,0 +1,225 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs::File;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use reqwest::Url;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+// WB скачивает файлы из media/save не сразу, а в своей очереди — ссылка живёт час после публикации
+const LINK_TTL: Duration = Duration::from_secs(60 * 60);
+
+// HTTP-сервер, раздающий локальные файлы по ссылкам, чтобы загружать их через media/save,
+// как файлы Яндекс.Диска. Доступны только опубликованные файлы по случайному токену,
+// списка файлов нет. Сервер останавливается, когда FileServer удаляется
+pub struct FileServer {
+    server: Arc<Server>,
+    port: u16,
+    public_url: String,
+    base_url: String,
+    files: Arc<Mutex<HashMap<String, Published>>>,
+}
+
+struct Published {
+    path: PathBuf,
+    until: Instant,
+}
+
+impl FileServer {
+    // public_url — внешний адрес туннеля (например, https://….trycloudflare.com), который
+    // ведёт на этот порт. Адрес в локальной сети WB не откроет: media/save ответит успехом,
+    // а фото в карточке так и не появятся, поэтому такой адрес не принимается
+    pub fn start(port: u16, public_url: &str) -> Result<Self> {
+        let base_url = public_base_url(public_url)?;
+        let address = format!("0.0.0.0:{}", port);
+        let server = Server::http(&address).map_err(|e| {
+            anyhow::anyhow!("Не удалось запустить сервер файлов на {}: {}", address, e)
+        })?;
+        let server = Arc::new(server);
+        let files: Arc<Mutex<HashMap<String, Published>>> = Arc::new(Mutex::new(HashMap::new()));
+        let thread_server = Arc::clone(&server);
+        let thread_files = Arc::clone(&files);
+        std::thread::spawn(move || {
+            for request in thread_server.incoming_requests() {
+                serve(request, &thread_files);
+            }
+        });
+        log::info!("Сервер файлов запущен на {}, ссылки: {}", address, base_url);
+        Ok(Self {
+            server,
+            port,
+            public_url: public_url.trim().to_string(),
+            base_url,
+            files,
+        })
+    }
+
+    // Запущен ли сервер с этими настройками — иначе его нужно перезапустить
+    pub fn matches(&self, port: u16, public_url: &str) -> bool {
+        self.port == port && self.public_url == public_url.trim()
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // Ссылка на файл для media/save; имя файла в конце ссылки — чтобы WB видел расширение
+    pub fn publish(&self, path: &Path) -> String {
+        let token = format!("{:032x}", rand::random::<u128>());
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let now = Instant::now();
+        let mut files = self.files.lock().unwrap();
+        files.retain(|_, published| published.until > now);
+        files.insert(
+            token.clone(),
+            Published {
+                path: path.to_path_buf(),
+                until: now + LINK_TTL,
+            },
+        );
+        format!(
+            "{}/{}/{}",
+            self.base_url,
+            token,
+            urlencoding::encode(&name)
+        )
+    }
+}
+
+impl Drop for FileServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        log::info!("Сервер файлов на порту {} остановлен", self.port);
+    }
+}
+
+fn serve(request: Request, files: &Mutex<HashMap<String, Published>>) {
+    let path = if matches!(request.method(), Method::Get | Method::Head) {
+        let token = request
+            .url()
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        files
+            .lock()
+            .unwrap()
+            .get(token)
+            .filter(|published| published.until > Instant::now())
+            .map(|published| published.path.clone())
+    } else {
+        None
+    };
+    let result = match path.map(|path| (File::open(&path), path)) {
+        Some((Ok(file), path)) => {
+            log::debug!("Сервер файлов: отдаётся {}", path.display());
+            let content_type = Header::from_bytes("Content-Type", content_type(&path)).unwrap();
+            request.respond(Response::from_file(file).with_header(content_type))
+        }
+        Some((Err(e), path)) => {
+            log::warn!("Сервер файлов: не удалось открыть {}: {}", path.display(), e);
+            request.respond(Response::from_string("Not Found").with_status_code(404))
+        }
+        None => request.respond(Response::from_string("Not Found").with_status_code(404)),
+    };
+    if let Err(e) = result {
+        log::warn!("Сервер файлов: не удалось отправить ответ: {}", e);
+    }
+}
+
+// Внешний адрес без «/» на конце; пустой, не http(s) и адрес внутри локальной сети — ошибка
+fn public_base_url(public_url: &str) -> Result<String> {
+    let public_url = public_url.trim().trim_end_matches('/');
+    if public_url.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Не указан внешний адрес сервера файлов: WB не видит компьютер в локальной сети, укажите туннель на порт сервера"
+        ));
+    }
+    let url = Url::parse(public_url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Внешний адрес сервера файлов должен начинаться с http:// или https://: {}",
+                public_url
+            )
+        })?;
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    let private = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified()
+        }
+        // fc00::/7 — уникальные локальные адреса, fe80::/10 — адреса канала
+        Ok(IpAddr::V6(ip)) => {
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || (ip.segments()[0] & 0xfe00) == 0xfc00
+                || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+        Err(_) => {
+            host.is_empty()
+                || host == "localhost"
+                || host.ends_with(".localhost")
+                || host.ends_with(".local")
+        }
+    };
+    if private {
+        return Err(anyhow::anyhow!(
+            "Внешний адрес сервера файлов {} находится в локальной сети, WB его не откроет: укажите туннель на порт сервера",
+            public_url
+        ));
+    }
+    Ok(public_url.to_string())
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_are_accepted() {
+        let cases = [
+            ("https://abc.trycloudflare.com/", Some("https://abc.trycloudflare.com")),
+            ("http://203.0.113.7:8766", Some("http://203.0.113.7:8766")),
+            ("http://[2001:db8::1]:8766", Some("http://[2001:db8::1]:8766")),
+            ("", None),
+            ("abc.trycloudflare.com", None),
+            ("ftp://files.example.com", None),
+            ("http://192.168.1.5:8766", None),
+            ("http://10.0.0.2:8766", None),
+            ("http://172.16.4.1", None),
+            ("http://127.0.0.1:8766", None),
+            ("http://169.254.1.1", None),
+            ("http://[fd00::1]:8766", None),
+            ("http://[fe80::1]", None),
+            ("http://[::1]:8766", None),
+            ("http://localhost:8766", None),
+            ("http://nas.local:8766", None),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(public_base_url(url).ok().as_deref(), expected, "{}", url);
+        }
+    }
+}
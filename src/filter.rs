@@ -0,0 +1,104 @@
+use anyhow::Result;
+use regex::Regex;
+
+// Ограничения сканирования. Glob-шаблон исключения проверяется на любой глубине пути:
+// «Исходники» или «**/Исходники/**» — папка со всем содержимым, «*.psd» и «*_raw.*» — файлы.
+// Пути передаются относительно корня источника
+#[derive(Clone, Default)]
+pub struct ScanFilter {
+    exclude: Vec<Regex>,
+    // Пусто — сканируется весь источник
+    folders: Vec<String>,
+    // 0 — без ограничения, 1 — корень и его подпапки и т.д.
+    max_depth: usize,
+}
+
+impl ScanFilter {
+    pub fn new(exclude_patterns: &[String], folders: &[String], max_depth: usize) -> Result<Self> {
+        let exclude = exclude_patterns
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .map(glob_to_regex)
+            .collect::<Result<Vec<_>>>()?;
+        let folders = folders
+            .iter()
+            .map(|f| normalize(f))
+            .filter(|f| !f.is_empty())
+            .collect();
+        Ok(Self {
+            exclude,
+            folders,
+            max_depth,
+        })
+    }
+
+    pub fn allows_dir(&self, path: &str) -> bool {
+        let path = normalize(path);
+        if self.max_depth > 0 && path.split('/').filter(|c| !c.is_empty()).count() > self.max_depth
+        {
+            log::debug!("Папка {} глубже ограничения {}", path, self.max_depth);
+            return false;
+        }
+        // Папки на пути к разрешённым тоже обходятся
+        let allowed = path.is_empty()
+            || self.folders.is_empty()
+            || self.folders.iter().any(|f| {
+                f == &path
+                    || f.starts_with(&format!("{}/", path))
+                    || path.starts_with(&format!("{}/", f))
+            });
+        allowed && !self.is_excluded(&path)
+    }
+
+    pub fn allows_file(&self, path: &str) -> bool {
+        let path = normalize(path);
+        let allowed = self.folders.is_empty()
+            || self
+                .folders
+                .iter()
+                .any(|f| path.starts_with(&format!("{}/", f)));
+        allowed && !self.is_excluded(&path)
+    }
+
+    // Для кэша сканирования: список папки зависит от этих настроек
+    pub fn signature(&self) -> String {
+        let exclude: Vec<&str> = self.exclude.iter().map(|re| re.as_str()).collect();
+        format!("{:?}|{:?}|{}", exclude, self.folders, self.max_depth)
+    }
+
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let path = path.replace('\\', "/");
+        let excluded = self.exclude.iter().any(|re| re.is_match(&path));
+        if excluded {
+            log::debug!("Путь {} пропущен по шаблону исключения", path);
+        }
+        excluded
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").trim_matches('/').to_lowercase()
+}
+
+fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let trimmed = pattern.replace('\\', "/");
+    let trimmed = trimmed.trim_start_matches("**/").trim_end_matches("/**");
+    let trimmed = trimmed.trim_matches('/');
+    let mut expression = String::from("(?i)(?:^|/)");
+    let mut chars = trimmed.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                expression.push_str(".*");
+            }
+            '*' => expression.push_str("[^/]*"),
+            '?' => expression.push_str("[^/]"),
+            c => expression.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    expression.push_str("(?:/|$)");
+    Regex::new(&expression)
+        .map_err(|e| anyhow::anyhow!("Неверный шаблон исключения {}: {}", pattern, e))
+}
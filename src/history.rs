@@ -0,0 +1,538 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    profile TEXT NOT NULL,
+    source TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT
+);
+CREATE TABLE IF NOT EXISTS attempts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    profile TEXT NOT NULL,
+    vendor_code TEXT NOT NULL,
+    nm_id INTEGER,
+    file TEXT,
+    photo_number INTEGER,
+    timestamp TEXT NOT NULL,
+    success INTEGER NOT NULL,
+    error TEXT,
+    hash TEXT
+);
+CREATE TABLE IF NOT EXISTS cards (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    profile TEXT NOT NULL,
+    vendor_code TEXT NOT NULL,
+    nm_id INTEGER,
+    success INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    finished_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    profile TEXT NOT NULL,
+    vendor_code TEXT NOT NULL,
+    nm_id INTEGER NOT NULL,
+    photos TEXT NOT NULL,
+    taken_at TEXT NOT NULL,
+    restored_at TEXT
+);
+-- Галерея карточки заменена целиком (media/save, откат): попытки до after_attempt
+-- больше не говорят, какие файлы лежат в карточке
+CREATE TABLE IF NOT EXISTS card_resets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nm_id INTEGER NOT NULL,
+    after_attempt INTEGER NOT NULL,
+    reset_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS card_resets_nm_id ON card_resets(nm_id);
+CREATE INDEX IF NOT EXISTS attempts_timestamp ON attempts(timestamp);
+CREATE INDEX IF NOT EXISTS attempts_vendor_code ON attempts(vendor_code);
+";
+
+// Одна попытка загрузки: файл или целый vendorCode, если до загрузки дело не дошло
+pub struct Attempt {
+    pub run_id: i64,
+    pub profile: String,
+    pub vendor_code: String,
+    pub nm_id: Option<i64>,
+    pub file: Option<String>,
+    pub photo_number: Option<u32>,
+    pub timestamp: DateTime<Local>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub hash: Option<String>,
+}
+
+// Итог обработки одной карточки (vendorCode) в рамках запуска
+pub struct CardRecord {
+    pub run_id: i64,
+    pub profile: String,
+    pub vendor_code: String,
+    pub nm_id: Option<i64>,
+    pub success: bool,
+    pub duration: Duration,
+    pub finished_at: DateTime<Local>,
+}
+
+// Фото карточки до замены медиа, по ним откатывается запуск
+pub struct MediaSnapshot {
+    pub id: i64,
+    pub run_id: i64,
+    pub vendor_code: String,
+    pub nm_id: i64,
+    pub photos: Vec<String>,
+    pub taken_at: DateTime<Local>,
+}
+
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    pub fn open(path: &Path) -> Result<Self> {
+        log::info!("Открытие истории загрузок: {}", path.display());
+        let conn = Connection::open(path).map_err(|e| {
+            anyhow::anyhow!("Не удалось открыть базу истории {}: {}", path.display(), e)
+        })?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| anyhow::anyhow!("Ошибка инициализации базы истории: {}", e))?;
+        // Базы старых версий без хэша файлов
+        if conn.prepare("SELECT hash FROM attempts LIMIT 0").is_err() {
+            conn.execute("ALTER TABLE attempts ADD COLUMN hash TEXT", [])
+                .map_err(|e| anyhow::anyhow!("Ошибка обновления базы истории: {}", e))?;
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS attempts_hash ON attempts(nm_id, hash)",
+            [],
+        )?;
+        Ok(History { conn })
+    }
+
+    pub fn begin_run(
+        &self,
+        profile: &str,
+        source: &str,
+        started_at: DateTime<Local>,
+    ) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO runs (profile, source, started_at) VALUES (?1, ?2, ?3)",
+            params![profile, source, started_at],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn finish_run(&self, run_id: i64, finished_at: DateTime<Local>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE runs SET finished_at = ?1 WHERE id = ?2",
+            params![finished_at, run_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_card(&self, card: &CardRecord) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO cards (run_id, profile, vendor_code, nm_id, success, duration_ms, finished_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                card.run_id,
+                card.profile,
+                card.vendor_code,
+                card.nm_id,
+                card.success,
+                card.duration.as_millis() as i64,
+                card.finished_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn was_uploaded(&self, profile: &str, vendor_code: &str, file: &str) -> Result<bool> {
+        let uploaded = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM attempts
+             WHERE profile = ?1 AND vendor_code = ?2 AND file = ?3 AND success = 1)",
+            params![profile, vendor_code, file],
+            |row| row.get(0),
+        )?;
+        Ok(uploaded)
+    }
+
+    // Файл (по хэшу) всё ещё лежит в карточке под этим номером фото: последняя успешная
+    // загрузка в этот слот после последней замены всей галереи была именно им
+    pub fn was_uploaded_to_card(&self, nm_id: i64, photo_number: u32, hash: &str) -> Result<bool> {
+        let latest: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT hash FROM attempts
+                 WHERE nm_id = ?1 AND photo_number = ?2 AND success = 1
+                   AND id > COALESCE((SELECT MAX(after_attempt) FROM card_resets WHERE nm_id = ?1), 0)
+                 ORDER BY id DESC LIMIT 1",
+                params![nm_id, photo_number],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(latest.as_deref() == Some(hash))
+    }
+
+    // Галерея карточки заменена целиком: номера фото прежних загрузок больше ничего не значат
+    pub fn reset_card(&self, nm_id: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO card_resets (nm_id, after_attempt, reset_at)
+             SELECT ?1, COALESCE(MAX(id), 0), ?2 FROM attempts",
+            params![nm_id, Local::now()],
+        )?;
+        Ok(())
+    }
+
+    // Один снимок на карточку за запуск: повторная загрузка в том же запуске не затирает исходные фото
+    pub fn save_snapshot(
+        &self,
+        run_id: i64,
+        profile: &str,
+        vendor_code: &str,
+        nm_id: i64,
+        photos: &[String],
+    ) -> Result<()> {
+        let photos = serde_json::to_string(photos)?;
+        self.conn.execute(
+            "INSERT INTO snapshots (run_id, profile, vendor_code, nm_id, photos, taken_at)
+             SELECT ?1, ?2, ?3, ?4, ?5, ?6
+             WHERE NOT EXISTS(SELECT 1 FROM snapshots WHERE run_id = ?1 AND nm_id = ?4)",
+            params![run_id, profile, vendor_code, nm_id, photos, Local::now()],
+        )?;
+        Ok(())
+    }
+
+    // Снимки последнего ещё не откаченного запуска профиля
+    pub fn last_snapshots(&self, profile: &str) -> Result<Vec<MediaSnapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, run_id, vendor_code, nm_id, photos, taken_at FROM snapshots
+             WHERE run_id = (SELECT MAX(run_id) FROM snapshots WHERE profile = ?1 AND restored_at IS NULL)
+               AND restored_at IS NULL
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![profile], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, DateTime<Local>>(5)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(id, run_id, vendor_code, nm_id, photos, taken_at)| {
+                Ok(MediaSnapshot {
+                    id,
+                    run_id,
+                    vendor_code,
+                    nm_id,
+                    photos: serde_json::from_str(&photos).map_err(|e| {
+                        anyhow::anyhow!("Повреждён снимок фото nmId {}: {}", nm_id, e)
+                    })?,
+                    taken_at,
+                })
+            })
+            .collect()
+    }
+
+    pub fn mark_restored(&self, snapshot_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE snapshots SET restored_at = ?1 WHERE id = ?2",
+            params![Local::now(), snapshot_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn record(&self, attempt: &Attempt) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO attempts (run_id, profile, vendor_code, nm_id, file, photo_number, timestamp, success, error, hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                attempt.run_id,
+                attempt.profile,
+                attempt.vendor_code,
+                attempt.nm_id,
+                attempt.file,
+                attempt.photo_number,
+                attempt.timestamp,
+                attempt.success,
+                attempt.error,
+                attempt.hash,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+const QUERY_LIMIT: usize = 1000;
+
+#[derive(Default, Clone)]
+pub struct HistoryFilter {
+    pub profile: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub vendor_code: String,
+}
+
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub run_id: i64,
+    pub run_started_at: DateTime<Local>,
+    pub profile: String,
+    pub vendor_code: String,
+    pub nm_id: Option<i64>,
+    pub file: Option<String>,
+    pub photo_number: Option<u32>,
+    pub timestamp: DateTime<Local>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl History {
+    pub fn profiles(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT profile FROM runs ORDER BY profile")?;
+        let profiles = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(profiles)
+    }
+
+    // Последние попытки, подходящие под фильтр; конец диапазона дат включается
+    pub fn query(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>> {
+        let mut sql = String::from(
+            "SELECT a.run_id, r.started_at, a.profile, a.vendor_code, a.nm_id, a.file, a.photo_number,
+                    a.timestamp, a.success, a.error
+             FROM attempts a JOIN runs r ON r.id = a.run_id WHERE 1 = 1",
+        );
+        let mut values: Vec<String> = Vec::new();
+        if let Some(profile) = &filter.profile {
+            values.push(profile.clone());
+            sql.push_str(&format!(" AND a.profile = ?{}", values.len()));
+        }
+        if let Some(from) = filter.from {
+            values.push(from.format("%Y-%m-%d").to_string());
+            sql.push_str(&format!(" AND a.timestamp >= ?{}", values.len()));
+        }
+        if let Some(to) = filter.to.and_then(|d| d.succ_opt()) {
+            values.push(to.format("%Y-%m-%d").to_string());
+            sql.push_str(&format!(" AND a.timestamp < ?{}", values.len()));
+        }
+        let vendor_code = filter.vendor_code.trim();
+        if !vendor_code.is_empty() {
+            values.push(format!("%{}%", vendor_code));
+            sql.push_str(&format!(" AND a.vendor_code LIKE ?{}", values.len()));
+        }
+        sql.push_str(&format!(" ORDER BY a.timestamp DESC LIMIT {}", QUERY_LIMIT));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let entries = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                Ok(HistoryEntry {
+                    run_id: row.get(0)?,
+                    run_started_at: row.get(1)?,
+                    profile: row.get(2)?,
+                    vendor_code: row.get(3)?,
+                    nm_id: row.get(4)?,
+                    file: row.get(5)?,
+                    photo_number: row.get(6)?,
+                    timestamp: row.get(7)?,
+                    success: row.get(8)?,
+                    error: row.get(9)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+}
+
+const TOP_ERRORS: usize = 5;
+
+#[derive(Clone)]
+pub struct ProfileStats {
+    pub profile: String,
+    pub uploads_since: usize,
+    pub cards: usize,
+    pub failed_cards: usize,
+    pub average_card_time: Option<Duration>,
+    pub top_errors: Vec<(String, usize)>,
+}
+
+impl ProfileStats {
+    pub fn failure_rate(&self) -> Option<f64> {
+        (self.cards > 0).then(|| self.failed_cards as f64 / self.cards as f64)
+    }
+}
+
+// Сводит похожие сообщения об ошибках к одному типу: без vendorCode, чисел и подробностей после ':'
+fn error_type(vendor_code: &str, error: &str) -> String {
+    let head = error.split(':').next().unwrap_or(error);
+    let head = if vendor_code.is_empty() {
+        head.to_string()
+    } else {
+        head.replace(vendor_code, "…")
+    };
+    let mut result = String::new();
+    for c in head.trim().chars() {
+        if c.is_ascii_digit() {
+            if !result.ends_with('#') {
+                result.push('#');
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn profile_stats(stats: &mut HashMap<String, ProfileStats>, profile: String) -> &mut ProfileStats {
+    stats
+        .entry(profile.clone())
+        .or_insert_with(|| ProfileStats {
+            profile,
+            uploads_since: 0,
+            cards: 0,
+            failed_cards: 0,
+            average_card_time: None,
+            top_errors: Vec::new(),
+        })
+}
+
+impl History {
+    pub fn stats(&self, since: DateTime<Local>) -> Result<Vec<ProfileStats>> {
+        let mut stats: HashMap<String, ProfileStats> = HashMap::new();
+        let mut stmt = self.conn.prepare(
+            "SELECT profile, COUNT(*) FROM attempts
+             WHERE success = 1 AND file IS NOT NULL AND timestamp >= ?1 GROUP BY profile",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (profile, count) in rows {
+            profile_stats(&mut stats, profile).uploads_since = count as usize;
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT profile, COUNT(*), SUM(success = 0), AVG(duration_ms) FROM cards GROUP BY profile",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, Option<f64>>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (profile, cards, failed, average_ms) in rows {
+            let s = profile_stats(&mut stats, profile);
+            s.cards = cards as usize;
+            s.failed_cards = failed as usize;
+            s.average_card_time = average_ms.map(|ms| Duration::from_millis(ms as u64));
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT profile, vendor_code, error FROM attempts WHERE success = 0 AND error IS NOT NULL",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut errors: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        for (profile, vendor_code, error) in rows {
+            *errors
+                .entry(profile)
+                .or_default()
+                .entry(error_type(&vendor_code, &error))
+                .or_default() += 1;
+        }
+        for (profile, counts) in errors {
+            let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            counts.truncate(TOP_ERRORS);
+            profile_stats(&mut stats, profile).top_errors = counts;
+        }
+
+        let mut stats: Vec<ProfileStats> = stats.into_values().collect();
+        stats.sort_by(|a, b| a.profile.cmp(&b.profile));
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt(nm_id: i64, photo_number: u32, hash: &str, success: bool) -> Attempt {
+        Attempt {
+            run_id: 1,
+            profile: "Основной".to_string(),
+            vendor_code: "ABC123".to_string(),
+            nm_id: Some(nm_id),
+            file: Some(format!("ABC123_{}.jpg", photo_number)),
+            photo_number: Some(photo_number),
+            timestamp: Local::now(),
+            success,
+            error: (!success).then(|| "ошибка".to_string()),
+            hash: Some(hash.to_string()),
+        }
+    }
+
+    #[test]
+    fn uploaded_file_is_in_card_until_replaced() {
+        let history = History::open(Path::new(":memory:")).unwrap();
+        history.begin_run("Основной", "local", Local::now()).unwrap();
+        assert!(!history.was_uploaded_to_card(1, 1, "a").unwrap());
+
+        history.record(&attempt(1, 1, "a", true)).unwrap();
+        assert!(history.was_uploaded_to_card(1, 1, "a").unwrap());
+        assert!(!history.was_uploaded_to_card(1, 2, "a").unwrap());
+        assert!(!history.was_uploaded_to_card(2, 1, "a").unwrap());
+
+        // Неудачная попытка не меняет содержимое слота
+        history.record(&attempt(1, 1, "b", false)).unwrap();
+        assert!(history.was_uploaded_to_card(1, 1, "a").unwrap());
+
+        // Другой файл в том же слоте заменил прежний
+        history.record(&attempt(1, 1, "b", true)).unwrap();
+        assert!(!history.was_uploaded_to_card(1, 1, "a").unwrap());
+        assert!(history.was_uploaded_to_card(1, 1, "b").unwrap());
+    }
+
+    #[test]
+    fn gallery_replacement_forgets_previous_uploads() {
+        let history = History::open(Path::new(":memory:")).unwrap();
+        history.begin_run("Основной", "local", Local::now()).unwrap();
+        history.record(&attempt(1, 1, "a", true)).unwrap();
+        history.record(&attempt(2, 1, "c", true)).unwrap();
+        history.reset_card(1).unwrap();
+        assert!(!history.was_uploaded_to_card(1, 1, "a").unwrap());
+        assert!(history.was_uploaded_to_card(2, 1, "c").unwrap());
+
+        history.record(&attempt(1, 1, "a", true)).unwrap();
+        assert!(history.was_uploaded_to_card(1, 1, "a").unwrap());
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use reqwest::blocking::Client;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+// Запрос к внешнему API; собирается как в reqwest, а отправляется через HttpClient,
+// поэтому в тестах ответы WB и Яндекс.Диска можно подменить без сети
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+pub enum Body {
+    Empty,
+    Json(Vec<u8>),
+    Multipart(FilePart),
+}
+
+// Файл в multipart-форме; reader читается клиентом по мере отправки
+pub struct FilePart {
+    pub field: String,
+    pub file_name: String,
+    pub mime: String,
+    pub reader: Box<dyn Read + Send>,
+    pub length: u64,
+}
+
+impl Request {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new(Method::Get, url)
+    }
+
+    pub fn post(url: impl Into<String>) -> Self {
+        Self::new(Method::Post, url)
+    }
+
+    fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: Body::Empty,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn json(mut self, body: &impl Serialize) -> Result<Self> {
+        self.body = Body::Json(serde_json::to_vec(body)?);
+        Ok(self)
+    }
+
+    pub fn multipart(mut self, part: FilePart) -> Self {
+        self.body = Body::Multipart(part);
+        self
+    }
+
+    // Запрос в читаемом виде для подробностей ошибки; файл multipart — без содержимого
+    pub fn describe(&self) -> String {
+        let method = match self.method {
+            Method::Get => "GET",
+            Method::Post => "POST",
+        };
+        let mut text = format!("{} {}", method, self.url);
+        for (name, value) in &self.headers {
+            text.push_str(&format!("\n{}: {}", name, value));
+        }
+        match &self.body {
+            Body::Empty => {}
+            Body::Json(bytes) => {
+                let body = serde_json::from_slice::<serde_json::Value>(bytes)
+                    .and_then(|value| serde_json::to_string_pretty(&value))
+                    .unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned());
+                text.push_str(&format!("\n\n{}", body));
+            }
+            Body::Multipart(part) => text.push_str(&format!(
+                "\n\n{}: файл {} ({}, {} байт)",
+                part.field, part.file_name, part.mime, part.length
+            )),
+        }
+        text
+    }
+}
+
+// Ответ целиком: тело читается при отправке; url — адрес после редиректов
+pub struct Response {
+    pub status: StatusCode,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    // Код ошибки превращается в Err, как error_for_status в reqwest
+    pub fn error_for_status(self) -> Result<Self> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            return Err(anyhow::anyhow!("HTTP статус {}", self.status));
+        }
+        Ok(self)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    // Пауза из Retry-After: число секунд или дата HTTP; дата в прошлом — повторять сразу
+    pub fn retry_after(&self) -> Option<Duration> {
+        let value = self.header_values("Retry-After").next()?.trim();
+        if let Ok(secs) = value.parse::<u64>() {
+            return Some(Duration::from_secs(secs));
+        }
+        let at = DateTime::parse_from_rfc2822(value).ok()?;
+        Some((at.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+    }
+
+    // Все значения заголовка без учёта регистра имени, например несколько Set-Cookie
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub trait HttpClient: Send + Sync {
+    fn send(&self, request: Request) -> Result<Response>;
+
+    // Тело успешного ответа пишется в out по мере получения, а не собирается в памяти;
+    // возвращает число записанных байт. Подменным клиентам хватает реализации через send
+    fn download(&self, request: Request, out: &mut dyn Write) -> Result<u64> {
+        let response = self.send(request)?.error_for_status()?;
+        out.write_all(&response.body)?;
+        Ok(response.body.len() as u64)
+    }
+}
+
+// Реальная отправка через reqwest; заголовки по умолчанию и таймауты задаются в Client
+pub struct ReqwestClient {
+    client: Client,
+}
+
+impl ReqwestClient {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpClient for ReqwestClient {
+    fn send(&self, request: Request) -> Result<Response> {
+        let mut builder = match request.method {
+            Method::Get => self.client.get(&request.url),
+            Method::Post => self.client.post(&request.url),
+        };
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        builder = match request.body {
+            Body::Empty => builder,
+            Body::Json(bytes) => builder
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(bytes),
+            Body::Multipart(part) => builder.multipart(
+                reqwest::blocking::multipart::Form::new().part(
+                    part.field,
+                    reqwest::blocking::multipart::Part::reader_with_length(
+                        part.reader,
+                        part.length,
+                    )
+                    .file_name(part.file_name)
+                    .mime_str(&part.mime)?,
+                ),
+            ),
+        };
+        let response = builder.send()?;
+        let status = response.status();
+        let url = response.url().to_string();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes()?.to_vec();
+        Ok(Response {
+            status,
+            url,
+            headers,
+            body,
+        })
+    }
+
+    // Скачивание — всегда GET без тела
+    fn download(&self, request: Request, out: &mut dyn Write) -> Result<u64> {
+        let mut builder = self.client.get(&request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let mut response = builder.send()?.error_for_status()?;
+        Ok(response.copy_to(out)?)
+    }
+}
+
+// Подменный клиент для тестов: отдаёт заготовленные ответы по очереди и запоминает запросы
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    pub struct ScriptedClient {
+        responses: Mutex<VecDeque<Result<Response>>>,
+        requests: Mutex<Vec<(String, Option<serde_json::Value>)>>,
+    }
+
+    impl ScriptedClient {
+        pub fn new(responses: Vec<Result<Response>>) -> Arc<Self> {
+            Arc::new(Self {
+                responses: Mutex::new(responses.into()),
+                requests: Mutex::new(Vec::new()),
+            })
+        }
+
+        // Адреса и JSON-тела отправленных запросов
+        pub fn requests(&self) -> Vec<(String, Option<serde_json::Value>)> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl HttpClient for ScriptedClient {
+        fn send(&self, request: Request) -> Result<Response> {
+            let body = match &request.body {
+                Body::Json(bytes) => serde_json::from_slice(bytes).ok(),
+                _ => None,
+            };
+            self.requests.lock().unwrap().push((request.url.clone(), body));
+            self.responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| panic!("Неожиданный запрос: {}", request.url))
+        }
+    }
+
+    pub fn response(status: u16, headers: &[(&str, &str)], body: &str) -> Result<Response> {
+        Ok(Response {
+            status: StatusCode::from_u16(status).unwrap(),
+            url: String::new(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::response;
+    use super::*;
+
+    fn retry_after(value: &str) -> Option<Duration> {
+        response(429, &[("retry-after", value)], "")
+            .unwrap()
+            .retry_after()
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        assert_eq!(retry_after("7"), Some(Duration::from_secs(7)));
+        assert_eq!(retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(
+            retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let at = (Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let delay = retry_after(&at).unwrap();
+        assert!(delay > Duration::from_secs(80) && delay <= Duration::from_secs(90));
+        assert_eq!(retry_after("скоро"), None);
+        assert_eq!(response(503, &[], "").unwrap().retry_after(), None);
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use calamine::{Reader, open_workbook_auto};
+use std::path::Path;
+
+// Таблица из CSV или первого листа Excel/ODS; все ячейки — строки
+pub fn read_table(path: &Path) -> Result<Vec<Vec<String>>> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let rows = if extension == "csv" || extension == "txt" {
+        read_csv(path)?
+    } else {
+        read_workbook(path)?
+    };
+    log::info!("Прочитано строк из {}: {}", path.display(), rows.len());
+    Ok(rows)
+}
+
+// Выгрузки из 1С обычно в Windows-1251 и с разделителем ';'
+fn read_csv(path: &Path) -> Result<Vec<Vec<String>>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось прочитать файл {}: {}", path.display(), e))?;
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(text) => text.trim_start_matches('\u{feff}').to_string(),
+        Err(_) => encoding_rs::WINDOWS_1251.decode(&bytes).0.into_owned(),
+    };
+    let first_line = text.lines().next().unwrap_or_default();
+    let delimiter = [b';', b',', b'\t']
+        .into_iter()
+        .max_by_key(|d| first_line.matches(*d as char).count())
+        .unwrap_or(b';');
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    reader
+        .records()
+        .map(|record| {
+            record
+                .map(|r| r.iter().map(|cell| cell.trim().to_string()).collect())
+                .map_err(|e| anyhow::anyhow!("Ошибка чтения CSV {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+fn read_workbook(path: &Path) -> Result<Vec<Vec<String>>> {
+    let mut workbook = open_workbook_auto(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось открыть таблицу {}: {}", path.display(), e))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| anyhow::anyhow!("В файле {} нет листов", path.display()))?
+        .map_err(|e| anyhow::anyhow!("Ошибка чтения листа {}: {}", path.display(), e))?;
+    Ok(range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell.to_string().trim().to_string())
+                .collect()
+        })
+        .collect())
+}
+
+// Столбец по умолчанию — тот, в заголовке которого есть «артикул» или «vendor»
+pub fn guess_column(header: &[String]) -> usize {
+    header
+        .iter()
+        .position(|cell| {
+            let cell = cell.to_lowercase();
+            cell.contains("артикул") || cell.contains("vendor")
+        })
+        .unwrap_or(0)
+}
+
+pub fn column_values(rows: &[Vec<String>], column: usize, skip_header: bool) -> Vec<String> {
+    rows.iter()
+        .skip(usize::from(skip_header))
+        .filter_map(|row| row.get(column))
+        .filter(|cell| !cell.is_empty())
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,3336 @@
+use crate::archive::{self, ArchivedFile};
+use crate::backup::{self, CardBackup};
+use crate::batch::BatchItem;
+use crate::cache::ScanCache;
+use crate::checkpoint;
+use crate::capture::{Capture, ExchangeSink};
+use crate::card_create::{self, NewCard};
+use crate::card_update::{self, CardUpdate};
+use crate::concurrency::AdaptiveLimit;
+use crate::config::Config;
+use crate::downloader::{Downloader, FileInfo, Matching};
+use crate::file_server::FileServer;
+use crate::history::{Attempt, CardRecord, History};
+use crate::limits;
+use crate::links;
+use crate::logs::{LogBuffer, LogEvent, Stage};
+use crate::metrics::Metrics;
+use crate::network;
+use crate::notify;
+use crate::profile::{HttpTimeouts, Profile};
+use crate::queue::QueuedJob;
+use crate::reference::ReferenceCache;
+use crate::report;
+use crate::rich::RichContent;
+use crate::settings::Settings;
+use crate::staging::Staging;
+use crate::uploader::{
+    Characteristic, Exchange, MediaSlot, TransferProgress, WB_API_URL, WbError, WbUploader,
+};
+use crate::utils::{format_duration, is_video_file};
+use crate::validation::{self, ValidationDecision, ValidationReport};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobSource {
+    Yandex { public_keys: Vec<String> },
+    LocalFolder { path: String },
+    SingleFile { path: String },
+    Files { paths: Vec<String> },
+    // Таблица с новыми названиями и описаниями карточек вместо медиафайлов
+    CardUpdates { path: String },
+    // Таблица новых карточек: создание через cards/upload и загрузка фото из столбца «фото»
+    NewCards { path: String },
+}
+
+impl JobSource {
+    pub fn describe(&self) -> String {
+        match self {
+            JobSource::Yandex { public_keys } => format!(
+                "Яндекс.Диск: {}",
+                public_keys
+                    .iter()
+                    .map(|k| links::mask_password(k))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            JobSource::LocalFolder { path } => format!("Папка: {}", path),
+            JobSource::SingleFile { path } => format!("Файл: {}", path),
+            JobSource::Files { paths } => format!("Новые файлы: {}", paths.len()),
+            JobSource::CardUpdates { path } => format!("Изменения карточек: {}", path),
+            JobSource::NewCards { path } => format!("Новые карточки: {}", path),
+        }
+    }
+
+    pub fn item_kind(&self) -> ItemKind {
+        match self {
+            JobSource::Yandex { .. } | JobSource::CardUpdates { .. } | JobSource::NewCards { .. } => {
+                ItemKind::Links
+            }
+            JobSource::LocalFolder { .. } | JobSource::SingleFile { .. } | JobSource::Files { .. } => {
+                ItemKind::Files
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JobSpec {
+    pub profile: String,
+    pub api_key: String,
+    // Адрес Content API WB из профиля; пустой — основной API
+    pub wb_base_url: String,
+    pub timeouts: HttpTimeouts,
+    pub vendor_codes: Vec<String>,
+    pub source: JobSource,
+    pub settings: Settings,
+    pub report_dir: PathBuf,
+    pub started_at: DateTime<Local>,
+    // Пропускать файлы, уже успешно загруженные этим профилем по данным истории
+    pub only_new: bool,
+    // Ждать решения пользователя по итогам проверки перед загрузкой (запуск из интерфейса)
+    pub confirm_validation: bool,
+    // Загружать заново файлы, которые по истории уже есть в карточке
+    pub force_reupload: bool,
+}
+
+// Тип обработки vendorCode: публикация ссылок или загрузка файлов длятся по-разному
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ItemKind {
+    #[default]
+    Files,
+    Links,
+}
+
+const ETA_WINDOW: usize = 10;
+
+// Как часто проверяется связь после её обрыва
+const OFFLINE_POLL: Duration = Duration::from_secs(15);
+
+// Скользящее среднее длительности обработки одного vendorCode по типам
+#[derive(Clone, Default)]
+pub struct EtaEstimator {
+    samples: HashMap<ItemKind, VecDeque<f64>>,
+}
+
+impl EtaEstimator {
+    fn record(&mut self, kind: ItemKind, secs: f64) {
+        let samples = self.samples.entry(kind).or_default();
+        if samples.len() >= ETA_WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(secs);
+    }
+
+    // Оценка (ожидаемое, минимум, максимум) в секундах: среднее ± стандартное отклонение
+    pub fn estimate(&self, kind: ItemKind, remaining: usize) -> Option<(u64, u64, u64)> {
+        let samples = self.samples.get(&kind).filter(|s| !s.is_empty())?;
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let std_dev = (samples.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+        let remaining = remaining as f64;
+        Some((
+            (mean * remaining) as u64,
+            ((mean - std_dev).max(0.0) * remaining) as u64,
+            ((mean + std_dev) * remaining) as u64,
+        ))
+    }
+}
+
+// Счётчики по этапам: vendor codes (total/processed/resolved/failed) и файлы (scanned/uploaded)
+#[derive(Clone, Default)]
+pub struct Progress {
+    pub total: usize,
+    pub processed: usize,
+    pub scanned: usize,
+    pub resolved: usize,
+    pub uploaded: usize,
+    pub failed: usize,
+    // Файлы, которые передаются сейчас; при параллельной загрузке их несколько
+    pub current_files: Vec<(String, Arc<TransferProgress>)>,
+    pub bytes_uploaded: u64,
+    // Время, когда шла хотя бы одна передача: параллельные загрузки не суммируются
+    pub upload_time: Duration,
+    uploading_since: Option<Instant>,
+    pub kind: ItemKind,
+    pub eta: EtaEstimator,
+    item_started: Option<Instant>,
+    current_item_files: usize,
+    current_item_uploaded: usize,
+}
+
+impl Progress {
+    pub fn new(total: usize, kind: ItemKind) -> Self {
+        Self {
+            total,
+            kind,
+            item_started: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn estimate_remaining(&self) -> Option<(u64, u64, u64)> {
+        self.eta
+            .estimate(self.kind, self.total.saturating_sub(self.processed))
+    }
+
+    // Доля выполнения с учётом уже загруженных файлов текущего vendorCode
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let current = if self.current_item_files > 0 {
+            self.current_item_uploaded as f32 / self.current_item_files as f32
+        } else {
+            0.0
+        };
+        ((self.processed as f32 + current) / self.total as f32).min(1.0)
+    }
+
+    fn begin_item(&mut self, files: usize) {
+        self.current_item_files = files;
+        self.current_item_uploaded = 0;
+    }
+
+    fn add_uploaded(&mut self, files: usize) {
+        self.uploaded += files;
+        self.current_item_uploaded += files;
+    }
+
+    fn begin_transfer(&mut self, name: &str, transfer: &Arc<TransferProgress>) {
+        if self.current_files.is_empty() {
+            self.uploading_since = Some(Instant::now());
+        }
+        self.current_files.push((name.to_string(), Arc::clone(transfer)));
+    }
+
+    fn end_transfer(&mut self, transfer: &Arc<TransferProgress>) {
+        self.current_files
+            .retain(|(_, current)| !Arc::ptr_eq(current, transfer));
+        if self.current_files.is_empty()
+            && let Some(since) = self.uploading_since.take()
+        {
+            self.upload_time += since.elapsed();
+        }
+    }
+
+    // Время передачи за запуск, включая идущие сейчас загрузки
+    pub fn upload_wall_time(&self) -> Duration {
+        self.upload_time
+            + self
+                .uploading_since
+                .map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    // Суммарная скорость файлов, которые передаются сейчас
+    pub fn current_bytes_per_second(&self) -> Option<f64> {
+        (!self.current_files.is_empty()).then(|| {
+            self.current_files
+                .iter()
+                .map(|(_, transfer)| transfer.bytes_per_second())
+                .sum()
+        })
+    }
+
+    // Средняя скорость передачи файлов за запуск, включая текущие файлы
+    pub fn average_bytes_per_second(&self) -> f64 {
+        let bytes = self.bytes_uploaded
+            + self
+                .current_files
+                .iter()
+                .map(|(_, transfer)| transfer.sent())
+                .sum::<u64>();
+        let secs = self.upload_wall_time().as_secs_f64();
+        if secs > 0.0 { bytes as f64 / secs } else { 0.0 }
+    }
+
+    fn finish_item(&mut self) {
+        if let Some(started) = self.item_started {
+            self.eta.record(self.kind, started.elapsed().as_secs_f64());
+        }
+        self.item_started = Some(Instant::now());
+        self.processed += 1;
+        self.current_item_files = 0;
+        self.current_item_uploaded = 0;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ItemStatus {
+    InProgress,
+    Success,
+    Failed,
+}
+
+impl ItemStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ItemStatus::InProgress => "в работе",
+            ItemStatus::Success => "успешно",
+            ItemStatus::Failed => "ошибка",
+        }
+    }
+
+    // Стабильный идентификатор для машиночитаемых отчётов
+    pub fn code(&self) -> &'static str {
+        match self {
+            ItemStatus::InProgress => "in_progress",
+            ItemStatus::Success => "success",
+            ItemStatus::Failed => "failed",
+        }
+    }
+}
+
+// Итог обработки одного vendorCode
+#[derive(Clone)]
+pub struct ItemResult {
+    pub vendor_code: String,
+    pub nm_id: Option<i64>,
+    pub files: Vec<FileInfo>,
+    pub files_uploaded: usize,
+    pub status: ItemStatus,
+    pub error: Option<String>,
+    pub duration: Option<Duration>,
+    pub started_at: DateTime<Local>,
+    pub finished_at: Option<DateTime<Local>>,
+    pub wb_responses: Vec<String>,
+    pub media_urls: Vec<String>,
+    // Ошибки обработки карточки из списка ошибок WB после загрузки
+    pub wb_errors: Vec<String>,
+    // Ошибка WB, на которой vendorCode не прошёл; None — ошибка не от WB или её нет
+    pub failure: Option<WbError>,
+    // Запросы к WB по vendorCode с ответами, для панели подробностей
+    pub exchanges: Vec<Exchange>,
+    started: Instant,
+    attempts: usize,
+}
+
+impl ItemResult {
+    fn new(vendor_code: &str) -> Self {
+        Self {
+            vendor_code: vendor_code.to_string(),
+            nm_id: None,
+            files: Vec::new(),
+            files_uploaded: 0,
+            status: ItemStatus::InProgress,
+            error: None,
+            duration: None,
+            started_at: Local::now(),
+            finished_at: None,
+            wb_responses: Vec::new(),
+            media_urls: Vec::new(),
+            wb_errors: Vec::new(),
+            failure: None,
+            exchanges: Vec::new(),
+            started: Instant::now(),
+            attempts: 0,
+        }
+    }
+
+    pub fn files_found(&self) -> usize {
+        self.files.len()
+    }
+}
+
+// Состояние, разделяемое между фоновым потоком и UI
+#[derive(Clone)]
+pub struct JobState {
+    pub is_processing: Arc<Mutex<bool>>,
+    pub progress: Arc<Mutex<Progress>>,
+    pub failed_vendor_codes: Arc<Mutex<Vec<String>>>,
+    pub results: Arc<Mutex<Vec<ItemResult>>>,
+    pub last_report: Arc<Mutex<Option<PathBuf>>>,
+    pub logs: Arc<Mutex<LogBuffer>>,
+    pub start_time: Arc<Mutex<Option<Instant>>>,
+    pub history: Option<Arc<Mutex<History>>>,
+    pub metrics: Arc<Metrics>,
+    history_run: Arc<Mutex<Option<(i64, String)>>>,
+    webhooks: Arc<Mutex<notify::Webhooks>>,
+    only_new: Arc<AtomicBool>,
+    scan_cache_path: Option<PathBuf>,
+    reference_cache_path: Option<PathBuf>,
+    // Точка восстановления запуска; у профилей пакетного запуска её нет
+    checkpoint_path: Option<PathBuf>,
+    staging_dir: Option<PathBuf>,
+    captures_dir: Option<PathBuf>,
+    // Запись HTTP-запросов текущего запуска, если она включена в настройках
+    capture: Arc<Mutex<Option<Arc<Capture>>>>,
+    // Подробный журнал HTTP: запросы и ответы целиком попадают в журнал до конца запуска
+    http_debug: Arc<AtomicBool>,
+    // Проверка ожидает решения пользователя, пока здесь лежит отчёт
+    pub pending_validation: Arc<Mutex<Option<ValidationReport>>>,
+    validation_decision: Arc<Mutex<Option<ValidationDecision>>>,
+    validate: Arc<AtomicBool>,
+    confirm_validation: Arc<AtomicBool>,
+    auto_renumber: Arc<AtomicBool>,
+    skip_uploaded: Arc<AtomicBool>,
+    snapshot_media: Arc<AtomicBool>,
+    restore_trashed: Arc<AtomicBool>,
+    // Файлы Яндекс.Диска скачиваются и загружаются через media/file, а не ссылками
+    download_yandex_files: Arc<AtomicBool>,
+    // Файлы, успешно загруженные текущим запуском, — для архива
+    uploaded_files: Arc<Mutex<Vec<ArchivedFile>>>,
+    // Порог свободного места для временных файлов скачивания, МБ
+    min_free_disk_mb: Arc<AtomicU64>,
+    delete_after_upload: Arc<AtomicBool>,
+    // Сервер, раздающий локальные файлы ссылками; живёт между запусками, пока включён,
+    // потому что WB скачивает файлы уже после ответа media/save
+    file_server: Arc<Mutex<Option<Arc<FileServer>>>>,
+    // Связь пропала: следующий vendorCode ждёт, пока WB и Яндекс.Диск снова станут доступны
+    offline: Arc<AtomicBool>,
+    went_offline: Arc<AtomicBool>,
+    // Адреса, доступность которых проверяется после обрыва связи
+    network_probes: Arc<Mutex<Vec<String>>>,
+    // Таймауты HTTP профиля запуска
+    timeouts: Arc<Mutex<HttpTimeouts>>,
+    // Остановка по запросу пользователя: текущий vendorCode дорабатывается, остальные пропускаются
+    cancelled: Arc<AtomicBool>,
+    // Срочное задание очереди: текущее приостанавливается так же, после текущего vendorCode
+    preempted: Arc<AtomicBool>,
+    rich_content: Arc<Mutex<Option<RichContent>>>,
+    nm_ids: Arc<Mutex<HashMap<String, i64>>>,
+    // vendorCode, к которому относятся текущие запросы к WB
+    current_item: Arc<Mutex<Option<String>>>,
+    // vendor codes текущего запуска загрузки; у отката, резервной копии и выгрузки список пуст
+    pub(crate) run_vendor_codes: Arc<Mutex<Vec<String>>>,
+    // Профили пакетного запуска и их статус; пусто, если запуск одиночный
+    pub batch: Arc<Mutex<Vec<BatchItem>>>,
+    // Подготовленные задания, которые выполняются по порядку, и их статус
+    pub queue: Arc<Mutex<Vec<QueuedJob>>>,
+    // Имя профиля перед сообщениями журнала, когда профили пакета обрабатываются параллельно
+    label: Option<String>,
+}
+
+// Обмены запуска с WB и Яндекс.Диском: в файл записи, если она включена, и в журнал,
+// пока включён подробный журнал HTTP
+struct HttpSink {
+    state: JobState,
+}
+
+impl ExchangeSink for HttpSink {
+    fn active(&self) -> bool {
+        self.state.http_debug() || self.state.capture().is_some()
+    }
+
+    fn record(&self, entry: &serde_json::Value) {
+        if let Some(capture) = self.state.capture() {
+            capture.record(entry);
+        }
+        if !self.state.http_debug() {
+            return;
+        }
+        let outcome = match (entry["response"]["status"].as_u64(), entry["error"].as_str()) {
+            (Some(status), _) => status.to_string(),
+            (None, Some(error)) => format!("ошибка: {}", error),
+            (None, None) => "скачано".to_string(),
+        };
+        let mut event = LogEvent::debug(
+            Stage::General,
+            format!(
+                "HTTP {} {} → {}\n{}",
+                entry["request"]["method"].as_str().unwrap_or_default(),
+                entry["request"]["url"].as_str().unwrap_or_default(),
+                outcome,
+                serde_json::to_string_pretty(entry).unwrap_or_default()
+            ),
+        );
+        if let Some(vendor_code) = self.state.current_item.lock().unwrap().clone() {
+            event = event.vendor_code(&vendor_code);
+        }
+        self.state.log(event);
+    }
+}
+
+impl JobState {
+    // Журнал и история в директории конфигурации; без истории обработка продолжается
+    pub fn open(config: &Config, settings: &Settings) -> Self {
+        let logs = LogBuffer::with_file(settings.log_capacity, &config.get_log_file_path());
+        let history = History::open(&config.get_history_db_path())
+            .map_err(|e| log::error!("История загрузок недоступна: {}", e))
+            .ok();
+        let mut state = Self::new(logs, history);
+        state.scan_cache_path = Some(config.get_scan_cache_path());
+        state.reference_cache_path = Some(config.get_reference_cache_path());
+        state.checkpoint_path = Some(config.get_checkpoint_file_path());
+        state.staging_dir = Some(config.get_staging_dir());
+        state.captures_dir = Some(config.get_captures_dir());
+        state.cleanup_staging(settings);
+        state
+    }
+
+    pub fn new(logs: LogBuffer, history: Option<History>) -> Self {
+        Self {
+            is_processing: Arc::new(Mutex::new(false)),
+            progress: Arc::new(Mutex::new(Progress::default())),
+            failed_vendor_codes: Arc::new(Mutex::new(Vec::new())),
+            results: Arc::new(Mutex::new(Vec::new())),
+            last_report: Arc::new(Mutex::new(None)),
+            logs: Arc::new(Mutex::new(logs)),
+            start_time: Arc::new(Mutex::new(None)),
+            history: history.map(|h| Arc::new(Mutex::new(h))),
+            metrics: Arc::new(Metrics::default()),
+            history_run: Arc::new(Mutex::new(None)),
+            webhooks: Arc::new(Mutex::new(notify::Webhooks::default())),
+            only_new: Arc::new(AtomicBool::new(false)),
+            scan_cache_path: None,
+            reference_cache_path: None,
+            checkpoint_path: None,
+            staging_dir: None,
+            captures_dir: None,
+            capture: Arc::new(Mutex::new(None)),
+            http_debug: Arc::new(AtomicBool::new(false)),
+            pending_validation: Arc::new(Mutex::new(None)),
+            validation_decision: Arc::new(Mutex::new(None)),
+            validate: Arc::new(AtomicBool::new(false)),
+            confirm_validation: Arc::new(AtomicBool::new(false)),
+            auto_renumber: Arc::new(AtomicBool::new(false)),
+            skip_uploaded: Arc::new(AtomicBool::new(false)),
+            snapshot_media: Arc::new(AtomicBool::new(false)),
+            restore_trashed: Arc::new(AtomicBool::new(false)),
+            download_yandex_files: Arc::new(AtomicBool::new(false)),
+            uploaded_files: Arc::new(Mutex::new(Vec::new())),
+            min_free_disk_mb: Arc::new(AtomicU64::new(0)),
+            delete_after_upload: Arc::new(AtomicBool::new(true)),
+            file_server: Arc::new(Mutex::new(None)),
+            offline: Arc::new(AtomicBool::new(false)),
+            went_offline: Arc::new(AtomicBool::new(false)),
+            network_probes: Arc::new(Mutex::new(Vec::new())),
+            timeouts: Arc::new(Mutex::new(HttpTimeouts::default())),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            preempted: Arc::new(AtomicBool::new(false)),
+            rich_content: Arc::new(Mutex::new(None)),
+            nm_ids: Arc::new(Mutex::new(HashMap::new())),
+            current_item: Arc::new(Mutex::new(None)),
+            run_vendor_codes: Arc::new(Mutex::new(Vec::new())),
+            batch: Arc::new(Mutex::new(Vec::new())),
+            queue: Arc::new(Mutex::new(Vec::new())),
+            label: None,
+        }
+    }
+
+    // Состояние профиля в пакетном запуске: журнал, история, метрики и остановка общие,
+    // прогресс, результаты и отчёты — свои
+    pub(crate) fn fork(&self, label: &str) -> Self {
+        Self {
+            logs: self.logs.clone(),
+            history: self.history.clone(),
+            metrics: self.metrics.clone(),
+            scan_cache_path: self.scan_cache_path.clone(),
+            reference_cache_path: self.reference_cache_path.clone(),
+            staging_dir: self.staging_dir.clone(),
+            captures_dir: self.captures_dir.clone(),
+            http_debug: Arc::new(AtomicBool::new(self.http_debug())),
+            file_server: self.file_server.clone(),
+            cancelled: self.cancelled.clone(),
+            label: Some(label.to_string()),
+            ..Self::new(LogBuffer::new(0), None)
+        }
+    }
+
+    pub fn scan_cache(&self, settings: &Settings) -> Option<ScanCache> {
+        settings
+            .cache_scans
+            .then(|| self.scan_cache_path.clone().map(ScanCache::new))
+            .flatten()
+    }
+
+    pub fn reference_cache(&self) -> Option<ReferenceCache> {
+        self.reference_cache_path.clone().map(ReferenceCache::new)
+    }
+
+    // Без директории конфигурации (например, в тестах) — системная временная папка
+    pub fn staging(&self) -> Staging {
+        Staging::new(
+            self.staging_dir
+                .clone()
+                .unwrap_or_else(|| std::env::temp_dir().join("wb_upload_manager")),
+        )
+    }
+
+    // Срок хранения и предел размера папки staging; применяется при старте и после запуска
+    pub fn cleanup_staging(&self, settings: &Settings) {
+        let removed = self
+            .staging()
+            .cleanup(settings.staging_max_mb, settings.staging_max_age_days);
+        if removed.files > 0 {
+            self.log(LogEvent::info(
+                Stage::General,
+                format!("Папка временных файлов очищена: {}", removed.describe()),
+            ));
+        }
+    }
+
+    // Запускает сервер файлов перед запуском, если он включён, и перезапускает при смене
+    // порта или адреса; выключенный сервер останавливается. Без сервера локальные файлы
+    // загружаются через media/file
+    fn prepare_file_server(&self, settings: &Settings) {
+        let mut current = self.file_server.lock().unwrap();
+        if !settings.file_server_enabled {
+            *current = None;
+            return;
+        }
+        if current
+            .as_ref()
+            .is_some_and(|server| server.matches(settings.file_server_port, &settings.file_server_url))
+        {
+            return;
+        }
+        // Старый сервер освобождает порт до запуска нового
+        *current = None;
+        match FileServer::start(settings.file_server_port, &settings.file_server_url) {
+            Ok(server) => {
+                self.log(LogEvent::info(
+                    Stage::General,
+                    format!("Локальные файлы раздаются ссылками: {}", server.base_url()),
+                ));
+                *current = Some(Arc::new(server));
+            }
+            Err(e) => self.log(LogEvent::warn(
+                Stage::General,
+                format!("{} — локальные файлы будут загружены файлами", e),
+            )),
+        }
+    }
+
+    // Файл записи на запуск; у профилей пакета — свой, с именем профиля
+    fn start_capture(&self) -> Option<Arc<Capture>> {
+        let dir = self
+            .captures_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("wb_upload_manager").join("captures"));
+        let mut name = format!("http_{}", Local::now().format("%Y-%m-%d_%H-%M-%S"));
+        if let Some(label) = &self.label {
+            name.push_str(&format!("_{}", backup::file_name(label)));
+        }
+        match Capture::create(&dir, &name) {
+            Ok(capture) => {
+                self.log(LogEvent::info(
+                    Stage::General,
+                    format!("HTTP-запросы запуска записываются в {}", capture.path().display()),
+                ));
+                Some(Arc::new(capture))
+            }
+            Err(e) => {
+                self.log(LogEvent::warn(
+                    Stage::General,
+                    format!("Запись HTTP-запросов не включена: {}", e),
+                ));
+                None
+            }
+        }
+    }
+
+    fn capture(&self) -> Option<Arc<Capture>> {
+        self.capture.lock().unwrap().clone()
+    }
+
+    // Переключается из UI в любой момент и действует до конца текущего или следующего запуска
+    pub fn set_http_debug(&self, enabled: bool) {
+        self.http_debug.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn http_debug(&self) -> bool {
+        self.http_debug.load(Ordering::Relaxed)
+    }
+
+    fn timeouts(&self) -> HttpTimeouts {
+        *self.timeouts.lock().unwrap()
+    }
+
+    fn http_sink(&self) -> Arc<dyn ExchangeSink> {
+        Arc::new(HttpSink {
+            state: self.clone(),
+        })
+    }
+
+    fn file_server(&self) -> Option<Arc<FileServer>> {
+        self.file_server.lock().unwrap().clone()
+    }
+
+    pub fn decide_validation(&self, decision: ValidationDecision) {
+        *self.validation_decision.lock().unwrap() = Some(decision);
+    }
+
+    // Ожидающая проверка отменяется сразу, идущая загрузка — после текущего vendorCode
+    pub fn cancel(&self) {
+        if !*self.is_processing.lock().unwrap() {
+            return;
+        }
+        if self.pending_validation.lock().unwrap().is_some() {
+            self.decide_validation(ValidationDecision::Cancel);
+            return;
+        }
+        if !self.cancelled.swap(true, Ordering::Relaxed) {
+            self.log(LogEvent::warn(
+                Stage::General,
+                "Остановка: обработка завершится после текущего vendorCode",
+            ));
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.preempted.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn preempt(&self) {
+        self.preempted.store(true, Ordering::Relaxed);
+    }
+
+    // true, если задание было приостановлено ради срочного, а не остановлено пользователем
+    pub(crate) fn take_preempted(&self) -> bool {
+        self.preempted.swap(false, Ordering::Relaxed)
+    }
+
+    // vendor codes запуска без итога: ещё не начатые и тот, что был в работе при остановке
+    pub fn unfinished_vendor_codes(&self) -> Vec<String> {
+        let Some(run_started) = *self.start_time.lock().unwrap() else {
+            return Vec::new();
+        };
+        let results = self.results.lock().unwrap();
+        let mut unfinished: Vec<String> = Vec::new();
+        for vendor_code in self.run_vendor_codes.lock().unwrap().iter() {
+            let finished = results.iter().any(|r| {
+                &r.vendor_code == vendor_code && r.started >= run_started && r.status != ItemStatus::InProgress
+            });
+            if !finished && !unfinished.contains(vendor_code) {
+                unfinished.push(vendor_code.clone());
+            }
+        }
+        unfinished
+    }
+
+    // Проверка перед загрузкой; false — пользователь отменил запуск
+    fn check_before_upload(
+        &self,
+        uploader: &WbUploader,
+        vendor_codes: &[String],
+        files: &mut [FileInfo],
+        unmatched_files: Vec<String>,
+        check_sizes: bool,
+    ) -> bool {
+        if self.auto_renumber.load(Ordering::Relaxed) {
+            validation::renumber(files);
+        }
+        if !self.validate.load(Ordering::Relaxed) {
+            return true;
+        }
+        self.log(LogEvent::info(Stage::Scan, "Проверка перед загрузкой..."));
+        let report = validation::validate(
+            vendor_codes,
+            files,
+            unmatched_files,
+            check_sizes,
+            |vendor_code| Ok(uploader.get_nm_id_by_vendor_code(vendor_code)?),
+        );
+        self.nm_ids.lock().unwrap().extend(report.nm_ids.clone());
+        if report.is_clean() {
+            self.log(LogEvent::info(
+                Stage::Scan,
+                "Проверка пройдена без замечаний",
+            ));
+            return true;
+        }
+        self.log(LogEvent::warn(
+            Stage::Scan,
+            format!("Проверка перед загрузкой: {}", report.summary()),
+        ));
+        for (vendor_code, numbers) in &report.number_gaps {
+            self.log(
+                LogEvent::warn(
+                    Stage::Scan,
+                    format!(
+                        "Пропуск в номерах фото {}: {}",
+                        vendor_code,
+                        validation::join_numbers(numbers)
+                    ),
+                )
+                .vendor_code(vendor_code),
+            );
+        }
+        for (vendor_code, number, names) in &report.number_conflicts {
+            self.log(
+                LogEvent::warn(
+                    Stage::Scan,
+                    format!(
+                        "Номер фото {} у нескольких файлов {}: {} — на WB останется только последний",
+                        number,
+                        vendor_code,
+                        names.join(", ")
+                    ),
+                )
+                .vendor_code(vendor_code),
+            );
+        }
+        for (vendor_code, names) in &report.extra_videos {
+            self.log(
+                LogEvent::warn(
+                    Stage::Scan,
+                    format!(
+                        "Несколько видео для {}: {} — WB принимает одно видео на карточку",
+                        vendor_code,
+                        names.join(", ")
+                    ),
+                )
+                .vendor_code(vendor_code),
+            );
+        }
+        if !self.confirm_validation.load(Ordering::Relaxed) {
+            return true;
+        }
+        *self.validation_decision.lock().unwrap() = None;
+        *self.pending_validation.lock().unwrap() = Some(report);
+        let decision = loop {
+            if let Some(decision) = self.validation_decision.lock().unwrap().take() {
+                break decision;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        };
+        *self.pending_validation.lock().unwrap() = None;
+        match decision {
+            ValidationDecision::Cancel => {
+                self.log(LogEvent::warn(
+                    Stage::Scan,
+                    "Запуск отменён после проверки для исправления",
+                ));
+                return false;
+            }
+            ValidationDecision::Renumber => {
+                validation::renumber(files);
+                self.log(LogEvent::info(
+                    Stage::Scan,
+                    "Номера фото перенумерованы подряд",
+                ));
+            }
+            ValidationDecision::Continue => {}
+        }
+        true
+    }
+
+    pub fn log(&self, mut event: LogEvent) {
+        if let Some(label) = &self.label {
+            event.message = format!("[{}] {}", label, event.message);
+        }
+        self.logs.lock().unwrap().push(event);
+    }
+
+    // Логирует ошибку и помечает vendorCode события как ошибочный
+    fn fail(&self, event: LogEvent) {
+        if let Some(vendor_code) = event.vendor_code.clone() {
+            let mut failed = self.failed_vendor_codes.lock().unwrap();
+            if !failed.contains(&vendor_code) {
+                self.progress.lock().unwrap().failed += 1;
+                failed.push(vendor_code.clone());
+            }
+            let message = event.message.clone();
+            self.update_result(&vendor_code, |r| {
+                r.status = ItemStatus::Failed;
+                r.error.get_or_insert(message);
+            });
+        }
+        self.log(event);
+    }
+
+    // fail с классом ошибки WB; после отказа в авторизации остальные vendor codes
+    // не пройдут с тем же ключом, поэтому запуск останавливается
+    fn fail_wb(&self, event: LogEvent, error: &WbError) {
+        if let Some(vendor_code) = &event.vendor_code {
+            self.update_result(vendor_code, |r| {
+                r.failure.get_or_insert_with(|| error.clone());
+            });
+        }
+        self.fail(event);
+        if let WbError::Network(_) = error {
+            self.go_offline();
+        }
+        if error.is_fatal() && !self.cancelled.swap(true, Ordering::Relaxed) {
+            self.log(LogEvent::error(
+                Stage::General,
+                "Запуск остановлен: WB отклонил API-ключ профиля, остальные vendor codes не обрабатываются",
+            ));
+        }
+    }
+
+    // Ошибка цепочки с файлами или Яндекс.Диском: класс известен, только если её источник — WB
+    fn fail_any(&self, event: LogEvent, error: &anyhow::Error) {
+        match error.downcast_ref::<WbError>() {
+            Some(wb_error) => self.fail_wb(event, wb_error),
+            None => {
+                self.fail(event);
+                if network::is_connection_error(error) {
+                    self.go_offline();
+                }
+            }
+        }
+    }
+
+    fn go_offline(&self) {
+        if self.network_probes.lock().unwrap().is_empty() {
+            return;
+        }
+        if !self.offline.swap(true, Ordering::Relaxed) {
+            self.went_offline.store(true, Ordering::Relaxed);
+            self.log(LogEvent::warn(
+                Stage::General,
+                format!(
+                    "Нет связи с сервером: обработка приостановится перед следующим vendorCode и продолжится, когда связь восстановится (проверка каждые {} с)",
+                    OFFLINE_POLL.as_secs()
+                ),
+            ));
+        }
+    }
+
+    // После обрыва связи ждёт, пока все адреса запуска снова принимают соединения, и только
+    // тогда пускает следующий vendorCode — иначе ошибкой завершились бы все оставшиеся.
+    // Ожидание прерывается остановкой обработки
+    fn wait_for_network(&self) {
+        if !self.offline.load(Ordering::Relaxed) {
+            return;
+        }
+        let probes = self.network_probes.lock().unwrap().clone();
+        loop {
+            if probes.iter().all(|url| network::is_reachable(url)) {
+                self.offline.store(false, Ordering::Relaxed);
+                self.log(LogEvent::info(
+                    Stage::General,
+                    "Связь восстановлена, обработка продолжается",
+                ));
+                return;
+            }
+            if !wait_unless_cancelled(OFFLINE_POLL, self) {
+                return;
+            }
+        }
+    }
+
+    // Начало обработки vendorCode; без связи сначала дожидается её восстановления
+    fn begin_result(&self, vendor_code: &str) {
+        self.wait_for_network();
+        let mut results = self.results.lock().unwrap();
+        results.retain(|r| r.vendor_code != vendor_code);
+        results.push(ItemResult::new(vendor_code));
+        *self.current_item.lock().unwrap() = Some(vendor_code.to_string());
+    }
+
+    // Запросы вне обработки vendorCode (проверка перед загрузкой, список ошибок WB) не сохраняются
+    fn record_exchange(&self, exchange: Exchange) {
+        let current = self.current_item.lock().unwrap().clone();
+        if let Some(vendor_code) = current {
+            self.update_result(&vendor_code, |r| r.exchanges.push(exchange));
+        }
+    }
+
+    fn update_result(&self, vendor_code: &str, update: impl FnOnce(&mut ItemResult)) {
+        let mut results = self.results.lock().unwrap();
+        if let Some(result) = results
+            .iter_mut()
+            .rev()
+            .find(|r| r.vendor_code == vendor_code)
+        {
+            update(result);
+        }
+    }
+
+    pub(crate) fn finish(&self) {
+        if let Some(path) = &self.checkpoint_path {
+            checkpoint::clear(path);
+        }
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.preempted.store(false, Ordering::Relaxed);
+        self.http_debug.store(false, Ordering::Relaxed);
+        *self.current_item.lock().unwrap() = None;
+        *self.is_processing.lock().unwrap() = false;
+    }
+
+    // Отбрасывает уже загруженные файлы, если задание запущено с only_new.
+    // vendorCode, у которых все файлы уже загружены, исключаются из обработки
+    fn only_new_files(
+        &self,
+        vendor_codes: &[String],
+        files: Vec<FileInfo>,
+    ) -> (Vec<String>, Vec<FileInfo>) {
+        if !self.only_new.load(Ordering::Relaxed) {
+            return (vendor_codes.to_vec(), files);
+        }
+        let (Some(history), Some((_, profile))) =
+            (&self.history, self.history_run.lock().unwrap().clone())
+        else {
+            return (vendor_codes.to_vec(), files);
+        };
+        let (new_files, old_files): (Vec<FileInfo>, Vec<FileInfo>) = {
+            let history = history.lock().unwrap();
+            files.into_iter().partition(|f| {
+                !history
+                    .was_uploaded(&profile, &f.articul, &f.name)
+                    .unwrap_or(false)
+            })
+        };
+        let vendor_codes: Vec<String> = vendor_codes
+            .iter()
+            .filter(|code| {
+                new_files.iter().any(|f| &f.articul == *code)
+                    || !old_files.iter().any(|f| &f.articul == *code)
+            })
+            .cloned()
+            .collect();
+        self.progress.lock().unwrap().total = vendor_codes.len();
+        self.log(LogEvent::info(
+            Stage::Scan,
+            format!(
+                "Новых файлов: {}, уже загружено ранее: {}",
+                new_files.len(),
+                old_files.len()
+            ),
+        ));
+        (vendor_codes, new_files)
+    }
+
+    // Хэши локальных файлов нужны истории и в режиме принудительной загрузки
+    fn hash_files(&self, files: &mut [FileInfo]) {
+        for file in files.iter_mut().filter(|f| f.hash.is_none()) {
+            match crate::utils::file_sha256(&file.path) {
+                Ok(hash) => file.hash = Some(hash),
+                Err(e) => self.log(LogEvent::warn(Stage::Scan, e.to_string())),
+            }
+        }
+    }
+
+    fn is_in_card(&self, nm_id: i64, file: &FileInfo) -> bool {
+        let (Some(history), Some(hash)) = (&self.history, &file.hash) else {
+            return false;
+        };
+        history
+            .lock()
+            .unwrap()
+            .was_uploaded_to_card(nm_id, file.photo_number, hash)
+            .unwrap_or(false)
+    }
+
+    // Убирает файлы, которые с тем же содержимым и номером фото уже загружены в карточку
+    fn without_uploaded(
+        &self,
+        vendor_code: &str,
+        nm_id: i64,
+        files: Vec<FileInfo>,
+    ) -> Vec<FileInfo> {
+        if !self.skip_uploaded.load(Ordering::Relaxed) {
+            return files;
+        }
+        let (skipped, files): (Vec<FileInfo>, Vec<FileInfo>) =
+            files.into_iter().partition(|f| self.is_in_card(nm_id, f));
+        if !skipped.is_empty() {
+            self.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    format!(
+                        "Пропущено уже загруженных файлов: {} ({})",
+                        skipped.len(),
+                        skipped
+                            .iter()
+                            .map(|f| f.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            );
+        }
+        files
+    }
+
+    // Ссылки заменяют всю галерею, поэтому vendorCode пропускается, только если загружены все файлы
+    fn all_uploaded(&self, vendor_code: &str, nm_id: i64, files: &[FileInfo]) -> bool {
+        let uploaded = self.skip_uploaded.load(Ordering::Relaxed)
+            && !files.is_empty()
+            && files.iter().all(|f| self.is_in_card(nm_id, f));
+        if uploaded {
+            self.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    "Все файлы уже загружены в карточку ранее, vendorCode пропущен",
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            );
+        }
+        uploaded
+    }
+
+    // Сохраняет текущие фото карточки, чтобы запуск можно было откатить
+    fn snapshot_card(&self, uploader: &WbUploader, vendor_code: &str, nm_id: i64) {
+        if !self.snapshot_media.load(Ordering::Relaxed) {
+            return;
+        }
+        let (Some(history), Some((run_id, profile))) =
+            (&self.history, self.history_run.lock().unwrap().clone())
+        else {
+            return;
+        };
+        let saved = uploader.get_card(vendor_code).map_err(anyhow::Error::from).and_then(|card| {
+            if card.nm_id != nm_id {
+                return Err(anyhow::anyhow!(
+                    "WB вернул карточку nmId {} вместо {}",
+                    card.nm_id,
+                    nm_id
+                ));
+            }
+            history.lock().unwrap().save_snapshot(
+                run_id,
+                &profile,
+                vendor_code,
+                nm_id,
+                &card.photos,
+            )?;
+            Ok(card.photos.len())
+        });
+        match saved {
+            Ok(count) => self.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    format!("Сохранены текущие фото карточки для отката: {}", count),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            ),
+            Err(e) => self.log(
+                LogEvent::warn(
+                    Stage::Upload,
+                    format!("Не удалось сохранить текущие фото карточки: {}", e),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            ),
+        }
+    }
+
+    // None — карточки нет и в корзине; удалённая карточка восстанавливается только по настройке
+    fn check_trash(&self, uploader: &WbUploader, vendor_code: &str) -> Option<Result<i64, WbError>> {
+        let nm_id = match uploader.find_in_trash(vendor_code) {
+            Ok(nm_id) => nm_id?,
+            Err(e) => {
+                log::warn!(
+                    "Не удалось проверить корзину для vendorCode {}: {}",
+                    vendor_code,
+                    e
+                );
+                return None;
+            }
+        };
+        if !self.restore_trashed.load(Ordering::Relaxed) {
+            return Some(Err(WbError::NotFound(format!(
+                "карточка nmId {} находится в корзине WB, загрузка пропущена; восстановите её или включите восстановление в настройках",
+                nm_id
+            ))));
+        }
+        Some(match uploader.recover_cards(&[nm_id]) {
+            Ok(_) => {
+                self.log(
+                    LogEvent::warn(
+                        Stage::Resolve,
+                        format!(
+                            "Карточка nmId {} для vendorCode {} восстановлена из корзины WB",
+                            nm_id, vendor_code
+                        ),
+                    )
+                    .vendor_code(vendor_code)
+                    .nm_id(nm_id),
+                );
+                Ok(nm_id)
+            }
+            Err(e) => Err(e.map_message(|message| {
+                format!(
+                    "карточка nmId {} находится в корзине WB, восстановить не удалось: {}",
+                    nm_id, message
+                )
+            })),
+        })
+    }
+
+    // Рич-контент по шаблону загружается после фото карточки
+    fn attach_rich_content(&self, uploader: &WbUploader, vendor_code: &str, nm_id: i64) {
+        let Some(rich_content) = self.rich_content.lock().unwrap().clone() else {
+            return;
+        };
+        let result = rich_content
+            .render(vendor_code, nm_id)
+            .and_then(|body| match body {
+                Some(body) => Ok(Some(uploader.upload_rich_content(nm_id, &body)?)),
+                None => Ok(None),
+            });
+        match result {
+            Ok(Some(response)) => {
+                self.update_result(vendor_code, |r| r.wb_responses.push(response));
+                self.log(
+                    LogEvent::info(Stage::Upload, "Рич-контент загружен")
+                        .vendor_code(vendor_code)
+                        .nm_id(nm_id),
+                );
+            }
+            Ok(None) => self.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    "Нет шаблона рич-контента для vendorCode, пропущено",
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            ),
+            Err(e) => self.fail(
+                LogEvent::error(
+                    Stage::Upload,
+                    format!("Ошибка загрузки рич-контента для nmId {}: {}", nm_id, e),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            ),
+        }
+    }
+
+    fn send_webhook(&self, event: &str, data: serde_json::Value) {
+        let state = self.clone();
+        self.webhooks
+            .lock()
+            .unwrap()
+            .send(event, data, move |message| {
+                state.log(LogEvent::warn(Stage::General, message))
+            });
+    }
+
+    fn notify_desktop(&self, spec: &JobSpec, summary: &str, body: &str) {
+        if spec.settings.desktop_notifications
+            && let Err(e) = notify::send_desktop(summary, body)
+        {
+            self.log(LogEvent::warn(Stage::Summary, e.to_string()));
+        }
+    }
+
+    fn begin_history_run(&self, spec: &JobSpec) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        let run_id = history.lock().unwrap().begin_run(
+            &spec.profile,
+            &spec.source.describe(),
+            spec.started_at,
+        );
+        match run_id {
+            Ok(id) => *self.history_run.lock().unwrap() = Some((id, spec.profile.clone())),
+            Err(e) => self.log(LogEvent::warn(
+                Stage::General,
+                format!("Не удалось записать запуск в историю: {}", e),
+            )),
+        }
+    }
+
+    fn finish_history_run(&self) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        if let Some((run_id, _)) = self.history_run.lock().unwrap().take()
+            && let Err(e) = history.lock().unwrap().finish_run(run_id, Local::now())
+        {
+            self.log(LogEvent::warn(
+                Stage::General,
+                format!("Не удалось завершить запуск в истории: {}", e),
+            ));
+        }
+    }
+
+    // Записывает попытку загрузки в историю; file = None — ошибка до загрузки файлов
+    fn record_attempt(
+        &self,
+        vendor_code: &str,
+        nm_id: Option<i64>,
+        file: Option<&FileInfo>,
+        error: Option<String>,
+    ) {
+        self.update_result(vendor_code, |r| r.attempts += 1);
+        let Some(history) = &self.history else {
+            return;
+        };
+        let Some((run_id, profile)) = self.history_run.lock().unwrap().clone() else {
+            return;
+        };
+        let attempt = Attempt {
+            run_id,
+            profile,
+            vendor_code: vendor_code.to_string(),
+            nm_id,
+            file: file.map(|f| f.name.clone()),
+            photo_number: file.map(|f| f.photo_number),
+            timestamp: Local::now(),
+            success: error.is_none(),
+            error,
+            hash: file.and_then(|f| f.hash.clone()),
+        };
+        if let Err(e) = history.lock().unwrap().record(&attempt) {
+            self.log(LogEvent::warn(
+                Stage::General,
+                format!("Не удалось записать попытку в историю: {}", e),
+            ));
+        }
+    }
+
+    // После замены всей галереи файлы прежних загрузок не пропускаются как уже загруженные
+    fn reset_card(&self, nm_id: i64) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        if let Err(e) = history.lock().unwrap().reset_card(nm_id) {
+            self.log(LogEvent::warn(
+                Stage::General,
+                format!("Не удалось записать замену галереи в историю: {}", e),
+            ));
+        }
+    }
+
+    // Повтор после ошибки может загрузить тот же файл ещё раз — в архив он попадёт один раз
+    fn remember_uploaded(&self, file: &FileInfo, source: Option<&Downloader>) {
+        let public_keys = source.map(|d| d.public_keys().to_vec()).unwrap_or_default();
+        let mut uploaded = self.uploaded_files.lock().unwrap();
+        if !uploaded
+            .iter()
+            .any(|a| a.file.path == file.path && a.public_keys == public_keys)
+        {
+            uploaded.push(ArchivedFile {
+                file: file.clone(),
+                public_keys,
+            });
+        }
+    }
+
+    // Архив называется по номеру запуска в истории, без истории — по времени запуска
+    fn archive_run(&self, spec: &JobSpec, dir: &Path) {
+        let files = self.uploaded_files.lock().unwrap().clone();
+        if files.is_empty() {
+            return;
+        }
+        let name = match self.history_run.lock().unwrap().as_ref() {
+            Some((run_id, _)) => format!("run_{}", run_id),
+            None => format!("run_{}", spec.started_at.format("%Y-%m-%d_%H-%M-%S")),
+        };
+        self.log(LogEvent::info(
+            Stage::Summary,
+            format!("Архивирование загруженных файлов: {}", files.len()),
+        ));
+        let saved = limits::check_free_space(dir, spec.settings.min_free_disk_mb)
+            .and_then(|()| archive::save(dir, &name, &files, &self.staging(), &spec.timeouts));
+        match saved {
+            Ok(path) => self.log(LogEvent::info(
+                Stage::Summary,
+                format!("Архив загруженных файлов сохранён: {}", path.display()),
+            )),
+            Err(e) => self.log(LogEvent::error(
+                Stage::Summary,
+                format!("Ошибка архивирования файлов: {}", e),
+            )),
+        }
+    }
+
+    fn record_card(&self, vendor_code: &str) {
+        let Some(history) = &self.history else {
+            return;
+        };
+        let Some((run_id, profile)) = self.history_run.lock().unwrap().clone() else {
+            return;
+        };
+        let card = self
+            .results
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|r| r.vendor_code == vendor_code)
+            .map(|r| CardRecord {
+                run_id,
+                profile,
+                vendor_code: vendor_code.to_string(),
+                nm_id: r.nm_id,
+                success: r.status == ItemStatus::Success,
+                duration: r.duration.unwrap_or_default(),
+                finished_at: r.finished_at.unwrap_or_else(Local::now),
+            });
+        if let Some(card) = card
+            && let Err(e) = history.lock().unwrap().record_card(&card)
+        {
+            self.log(LogEvent::warn(
+                Stage::General,
+                format!("Не удалось записать карточку в историю: {}", e),
+            ));
+        }
+    }
+
+    fn report_progress(&self, vendor_code: &str) {
+        let unrecorded_failure = self
+            .results
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|r| r.vendor_code == vendor_code)
+            .filter(|r| r.status == ItemStatus::Failed && r.attempts == 0)
+            .map(|r| (r.nm_id, r.error.clone()));
+        if let Some((nm_id, error)) = unrecorded_failure {
+            self.record_attempt(vendor_code, nm_id, None, error);
+        }
+        let failure = self
+            .results
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|r| r.vendor_code == vendor_code)
+            .filter(|r| r.status == ItemStatus::Failed)
+            .map(|r| (r.nm_id, r.error.clone()));
+        if let Some((nm_id, error)) = failure {
+            self.send_webhook(
+                "item_failed",
+                serde_json::json!({ "vendor_code": vendor_code, "nm_id": nm_id, "error": error }),
+            );
+        }
+        self.update_result(vendor_code, |r| {
+            if r.status == ItemStatus::InProgress {
+                r.status = ItemStatus::Success;
+            }
+            r.duration = Some(r.started.elapsed());
+            r.finished_at = Some(Local::now());
+            self.metrics
+                .observe_item(r.status == ItemStatus::Success, r.started.elapsed());
+        });
+        self.record_card(vendor_code);
+        if let Some(path) = &self.checkpoint_path
+            && let Err(e) = checkpoint::record(path, vendor_code)
+        {
+            self.log(LogEvent::warn(Stage::General, e.to_string()));
+        }
+        *self.current_item.lock().unwrap() = None;
+        let (processed_count, total, eta) = {
+            let mut progress = self.progress.lock().unwrap();
+            progress.finish_item();
+            (
+                progress.processed,
+                progress.total,
+                progress.estimate_remaining(),
+            )
+        };
+        if processed_count < total
+            && let Some(eta) = eta
+        {
+            self.log(LogEvent::info(
+                Stage::Summary,
+                format!(
+                    "Прогресс: Обработано {}/{} vendor codes. Примерное время до завершения: {}",
+                    processed_count,
+                    total,
+                    format_eta(eta)
+                ),
+            ));
+        }
+    }
+}
+
+pub fn format_eta((expected, low, high): (u64, u64, u64)) -> String {
+    if low == high {
+        format!("≈ {}", format_duration(expected))
+    } else {
+        format!(
+            "≈ {} ({} – {})",
+            format_duration(expected),
+            format_duration(low),
+            format_duration(high)
+        )
+    }
+}
+
+pub fn start(spec: JobSpec, state: JobState) {
+    prepare(&spec, &state);
+    std::thread::spawn(move || run(spec, state));
+}
+
+// Сброс состояния под новое задание; пакетный запуск вызывает его для каждого профиля
+pub(crate) fn prepare(spec: &JobSpec, state: &JobState) {
+    state.log(LogEvent::info(Stage::General, "Начало обработки..."));
+    *state.is_processing.lock().unwrap() = true;
+    let kind = spec.source.item_kind();
+    let total = match spec.source {
+        JobSource::SingleFile { .. } => 1,
+        _ => spec.vendor_codes.len(),
+    };
+    *state.progress.lock().unwrap() = Progress::new(total, kind);
+    *state.start_time.lock().unwrap() = Some(Instant::now());
+    *state.run_vendor_codes.lock().unwrap() = spec.vendor_codes.clone();
+    state.batch.lock().unwrap().clear();
+    if let Some(path) = &state.checkpoint_path
+        && let Err(e) = checkpoint::begin(path, spec)
+    {
+        state.log(LogEvent::warn(Stage::General, e.to_string()));
+    }
+    state.failed_vendor_codes.lock().unwrap().clear();
+    state.uploaded_files.lock().unwrap().clear();
+    state.offline.store(false, Ordering::Relaxed);
+    state.went_offline.store(false, Ordering::Relaxed);
+    // Демо-режим работает без сети, проверять в нём нечего
+    let mut probes = Vec::new();
+    if !crate::demo::is_enabled() {
+        let wb_base_url = spec.wb_base_url.trim();
+        probes.push(if wb_base_url.is_empty() { WB_API_URL } else { wb_base_url }.to_string());
+        if let JobSource::Yandex { .. } = spec.source {
+            probes.push(network::YANDEX_API_URL.to_string());
+        }
+    }
+    *state.network_probes.lock().unwrap() = probes;
+    // Запуски из API и расписания читают настройки с диска: параметры сети берутся из них
+    network::set_options(&spec.settings.network);
+    *state.timeouts.lock().unwrap() = spec.timeouts;
+    state.only_new.store(spec.only_new, Ordering::Relaxed);
+    state
+        .validate
+        .store(spec.settings.validate_before_upload, Ordering::Relaxed);
+    state
+        .confirm_validation
+        .store(spec.confirm_validation, Ordering::Relaxed);
+    state
+        .auto_renumber
+        .store(spec.settings.auto_renumber_photos, Ordering::Relaxed);
+    state.skip_uploaded.store(
+        spec.settings.skip_uploaded && !spec.force_reupload,
+        Ordering::Relaxed,
+    );
+    state
+        .snapshot_media
+        .store(spec.settings.snapshot_media, Ordering::Relaxed);
+    state
+        .restore_trashed
+        .store(spec.settings.restore_trashed_cards, Ordering::Relaxed);
+    state
+        .download_yandex_files
+        .store(spec.settings.download_yandex_files, Ordering::Relaxed);
+    state
+        .min_free_disk_mb
+        .store(spec.settings.min_free_disk_mb, Ordering::Relaxed);
+    state
+        .delete_after_upload
+        .store(spec.settings.delete_after_upload, Ordering::Relaxed);
+    state.prepare_file_server(&spec.settings);
+    *state.capture.lock().unwrap() = if spec.settings.capture_http {
+        state.start_capture()
+    } else {
+        None
+    };
+    *state.rich_content.lock().unwrap() =
+        RichContent::from_settings(&spec.settings.rich_content_template).unwrap_or_else(|e| {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Рич-контент не будет загружен: {}", e),
+            ));
+            None
+        });
+    state.nm_ids.lock().unwrap().clear();
+    *state.webhooks.lock().unwrap() =
+        notify::Webhooks::new(&spec.settings.webhook_urls, &spec.profile);
+    state.send_webhook(
+        "job_started",
+        serde_json::json!({
+            "source": spec.source.describe(),
+            "vendor_codes": spec.vendor_codes,
+        }),
+    );
+}
+
+// Возвращает карточкам последнего запуска профиля фото, сохранённые перед заменой
+pub fn start_undo(profile: Profile, state: JobState) {
+    state.log(LogEvent::info(
+        Stage::General,
+        format!("Откат последней загрузки профиля {}", profile.name),
+    ));
+    *state.is_processing.lock().unwrap() = true;
+    *state.start_time.lock().unwrap() = Some(Instant::now());
+    state.run_vendor_codes.lock().unwrap().clear();
+    state.batch.lock().unwrap().clear();
+    std::thread::spawn(move || {
+        undo(&profile, &state);
+        state.finish();
+    });
+}
+
+fn undo(profile: &Profile, state: &JobState) {
+    let Some(history) = &state.history else {
+        state.log(LogEvent::error(
+            Stage::General,
+            "История загрузок недоступна, откат невозможен",
+        ));
+        return;
+    };
+    let snapshots = match history.lock().unwrap().last_snapshots(&profile.name) {
+        Ok(snapshots) => snapshots,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка чтения сохранённых фото: {}", e),
+            ));
+            return;
+        }
+    };
+    let Some(run_id) = snapshots.first().map(|s| s.run_id) else {
+        state.log(LogEvent::warn(
+            Stage::General,
+            "Нет сохранённых фото для отката",
+        ));
+        return;
+    };
+    let uploader = match WbUploader::for_profile(profile) {
+        Ok(u) => u,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка инициализации WB: {}", e),
+            ));
+            return;
+        }
+    };
+    state.log(LogEvent::info(
+        Stage::General,
+        format!("Откат запуска #{}: карточек {}", run_id, snapshots.len()),
+    ));
+    *state.progress.lock().unwrap() = Progress::new(snapshots.len(), ItemKind::Links);
+    for snapshot in &snapshots {
+        let event = |event: LogEvent| {
+            event
+                .vendor_code(&snapshot.vendor_code)
+                .nm_id(snapshot.nm_id)
+        };
+        // media/save без ссылок не очищает галерею, такие карточки остаются как есть
+        let restored = if snapshot.photos.is_empty() {
+            state.log(event(LogEvent::warn(
+                Stage::Upload,
+                "До загрузки у карточки не было фото, новые фото нужно удалить в кабинете WB",
+            )));
+            true
+        } else {
+            match uploader.upload_links(snapshot.nm_id, &snapshot.photos) {
+                Ok(_) => {
+                    state.log(event(LogEvent::info(
+                        Stage::Upload,
+                        format!(
+                            "Восстановлены фото от {}: {}",
+                            snapshot.taken_at.format("%Y-%m-%d %H:%M"),
+                            snapshot.photos.len()
+                        ),
+                    )));
+                    true
+                }
+                Err(e) => {
+                    state.progress.lock().unwrap().failed += 1;
+                    state.log(event(LogEvent::error(
+                        Stage::Upload,
+                        format!("Ошибка восстановления фото: {}", e),
+                    )));
+                    false
+                }
+            }
+        };
+        if restored {
+            state.reset_card(snapshot.nm_id);
+        }
+        // Неудачные откаты остаются в истории для повторной попытки
+        if restored && let Err(e) = history.lock().unwrap().mark_restored(snapshot.id) {
+            state.log(event(LogEvent::warn(
+                Stage::General,
+                format!("Не удалось отметить откат в истории: {}", e),
+            )));
+        }
+        state.progress.lock().unwrap().finish_item();
+    }
+    state.log(LogEvent::info(Stage::Summary, "Откат завершён."));
+}
+
+// Скачивает текущие фото карточек в папку, по подпапке на vendorCode
+pub fn start_backup(
+    profile: Profile,
+    vendor_codes: Vec<String>,
+    folder: PathBuf,
+    min_free_disk_mb: u64,
+    state: JobState,
+) {
+    state.log(LogEvent::info(
+        Stage::General,
+        format!("Резервное копирование фото карточек в {}", folder.display()),
+    ));
+    *state.is_processing.lock().unwrap() = true;
+    *state.start_time.lock().unwrap() = Some(Instant::now());
+    state.run_vendor_codes.lock().unwrap().clear();
+    state.batch.lock().unwrap().clear();
+    *state.progress.lock().unwrap() = Progress::new(vendor_codes.len(), ItemKind::Links);
+    std::thread::spawn(move || {
+        backup(&profile, &vendor_codes, &folder, min_free_disk_mb, &state);
+        state.finish();
+    });
+}
+
+// Выгружает vendorCode/nmId всех карточек продавца в CSV
+pub fn start_export_cards(profile: Profile, path: PathBuf, state: JobState) {
+    state.log(LogEvent::info(
+        Stage::General,
+        "Выгрузка vendor codes из каталога WB...",
+    ));
+    *state.is_processing.lock().unwrap() = true;
+    *state.start_time.lock().unwrap() = Some(Instant::now());
+    state.run_vendor_codes.lock().unwrap().clear();
+    state.batch.lock().unwrap().clear();
+    std::thread::spawn(move || {
+        let exported = WbUploader::for_profile(&profile).and_then(|uploader| {
+            uploader.export_vendor_codes(&path, |count| {
+                state.progress.lock().unwrap().scanned = count;
+            })
+        });
+        match exported {
+            Ok(count) => state.log(LogEvent::info(
+                Stage::Summary,
+                format!(
+                    "Выгружено vendor codes: {} в {} — файл можно открыть через «Импорт из файла»",
+                    count,
+                    path.display()
+                ),
+            )),
+            Err(e) => state.log(LogEvent::error(
+                Stage::Summary,
+                format!("Ошибка выгрузки vendor codes из WB: {}", e),
+            )),
+        }
+        state.finish();
+    });
+}
+
+fn backup(
+    profile: &Profile,
+    vendor_codes: &[String],
+    folder: &Path,
+    min_free_disk_mb: u64,
+    state: &JobState,
+) {
+    let uploader = match WbUploader::for_profile(profile) {
+        Ok(u) => u,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка инициализации WB: {}", e),
+            ));
+            return;
+        }
+    };
+    let backup = match CardBackup::new(folder) {
+        Ok(b) => b,
+        Err(e) => {
+            state.log(LogEvent::error(Stage::General, e.to_string()));
+            return;
+        }
+    };
+    let mut saved = 0;
+    for vendor_code in vendor_codes {
+        if state.is_cancelled() {
+            break;
+        }
+        // Место проверяется перед каждой карточкой: фото пишутся на диск одно за другим
+        if let Err(e) = limits::check_free_space(folder, min_free_disk_mb) {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Резервное копирование остановлено: {}", e),
+            ));
+            break;
+        }
+        let result = uploader.get_card(vendor_code).map_err(anyhow::Error::from).and_then(|card| {
+            let dir = backup.save(vendor_code, &card)?;
+            Ok((card, dir))
+        });
+        match result {
+            Ok((card, dir)) => {
+                saved += card.photos.len();
+                state.log(
+                    LogEvent::info(
+                        Stage::Upload,
+                        format!("Сохранено фото: {} в {}", card.photos.len(), dir.display()),
+                    )
+                    .vendor_code(vendor_code)
+                    .nm_id(card.nm_id),
+                );
+            }
+            Err(e) => {
+                state.progress.lock().unwrap().failed += 1;
+                state.log(
+                    LogEvent::error(
+                        Stage::Upload,
+                        format!("Ошибка резервного копирования фото: {}", e),
+                    )
+                    .vendor_code(vendor_code),
+                );
+            }
+        }
+        state.progress.lock().unwrap().finish_item();
+    }
+    state.log(LogEvent::info(
+        Stage::Summary,
+        format!("Резервное копирование завершено, сохранено фото: {}", saved),
+    ));
+}
+
+fn run(spec: JobSpec, state: JobState) {
+    execute(spec, state.clone());
+    state.finish();
+}
+
+// Само задание; флаг обработки снимает вызывающий, чтобы пакет профилей шёл как один запуск
+pub(crate) fn execute(spec: JobSpec, state: JobState) {
+    state.log(LogEvent::info(Stage::General, "Запущен фоновый поток"));
+
+    // Отчёт, история и точка восстановления пишутся по ходу запуска
+    if let Err(e) = limits::check_free_space(&spec.report_dir, spec.settings.min_free_disk_mb) {
+        state.log(LogEvent::error(Stage::General, e.to_string()));
+        state.notify_desktop(&spec, "Ошибка запуска", &e.to_string());
+        return;
+    }
+
+    let mut uploader = match WbUploader::new(spec.api_key.clone(), &spec.timeouts) {
+        Ok(u) => u,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка инициализации WB: {}", e),
+            ));
+            state.notify_desktop(
+                &spec,
+                "Ошибка запуска",
+                &format!("Ошибка инициализации WB: {}", e),
+            );
+            return;
+        }
+    };
+    let matching = match Matching::from_settings(&spec.settings) {
+        Ok(matching) => matching,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка настройки сопоставления файлов: {}", e),
+            ));
+            state.notify_desktop(
+                &spec,
+                "Ошибка запуска",
+                &format!("Ошибка настройки сопоставления файлов: {}", e),
+            );
+            return;
+        }
+    };
+    uploader.set_base_url(&spec.wb_base_url);
+    uploader.set_upload_limit_kbps(spec.settings.max_upload_kbps);
+    uploader.set_concurrency(Arc::new(AdaptiveLimit::new(spec.settings.upload_concurrency)));
+    uploader.set_capture(state.http_sink());
+    uploader.set_memory_limit_mb(spec.settings.max_file_memory_mb);
+    uploader.set_metrics(Arc::clone(&state.metrics));
+    let recorder_state = state.clone();
+    uploader.set_recorder(Arc::new(move |exchange| recorder_state.record_exchange(exchange)));
+    state.begin_history_run(&spec);
+    state.log(LogEvent::info(
+        Stage::General,
+        "WbUploader успешно инициализирован",
+    ));
+    state.log(LogEvent::info(
+        Stage::General,
+        format!("Обработка {} vendor codes", spec.vendor_codes.len()),
+    ));
+
+    let mut completed = run_source(&uploader, &spec, &spec.vendor_codes, &matching, &state);
+    // Vendor codes, не прошедшие из-за обрыва связи, повторяются после её восстановления,
+    // даже если автоповтор выключен
+    let retries = if state.went_offline.load(Ordering::Relaxed) {
+        spec.settings.auto_retry_count.max(1)
+    } else {
+        spec.settings.auto_retry_count
+    };
+    for attempt in 1..=retries {
+        let failed = state.failed_vendor_codes.lock().unwrap().clone();
+        if !completed || failed.is_empty() || state.is_cancelled() {
+            break;
+        }
+        // Карточка не найдена или данные отклонены — повтор ничего не изменит
+        let (failed, permanent): (Vec<String>, Vec<String>) = {
+            let results = state.results.lock().unwrap();
+            failed.into_iter().partition(|vendor_code| {
+                results
+                    .iter()
+                    .rev()
+                    .find(|r| &r.vendor_code == vendor_code)
+                    .and_then(|r| r.failure.as_ref())
+                    .is_none_or(WbError::is_retryable)
+            })
+        };
+        if !permanent.is_empty() {
+            state.log(LogEvent::info(
+                Stage::General,
+                format!(
+                    "Без автоповтора (ошибка не временная): {}",
+                    permanent.join(", ")
+                ),
+            ));
+        }
+        if failed.is_empty() {
+            break;
+        }
+        // Повторное создание карточки может завести дубль, если WB уже принял первую попытку
+        if matches!(spec.source, JobSource::NewCards { .. }) {
+            state.log(LogEvent::warn(
+                Stage::General,
+                "Автоповтор не выполняется для создания карточек из таблицы",
+            ));
+            break;
+        }
+        state.log(LogEvent::info(
+            Stage::General,
+            format!(
+                "Автоповтор {}/{} через {} с для vendor codes: {}",
+                attempt,
+                retries,
+                spec.settings.auto_retry_delay_secs,
+                failed.join(", ")
+            ),
+        ));
+        if !wait_unless_cancelled(Duration::from_secs(spec.settings.auto_retry_delay_secs), &state) {
+            break;
+        }
+        {
+            let mut progress = state.progress.lock().unwrap();
+            progress.total += failed.len();
+            progress.failed = progress.failed.saturating_sub(failed.len());
+        }
+        state
+            .failed_vendor_codes
+            .lock()
+            .unwrap()
+            .retain(|vendor_code| permanent.contains(vendor_code));
+        completed = run_source(&uploader, &spec, &failed, &matching, &state);
+    }
+
+    if completed && !state.is_cancelled() && spec.settings.card_errors_delay_secs > 0 {
+        check_card_errors(&uploader, &spec, &state);
+    }
+    // Остановка во время ожидания списка ошибок WB тоже прерывает запуск
+    let completed = completed && !state.is_cancelled();
+
+    let failed = state.failed_vendor_codes.lock().unwrap().clone();
+    if !completed {
+        state.log(LogEvent::error(Stage::Summary, "Обработка прервана."));
+    } else if !failed.is_empty() {
+        state.log(LogEvent::warn(
+            Stage::Summary,
+            format!(
+                "Ошибочные vendor codes для повторного запуска: {}",
+                failed.join(", ")
+            ),
+        ));
+    } else {
+        state.log(LogEvent::info(
+            Stage::Summary,
+            "Все vendor codes обработаны успешно.",
+        ));
+    }
+
+    let results = state.results.lock().unwrap().clone();
+    let mut classes: Vec<(&str, usize)> = Vec::new();
+    for error in results.iter().filter_map(|r| r.failure.as_ref()) {
+        match classes.iter_mut().find(|(label, _)| *label == error.label()) {
+            Some((_, count)) => *count += 1,
+            None => classes.push((error.label(), 1)),
+        }
+    }
+    if !classes.is_empty() {
+        state.log(LogEvent::warn(
+            Stage::Summary,
+            format!(
+                "Ошибки WB по типам: {}",
+                classes
+                    .iter()
+                    .map(|(label, count)| format!("{} — {}", label, count))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ));
+    }
+    let mut report_files = Vec::new();
+    if !results.is_empty() {
+        let path = spec.report_dir.join(format!(
+            "report_{}.csv",
+            Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+        match report::save_csv(&path, &results) {
+            Ok(()) => {
+                state.log(LogEvent::info(
+                    Stage::Summary,
+                    format!("Отчёт сохранён: {}", path.display()),
+                ));
+                report_files.push(path.clone());
+                *state.last_report.lock().unwrap() = Some(path);
+            }
+            Err(e) => state.log(LogEvent::error(
+                Stage::Summary,
+                format!("Ошибка сохранения отчёта: {}", e),
+            )),
+        }
+        let json_report_dir = spec.settings.json_report_dir.trim();
+        if !json_report_dir.is_empty() {
+            let path = Path::new(json_report_dir).join(format!(
+                "report_{}.json",
+                spec.started_at.format("%Y-%m-%d_%H-%M-%S")
+            ));
+            match report::save_json(&path, spec.started_at, &results) {
+                Ok(()) => state.log(LogEvent::info(
+                    Stage::Summary,
+                    format!("JSON-отчёт сохранён: {}", path.display()),
+                )),
+                Err(e) => state.log(LogEvent::error(
+                    Stage::Summary,
+                    format!("Ошибка сохранения JSON-отчёта: {}", e),
+                )),
+            }
+        }
+    }
+
+    if let Some(capture) = state.capture() {
+        state.log(LogEvent::info(
+            Stage::Summary,
+            format!("HTTP-запросы запуска записаны: {}", capture.path().display()),
+        ));
+    }
+    // Подробный журнал HTTP включается на один запуск
+    state.set_http_debug(false);
+    let archive_dir = spec.settings.archive_dir.trim();
+    if !archive_dir.is_empty() {
+        state.archive_run(&spec, Path::new(archive_dir));
+    }
+    state.cleanup_staging(&spec.settings);
+
+    state.finish_history_run();
+    let failed_any = !completed || results.iter().any(|r| r.status == ItemStatus::Failed);
+    state.notify_desktop(
+        &spec,
+        if failed_any {
+            "Обработка завершена с ошибками"
+        } else {
+            "Обработка завершена"
+        },
+        &notify::run_summary(&spec.profile, &results, completed),
+    );
+    state.send_webhook(
+        "job_finished",
+        serde_json::json!({
+            "completed": completed,
+            "total": results.len(),
+            "succeeded": results.iter().filter(|r| r.status == ItemStatus::Success).count(),
+            "failed": results
+                .iter()
+                .filter(|r| r.status == ItemStatus::Failed)
+                .map(|r| r.vendor_code.as_str())
+                .collect::<Vec<_>>(),
+            "summary": notify::run_summary(&spec.profile, &results, completed),
+        }),
+    );
+    let token = spec.settings.telegram_token.trim();
+    let chat_id = spec.settings.telegram_chat_id.trim();
+    if !token.is_empty() && !chat_id.is_empty() {
+        let summary = notify::run_summary(&spec.profile, &results, completed);
+        match notify::send_telegram(token, chat_id, &summary) {
+            Ok(()) => state.log(LogEvent::info(
+                Stage::Summary,
+                "Уведомление отправлено в Telegram",
+            )),
+            Err(e) => state.log(LogEvent::warn(
+                Stage::Summary,
+                format!("Не удалось отправить уведомление в Telegram: {}", e),
+            )),
+        }
+    }
+    if notify::smtp_configured(&spec.settings) && !results.is_empty() {
+        let html_path = spec.report_dir.join(format!(
+            "report_{}.html",
+            spec.started_at.format("%Y-%m-%d_%H-%M-%S")
+        ));
+        match report::save_html(&html_path, &results) {
+            Ok(()) => report_files.push(html_path),
+            Err(e) => state.log(LogEvent::warn(
+                Stage::Summary,
+                format!("Ошибка сохранения HTML-отчёта для письма: {}", e),
+            )),
+        }
+        let subject = format!(
+            "Отчёт о загрузке от {}",
+            spec.started_at.format("%Y-%m-%d %H:%M")
+        );
+        let body = notify::run_summary(&spec.profile, &results, completed);
+        match notify::send_email(&spec.settings, &subject, &body, &report_files) {
+            Ok(()) => state.log(LogEvent::info(
+                Stage::Summary,
+                format!("Отчёт отправлен на {}", spec.settings.smtp_to),
+            )),
+            Err(e) => state.log(LogEvent::warn(
+                Stage::Summary,
+                format!("Не удалось отправить отчёт по почте: {}", e),
+            )),
+        }
+    }
+    state.log(LogEvent::info(Stage::Summary, "Процесс завершен."));
+}
+
+// Один проход по источнику задания для указанных vendor codes; false — проход прерван
+fn run_source(
+    uploader: &WbUploader,
+    spec: &JobSpec,
+    vendor_codes: &[String],
+    matching: &Matching,
+    state: &JobState,
+) -> bool {
+    match &spec.source {
+        JobSource::SingleFile { path } => {
+            run_single_file(uploader, vendor_codes, path, matching, state)
+        }
+        JobSource::LocalFolder { path } => {
+            run_local_folder(uploader, vendor_codes, path, matching, state)
+        }
+        JobSource::Yandex { public_keys } => run_yandex(
+            uploader,
+            vendor_codes,
+            public_keys,
+            matching,
+            state.scan_cache(&spec.settings),
+            state,
+        ),
+        JobSource::Files { paths } => run_files(uploader, vendor_codes, paths, matching, state),
+        JobSource::CardUpdates { path } => run_card_updates(uploader, vendor_codes, path, state),
+        JobSource::NewCards { path } => {
+            run_new_cards(uploader, vendor_codes, path, matching, state)
+        }
+    }
+}
+
+// Пауза, которую можно прервать остановкой обработки; false — обработку остановили
+fn wait_unless_cancelled(duration: Duration, state: &JobState) -> bool {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if state.is_cancelled() {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(200).min(deadline.saturating_duration_since(Instant::now())));
+    }
+    !state.is_cancelled()
+}
+
+// WB обрабатывает медиа и карточки асинхронно: ошибки модерации появляются
+// в cards/error/list не сразу, поэтому список запрашивается с задержкой
+fn check_card_errors(uploader: &WbUploader, spec: &JobSpec, state: &JobState) {
+    let sent: Vec<String> = state
+        .results
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|r| !r.wb_responses.is_empty())
+        .map(|r| r.vendor_code.clone())
+        .collect();
+    if sent.is_empty() {
+        return;
+    }
+    state.log(LogEvent::info(
+        Stage::Summary,
+        format!(
+            "Проверка ошибок обработки карточек WB через {} сек",
+            spec.settings.card_errors_delay_secs
+        ),
+    ));
+    if !wait_unless_cancelled(Duration::from_secs(spec.settings.card_errors_delay_secs), state) {
+        return;
+    }
+    let card_errors = match uploader.get_card_errors() {
+        Ok(card_errors) => card_errors,
+        Err(e) => {
+            state.log(LogEvent::warn(
+                Stage::Summary,
+                format!("Не удалось получить список ошибок карточек WB: {}", e),
+            ));
+            return;
+        }
+    };
+    let mut found = 0;
+    for card_error in card_errors {
+        // Ошибки прошлых запусков остаются в списке, пока карточку не исправят
+        let before_run = DateTime::parse_from_rfc3339(&card_error.updated_at)
+            .is_ok_and(|updated_at| updated_at < spec.started_at);
+        if before_run || card_error.errors.is_empty() || !sent.contains(&card_error.vendor_code) {
+            continue;
+        }
+        found += 1;
+        let nm_id = state
+            .nm_ids
+            .lock()
+            .unwrap()
+            .get(&card_error.vendor_code)
+            .copied();
+        let mut event = LogEvent::warn(
+            Stage::Summary,
+            format!(
+                "Ошибка обработки карточки WB для vendorCode {}: {}",
+                card_error.vendor_code,
+                card_error.errors.join("; ")
+            ),
+        )
+        .vendor_code(&card_error.vendor_code);
+        if let Some(nm_id) = nm_id {
+            event = event.nm_id(nm_id);
+        }
+        state.log(event);
+        state.update_result(&card_error.vendor_code, |r| {
+            r.wb_errors.extend(card_error.errors)
+        });
+    }
+    if found == 0 {
+        state.log(LogEvent::info(
+            Stage::Summary,
+            "Ошибок обработки карточек WB не найдено",
+        ));
+    }
+}
+
+fn run_single_file(
+    uploader: &WbUploader,
+    vendor_codes: &[String],
+    single_file_path: &str,
+    matching: &Matching,
+    state: &JobState,
+) -> bool {
+    state.log(LogEvent::info(
+        Stage::Scan,
+        format!("Режим загрузки одного файла: {}", single_file_path),
+    ));
+    let path = Path::new(single_file_path);
+    let name = path.file_name().unwrap().to_string_lossy().to_string();
+    let mut downloader = match Downloader::new(Vec::new(), vendor_codes.to_vec(), &state.timeouts()) {
+        Ok(d) => d,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка инициализации Downloader: {}", e),
+            ));
+            return false;
+        }
+    };
+    downloader.set_matching(matching.clone());
+    state.log(LogEvent::info(
+        Stage::General,
+        "Downloader успешно инициализирован для одиночного файла",
+    ));
+    let file_info = match downloader.match_local_paths(&[single_file_path.to_string()]) {
+        Ok(files) if !files.is_empty() => files[0].clone(),
+        Ok(_) => {
+            state.log(LogEvent::error(
+                Stage::Scan,
+                format!(
+                    "Ошибка: Файл {} не соответствует ни одному vendorCode: {:?}",
+                    name, vendor_codes
+                ),
+            ));
+            return false;
+        }
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::Scan,
+                format!("Ошибка сопоставления файла: {}", e),
+            ));
+            return false;
+        }
+    };
+
+    state.progress.lock().unwrap().scanned = 1;
+    state.begin_result(&file_info.articul);
+    if let Some(nm_id) = resolve_nm_id(uploader, &file_info.articul, state) {
+        state.update_result(&file_info.articul, |r| r.files = vec![file_info.clone()]);
+        let mut files = vec![file_info.clone()];
+        state.hash_files(&mut files);
+        let files = state.without_uploaded(&file_info.articul, nm_id, files);
+        if !files.is_empty() {
+            state.snapshot_card(uploader, &file_info.articul, nm_id);
+        }
+        state.progress.lock().unwrap().begin_item(files.len());
+        for file in &files {
+            upload_file(uploader, nm_id, file, None, state);
+        }
+        state.attach_rich_content(uploader, &file_info.articul, nm_id);
+    }
+    state.report_progress(&file_info.articul);
+    true
+}
+
+fn run_local_folder(
+    uploader: &WbUploader,
+    vendor_codes: &[String],
+    local_source_path: &str,
+    matching: &Matching,
+    state: &JobState,
+) -> bool {
+    state.log(LogEvent::info(
+        Stage::General,
+        "Инициализация Downloader для локального режима",
+    ));
+    let mut downloader = match Downloader::new(Vec::new(), vendor_codes.to_vec(), &state.timeouts()) {
+        Ok(d) => d,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка инициализации Downloader: {}", e),
+            ));
+            return false;
+        }
+    };
+    downloader.set_matching(matching.clone());
+    state.log(LogEvent::info(
+        Stage::General,
+        "Downloader успешно инициализирован для локального режима",
+    ));
+    state.log(LogEvent::info(
+        Stage::Scan,
+        format!("Начало сканирования локальной папки: {}", local_source_path),
+    ));
+    let files = match downloader.find_local_files(local_source_path) {
+        Ok(files) => {
+            state.progress.lock().unwrap().scanned = files.len();
+            state.log(LogEvent::info(
+                Stage::Scan,
+                format!("Найдено файлов: {}", files.len()),
+            ));
+            files
+        }
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::Scan,
+                format!("Ошибка сканирования локальной папки: {}", e),
+            ));
+            return false;
+        }
+    };
+    let (vendor_codes, mut files) = state.only_new_files(vendor_codes, files);
+    if !state.check_before_upload(
+        uploader,
+        &vendor_codes,
+        &mut files,
+        downloader.unmatched_files(),
+        true,
+    ) {
+        return false;
+    }
+    upload_local_files(uploader, &vendor_codes, &files, state);
+    true
+}
+
+// Загрузка ровно переданных файлов, без сканирования папки (режим отслеживания)
+fn run_files(
+    uploader: &WbUploader,
+    vendor_codes: &[String],
+    paths: &[String],
+    matching: &Matching,
+    state: &JobState,
+) -> bool {
+    let mut downloader = match Downloader::new(Vec::new(), vendor_codes.to_vec(), &state.timeouts()) {
+        Ok(d) => d,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка инициализации Downloader: {}", e),
+            ));
+            return false;
+        }
+    };
+    downloader.set_matching(matching.clone());
+    let mut files = match downloader.match_local_paths(paths) {
+        Ok(files) => files,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::Scan,
+                format!("Ошибка сопоставления файлов: {}", e),
+            ));
+            return false;
+        }
+    };
+    state.progress.lock().unwrap().scanned = files.len();
+    state.log(LogEvent::info(
+        Stage::Scan,
+        format!("Новых файлов для загрузки: {}", files.len()),
+    ));
+    if !state.check_before_upload(
+        uploader,
+        vendor_codes,
+        &mut files,
+        downloader.unmatched_files(),
+        true,
+    ) {
+        return false;
+    }
+    upload_local_files(uploader, vendor_codes, &files, state);
+    true
+}
+
+fn upload_local_files(
+    uploader: &WbUploader,
+    vendor_codes: &[String],
+    files: &[FileInfo],
+    state: &JobState,
+) {
+    for vendor_code in vendor_codes {
+        if state.is_cancelled() {
+            break;
+        }
+        state.log(
+            LogEvent::info(
+                Stage::Resolve,
+                format!("Обработка vendorCode: {}", vendor_code),
+            )
+            .vendor_code(vendor_code),
+        );
+        state.begin_result(vendor_code);
+        if let Some(nm_id) = resolve_nm_id(uploader, vendor_code, state) {
+            let mut relevant_files = relevant_files(files, vendor_code, state);
+            state.hash_files(&mut relevant_files);
+            // Ссылки media/save заменяют всю галерею — отправляются все файлы vendorCode,
+            // как в режиме Яндекс.Диска
+            if state.file_server().is_some() {
+                if !relevant_files.is_empty()
+                    && !state.all_uploaded(vendor_code, nm_id, &relevant_files)
+                {
+                    state
+                        .progress
+                        .lock()
+                        .unwrap()
+                        .begin_item(relevant_files.len());
+                    state.snapshot_card(uploader, vendor_code, nm_id);
+                    upload_links(uploader, &[], vendor_code, nm_id, &relevant_files, state);
+                }
+                state.attach_rich_content(uploader, vendor_code, nm_id);
+                state.report_progress(vendor_code);
+                continue;
+            }
+            let relevant_files = state.without_uploaded(vendor_code, nm_id, relevant_files);
+            if !relevant_files.is_empty() {
+                state.snapshot_card(uploader, vendor_code, nm_id);
+            }
+            state
+                .progress
+                .lock()
+                .unwrap()
+                .begin_item(relevant_files.len());
+            upload_local(uploader, vendor_code, nm_id, &relevant_files, state);
+            state.attach_rich_content(uploader, vendor_code, nm_id);
+        }
+        state.report_progress(vendor_code);
+    }
+}
+
+// Локальные файлы vendorCode по одному через media/file; с несколькими потоками в настройках —
+// одновременно, сколько позволяет предел, который уменьшается при ответах 429
+fn upload_local(
+    uploader: &WbUploader,
+    vendor_code: &str,
+    nm_id: i64,
+    files: &[FileInfo],
+    state: &JobState,
+) {
+    let upload = |file: &FileInfo| {
+        state.log(
+            LogEvent::info(
+                Stage::Upload,
+                format!("Загрузка файла {} для nmId {}", file.path, nm_id),
+            )
+            .vendor_code(vendor_code)
+            .nm_id(nm_id),
+        );
+        upload_file(uploader, nm_id, file, None, state);
+    };
+    match uploader.concurrency().filter(|c| c.max() > 1 && files.len() > 1) {
+        Some(concurrency) => {
+            let next = AtomicUsize::new(0);
+            std::thread::scope(|scope| {
+                for _ in 0..concurrency.max().min(files.len()) {
+                    scope.spawn(|| {
+                        while let Some(file) = files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            let _permit = concurrency.acquire();
+                            upload(file);
+                        }
+                    });
+                }
+            });
+        }
+        None => files.iter().for_each(upload),
+    }
+}
+
+fn run_yandex(
+    uploader: &WbUploader,
+    vendor_codes: &[String],
+    public_keys: &[String],
+    matching: &Matching,
+    scan_cache: Option<ScanCache>,
+    state: &JobState,
+) -> bool {
+    state.log(LogEvent::info(
+        Stage::General,
+        "Инициализация Downloader для Яндекс.Диска",
+    ));
+    let mut downloader = match Downloader::new(public_keys.to_vec(), vendor_codes.to_vec(), &state.timeouts()) {
+        Ok(d) => d,
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::General,
+                format!("Ошибка инициализации Downloader: {}", e),
+            ));
+            return false;
+        }
+    };
+    downloader.set_matching(matching.clone());
+    downloader.set_scan_cache(scan_cache);
+    downloader.set_capture(state.http_sink());
+    state.log(LogEvent::info(
+        Stage::General,
+        "Downloader успешно инициализирован для Яндекс.Диска",
+    ));
+    state.log(LogEvent::info(
+        Stage::Scan,
+        format!(
+            "Начало поиска файлов с URL: {:?}",
+            public_keys
+                .iter()
+                .map(|k| links::mask_password(k))
+                .collect::<Vec<_>>()
+        ),
+    ));
+    let files = match downloader.find_files() {
+        Ok(files) => {
+            state.progress.lock().unwrap().scanned = files.len();
+            state.log(LogEvent::info(
+                Stage::Scan,
+                format!("Найдено файлов: {}", files.len()),
+            ));
+            files
+        }
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::Scan,
+                format!("Ошибка поиска файлов: {}", e),
+            ));
+            return false;
+        }
+    };
+    let (vendor_codes, mut files) = state.only_new_files(vendor_codes, files);
+    if !state.check_before_upload(
+        uploader,
+        &vendor_codes,
+        &mut files,
+        downloader.unmatched_files(),
+        false,
+    ) {
+        return false;
+    }
+
+    for vendor_code in &vendor_codes {
+        if state.is_cancelled() {
+            break;
+        }
+        state.log(
+            LogEvent::info(
+                Stage::Resolve,
+                format!("Обработка vendorCode: {}", vendor_code),
+            )
+            .vendor_code(vendor_code),
+        );
+        state.begin_result(vendor_code);
+        if let Some(nm_id) = resolve_nm_id(uploader, vendor_code, state) {
+            let relevant_files = relevant_files(&files, vendor_code, state);
+            if !relevant_files.is_empty()
+                && !state.all_uploaded(vendor_code, nm_id, &relevant_files)
+            {
+                state
+                    .progress
+                    .lock()
+                    .unwrap()
+                    .begin_item(relevant_files.len());
+                state.snapshot_card(uploader, vendor_code, nm_id);
+                upload_links(
+                    uploader,
+                    public_keys,
+                    vendor_code,
+                    nm_id,
+                    &relevant_files,
+                    state,
+                );
+            }
+            state.attach_rich_content(uploader, vendor_code, nm_id);
+        }
+        state.report_progress(vendor_code);
+    }
+    true
+}
+
+fn run_card_updates(
+    uploader: &WbUploader,
+    vendor_codes: &[String],
+    path: &str,
+    state: &JobState,
+) -> bool {
+    state.log(LogEvent::info(
+        Stage::Scan,
+        format!("Чтение изменений карточек из {}", path),
+    ));
+    let updates = match card_update::load(Path::new(path)) {
+        Ok(updates) => {
+            state.progress.lock().unwrap().scanned = updates.len();
+            updates
+        }
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::Scan,
+                format!("Ошибка чтения изменений карточек: {}", e),
+            ));
+            return false;
+        }
+    };
+    let mut references = HashMap::new();
+    for vendor_code in vendor_codes {
+        if state.is_cancelled() {
+            break;
+        }
+        state.log(
+            LogEvent::info(
+                Stage::Resolve,
+                format!("Обработка vendorCode: {}", vendor_code),
+            )
+            .vendor_code(vendor_code),
+        );
+        state.begin_result(vendor_code);
+        match updates.iter().find(|u| &u.vendor_code == vendor_code) {
+            Some(update) => {
+                if let Some(nm_id) = resolve_nm_id(uploader, vendor_code, state) {
+                    update_card(uploader, nm_id, update, &mut references, state);
+                }
+            }
+            None => state.fail(
+                LogEvent::error(
+                    Stage::Scan,
+                    format!(
+                        "Ошибка: Нет изменений для vendorCode {} в файле",
+                        vendor_code
+                    ),
+                )
+                .vendor_code(vendor_code),
+            ),
+        }
+        state.report_progress(vendor_code);
+    }
+    true
+}
+
+// Сколько ждать появления созданной карточки в cards/list
+const NEW_CARD_ATTEMPTS: u32 = 12;
+const NEW_CARD_DELAY: Duration = Duration::from_secs(10);
+
+fn run_new_cards(
+    uploader: &WbUploader,
+    vendor_codes: &[String],
+    path: &str,
+    matching: &Matching,
+    state: &JobState,
+) -> bool {
+    state.log(LogEvent::info(
+        Stage::Scan,
+        format!("Чтение новых карточек из {}", path),
+    ));
+    let cards = match card_create::load(Path::new(path)) {
+        Ok(cards) => {
+            state.progress.lock().unwrap().scanned = cards.len();
+            cards
+        }
+        Err(e) => {
+            state.log(LogEvent::error(
+                Stage::Scan,
+                format!("Ошибка чтения новых карточек: {}", e),
+            ));
+            return false;
+        }
+    };
+    let planned = cards
+        .iter()
+        .filter(|card| vendor_codes.contains(&card.vendor_code))
+        .count();
+    check_card_limits(uploader, planned, state);
+    let mut references = HashMap::new();
+    for vendor_code in vendor_codes {
+        if state.is_cancelled() {
+            break;
+        }
+        state.log(
+            LogEvent::info(
+                Stage::Resolve,
+                format!("Создание карточки vendorCode: {}", vendor_code),
+            )
+            .vendor_code(vendor_code),
+        );
+        state.begin_result(vendor_code);
+        match cards.iter().find(|c| &c.vendor_code == vendor_code) {
+            Some(card) => {
+                if let Some(nm_id) = create_card(uploader, card, &mut references, state) {
+                    if let Some(photos) = &card.photos {
+                        attach_new_card_photos(
+                            uploader,
+                            vendor_code,
+                            nm_id,
+                            photos,
+                            matching,
+                            state,
+                        );
+                    }
+                    state.attach_rich_content(uploader, vendor_code, nm_id);
+                }
+            }
+            None => state.fail(
+                LogEvent::error(
+                    Stage::Scan,
+                    format!("Ошибка: Нет данных для vendorCode {} в файле", vendor_code),
+                )
+                .vendor_code(vendor_code),
+            ),
+        }
+        state.report_progress(vendor_code);
+    }
+    true
+}
+
+// Предупреждает, если запуск создаёт больше карточек, чем позволяет лимит продавца:
+// сверх лимита WB карточки не создаст. Запуск не останавливается — лимит могут докупить
+fn check_card_limits(uploader: &WbUploader, planned: usize, state: &JobState) {
+    match uploader.get_card_limits() {
+        Ok(limits) if planned as u64 > limits.total() => state.log(LogEvent::warn(
+            Stage::General,
+            format!(
+                "Запуск создаёт карточек: {}, а лимит WB — {}: карточки сверх лимита не будут созданы",
+                planned,
+                limits.describe()
+            ),
+        )),
+        Ok(limits) => state.log(LogEvent::info(
+            Stage::General,
+            format!(
+                "Лимит создания карточек: {}, запуск создаёт: {}",
+                limits.describe(),
+                planned
+            ),
+        )),
+        Err(e) => state.log(LogEvent::warn(
+            Stage::General,
+            format!("Не удалось получить лимит создания карточек: {}", e),
+        )),
+    }
+}
+
+// Отправляет карточку в cards/upload и ждёт, пока WB присвоит ей nmId
+fn create_card(
+    uploader: &WbUploader,
+    card: &NewCard,
+    references: &mut HashMap<i64, Vec<Characteristic>>,
+    state: &JobState,
+) -> Option<i64> {
+    let vendor_code = card.vendor_code.as_str();
+    let body = characteristics_reference(uploader, card.subject_id, references, state)
+        .and_then(|reference| card_create::body(card, &reference))
+        .and_then(|body| Ok(uploader.create_cards(std::slice::from_ref(&body))?));
+    match body {
+        Ok(response) => {
+            state.update_result(vendor_code, |r| r.wb_responses.push(response));
+            state.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    format!("Карточка vendorCode {} отправлена на создание", vendor_code),
+                )
+                .vendor_code(vendor_code),
+            );
+        }
+        Err(e) => {
+            state.fail_any(
+                LogEvent::error(
+                    Stage::Upload,
+                    format!("Ошибка создания карточки vendorCode {}: {}", vendor_code, e),
+                )
+                .vendor_code(vendor_code),
+                &e,
+            );
+            return None;
+        }
+    }
+    for attempt in 1..=NEW_CARD_ATTEMPTS {
+        if !wait_unless_cancelled(NEW_CARD_DELAY, state) {
+            return None;
+        }
+        if uploader.get_nm_id_by_vendor_code(vendor_code).is_ok() {
+            return resolve_nm_id(uploader, vendor_code, state);
+        }
+        state.log(
+            LogEvent::info(
+                Stage::Resolve,
+                format!(
+                    "Карточка vendorCode {} ещё не создана (попытка {}/{})",
+                    vendor_code, attempt, NEW_CARD_ATTEMPTS
+                ),
+            )
+            .vendor_code(vendor_code),
+        );
+    }
+    state.fail(
+        LogEvent::error(
+            Stage::Resolve,
+            format!(
+                "Ошибка: WB не создал карточку vendorCode {} — причина в списке несозданных карточек в личном кабинете",
+                vendor_code
+            ),
+        )
+        .vendor_code(vendor_code),
+    );
+    None
+}
+
+// photos — публичная ссылка Яндекс.Диска или локальная папка с фото
+fn attach_new_card_photos(
+    uploader: &WbUploader,
+    vendor_code: &str,
+    nm_id: i64,
+    photos: &str,
+    matching: &Matching,
+    state: &JobState,
+) {
+    let yandex = links::is_valid_public_link(photos);
+    let public_keys = if yandex {
+        vec![photos.to_string()]
+    } else {
+        Vec::new()
+    };
+    let files = Downloader::new(
+        public_keys.clone(),
+        vec![vendor_code.to_string()],
+        &state.timeouts(),
+    ).and_then(
+        |mut downloader| {
+            downloader.set_matching(matching.clone());
+            downloader.set_capture(state.http_sink());
+            if yandex {
+                downloader.find_files()
+            } else {
+                downloader.find_local_files(photos)
+            }
+        },
+    );
+    let files = match files {
+        Ok(files) => relevant_files(&files, vendor_code, state),
+        Err(e) => {
+            state.fail(
+                LogEvent::error(
+                    Stage::Scan,
+                    format!(
+                        "Ошибка поиска фото в {}: {}",
+                        links::mask_password(photos),
+                        e
+                    ),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            );
+            return;
+        }
+    };
+    if files.is_empty() {
+        return;
+    }
+    state.progress.lock().unwrap().begin_item(files.len());
+    if yandex || state.file_server().is_some() {
+        upload_links(uploader, &public_keys, vendor_code, nm_id, &files, state);
+        return;
+    }
+    upload_local(uploader, vendor_code, nm_id, &files, state);
+}
+
+// Справочник загружается один раз на категорию за запуск, из локального кэша, если он есть
+fn characteristics_reference(
+    uploader: &WbUploader,
+    subject_id: i64,
+    references: &mut HashMap<i64, Vec<Characteristic>>,
+    state: &JobState,
+) -> anyhow::Result<Vec<Characteristic>> {
+    if let Entry::Vacant(entry) = references.entry(subject_id) {
+        entry.insert(match state.reference_cache() {
+            Some(cache) => cache.characteristics(uploader, subject_id)?,
+            None => uploader.get_characteristics(subject_id)?,
+        });
+    }
+    Ok(references[&subject_id].clone())
+}
+
+fn update_card(
+    uploader: &WbUploader,
+    nm_id: i64,
+    update: &CardUpdate,
+    references: &mut HashMap<i64, Vec<Characteristic>>,
+    state: &JobState,
+) {
+    let vendor_code = update.vendor_code.as_str();
+    let card = match uploader.get_card_json(vendor_code) {
+        Ok(card) => card,
+        Err(e) => {
+            state.fail_wb(
+                LogEvent::error(
+                    Stage::Upload,
+                    format!("Ошибка получения карточки nmId {}: {}", nm_id, e),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+                &e,
+            );
+            return;
+        }
+    };
+    let reference = if update.characteristics.is_empty() {
+        Ok(Vec::new())
+    } else {
+        card["subjectID"]
+            .as_i64()
+            .ok_or_else(|| anyhow::anyhow!("В карточке нет subjectID"))
+            .and_then(|subject_id| {
+                characteristics_reference(uploader, subject_id, references, state)
+            })
+    };
+    let body = match reference.and_then(|reference| card_update::apply(&card, update, &reference)) {
+        Ok(body) => body,
+        Err(e) => {
+            state.fail_any(
+                LogEvent::error(Stage::Upload, format!("Ошибка: {}", e))
+                    .vendor_code(vendor_code)
+                    .nm_id(nm_id),
+                &e,
+            );
+            return;
+        }
+    };
+    if ["title", "description", "characteristics"]
+        .iter()
+        .all(|field| body.get(field) == card.get(field))
+    {
+        state.log(
+            LogEvent::info(Stage::Upload, "Карточка не изменилась")
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+        );
+        return;
+    }
+    match uploader.update_cards(std::slice::from_ref(&body)) {
+        Ok(response) => {
+            state.update_result(vendor_code, |r| r.wb_responses.push(response));
+            state.log(
+                LogEvent::info(Stage::Upload, format!("Карточка nmId {} обновлена", nm_id))
+                    .vendor_code(vendor_code)
+                    .nm_id(nm_id),
+            );
+        }
+        Err(e) => state.fail_wb(
+            LogEvent::error(
+                Stage::Upload,
+                format!("Ошибка обновления карточки nmId {}: {}", nm_id, e),
+            )
+            .vendor_code(vendor_code)
+            .nm_id(nm_id),
+            &e,
+        ),
+    }
+}
+
+fn resolve_nm_id(uploader: &WbUploader, vendor_code: &str, state: &JobState) -> Option<i64> {
+    let cached = state.nm_ids.lock().unwrap().get(vendor_code).copied();
+    match cached
+        .map(Ok)
+        .unwrap_or_else(|| uploader.get_nm_id_by_vendor_code(vendor_code))
+        .or_else(|e| state.check_trash(uploader, vendor_code).unwrap_or(Err(e)))
+    {
+        Ok(nm_id) => {
+            state.log(
+                LogEvent::info(
+                    Stage::Resolve,
+                    format!("Найден nmId {} для vendorCode {}", nm_id, vendor_code),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            );
+            state.progress.lock().unwrap().resolved += 1;
+            state.update_result(vendor_code, |r| r.nm_id = Some(nm_id));
+            Some(nm_id)
+        }
+        Err(e) => {
+            state.fail_wb(
+                LogEvent::error(
+                    Stage::Resolve,
+                    format!(
+                        "Ошибка получения nmId для vendorCode {}: {}",
+                        vendor_code, e
+                    ),
+                )
+                .vendor_code(vendor_code),
+                &e,
+            );
+            None
+        }
+    }
+}
+
+// Файлы vendorCode: сначала фото, видео — последним; WB принимает одно видео на карточку
+fn relevant_files(files: &[FileInfo], vendor_code: &str, state: &JobState) -> Vec<FileInfo> {
+    let (mut videos, mut relevant_files): (Vec<FileInfo>, Vec<FileInfo>) = files
+        .iter()
+        .filter(|f| f.articul == vendor_code)
+        .cloned()
+        .partition(|f| is_video_file(&f.name));
+    videos.sort_by(|a, b| (a.photo_number, &a.name).cmp(&(b.photo_number, &b.name)));
+    if videos.len() > 1 {
+        state.log(
+            LogEvent::warn(
+                Stage::Scan,
+                format!(
+                    "Несколько видео для vendorCode {}, загружается только {}; пропущены: {}",
+                    vendor_code,
+                    videos[0].name,
+                    videos[1..]
+                        .iter()
+                        .map(|f| f.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            )
+            .vendor_code(vendor_code),
+        );
+    }
+    relevant_files.extend(videos.into_iter().take(1));
+    state.update_result(vendor_code, |r| r.files = relevant_files.clone());
+    if relevant_files.is_empty() {
+        state.fail(
+            LogEvent::error(
+                Stage::Scan,
+                format!("Ошибка: Не найдено файлов для vendorCode: {}", vendor_code),
+            )
+            .vendor_code(vendor_code),
+        );
+    }
+    relevant_files
+}
+
+fn media_slot(file: &FileInfo) -> MediaSlot {
+    if is_video_file(&file.name) {
+        MediaSlot::Video
+    } else {
+        MediaSlot::Photo(file.photo_number)
+    }
+}
+
+// Файлы Яндекс.Диска (source) сначала скачиваются во временный файл, локальные читаются с диска
+fn upload_file(
+    uploader: &WbUploader,
+    nm_id: i64,
+    file: &FileInfo,
+    source: Option<&Downloader>,
+    state: &JobState,
+) {
+    let transfer = Arc::new(TransferProgress::default());
+    state.progress.lock().unwrap().begin_transfer(&file.name, &transfer);
+    let slot = media_slot(file);
+    let result = match source {
+        Some(downloader) => upload_downloaded(uploader, nm_id, file, downloader, slot, &transfer, state),
+        None => uploader
+            .upload_local_file(nm_id, &file.path, slot, &transfer)
+            .map_err(anyhow::Error::from),
+    };
+    {
+        let mut progress = state.progress.lock().unwrap();
+        progress.end_transfer(&transfer);
+        if result.is_ok() {
+            state.metrics.add_bytes_sent(transfer.total());
+            progress.bytes_uploaded += transfer.total();
+        }
+    }
+    match result {
+        Ok(response) => {
+            state.metrics.add_uploads(1);
+            state.progress.lock().unwrap().add_uploaded(1);
+            state.update_result(&file.articul, |r| {
+                r.files_uploaded += 1;
+                r.wb_responses.push(response);
+            });
+            state.record_attempt(&file.articul, Some(nm_id), Some(file), None);
+            state.remember_uploaded(file, source);
+            state.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    format!(
+                        "Файл {} успешно загружен для nmId {} в {}",
+                        file.path,
+                        nm_id,
+                        slot.describe()
+                    ),
+                )
+                .vendor_code(&file.articul)
+                .nm_id(nm_id),
+            );
+        }
+        Err(e) => {
+            state.metrics.add_upload_failure();
+            state.record_attempt(&file.articul, Some(nm_id), Some(file), Some(e.to_string()));
+            state.fail_any(
+                LogEvent::error(
+                    Stage::Upload,
+                    format!(
+                        "Ошибка загрузки файла {} для nmId {}: {}",
+                        file.path, nm_id, e
+                    ),
+                )
+                .vendor_code(&file.articul)
+                .nm_id(nm_id),
+                &e,
+            );
+        }
+    }
+}
+
+// Файл Яндекс.Диска скачивается в staging после проверки свободного места на её диске
+// и после успешной загрузки удаляется или остаётся там по настройке; при ошибке временный
+// файл удаляется всегда
+fn upload_downloaded(
+    uploader: &WbUploader,
+    nm_id: i64,
+    file: &FileInfo,
+    downloader: &Downloader,
+    slot: MediaSlot,
+    transfer: &Arc<TransferProgress>,
+    state: &JobState,
+) -> anyhow::Result<String> {
+    let staging = state.staging();
+    limits::check_free_space(staging.dir(), state.min_free_disk_mb.load(Ordering::Relaxed))?;
+    let temp = downloader.download_to_temp(file, &staging)?;
+    let response = uploader.upload_downloaded_file(nm_id, &file.path, temp.path(), slot, transfer)?;
+    let path = temp.keep();
+    if state.delete_after_upload.load(Ordering::Relaxed) {
+        match downloader.cleanup_file(&path.to_string_lossy(), &staging) {
+            Ok(()) => state.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    format!("Скачанный файл {} удалён из {}", file.path, path.display()),
+                )
+                .vendor_code(&file.articul),
+            ),
+            Err(e) => state.log(LogEvent::warn(Stage::Upload, e.to_string()).vendor_code(&file.articul)),
+        }
+    } else {
+        state.log(
+            LogEvent::info(
+                Stage::Upload,
+                format!("Скачанный файл {} оставлен в {}", file.path, path.display()),
+            )
+            .vendor_code(&file.articul),
+        );
+    }
+    Ok(response)
+}
+
+fn upload_links(
+    uploader: &WbUploader,
+    public_keys: &[String],
+    vendor_code: &str,
+    nm_id: i64,
+    files: &[FileInfo],
+    state: &JobState,
+) {
+    let mut downloader = match Downloader::new(
+        public_keys.to_vec(),
+        vec![vendor_code.to_string()],
+        &state.timeouts(),
+    ) {
+        Ok(d) => d,
+        Err(e) => {
+            state.fail(
+                LogEvent::error(
+                    Stage::Upload,
+                    format!("Ошибка инициализации Downloader для публикации: {}", e),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            );
+            return;
+        }
+    };
+    downloader.set_capture(state.http_sink());
+    if !public_keys.is_empty() && state.download_yandex_files.load(Ordering::Relaxed) {
+        state.log(
+            LogEvent::info(
+                Stage::Upload,
+                format!(
+                    "Файлы Яндекс.Диска для nmId {} скачиваются и загружаются файлами: {}",
+                    nm_id,
+                    files.len()
+                ),
+            )
+            .vendor_code(vendor_code)
+            .nm_id(nm_id),
+        );
+        for file in files {
+            upload_file(uploader, nm_id, file, Some(&downloader), state);
+        }
+        return;
+    }
+    publish_links(uploader, &downloader, vendor_code, nm_id, files, state);
+}
+
+fn publish_links(
+    uploader: &WbUploader,
+    downloader: &Downloader,
+    vendor_code: &str,
+    nm_id: i64,
+    files: &[FileInfo],
+    state: &JobState,
+) {
+    // Без публичных ссылок файлы локальные: WB не скачает их по file://, поэтому они
+    // раздаются сервером файлов, а если он выключен — отправляются через media/file
+    let server = if downloader.public_keys().is_empty() {
+        state.file_server()
+    } else {
+        None
+    };
+    if downloader.public_keys().is_empty() && server.is_none() {
+        state.log(
+            LogEvent::info(
+                Stage::Upload,
+                format!(
+                    "Локальные файлы для nmId {} загружаются файлами, а не ссылками: {}",
+                    nm_id,
+                    files.len()
+                ),
+            )
+            .vendor_code(vendor_code)
+            .nm_id(nm_id),
+        );
+        for file in files {
+            upload_file(uploader, nm_id, file, None, state);
+        }
+        return;
+    }
+    match downloader.generate_media_json(nm_id, files, server.as_deref()) {
+        Ok(media) => {
+            let json_output = serde_json::to_string_pretty(&media)
+                .unwrap_or_else(|e| format!("Ошибка сериализации JSON: {}", e));
+            state.log(
+                LogEvent::info(
+                    Stage::Upload,
+                    format!("JSON Output для nmId {}:\n{}", nm_id, json_output),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+            );
+            state.update_result(vendor_code, |r| r.media_urls = media.data.clone());
+            match uploader.upload_links(nm_id, &media.data) {
+                Err(e) => {
+                    state.metrics.add_upload_failure();
+                    for file in files {
+                        state.record_attempt(
+                            vendor_code,
+                            Some(nm_id),
+                            Some(file),
+                            Some(e.to_string()),
+                        );
+                    }
+                    state.fail_wb(
+                        LogEvent::error(
+                            Stage::Upload,
+                            format!("Ошибка загрузки ссылок на WB для nmId {}: {}", nm_id, e),
+                        )
+                        .vendor_code(vendor_code)
+                        .nm_id(nm_id),
+                        &e,
+                    )
+                }
+                Ok(response) => {
+                    state.metrics.add_uploads(files.len() as u64);
+                    state.progress.lock().unwrap().add_uploaded(files.len());
+                    state.update_result(vendor_code, |r| {
+                        r.files_uploaded += files.len();
+                        r.wb_responses.push(response);
+                    });
+                    for file in files {
+                        state.record_attempt(vendor_code, Some(nm_id), Some(file), None);
+                        state.remember_uploaded(file, Some(downloader));
+                    }
+                    // Порядок галереи media/save не совпадает с номерами фото в именах файлов
+                    state.reset_card(nm_id);
+                    state.log(
+                        LogEvent::info(
+                            Stage::Upload,
+                            format!("Ссылки для nmId {} загружены успешно", nm_id),
+                        )
+                        .vendor_code(vendor_code)
+                        .nm_id(nm_id),
+                    );
+                }
+            }
+        }
+        Err(e) => {
+            state.fail_any(
+                LogEvent::error(
+                    Stage::Upload,
+                    format!("Ошибка генерации JSON для nmId {}: {}", nm_id, e),
+                )
+                .vendor_code(vendor_code)
+                .nm_id(nm_id),
+                &e,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallel_transfers_count_wall_time_once() {
+        let mut progress = Progress::default();
+        let (first, second) = (
+            Arc::new(TransferProgress::default()),
+            Arc::new(TransferProgress::default()),
+        );
+        progress.begin_transfer("1.jpg", &first);
+        progress.begin_transfer("2.jpg", &second);
+        assert_eq!(progress.current_files.len(), 2);
+        std::thread::sleep(Duration::from_millis(100));
+        progress.end_transfer(&first);
+        assert_eq!(progress.current_files.len(), 1);
+        progress.end_transfer(&second);
+        assert!(progress.current_files.is_empty());
+        assert!(progress.upload_time >= Duration::from_millis(100));
+        assert!(progress.upload_time < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn failed_vendor_code_is_listed_once() {
+        let state = JobState::new(LogBuffer::new(100), None);
+        for file in ["1.jpg", "2.jpg"] {
+            state.fail(LogEvent::error(Stage::Upload, file).vendor_code("ABC123"));
+        }
+        state.fail(LogEvent::error(Stage::Upload, "1.jpg").vendor_code("XYZ"));
+        assert_eq!(*state.failed_vendor_codes.lock().unwrap(), ["ABC123", "XYZ"]);
+        assert_eq!(state.progress.lock().unwrap().failed, 2);
+    }
+}
@@ -0,0 +1,128 @@
+//! Ядро менеджера контента Wildberries: поиск медиафайлов на Яндекс.Диске и в локальных
+//! папках, сопоставление их с vendor code, загрузка в карточки WB и оркестрация запусков.
+//!
+//! Интерфейс (`app.rs`) — один из потребителей библиотеки; тот же API используют режим
+//! `--headless`, локальный HTTP API и тесты. Типичный запуск:
+//!
+//! ```no_run
+//! use chrono::Local;
+//! use wb_upload_core::config::Config;
+//! use wb_upload_core::job::{self, JobSource, JobSpec, JobState};
+//! use wb_upload_core::settings::Settings;
+//!
+//! let config = Config::new()?;
+//! let settings = Settings::load(&config);
+//! let state = JobState::open(&config, &settings);
+//! job::start(
+//!     JobSpec {
+//!         profile: "Основной".to_string(),
+//!         api_key: "<ключ WB>".to_string(),
+//!         wb_base_url: String::new(),
+//!         timeouts: Default::default(),
+//!         vendor_codes: vec!["ABC123".to_string()],
+//!         source: JobSource::LocalFolder { path: "/photos".to_string() },
+//!         settings,
+//!         report_dir: config.get_reports_dir(),
+//!         started_at: Local::now(),
+//!         only_new: false,
+//!         confirm_validation: false,
+//!         force_reupload: false,
+//!     },
+//!     state.clone(),
+//! );
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+/// Поиск файлов на Яндекс.Диске и в локальных папках, сопоставление имён с vendor code.
+pub mod downloader;
+/// Клиент Content API Wildberries: поиск карточек, загрузка медиа, изменение карточек.
+pub mod uploader;
+/// Профили с API-ключами WB.
+pub mod profile;
+/// Оркестрация запуска: этапы, прогресс, результаты по vendor code, отчёты.
+pub mod job;
+
+/// Локальный HTTP API управления запусками.
+pub mod api;
+/// ZIP-архив файлов, загруженных запуском.
+pub mod archive;
+/// Аудит каталога: карточки без фото или с малым числом фото.
+pub mod audit;
+/// Резервная копия текущих фото карточек.
+pub mod backup;
+/// Пакетный запуск нескольких профилей, каждого со своим списком vendor codes.
+pub mod batch;
+/// Кэш списков файлов публичных папок.
+pub mod cache;
+/// Запись HTTP-запросов запуска в JSONL для обращений в поддержку.
+pub mod capture;
+/// Создание карточек из таблицы.
+pub mod card_create;
+/// Изменение карточек из таблицы.
+pub mod card_update;
+/// Поиск по каталогу карточек продавца.
+pub mod cards;
+/// Точка восстановления прерванного запуска.
+pub mod checkpoint;
+/// Сравнение числа фото в источнике и в карточках WB.
+pub mod compare;
+/// Число параллельных загрузок, подстраиваемое под ответы 429 от WB.
+pub mod concurrency;
+/// Пути к файлам конфигурации.
+pub mod config;
+/// Отчёт о сбое: сообщение паники, стек вызовов и последние события журнала.
+pub mod crash;
+/// Демо-режим: WB и Яндекс.Диск подменяются встроенным макетом с готовыми данными.
+pub mod demo;
+/// Проверка полей ввода по мере набора: None — поле в порядке или ещё пустое, иначе подсказка.
+pub mod fields;
+/// HTTP-сервер, раздающий локальные файлы по ссылкам для media/save.
+pub mod file_server;
+/// Glob-фильтры сканирования папок.
+pub mod filter;
+/// HTTP-клиент за трейтом: запросы к WB и Яндекс.Диску можно подменить в тестах.
+pub mod http;
+/// История загрузок в SQLite.
+pub mod history;
+/// Чтение таблиц CSV/Excel.
+pub mod import;
+/// Проверки свободного места на диске и предела памяти на файл.
+pub mod limits;
+/// Разбор публичных ссылок Яндекс.Диска.
+pub mod links;
+/// Журнал событий запуска.
+pub mod logs;
+/// Файл соответствий имён файлов и vendor code.
+pub mod mapping;
+/// Метрики Prometheus.
+pub mod metrics;
+/// Параметры сети HTTP-клиентов и проверка связи с WB и Яндекс.Диском.
+pub mod network;
+/// Уведомления: рабочий стол, webhooks, почта.
+pub mod notify;
+/// Предпросмотр найденных файлов.
+pub mod preview;
+/// Очередь подготовленных заданий, выполняемых по порядку.
+pub mod queue;
+/// Кэш справочников предметов и характеристик WB.
+pub mod reference;
+/// Отчёты о запуске в Excel и JSON.
+pub mod report;
+/// Рич-контент по шаблонам.
+pub mod rich;
+/// Запуски по расписанию.
+pub mod scheduler;
+/// Настройки приложения.
+pub mod settings;
+/// Папка staging для скачанных файлов и её очистка.
+pub mod staging;
+/// Поля интерфейса, сохраняемые между сеансами.
+pub mod ui_state;
+/// Проверка новой версии приложения по релизам GitHub.
+pub mod update;
+/// Вспомогательные функции: медиафайлы, хэши, разбор vendor codes.
+pub mod utils;
+/// Проверка vendor codes и файлов перед загрузкой.
+pub mod validation;
+/// Отслеживание новых файлов в папке.
+pub mod watcher;
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use std::path::Path;
+
+const MB: u64 = 1024 * 1024;
+
+// Запуск, который пишет файлы на диск, проверяет место заранее: лучше сразу сообщить,
+// чем оборвать отчёт, историю или копию фото на середине
+pub fn check_free_space(dir: &Path, min_free_mb: u64) -> Result<()> {
+    if min_free_mb == 0 {
+        return Ok(());
+    }
+    // Папка может быть ещё не создана — место смотрим на ближайшей существующей
+    let Some(existing) = dir.ancestors().find(|path| path.exists()) else {
+        return Ok(());
+    };
+    let available = fs4::available_space(existing).map_err(|e| {
+        anyhow::anyhow!(
+            "Не удалось узнать свободное место на диске {}: {}",
+            existing.display(),
+            e
+        )
+    })?;
+    if available < min_free_mb * MB {
+        return Err(anyhow::anyhow!(
+            "Недостаточно места на диске {}: свободно {} МБ, нужно не меньше {} МБ. \
+             Освободите место или уменьшите порог в настройках",
+            existing.display(),
+            available / MB,
+            min_free_mb
+        ));
+    }
+    Ok(())
+}
+
+// Файл целиком читается или скачивается в память перед загрузкой в WB; слишком большой
+// отклоняется до чтения, а не роняет приложение нехваткой памяти
+pub fn check_file_memory(name: &str, size: u64, max_memory_mb: u64) -> Result<()> {
+    if max_memory_mb > 0 && size > max_memory_mb * MB {
+        return Err(anyhow::anyhow!(
+            "Файл {} занимает {} МБ — больше предела памяти {} МБ из настроек",
+            name,
+            size.div_ceil(MB),
+            max_memory_mb
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use crate::http::{HttpClient, Request};
+use anyhow::Result;
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+// Публичная ссылка Яндекс.Диска: disk.yandex.ru/d/…, yadi.sk/i/…, disk.360.yandex.ru/d/… и т.п.
+// Путь после идентификатора ссылки («/d/abc/Май») — папка внутри публичной папки
+static PUBLIC_LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(?:https?://)?(?:www\.)?((?:disk\.360\.yandex|disk\.yandex)\.(?:ru|com|com\.tr|kz|by|ua|uz|az|am)|yadi\.sk)/(d|i)/([^/?#\s]+)(/[^?#]*)?",
+    )
+    .unwrap()
+});
+
+// Старый формат: disk.yandex.ru/public/?hash=…
+static HASH_LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(?:https?://)?(?:www\.)?disk\.(?:360\.)?yandex\.[a-z.]+/public/?\?(?:.*&)?hash=([^&#\s]+)")
+        .unwrap()
+});
+
+// Сокращённые ссылки, которые раскрываются переходом по редиректу
+static SHORT_LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^(?:https?://)?(?:clck\.ru|ya\.cc)/[^\s]+$").unwrap());
+
+#[derive(Clone)]
+pub struct PublicLink {
+    pub public_key: String,
+    // Папка внутри публичной папки, с которой начинается сканирование
+    pub path: String,
+    pub password: Option<String>,
+    // Cookie, полученные после ввода пароля
+    pub cookie: Option<String>,
+}
+
+pub fn is_short_link(link: &str) -> bool {
+    SHORT_LINK_RE.is_match(link.trim())
+}
+
+// Ссылка с необязательными путём внутри папки и паролем:
+// «https://disk.yandex.ru/d/abc|/Май/Новинки», «https://disk.yandex.ru/d/abc||пароль»
+fn split_spec(spec: &str) -> (&str, Option<String>, Option<String>) {
+    let mut parts = spec.splitn(3, '|');
+    let link = parts.next().unwrap_or_default().trim();
+    let path = parts.next().and_then(normalize_path);
+    let password = parts
+        .next()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty());
+    (link, path, password)
+}
+
+// Для логов и описания задания
+pub fn mask_password(spec: &str) -> String {
+    match spec.splitn(3, '|').collect::<Vec<_>>().as_slice() {
+        [link, path, password] if !password.trim().is_empty() => {
+            format!("{}|{}|***", link, path)
+        }
+        _ => spec.to_string(),
+    }
+}
+
+fn normalize_path(path: &str) -> Option<String> {
+    let path = path.trim().replace('\\', "/");
+    let path = path.trim_matches('/');
+    (!path.is_empty()).then(|| format!("/{}", path))
+}
+
+// Ссылка в виде, который принимает API Яндекс.Диска, или None, если это не публичная ссылка
+pub fn normalize_public_link(spec: &str) -> Option<PublicLink> {
+    let (link, subpath, password) = split_spec(spec);
+    let link = link.trim_matches(['"', '\'', '<', '>']);
+    if let Some(caps) = PUBLIC_LINK_RE.captures(link) {
+        let url_path = caps.get(4).and_then(|p| {
+            let decoded = urlencoding::decode(p.as_str()).ok()?;
+            normalize_path(&decoded)
+        });
+        return Some(PublicLink {
+            public_key: format!(
+                "https://{}/{}/{}",
+                caps[1].to_lowercase(),
+                caps[2].to_lowercase(),
+                &caps[3]
+            ),
+            path: subpath.or(url_path).unwrap_or_else(|| "/".to_string()),
+            password,
+            cookie: None,
+        });
+    }
+    HASH_LINK_RE.captures(link).map(|caps| PublicLink {
+        public_key: urlencoding::decode(&caps[1])
+            .map(|hash| hash.into_owned())
+            .unwrap_or_else(|_| caps[1].to_string()),
+        path: subpath.unwrap_or_else(|| "/".to_string()),
+        password,
+        cookie: None,
+    })
+}
+
+pub fn is_valid_public_link(spec: &str) -> bool {
+    normalize_public_link(spec).is_some() || is_short_link(split_spec(spec).0)
+}
+
+// Сокращённая ссылка раскрывается запросом с переходом по редиректам,
+// для ссылки с паролем запрашиваются cookie доступа
+pub fn resolve_public_link(client: &dyn HttpClient, spec: &str) -> PublicLink {
+    let mut link = resolve_link(client, spec);
+    if let Some(password) = link.password.clone() {
+        match unlock(client, &link.public_key, &password) {
+            Ok(cookie) => {
+                log::info!("Пароль для ссылки {} принят", link.public_key);
+                link.cookie = Some(cookie);
+            }
+            Err(e) => log::error!("{}", e),
+        }
+    }
+    link
+}
+
+fn resolve_link(client: &dyn HttpClient, spec: &str) -> PublicLink {
+    if let Some(link) = normalize_public_link(spec) {
+        return link;
+    }
+    let (link, subpath, password) = split_spec(spec);
+    let fallback = PublicLink {
+        public_key: link.to_string(),
+        path: subpath.clone().unwrap_or_else(|| "/".to_string()),
+        password: password.clone(),
+        cookie: None,
+    };
+    if !is_short_link(link) {
+        log::warn!(
+            "Ссылка {} не распознана как публичная ссылка Яндекс.Диска",
+            link
+        );
+        return fallback;
+    }
+    let url = if link.contains("://") {
+        link.to_string()
+    } else {
+        format!("https://{}", link)
+    };
+    match client.send(Request::get(&url)) {
+        Ok(response) => {
+            if let Some(mut resolved) = normalize_public_link(&response.url) {
+                log::info!(
+                    "Сокращённая ссылка {} раскрыта в {}",
+                    link,
+                    resolved.public_key
+                );
+                if let Some(subpath) = subpath {
+                    resolved.path = subpath;
+                }
+                resolved.password = password;
+                return resolved;
+            }
+            log::warn!(
+                "Сокращённая ссылка {} ведёт не на Яндекс.Диск: {}",
+                link,
+                response.url
+            );
+        }
+        Err(e) => log::warn!("Не удалось раскрыть сокращённую ссылку {}: {}", link, e),
+    }
+    fallback
+}
+
+// Пароль проверяется так же, как в веб-интерфейсе Диска; полученные cookie
+// передаются во все запросы к API по этой ссылке
+fn unlock(client: &dyn HttpClient, public_key: &str, password: &str) -> Result<String> {
+    let request = Request::post("https://disk.yandex.ru/public/api/check-password")
+        .json(&serde_json::json!({ "hash": public_key, "password": password }))?;
+    let response = client
+        .send(request)
+        .map_err(|e| anyhow::anyhow!("Ошибка проверки пароля для {}: {}", public_key, e))?;
+    let status = response.status();
+    let cookie = response
+        .header_values("Set-Cookie")
+        .filter_map(|value| value.split(';').next())
+        .collect::<Vec<_>>()
+        .join("; ");
+    if !status.is_success() || cookie.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Неверный пароль для ссылки {}: Статус {}",
+            public_key,
+            status
+        ));
+    }
+    Ok(cookie)
+}
+
+pub struct ParsedLinks {
+    pub links: Vec<String>,
+    pub invalid: Vec<String>,
+    pub duplicates: usize,
+}
+
+// Одна строка — одна ссылка; повторы определяются по нормализованной ссылке и пути
+pub fn parse_link_list(text: &str) -> ParsedLinks {
+    let mut seen = HashSet::new();
+    let mut parsed = ParsedLinks {
+        links: Vec::new(),
+        invalid: Vec::new(),
+        duplicates: 0,
+    };
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if !is_valid_public_link(line) {
+            parsed.invalid.push(line.to_string());
+            continue;
+        }
+        let key = match normalize_public_link(line) {
+            Some(link) => format!("{}|{}", link.public_key, link.path),
+            None => line.to_string(),
+        };
+        if !seen.insert(key) {
+            parsed.duplicates += 1;
+            continue;
+        }
+        parsed.links.push(line.to_string());
+    }
+    parsed
+}
@@ -0,0 +1,264 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+pub const DEFAULT_LOG_CAPACITY: usize = 5000;
+pub const MIN_LOG_CAPACITY: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Debug)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+impl From<LogLevel> for log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Debug => log::Level::Debug,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Error => log::Level::Error,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Stage {
+    General,
+    Scan,
+    Resolve,
+    Upload,
+    Summary,
+}
+
+impl Stage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stage::General => "общее",
+            Stage::Scan => "сканирование",
+            Stage::Resolve => "поиск nmId",
+            Stage::Upload => "загрузка",
+            Stage::Summary => "итог",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub timestamp: DateTime<Local>,
+    pub level: LogLevel,
+    pub stage: Stage,
+    pub vendor_code: Option<String>,
+    pub nm_id: Option<i64>,
+    pub message: String,
+}
+
+impl LogEvent {
+    pub fn new(level: LogLevel, stage: Stage, message: impl Into<String>) -> Self {
+        Self {
+            timestamp: Local::now(),
+            level,
+            stage,
+            vendor_code: None,
+            nm_id: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn debug(stage: Stage, message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Debug, stage, message)
+    }
+
+    pub fn info(stage: Stage, message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Info, stage, message)
+    }
+
+    pub fn warn(stage: Stage, message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Warn, stage, message)
+    }
+
+    pub fn error(stage: Stage, message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Error, stage, message)
+    }
+
+    pub fn vendor_code(mut self, vendor_code: &str) -> Self {
+        self.vendor_code = Some(vendor_code.to_string());
+        self
+    }
+
+    pub fn nm_id(mut self, nm_id: i64) -> Self {
+        self.nm_id = Some(nm_id);
+        self
+    }
+}
+
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {} [{}]",
+            self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.level.label(),
+            self.stage.label()
+        )?;
+        if let Some(vendor_code) = &self.vendor_code {
+            write!(f, " [{}]", vendor_code)?;
+        }
+        if let Some(nm_id) = self.nm_id {
+            write!(f, " [nmId {}]", nm_id)?;
+        }
+        write!(f, " {}", self.message)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct LogFilter {
+    pub min_level: Option<LogLevel>,
+    pub vendor_code: String,
+}
+
+impl LogFilter {
+    pub fn matches(&self, event: &LogEvent) -> bool {
+        if let Some(min_level) = self.min_level
+            && event.level < min_level
+        {
+            return false;
+        }
+        let vendor_code = self.vendor_code.trim();
+        vendor_code.is_empty()
+            || event
+                .vendor_code
+                .as_deref()
+                .is_some_and(|v| v.to_lowercase().contains(&vendor_code.to_lowercase()))
+    }
+}
+
+// Кольцевой буфер событий для UI: старые записи вытесняются, полная история пишется в файл
+pub struct LogBuffer {
+    entries: VecDeque<LogEvent>,
+    capacity: usize,
+    file: Option<File>,
+    subscribers: Vec<Sender<LogEvent>>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(MIN_LOG_CAPACITY);
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            file: None,
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn with_file(capacity: usize, path: &Path) -> Self {
+        let mut buffer = Self::new(capacity);
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => {
+                log::info!("Файл логов: {}", path.display());
+                buffer.file = Some(file);
+            }
+            Err(e) => {
+                log::error!("Не удалось открыть файл логов {}: {}", path.display(), e);
+            }
+        }
+        buffer
+    }
+
+    pub fn push(&mut self, event: LogEvent) {
+        log::log!(event.level.into(), "{}", event.message);
+        if let Some(file) = self.file.as_mut()
+            && let Err(e) = writeln!(file, "{}", event)
+        {
+            log::error!("Ошибка записи в файл логов: {}", e);
+            self.file = None;
+        }
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(event);
+    }
+
+    // Поток новых событий для внешних потребителей; отписка — удаление Receiver
+    pub fn subscribe(&mut self) -> Receiver<LogEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(MIN_LOG_CAPACITY);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn filtered(&self, filter: &LogFilter) -> Vec<LogEvent> {
+        self.entries
+            .iter()
+            .filter(|e| filter.matches(e))
+            .cloned()
+            .collect()
+    }
+
+    pub fn to_text(&self, filter: &LogFilter) -> String {
+        self.entries
+            .iter()
+            .filter(|e| filter.matches(e))
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Последние count событий в текстовом виде, например для отчёта о сбое
+    pub fn last_text(&self, count: usize) -> String {
+        let skip = self.entries.len().saturating_sub(count);
+        self.entries
+            .iter()
+            .skip(skip)
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_json_lines(&self, filter: &LogFilter) -> String {
+        self.entries
+            .iter()
+            .filter(|e| filter.matches(e))
+            .filter_map(|e| serde_json::to_string(e).ok())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
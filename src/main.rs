@@ -1,28 +1,59 @@
 #![cfg_attr(windows, windows_subsystem = "windows")] // Закоментировать если нужно посмотреть ошибки(логи)
 mod app;
-mod config;
-mod downloader;
-mod profile;
-mod uploader;
-mod utils;
+mod toast;
+mod tray;
 
 use anyhow::Result;
 use app::DownloaderApp;
 use eframe::{self};
+use std::sync::{Arc, Mutex};
+use wb_upload_core::{api, config, crash, demo, job, network, scheduler, settings};
 
 fn main() -> Result<()> {
     env_logger::init(); // Инициализация логгера
     log::info!("Приложение запущено");
 
+    if std::env::args().any(|arg| arg == "--demo") {
+        demo::enable();
+    }
+
+    if std::env::args().any(|arg| arg == "--headless") {
+        return run_headless();
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default().with_inner_size([800.0, 1000.0]),
         ..Default::default()
     };
+    let title = if demo::is_enabled() {
+        "Менеджер контента Wildberries — демо-режим"
+    } else {
+        "Менеджер контента Wildberries"
+    };
     eframe::run_native(
-        "Менеджер контента Wildberries",
+        title,
         native_options,
-        Box::new(|_cc| Ok(Box::new(DownloaderApp::default()))),
+        Box::new(|cc| {
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            Ok(Box::new(DownloaderApp::default()))
+        }),
     )
     .map_err(|e| anyhow::anyhow!("Ошибка GUI: {}", e))?;
     Ok(())
 }
+
+// Режим без GUI: только локальный API управления
+fn run_headless() -> Result<()> {
+    let config = config::Config::new()?;
+    let settings = settings::Settings::load(&config);
+    network::set_options(&settings.network);
+    let state = job::JobState::open(&config, &settings);
+    crash::install(&config, state.logs.clone());
+    let saved_jobs = Arc::new(Mutex::new(scheduler::load_jobs(&config)));
+    scheduler::spawn(config, saved_jobs, state.clone());
+    api::spawn(settings.api_port, &settings.api_token, state)?;
+    log::info!("Запущено без интерфейса, API на порту {}", settings.api_port);
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,146 @@
+use crate::downloader::FileInfo;
+use crate::import;
+use anyhow::Result;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Соответствие vendorCode → шаблон имени файла или папка для фотографий,
+// имена которых не начинаются с vendorCode
+#[derive(Clone)]
+pub struct VendorMapping {
+    rules: Vec<MappingRule>,
+}
+
+#[derive(Clone)]
+struct MappingRule {
+    vendor_code: String,
+    pattern: MappingPattern,
+}
+
+#[derive(Clone)]
+enum MappingPattern {
+    // Папка задаётся со слешем на конце: «Съёмка 12/»
+    Folder(String),
+    // Шаблон имени с * и ?; без них — начало имени
+    Name(Regex),
+}
+
+impl VendorMapping {
+    pub fn load(path: &Path) -> Result<Self> {
+        let rows = import::read_table(path)?;
+        let mut rules = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            let (Some(vendor_code), Some(pattern)) = (row.first(), row.get(1)) else {
+                continue;
+            };
+            if vendor_code.is_empty() || pattern.is_empty() {
+                continue;
+            }
+            if index == 0 && is_header(pattern) {
+                continue;
+            }
+            rules.push(MappingRule {
+                vendor_code: vendor_code.clone(),
+                pattern: MappingPattern::parse(pattern).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Ошибка в файле соответствий {}, строка {}: {}",
+                        path.display(),
+                        index + 1,
+                        e
+                    )
+                })?,
+            });
+        }
+        log::info!(
+            "Загружено правил соответствия из {}: {}",
+            path.display(),
+            rules.len()
+        );
+        Ok(Self { rules })
+    }
+
+    // Пустой путь — сопоставление только по началу имени файла
+    pub fn load_optional(path: &str) -> Result<Option<Self>> {
+        if path.trim().is_empty() {
+            return Ok(None);
+        }
+        Self::load(Path::new(path.trim())).map(Some)
+    }
+
+    // Возвращает vendorCode из списка prefixes, которому соответствует файл
+    pub fn vendor_code_for<'a>(
+        &self,
+        file_path: &str,
+        prefixes: &'a [String],
+    ) -> Option<&'a String> {
+        let normalized = file_path.replace('\\', "/");
+        let (parent, name) = normalized.rsplit_once('/').unwrap_or(("", &normalized));
+        self.rules
+            .iter()
+            .filter(|rule| rule.pattern.matches(parent, name))
+            .find_map(|rule| {
+                prefixes
+                    .iter()
+                    .find(|p| p.to_lowercase() == rule.vendor_code.to_lowercase())
+            })
+    }
+}
+
+fn is_header(pattern: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    !pattern.contains(['*', '?', '/', '\\', '.'])
+        && ["шаблон", "файл", "папк", "pattern", "file", "folder"]
+            .iter()
+            .any(|word| pattern.contains(word))
+}
+
+impl MappingPattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        if pattern.ends_with('/') || pattern.ends_with('\\') {
+            let folder = pattern.replace('\\', "/").trim_matches('/').to_lowercase();
+            return Ok(MappingPattern::Folder(folder));
+        }
+        let mut expression = String::from("(?i)^");
+        for c in pattern.chars() {
+            match c {
+                '*' => expression.push_str(".*"),
+                '?' => expression.push('.'),
+                c => expression.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        if pattern.contains(['*', '?']) {
+            expression.push('$');
+        }
+        Regex::new(&expression)
+            .map(MappingPattern::Name)
+            .map_err(|e| anyhow::anyhow!("неверный шаблон {}: {}", pattern, e))
+    }
+
+    fn matches(&self, parent: &str, name: &str) -> bool {
+        match self {
+            MappingPattern::Folder(folder) => {
+                format!("/{}/", parent.trim_matches('/').to_lowercase())
+                    .contains(&format!("/{}/", folder))
+            }
+            MappingPattern::Name(regex) => regex.is_match(name),
+        }
+    }
+}
+
+// Файлам, найденным по соответствию (photo_number == 0), номера фото назначаются
+// по порядку имён после уже пронумерованных файлов того же vendorCode
+pub fn number_mapped_files(files: &mut [FileInfo]) {
+    let mut next_numbers: HashMap<String, u32> = HashMap::new();
+    for file in files.iter().filter(|f| f.photo_number > 0) {
+        let next = next_numbers.entry(file.articul.clone()).or_insert(1);
+        *next = (*next).max(file.photo_number + 1);
+    }
+    let mut mapped: Vec<&mut FileInfo> = files.iter_mut().filter(|f| f.photo_number == 0).collect();
+    mapped.sort_by(|a, b| a.path.cmp(&b.path));
+    for file in mapped {
+        let next = next_numbers.entry(file.articul.clone()).or_insert(1);
+        file.photo_number = *next;
+        *next += 1;
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Границы корзин гистограммы длительности обработки vendorCode, сек
+const DURATION_BUCKETS: [f64; 9] = [1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+#[derive(Default)]
+struct Histogram {
+    counts: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+// Счётчики за время работы процесса для эндпоинта /metrics
+#[derive(Default)]
+pub struct Metrics {
+    uploads: AtomicU64,
+    upload_failures: AtomicU64,
+    rate_limited: AtomicU64,
+    bytes_sent: AtomicU64,
+    items_succeeded: AtomicU64,
+    items_failed: AtomicU64,
+    item_duration: Mutex<Histogram>,
+}
+
+impl Metrics {
+    pub fn add_uploads(&self, count: u64) {
+        self.uploads.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn add_upload_failure(&self) {
+        self.upload_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes_sent(&self, bytes: u64) {
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn observe_item(&self, success: bool, duration: Duration) {
+        if success {
+            self.items_succeeded.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.items_failed.fetch_add(1, Ordering::Relaxed);
+        }
+        let secs = duration.as_secs_f64();
+        let mut histogram = self.item_duration.lock().unwrap();
+        for (count, bound) in histogram.counts.iter_mut().zip(DURATION_BUCKETS) {
+            if secs <= bound {
+                *count += 1;
+            }
+        }
+        histogram.count += 1;
+        histogram.sum += secs;
+    }
+
+    // Текстовый формат экспозиции Prometheus
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            (
+                "wb_uploads_total",
+                "Успешно загруженные файлы",
+                &self.uploads,
+            ),
+            (
+                "wb_upload_failures_total",
+                "Ошибки загрузки",
+                &self.upload_failures,
+            ),
+            (
+                "wb_rate_limited_total",
+                "Ответы 429 от WB",
+                &self.rate_limited,
+            ),
+            (
+                "wb_bytes_sent_total",
+                "Отправлено байт файлов",
+                &self.bytes_sent,
+            ),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+        }
+        let _ = writeln!(
+            out,
+            "# HELP wb_items_total Обработанные vendor codes по результату"
+        );
+        let _ = writeln!(out, "# TYPE wb_items_total counter");
+        let _ = writeln!(
+            out,
+            "wb_items_total{{status=\"success\"}} {}",
+            self.items_succeeded.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            out,
+            "wb_items_total{{status=\"failed\"}} {}",
+            self.items_failed.load(Ordering::Relaxed)
+        );
+
+        let histogram = self.item_duration.lock().unwrap();
+        let name = "wb_item_duration_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {} Длительность обработки одного vendorCode",
+            name
+        );
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (count, bound) in histogram.counts.iter().zip(DURATION_BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+        let _ = writeln!(out, "{}_sum {}", name, histogram.sum);
+        let _ = writeln!(out, "{}_count {}", name, histogram.count);
+        out
+    }
+}
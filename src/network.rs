@@ -0,0 +1,172 @@
+use crate::settings::NetworkOptions;
+use anyhow::Result;
+use reqwest::blocking::ClientBuilder;
+use reqwest::{Certificate, Url};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Сетевые параметры из настроек; действуют на все HTTP-клиенты, созданные после изменения
+static OPTIONS: LazyLock<RwLock<NetworkOptions>> = LazyLock::new(Default::default);
+
+pub fn set_options(options: &NetworkOptions) {
+    *OPTIONS.write().unwrap() = options.clone();
+}
+
+// Проверка параметров сети перед сохранением настроек
+pub fn validate(options: &NetworkOptions) -> Result<()> {
+    local_address(options)?;
+    certificates(options)?;
+    for entry in &options.host_overrides {
+        host_override(entry)?;
+    }
+    Ok(())
+}
+
+// ClientBuilder клиентов WB и Яндекс.Диска с параметрами сети из настроек
+pub fn client_builder() -> Result<ClientBuilder> {
+    let options = OPTIONS.read().unwrap().clone();
+    let mut builder = ClientBuilder::new();
+    if let Some(address) = local_address(&options)? {
+        builder = builder.local_address(address);
+    }
+    if options.force_ipv4 {
+        builder = builder.dns_resolver(Arc::new(Ipv4Resolver));
+    }
+    for entry in &options.host_overrides {
+        if let Some((host, ip)) = host_override(entry)? {
+            // Порт 0 — порт схемы из адреса запроса
+            builder = builder.resolve(&host, SocketAddr::new(ip, 0));
+        }
+    }
+    for certificate in certificates(&options)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    Ok(builder)
+}
+
+// Корневые сертификаты из PEM-файла; к системным добавляются, а не заменяют их
+fn certificates(options: &NetworkOptions) -> Result<Vec<Certificate>> {
+    let path = options.ca_certificate.trim();
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pem = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось прочитать сертификат {}: {}", path, e))?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .map_err(|e| anyhow::anyhow!("Некорректный сертификат {}: {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(anyhow::anyhow!("В файле {} нет сертификатов PEM", path));
+    }
+    Ok(certificates)
+}
+
+fn local_address(options: &NetworkOptions) -> Result<Option<IpAddr>> {
+    let address = options.local_address.trim();
+    if address.is_empty() {
+        return Ok(None);
+    }
+    let address: IpAddr = address
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Некорректный локальный адрес: {}", address))?;
+    if options.force_ipv4 && address.is_ipv6() {
+        return Err(anyhow::anyhow!(
+            "Локальный адрес {} — IPv6, а соединения только по IPv4",
+            address
+        ));
+    }
+    Ok(Some(address))
+}
+
+// Строка «host=IP»; пустые строки пропускаются
+fn host_override(entry: &str) -> Result<Option<(String, IpAddr)>> {
+    let entry = entry.trim();
+    if entry.is_empty() {
+        return Ok(None);
+    }
+    let parsed = entry.split_once('=').and_then(|(host, ip)| {
+        let host = host.trim();
+        let ip = ip.trim().parse::<IpAddr>().ok()?;
+        (!host.is_empty()).then(|| (host.to_lowercase(), ip))
+    });
+    parsed
+        .map(Some)
+        .ok_or_else(|| anyhow::anyhow!("Ожидается «host=IP»: {}", entry))
+}
+
+// Системный DNS, из ответа которого берутся только адреса IPv4. Запрос идёт в отдельном
+// потоке, чтобы не останавливать остальные запросы клиента
+struct Ipv4Resolver;
+
+type Lookup = (Option<std::io::Result<Vec<SocketAddr>>>, Option<Waker>);
+
+impl Resolve for Ipv4Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let lookup: Arc<Mutex<Lookup>> = Arc::new(Mutex::new((None, None)));
+        let thread_lookup = Arc::clone(&lookup);
+        std::thread::spawn(move || {
+            let result = (host.as_str(), 0).to_socket_addrs().and_then(|addresses| {
+                let addresses: Vec<SocketAddr> = addresses.filter(|a| a.is_ipv4()).collect();
+                if addresses.is_empty() {
+                    return Err(std::io::Error::other(format!("У {} нет адресов IPv4", host)));
+                }
+                Ok(addresses)
+            });
+            let mut lookup = thread_lookup.lock().unwrap();
+            lookup.0 = Some(result);
+            if let Some(waker) = lookup.1.take() {
+                waker.wake();
+            }
+        });
+        Box::pin(std::future::poll_fn(move |cx| {
+            let mut lookup = lookup.lock().unwrap();
+            match lookup.0.take() {
+                Some(Ok(addresses)) => Poll::Ready(Ok(Box::new(addresses.into_iter()) as Addrs)),
+                Some(Err(e)) => Poll::Ready(Err(e.into())),
+                None => {
+                    lookup.1 = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }))
+    }
+}
+
+// Адрес Яндекс.Диска для проверки связи в запусках с публичными ссылками
+pub const YANDEX_API_URL: &str = "https://cloud-api.yandex.net";
+
+// Сервер по адресу API принимает TCP-соединения. Проверяется только доступность, без
+// запросов к API, поэтому проверка не тратит лимиты WB и не требует ключа
+pub fn is_reachable(url: &str) -> bool {
+    let Some(address) = Url::parse(url).ok().and_then(|url| {
+        Some(format!(
+            "{}:{}",
+            url.host_str()?,
+            url.port_or_known_default()?
+        ))
+    }) else {
+        return false;
+    };
+    let Ok(addresses) = address.to_socket_addrs() else {
+        return false;
+    };
+    let ipv4_only = OPTIONS.read().unwrap().force_ipv4;
+    addresses
+        .into_iter()
+        .filter(|address| !ipv4_only || address.is_ipv4())
+        .any(|address| TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).is_ok())
+}
+
+// Ошибка цепочки — обрыв связи или таймаут соединения, а не ответ сервера
+pub fn is_connection_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_connect() || e.is_timeout())
+    })
+}
@@ -0,0 +1,215 @@
+use crate::job::{ItemResult, ItemStatus};
+use crate::settings::Settings;
+use anyhow::Result;
+use chrono::Local;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use notify_rust::Notification;
+use reqwest::blocking::Client;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const MAX_LISTED_FAILURES: usize = 50;
+
+// Краткий итог запуска: "Загружено 120/125, ошибки: ABC1, ABC7"
+pub fn run_summary(profile: &str, results: &[ItemResult], completed: bool) -> String {
+    let succeeded = results
+        .iter()
+        .filter(|r| r.status == ItemStatus::Success)
+        .count();
+    let failed: Vec<&str> = results
+        .iter()
+        .filter(|r| r.status == ItemStatus::Failed)
+        .map(|r| r.vendor_code.as_str())
+        .collect();
+    let mut summary = format!(
+        "Профиль {}: загружено {}/{}",
+        profile,
+        succeeded,
+        results.len()
+    );
+    if !failed.is_empty() {
+        summary.push_str(", ошибки: ");
+        summary.push_str(&failed[..failed.len().min(MAX_LISTED_FAILURES)].join(", "));
+        if failed.len() > MAX_LISTED_FAILURES {
+            summary.push_str(&format!(" и ещё {}", failed.len() - MAX_LISTED_FAILURES));
+        }
+    }
+    if !completed {
+        summary.push_str(". Обработка прервана");
+    }
+    summary
+}
+
+pub fn send_telegram(token: &str, chat_id: &str, text: &str) -> Result<()> {
+    log::info!("Отправка уведомления в Telegram, чат {}", chat_id);
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let response = client
+        .post(format!("https://api.telegram.org/bot{}/sendMessage", token))
+        .json(&serde_json::json!({ "chat_id": chat_id, "text": text }))
+        .send()
+        // В адресе запроса токен бота: в журнал и уведомления об ошибке он попасть не должен
+        .map_err(|e| anyhow::anyhow!("Ошибка запроса к Telegram: {}", e.without_url()))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        return Err(anyhow::anyhow!(
+            "Telegram вернул ошибку {}: {}",
+            status,
+            body
+        ));
+    }
+    log::info!("Уведомление в Telegram отправлено");
+    Ok(())
+}
+
+pub fn send_desktop(summary: &str, body: &str) -> Result<()> {
+    Notification::new()
+        .appname("Менеджер контента Wildberries")
+        .summary(summary)
+        .body(body)
+        .show()
+        .map_err(|e| anyhow::anyhow!("Ошибка показа системного уведомления: {}", e))?;
+    Ok(())
+}
+
+const SMTPS_PORT: u16 = 465;
+
+pub fn smtp_configured(settings: &Settings) -> bool {
+    !settings.smtp_host.trim().is_empty() && !settings.smtp_to.trim().is_empty()
+}
+
+// Отправляет итог запуска с файлами отчётов во вложении; порт 465 — TLS сразу, иначе STARTTLS
+pub fn send_email(
+    settings: &Settings,
+    subject: &str,
+    body: &str,
+    attachments: &[PathBuf],
+) -> Result<()> {
+    log::info!("Отправка отчёта на {}", settings.smtp_to);
+    let from = if settings.smtp_from.trim().is_empty() {
+        settings.smtp_username.trim()
+    } else {
+        settings.smtp_from.trim()
+    };
+    let mut builder = Message::builder()
+        .from(
+            from.parse()
+                .map_err(|e| anyhow::anyhow!("Неверный адрес отправителя {}: {}", from, e))?,
+        )
+        .subject(subject);
+    for to in settings
+        .smtp_to
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        builder = builder.to(to
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Неверный адрес получателя {}: {}", to, e))?);
+    }
+
+    let mut multipart = MultiPart::mixed().singlepart(SinglePart::plain(body.to_string()));
+    for path in attachments {
+        let content = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать отчёт {}: {}", path.display(), e))?;
+        let content_type = match path.extension().and_then(|e| e.to_str()) {
+            Some("html") => ContentType::TEXT_HTML,
+            Some("csv") => ContentType::parse("text/csv")?,
+            _ => ContentType::parse("application/octet-stream")?,
+        };
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        multipart = multipart.singlepart(Attachment::new(name).body(content, content_type));
+    }
+    let email = builder
+        .multipart(multipart)
+        .map_err(|e| anyhow::anyhow!("Ошибка формирования письма: {}", e))?;
+
+    let host = settings.smtp_host.trim();
+    let transport = if settings.smtp_port == SMTPS_PORT {
+        SmtpTransport::relay(host)
+    } else {
+        SmtpTransport::starttls_relay(host)
+    }
+    .map_err(|e| anyhow::anyhow!("Ошибка подключения к SMTP {}: {}", host, e))?
+    .port(settings.smtp_port);
+    let transport = if settings.smtp_username.trim().is_empty() {
+        transport
+    } else {
+        transport.credentials(Credentials::new(
+            settings.smtp_username.trim().to_string(),
+            settings.smtp_password.clone(),
+        ))
+    };
+    transport
+        .build()
+        .send(&email)
+        .map_err(|e| anyhow::anyhow!("Ошибка отправки письма: {}", e))?;
+    log::info!("Отчёт отправлен по почте");
+    Ok(())
+}
+
+// Адреса для JSON-уведомлений о событиях задания; отправка не блокирует обработку
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    urls: Vec<String>,
+    profile: String,
+}
+
+impl Webhooks {
+    pub fn new(urls: &[String], profile: &str) -> Self {
+        Self {
+            urls: urls
+                .iter()
+                .map(|u| u.trim().to_string())
+                .filter(|u| !u.is_empty())
+                .collect(),
+            profile: profile.to_string(),
+        }
+    }
+
+    pub fn send(
+        &self,
+        event: &str,
+        data: serde_json::Value,
+        on_error: impl Fn(String) + Clone + Send + 'static,
+    ) {
+        if self.urls.is_empty() {
+            return;
+        }
+        let mut payload = serde_json::json!({
+            "event": event,
+            "profile": self.profile,
+            "timestamp": Local::now().to_rfc3339(),
+        });
+        if let (Some(payload), serde_json::Value::Object(data)) = (payload.as_object_mut(), data) {
+            payload.extend(data);
+        }
+        for url in &self.urls {
+            let url = url.clone();
+            let payload = payload.clone();
+            let on_error = on_error.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = post_webhook(&url, &payload) {
+                    on_error(format!("Ошибка отправки webhook {}: {}", url, e));
+                }
+            });
+        }
+    }
+}
+
+fn post_webhook(url: &str, payload: &serde_json::Value) -> Result<()> {
+    let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
+    let response = client.post(url).json(payload).send()?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("сервер вернул {}", response.status()));
+    }
+    log::debug!("Webhook {} доставлен", url);
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::cache::ScanCache;
+use crate::downloader::{Downloader, FileInfo, Matching};
+use crate::job::JobSource;
+use crate::profile::HttpTimeouts;
+use anyhow::Result;
+use image::ImageFormat;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+// Сторона миниатюры в пикселях
+pub const THUMBNAIL_SIZE: u32 = 128;
+
+#[derive(Clone)]
+pub struct PreviewItem {
+    pub file: FileInfo,
+    // PNG-миниатюра; None — ещё не загружена или недоступна
+    pub thumbnail: Option<Arc<[u8]>>,
+    pub error: Option<String>,
+}
+
+// Результат сканирования без загрузки: файлы с миниатюрами, сгруппированные в интерфейсе по vendorCode
+#[derive(Clone, Default)]
+pub struct PreviewState {
+    pub running: Arc<Mutex<bool>>,
+    pub items: Arc<Mutex<Vec<PreviewItem>>>,
+    pub error: Arc<Mutex<Option<String>>>,
+}
+
+pub fn spawn(
+    source: JobSource,
+    vendor_codes: Vec<String>,
+    matching: Matching,
+    scan_cache: Option<ScanCache>,
+    timeouts: HttpTimeouts,
+    state: PreviewState,
+) {
+    *state.running.lock().unwrap() = true;
+    state.items.lock().unwrap().clear();
+    *state.error.lock().unwrap() = None;
+    std::thread::spawn(move || {
+        let remote = matches!(source, JobSource::Yandex { .. });
+        match scan(&source, vendor_codes, matching, scan_cache, &timeouts) {
+            Ok((downloader, mut files)) => {
+                files.sort_by(|a, b| {
+                    (&a.articul, a.photo_number).cmp(&(&b.articul, b.photo_number))
+                });
+                log::info!("Предпросмотр: найдено файлов {}", files.len());
+                *state.items.lock().unwrap() = files
+                    .into_iter()
+                    .map(|file| PreviewItem {
+                        file,
+                        thumbnail: None,
+                        error: None,
+                    })
+                    .collect();
+                let count = state.items.lock().unwrap().len();
+                for index in 0..count {
+                    let file = state.items.lock().unwrap()[index].file.clone();
+                    let result = thumbnail(&downloader, &file, remote);
+                    let mut items = state.items.lock().unwrap();
+                    match result {
+                        Ok(bytes) => items[index].thumbnail = Some(bytes.into()),
+                        Err(e) => {
+                            log::debug!("Нет миниатюры {}: {}", file.path, e);
+                            items[index].error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Ошибка сканирования для предпросмотра: {}", e);
+                *state.error.lock().unwrap() = Some(e.to_string());
+            }
+        }
+        *state.running.lock().unwrap() = false;
+    });
+}
+
+pub fn scan(
+    source: &JobSource,
+    vendor_codes: Vec<String>,
+    matching: Matching,
+    scan_cache: Option<ScanCache>,
+    timeouts: &HttpTimeouts,
+) -> Result<(Downloader, Vec<FileInfo>)> {
+    let public_keys = match source {
+        JobSource::Yandex { public_keys } => public_keys.clone(),
+        _ => Vec::new(),
+    };
+    let mut downloader = Downloader::new(public_keys, vendor_codes, timeouts)?;
+    downloader.set_matching(matching);
+    downloader.set_scan_cache(scan_cache);
+    let files = match source {
+        JobSource::Yandex { .. } => downloader.find_files()?,
+        JobSource::LocalFolder { path } => downloader.find_local_files(path)?,
+        JobSource::SingleFile { path } => {
+            downloader.match_local_paths(std::slice::from_ref(path))?
+        }
+        JobSource::Files { paths } => downloader.match_local_paths(paths)?,
+        JobSource::CardUpdates { .. } | JobSource::NewCards { .. } => {
+            return Err(anyhow::anyhow!("Источник не содержит медиафайлов"));
+        }
+    };
+    Ok((downloader, files))
+}
+
+fn thumbnail(downloader: &Downloader, file: &FileInfo, remote: bool) -> Result<Vec<u8>> {
+    if ImageFormat::from_path(&file.name).is_err() {
+        return Err(anyhow::anyhow!("предпросмотр видео не поддерживается"));
+    }
+    let bytes = if remote {
+        downloader.download_preview(&file.path)?
+    } else {
+        std::fs::read(&file.path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать файл {}: {}", file.path, e))?
+    };
+    let image = image::load_from_memory(&bytes)
+        .map_err(|e| anyhow::anyhow!("Не удалось декодировать {}: {}", file.name, e))?;
+    let mut output = Vec::new();
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut output), ImageFormat::Png)
+        .map_err(|e| anyhow::anyhow!("Не удалось сохранить миниатюру {}: {}", file.name, e))?;
+    Ok(output)
+}
@@ -1,11 +1,134 @@
 use crate::config::Config;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-#[derive(Serialize, Deserialize, Clone)]
+// Сколько недавних папок и ссылок хранится у профиля
+const RECENT_LIMIT: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Profile {
     pub name: String,
     pub api_key: String,
+    // Адрес Content API WB: песочница или корпоративный прокси; пустой — основной API
+    #[serde(default)]
+    pub wb_base_url: String,
+    // Недавно использованные локальные папки и ссылки, последние — первыми
+    #[serde(default)]
+    pub recent_folders: Vec<String>,
+    #[serde(default)]
+    pub recent_links: Vec<String>,
+    #[serde(default)]
+    pub favorite_links: Vec<FavoriteLink>,
+    #[serde(default)]
+    pub timeouts: HttpTimeouts,
+}
+
+// Пределы таймаутов, секунды: ожидание соединения дольше двух минут бессмысленно,
+// а запрос дольше часа скорее завис, чем грузит файл
+pub const MAX_CONNECT_SECS: u64 = 120;
+pub const MAX_REQUEST_SECS: u64 = 3600;
+
+// Таймауты HTTP профиля, секунды: установка соединения и весь запрос целиком, вместе с
+// передачей файла. На медленном канале видео на 100 МБ не успевает загрузиться за 30 секунд
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct HttpTimeouts {
+    pub wb_connect_secs: u64,
+    pub wb_request_secs: u64,
+    pub yandex_connect_secs: u64,
+    pub yandex_request_secs: u64,
+}
+
+impl Default for HttpTimeouts {
+    fn default() -> Self {
+        Self {
+            wb_connect_secs: 10,
+            wb_request_secs: 30,
+            yandex_connect_secs: 5,
+            yandex_request_secs: 20,
+        }
+    }
+}
+
+impl HttpTimeouts {
+    pub fn validate(&self) -> Result<()> {
+        check_timeouts("WB", self.wb_connect_secs, self.wb_request_secs)?;
+        check_timeouts("Яндекс.Диска", self.yandex_connect_secs, self.yandex_request_secs)
+    }
+
+    // Таймауты соединения и запроса к WB; значения из файла профиля приводятся к пределам
+    pub fn wb(&self) -> (Duration, Duration) {
+        clamp_timeouts(self.wb_connect_secs, self.wb_request_secs)
+    }
+
+    pub fn yandex(&self) -> (Duration, Duration) {
+        clamp_timeouts(self.yandex_connect_secs, self.yandex_request_secs)
+    }
+}
+
+fn check_timeouts(service: &str, connect: u64, request: u64) -> Result<()> {
+    if !(1..=MAX_CONNECT_SECS).contains(&connect) {
+        return Err(anyhow::anyhow!(
+            "Таймаут соединения {} должен быть от 1 до {} с",
+            service,
+            MAX_CONNECT_SECS
+        ));
+    }
+    if !(connect..=MAX_REQUEST_SECS).contains(&request) {
+        return Err(anyhow::anyhow!(
+            "Таймаут запроса {} должен быть от таймаута соединения до {} с",
+            service,
+            MAX_REQUEST_SECS
+        ));
+    }
+    Ok(())
+}
+
+fn clamp_timeouts(connect: u64, request: u64) -> (Duration, Duration) {
+    let connect = connect.clamp(1, MAX_CONNECT_SECS);
+    let request = request.clamp(connect, MAX_REQUEST_SECS);
+    (Duration::from_secs(connect), Duration::from_secs(request))
+}
+
+// Закреплённая ссылка Яндекс.Диска с понятным именем, например «Фотостудия май»
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FavoriteLink {
+    pub name: String,
+    pub link: String,
+}
+
+impl Profile {
+    pub fn remember_folder(&mut self, folder: &str) {
+        remember(&mut self.recent_folders, folder);
+    }
+
+    pub fn remember_links(&mut self, links: &[String]) {
+        // Первая ссылка запуска оказывается в начале списка
+        for link in links.iter().rev() {
+            remember(&mut self.recent_links, link);
+        }
+    }
+
+    // Ссылка с тем же именем заменяется
+    pub fn add_favorite(&mut self, name: &str, link: &str) {
+        let name = name.trim();
+        self.favorite_links.retain(|f| f.name != name);
+        self.favorite_links.push(FavoriteLink {
+            name: name.to_string(),
+            link: link.trim().to_string(),
+        });
+    }
+}
+
+fn remember(recent: &mut Vec<String>, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+    recent.retain(|v| v != value);
+    recent.insert(0, value.to_string());
+    recent.truncate(RECENT_LIMIT);
 }
 
 #[derive(Serialize, Deserialize)]
@@ -36,14 +159,20 @@ impl ProfileManager {
                 );
                 vec![Profile {
                     name: "Добавить".to_string(),
-                    api_key: String::new(),
+                    ..Default::default()
                 }]
             })
+        } else if crate::demo::is_enabled() {
+            vec![Profile {
+                name: "Демо".to_string(),
+                api_key: crate::demo::API_KEY.to_string(),
+                ..Default::default()
+            }]
         } else {
             log::info!("Конфигурация не найдена, создаётся профиль по умолчанию");
             vec![Profile {
                 name: "Добавить".to_string(),
-                api_key: String::new(),
+                ..Default::default()
             }]
         };
         Ok(ProfileManager {
@@ -57,7 +186,7 @@ impl ProfileManager {
         log::info!("Добавление профиля: {}", name);
         self.profiles.push(Profile {
             name,
-            api_key: String::new(),
+            ..Default::default()
         });
         self.selected_index = self.profiles.len() - 1;
     }
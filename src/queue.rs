@@ -0,0 +1,233 @@
+use crate::batch::BatchStatus;
+use crate::job::{self, ItemStatus, JobSpec, JobState};
+use crate::logs::{LogEvent, Stage};
+use chrono::Local;
+
+// Подготовленное задание в очереди; статус тот же, что у профиля в пакете
+#[derive(Clone)]
+pub struct QueuedJob {
+    pub id: u64,
+    pub spec: JobSpec,
+    pub status: BatchStatus,
+    // Срочное задание выполняется раньше остальных и приостанавливает идущее несрочное
+    pub urgent: bool,
+    // Успешные и ошибочные vendor codes части, выполненной до приостановки
+    pub done_before: (usize, usize),
+}
+
+impl QueuedJob {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} · {} · vendor codes: {}",
+            self.spec.profile,
+            self.spec.source.describe(),
+            self.spec.vendor_codes.len()
+        )
+    }
+
+    pub fn status_label(&self) -> String {
+        if self.status == BatchStatus::Pending && self.done_before != (0, 0) {
+            "приостановлено".to_string()
+        } else {
+            self.status.label()
+        }
+    }
+}
+
+// Добавленное во время работы очереди задание выполнится в ней же, иначе ждёт запуска очереди
+pub fn add(state: &JobState, spec: JobSpec, urgent: bool) {
+    let mut queue = state.queue.lock().unwrap();
+    let id = queue.iter().map(|queued| queued.id).max().unwrap_or(0) + 1;
+    state.log(LogEvent::info(
+        Stage::General,
+        format!(
+            "В очередь добавлено {}задание {}: профиль {}",
+            if urgent { "срочное " } else { "" },
+            id,
+            spec.profile
+        ),
+    ));
+    queue.push(QueuedJob {
+        id,
+        spec,
+        status: BatchStatus::Pending,
+        urgent,
+        done_before: (0, 0),
+    });
+    if urgent {
+        preempt_running(state, &queue);
+    }
+}
+
+pub fn set_urgent(state: &JobState, id: u64, urgent: bool) {
+    let mut queue = state.queue.lock().unwrap();
+    let Some(queued) = queue
+        .iter_mut()
+        .find(|queued| queued.id == id && queued.status == BatchStatus::Pending)
+    else {
+        return;
+    };
+    queued.urgent = urgent;
+    if urgent {
+        preempt_running(state, &queue);
+    }
+}
+
+// Идущее несрочное задание доделывает текущий vendorCode и возвращается в очередь с остатком
+fn preempt_running(state: &JobState, queue: &[QueuedJob]) {
+    if let Some(running) = queue
+        .iter()
+        .find(|queued| queued.status == BatchStatus::Running && !queued.urgent)
+    {
+        state.log(LogEvent::warn(
+            Stage::General,
+            format!(
+                "Срочное задание: задание {} приостановится после текущего vendorCode",
+                running.id
+            ),
+        ));
+        state.preempt();
+    }
+}
+
+// Убрать можно только задание, которое ещё не начато
+pub fn remove(state: &JobState, id: u64) {
+    state
+        .queue
+        .lock()
+        .unwrap()
+        .retain(|queued| queued.id != id || queued.status != BatchStatus::Pending);
+}
+
+// Переставляет ожидающее задание на место другого ожидающего
+pub fn move_job(state: &JobState, id: u64, to: usize) {
+    let mut queue = state.queue.lock().unwrap();
+    let Some(from) = queue.iter().position(|queued| queued.id == id) else {
+        return;
+    };
+    let to = to.min(queue.len() - 1);
+    if queue[from].status != BatchStatus::Pending || queue[to].status != BatchStatus::Pending {
+        return;
+    }
+    let queued = queue.remove(from);
+    queue.insert(to, queued);
+}
+
+pub fn clear_finished(state: &JobState) {
+    state
+        .queue
+        .lock()
+        .unwrap()
+        .retain(|queued| matches!(queued.status, BatchStatus::Pending | BatchStatus::Running));
+}
+
+// Задания выполняются по порядку в одном фоновом потоке, как один долгий запуск:
+// расписание и API не вклиниваются между ними. Остановка прерывает всю очередь,
+// не начатые задания остаются в ней
+pub fn start(state: JobState) {
+    if *state.is_processing.lock().unwrap() {
+        return;
+    }
+    *state.is_processing.lock().unwrap() = true;
+    state.log(LogEvent::info(Stage::General, "Запуск очереди заданий"));
+    std::thread::spawn(move || {
+        while let Some((id, mut spec)) = next(&state) {
+            state.log(LogEvent::info(
+                Stage::General,
+                format!("Задание {} из очереди: профиль {}", id, spec.profile),
+            ));
+            state.results.lock().unwrap().clear();
+            spec.started_at = Local::now();
+            job::prepare(&spec, &state);
+            job::execute(spec, state.clone());
+            let preempted = state.take_preempted();
+            let cancelled = state.is_cancelled();
+            let (succeeded, failed) = {
+                let results = state.results.lock().unwrap();
+                (
+                    results.iter().filter(|r| r.status == ItemStatus::Success).count(),
+                    results.iter().filter(|r| r.status == ItemStatus::Failed).count(),
+                )
+            };
+            if preempted && !cancelled {
+                let remaining = state.unfinished_vendor_codes();
+                if !remaining.is_empty() {
+                    state.log(LogEvent::info(
+                        Stage::General,
+                        format!(
+                            "Задание {} приостановлено, осталось vendor codes: {}",
+                            id,
+                            remaining.len()
+                        ),
+                    ));
+                    suspend(&state, id, remaining, (succeeded, failed));
+                    continue;
+                }
+            }
+            let status = if cancelled {
+                BatchStatus::Cancelled
+            } else {
+                BatchStatus::Done { succeeded, failed }
+            };
+            set_status(&state, id, status);
+            if cancelled {
+                state.log(LogEvent::warn(
+                    Stage::General,
+                    "Очередь остановлена, не начатые задания остались в ней",
+                ));
+                break;
+            }
+        }
+        state.finish();
+    });
+}
+
+// Срочные задания идут первыми, остальные — в порядке очереди
+fn next(state: &JobState) -> Option<(u64, JobSpec)> {
+    let mut queue = state.queue.lock().unwrap();
+    let index = queue
+        .iter()
+        .position(|queued| queued.status == BatchStatus::Pending && queued.urgent)
+        .or_else(|| {
+            queue
+                .iter()
+                .position(|queued| queued.status == BatchStatus::Pending)
+        })?;
+    let queued = &mut queue[index];
+    queued.status = BatchStatus::Running;
+    Some((queued.id, queued.spec.clone()))
+}
+
+// Приостановленное задание остаётся на своём месте с необработанными vendor codes
+fn suspend(state: &JobState, id: u64, remaining: Vec<String>, done: (usize, usize)) {
+    if let Some(queued) = state
+        .queue
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .find(|queued| queued.id == id)
+    {
+        queued.spec.vendor_codes = remaining;
+        queued.status = BatchStatus::Pending;
+        queued.done_before.0 += done.0;
+        queued.done_before.1 += done.1;
+    }
+}
+
+fn set_status(state: &JobState, id: u64, status: BatchStatus) {
+    if let Some(queued) = state
+        .queue
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .find(|queued| queued.id == id)
+    {
+        queued.status = match status {
+            BatchStatus::Done { succeeded, failed } => BatchStatus::Done {
+                succeeded: succeeded + queued.done_before.0,
+                failed: failed + queued.done_before.1,
+            },
+            status => status,
+        };
+    }
+}
@@ -0,0 +1,238 @@
+use crate::profile::Profile;
+use crate::uploader::{CardLimits, Characteristic, Subject, WbUploader};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// Справочники WB меняются редко; старше этого срока загружаются заново
+const CACHE_DAYS: i64 = 7;
+
+#[derive(Serialize, Deserialize)]
+struct CachedSubjects {
+    saved_at: DateTime<Local>,
+    items: Vec<Subject>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedCharacteristics {
+    subject_id: i64,
+    saved_at: DateTime<Local>,
+    items: Vec<Characteristic>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheData {
+    subjects: Option<CachedSubjects>,
+    #[serde(default)]
+    characteristics: Vec<CachedCharacteristics>,
+}
+
+fn is_fresh(saved_at: DateTime<Local>) -> bool {
+    Local::now() - saved_at < Duration::days(CACHE_DAYS)
+}
+
+// Локальный кэш справочников предметов и характеристик WB
+#[derive(Clone)]
+pub struct ReferenceCache {
+    path: PathBuf,
+}
+
+impl ReferenceCache {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn load(&self) -> CacheData {
+        let Ok(data) = std::fs::read_to_string(&self.path) else {
+            return CacheData::default();
+        };
+        serde_json::from_str(&data).unwrap_or_else(|e| {
+            log::warn!("Ошибка парсинга кэша справочников WB: {}", e);
+            CacheData::default()
+        })
+    }
+
+    fn save(&self, data: &CacheData) {
+        let result = serde_json::to_string(data)
+            .map_err(|e| anyhow::anyhow!("Ошибка сериализации кэша справочников WB: {}", e))
+            .and_then(|json| {
+                std::fs::write(&self.path, json).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Не удалось записать кэш справочников WB {}: {}",
+                        self.path.display(),
+                        e
+                    )
+                })
+            });
+        if let Err(e) = result {
+            log::warn!("{}", e);
+        }
+    }
+
+    // refresh — загрузить с WB, даже если кэш свежий
+    pub fn subjects(
+        &self,
+        uploader: &WbUploader,
+        refresh: bool,
+        on_page: impl FnMut(usize),
+    ) -> Result<Vec<Subject>> {
+        let mut data = self.load();
+        if !refresh
+            && let Some(cached) = &data.subjects
+            && is_fresh(cached.saved_at)
+        {
+            return Ok(cached.items.clone());
+        }
+        let items = uploader.get_subjects(on_page)?;
+        data.subjects = Some(CachedSubjects {
+            saved_at: Local::now(),
+            items: items.clone(),
+        });
+        self.save(&data);
+        Ok(items)
+    }
+
+    pub fn characteristics(
+        &self,
+        uploader: &WbUploader,
+        subject_id: i64,
+    ) -> Result<Vec<Characteristic>> {
+        let mut data = self.load();
+        if let Some(cached) = data
+            .characteristics
+            .iter()
+            .find(|c| c.subject_id == subject_id && is_fresh(c.saved_at))
+        {
+            return Ok(cached.items.clone());
+        }
+        let items = uploader.get_characteristics(subject_id)?;
+        data.characteristics.retain(|c| c.subject_id != subject_id);
+        data.characteristics.push(CachedCharacteristics {
+            subject_id,
+            saved_at: Local::now(),
+            items: items.clone(),
+        });
+        self.save(&data);
+        Ok(items)
+    }
+}
+
+// Справочник для вкладки «Справочник WB»: предметы и характеристики выбранного
+#[derive(Clone, Default)]
+pub struct ReferenceState {
+    pub running: Arc<Mutex<bool>>,
+    pub fetched: Arc<Mutex<usize>>,
+    pub subjects: Arc<Mutex<Vec<Subject>>>,
+    pub selected: Arc<Mutex<Option<Subject>>>,
+    pub characteristics: Arc<Mutex<Vec<Characteristic>>>,
+    pub error: Arc<Mutex<Option<String>>>,
+    // Оставшийся лимит создания карточек; None — ещё не получен или WB не ответил
+    pub limits: Arc<Mutex<Option<CardLimits>>>,
+}
+
+pub fn spawn_subjects(
+    profile: Profile,
+    cache: ReferenceCache,
+    refresh: bool,
+    state: ReferenceState,
+) {
+    *state.running.lock().unwrap() = true;
+    *state.fetched.lock().unwrap() = 0;
+    *state.error.lock().unwrap() = None;
+    std::thread::spawn(move || {
+        let fetched = Arc::clone(&state.fetched);
+        let subjects = WbUploader::for_profile(&profile).and_then(|uploader| {
+            cache.subjects(&uploader, refresh, |count| *fetched.lock().unwrap() = count)
+        });
+        match subjects {
+            Ok(mut subjects) => {
+                subjects.sort_by(|a, b| (&a.parent_name, &a.name).cmp(&(&b.parent_name, &b.name)));
+                *state.subjects.lock().unwrap() = subjects;
+            }
+            Err(e) => {
+                log::error!("Ошибка загрузки справочника предметов: {}", e);
+                *state.error.lock().unwrap() = Some(e.to_string());
+            }
+        }
+        *state.running.lock().unwrap() = false;
+    });
+}
+
+// Лимит не кэшируется: он меняется с каждой созданной карточкой
+pub fn spawn_limits(profile: Profile, state: ReferenceState) {
+    std::thread::spawn(move || {
+        let limits = WbUploader::for_profile(&profile)
+            .and_then(|uploader| Ok(uploader.get_card_limits()?));
+        match limits {
+            Ok(limits) => *state.limits.lock().unwrap() = Some(limits),
+            Err(e) => {
+                log::error!("Ошибка получения лимита карточек: {}", e);
+                *state.limits.lock().unwrap() = None;
+            }
+        }
+    });
+}
+
+pub fn spawn_characteristics(
+    profile: Profile,
+    cache: ReferenceCache,
+    subject: Subject,
+    state: ReferenceState,
+) {
+    *state.running.lock().unwrap() = true;
+    *state.selected.lock().unwrap() = Some(subject.clone());
+    state.characteristics.lock().unwrap().clear();
+    *state.error.lock().unwrap() = None;
+    std::thread::spawn(move || {
+        let characteristics = WbUploader::for_profile(&profile)
+            .and_then(|uploader| cache.characteristics(&uploader, subject.id));
+        match characteristics {
+            Ok(characteristics) => *state.characteristics.lock().unwrap() = characteristics,
+            Err(e) => {
+                log::error!(
+                    "Ошибка загрузки характеристик предмета {}: {}",
+                    subject.name,
+                    e
+                );
+                *state.error.lock().unwrap() = Some(e.to_string());
+            }
+        }
+        *state.running.lock().unwrap() = false;
+    });
+}
+
+// Заготовка таблицы для создания карточек или изменения характеристик:
+// заголовок со столбцами характеристик предмета, обязательные — первыми
+pub fn save_template(
+    path: &Path,
+    subject: &Subject,
+    characteristics: &[Characteristic],
+    create: bool,
+) -> Result<()> {
+    let mut characteristics: Vec<&Characteristic> = characteristics.iter().collect();
+    characteristics.sort_by_key(|c| !c.required);
+    let mut header = vec!["vendorCode".to_string()];
+    if create {
+        header.extend(["subjectID", "название", "описание", "бренд", "фото"].map(str::to_string));
+    }
+    header.extend(characteristics.iter().map(|c| c.name.clone()));
+    let mut writer = csv::Writer::from_path(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось создать файл {}: {}", path.display(), e))?;
+    writer.write_record(&header)?;
+    if create {
+        let mut row = vec![String::new(); header.len()];
+        row[1] = subject.id.to_string();
+        writer.write_record(&row)?;
+    }
+    writer
+        .flush()
+        .map_err(|e| anyhow::anyhow!("Ошибка записи файла {}: {}", path.display(), e))?;
+    log::info!(
+        "Шаблон таблицы для предмета «{}» сохранён в {}",
+        subject.name,
+        path.display()
+    );
+    Ok(())
+}
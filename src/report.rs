@@ -0,0 +1,410 @@
+use crate::downloader::FileInfo;
+use crate::http::{HttpClient, Request, ReqwestClient};
+use crate::job::{ItemResult, ItemStatus};
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Local};
+use image::{DynamicImage, ImageFormat};
+use rust_xlsxwriter::{Color, Format, FormatBorder, Workbook};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+const HEADER: [&str; 9] = [
+    "vendorCode",
+    "nmId",
+    "Файлы",
+    "Номера фото",
+    "Результат",
+    "Ошибка",
+    "Тип ошибки",
+    "Ошибки WB",
+    "Длительность, сек",
+];
+
+const STATUS_COLUMN: usize = 4;
+
+fn report_row(result: &ItemResult) -> [String; 9] {
+    [
+        result.vendor_code.clone(),
+        result.nm_id.map(|id| id.to_string()).unwrap_or_default(),
+        result
+            .files
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        result
+            .files
+            .iter()
+            .map(|f| f.photo_number.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+        result.status.label().to_string(),
+        result.error.clone().unwrap_or_default(),
+        error_class(result).to_string(),
+        result.wb_errors.join("; "),
+        result
+            .duration
+            .map(|d| format!("{:.1}", d.as_secs_f64()))
+            .unwrap_or_default(),
+    ]
+}
+
+const UTF8_BOM: &str = "\u{feff}";
+
+// Класс ошибки WB; пусто, если vendorCode не упал на запросе к WB
+fn error_class(result: &ItemResult) -> &'static str {
+    result.failure.as_ref().map(|e| e.label()).unwrap_or_default()
+}
+
+// Отчёт открывают в Excel с русской локалью: без BOM он читает файл в Windows-1251,
+// а разделитель списка там — «;», запятые дали бы всё в одном столбце
+pub fn save_csv(path: &Path, results: &[ItemResult]) -> Result<()> {
+    log::info!("Сохранение CSV-отчёта в {}", path.display());
+    let mut file = File::create(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось создать файл отчёта {}: {}", path.display(), e))?;
+    file.write_all(UTF8_BOM.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Ошибка записи отчёта {}: {}", path.display(), e))?;
+    let mut writer = csv::WriterBuilder::new().delimiter(b';').from_writer(file);
+    writer.write_record(HEADER)?;
+    for result in results {
+        writer.write_record(report_row(result))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| anyhow::anyhow!("Ошибка записи отчёта {}: {}", path.display(), e))?;
+    log::info!("CSV-отчёт сохранён: {} строк", results.len());
+    Ok(())
+}
+
+pub fn save_xlsx(path: &Path, results: &[ItemResult]) -> Result<()> {
+    log::info!("Сохранение XLSX-отчёта в {}", path.display());
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Отчёт")?;
+
+    let header_format = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xD9E1F2))
+        .set_border(FormatBorder::Thin);
+    let status_format = |status: ItemStatus| {
+        let color = match status {
+            ItemStatus::Success => Color::RGB(0xC6EFCE),
+            ItemStatus::Failed => Color::RGB(0xFFC7CE),
+            ItemStatus::InProgress => Color::RGB(0xFFEB9C),
+        };
+        Format::new().set_background_color(color)
+    };
+
+    for (col, title) in HEADER.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, &header_format)?;
+    }
+    for (i, result) in results.iter().enumerate() {
+        let row = i as u32 + 1;
+        for (col, value) in report_row(result).iter().enumerate() {
+            if col == STATUS_COLUMN {
+                sheet.write_string_with_format(
+                    row,
+                    col as u16,
+                    value,
+                    &status_format(result.status),
+                )?;
+            } else {
+                sheet.write_string(row, col as u16, value)?;
+            }
+        }
+        if let Some(nm_id) = result.nm_id {
+            sheet.write_number(row, 1, nm_id as f64)?;
+        }
+    }
+    sheet.autofit();
+    sheet.set_freeze_panes(1, 0)?;
+
+    workbook
+        .save(path)
+        .map_err(|e| anyhow::anyhow!("Ошибка записи отчёта {}: {}", path.display(), e))?;
+    log::info!("XLSX-отчёт сохранён: {} строк", results.len());
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    started_at: DateTime<Local>,
+    finished_at: DateTime<Local>,
+    total: usize,
+    succeeded: usize,
+    failed: usize,
+    items: Vec<JsonReportItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonReportItem<'a> {
+    vendor_code: &'a str,
+    nm_id: Option<i64>,
+    status: &'static str,
+    error: Option<&'a str>,
+    error_class: Option<&'static str>,
+    files: &'a [FileInfo],
+    files_uploaded: usize,
+    started_at: DateTime<Local>,
+    finished_at: Option<DateTime<Local>>,
+    duration_secs: Option<f64>,
+    wb_responses: Vec<serde_json::Value>,
+    wb_errors: &'a [String],
+}
+
+pub fn save_json(path: &Path, started_at: DateTime<Local>, results: &[ItemResult]) -> Result<()> {
+    log::info!("Сохранение JSON-отчёта в {}", path.display());
+    let report = JsonReport {
+        started_at,
+        finished_at: Local::now(),
+        total: results.len(),
+        succeeded: results
+            .iter()
+            .filter(|r| r.status == ItemStatus::Success)
+            .count(),
+        failed: results
+            .iter()
+            .filter(|r| r.status == ItemStatus::Failed)
+            .count(),
+        items: results
+            .iter()
+            .map(|r| JsonReportItem {
+                vendor_code: &r.vendor_code,
+                nm_id: r.nm_id,
+                status: r.status.code(),
+                error: r.error.as_deref(),
+                error_class: r.failure.as_ref().map(|e| e.label()),
+                files: &r.files,
+                files_uploaded: r.files_uploaded,
+                started_at: r.started_at,
+                finished_at: r.finished_at,
+                duration_secs: r.duration.map(|d| d.as_secs_f64()),
+                // Ответы WB сохраняются как JSON, если их удаётся распарсить
+                wb_responses: r
+                    .wb_responses
+                    .iter()
+                    .map(|body| {
+                        serde_json::from_str(body)
+                            .unwrap_or_else(|_| serde_json::Value::String(body.clone()))
+                    })
+                    .collect(),
+                wb_errors: &r.wb_errors,
+            })
+            .collect(),
+    };
+    let data = serde_json::to_string_pretty(&report)
+        .map_err(|e| anyhow::anyhow!("Ошибка сериализации JSON-отчёта: {}", e))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            anyhow::anyhow!("Не удалось создать директорию {}: {}", parent.display(), e)
+        })?;
+    }
+    std::fs::write(path, data)
+        .map_err(|e| anyhow::anyhow!("Ошибка записи отчёта {}: {}", path.display(), e))?;
+    log::info!("JSON-отчёт сохранён: {} элементов", results.len());
+    Ok(())
+}
+
+const THUMBNAIL_SIZE: u32 = 96;
+// На скачивание одного фото для миниатюры
+const THUMBNAIL_TIMEOUT: Duration = Duration::from_secs(15);
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Картинка по ссылке публикации: локальный файл, если он ещё на месте, иначе скачанная
+fn thumbnail_bytes(
+    file: Option<&FileInfo>,
+    url: Option<&str>,
+    client: Option<&dyn HttpClient>,
+) -> Result<Vec<u8>> {
+    if let Some(file) = file
+        && Path::new(&file.path).is_file()
+    {
+        return std::fs::read(&file.path)
+            .map_err(|e| anyhow::anyhow!("Не удалось прочитать файл {}: {}", file.path, e));
+    }
+    match (url, client) {
+        (Some(url), Some(client)) => Ok(client.send(Request::get(url))?.error_for_status()?.body),
+        _ => Err(anyhow::anyhow!("Нет источника миниатюры")),
+    }
+}
+
+// Уменьшенная копия в JPEG как data:-URI: ссылки Яндекс.Диска и файлового сервера
+// со временем перестают открываться, а пути file:// — на другом компьютере
+fn thumbnail_data_uri(bytes: &[u8]) -> Result<String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| anyhow::anyhow!("Не удалось декодировать изображение: {}", e))?;
+    let mut output = Vec::new();
+    // Двойной размер — чтобы миниатюра не была мутной на экранах высокой плотности
+    DynamicImage::ImageRgb8(
+        image
+            .thumbnail(THUMBNAIL_SIZE * 2, THUMBNAIL_SIZE * 2)
+            .to_rgb8(),
+    )
+    .write_to(&mut Cursor::new(&mut output), ImageFormat::Jpeg)
+    .map_err(|e| anyhow::anyhow!("Не удалось сохранить миниатюру: {}", e))?;
+    Ok(format!("data:image/jpeg;base64,{}", BASE64.encode(output)))
+}
+
+// Для публикации ссылками берём опубликованные URL, для файлов — локальные пути; видео пропускаются
+fn thumbnails(result: &ItemResult, client: Option<&dyn HttpClient>) -> Vec<(String, String)> {
+    let sources: Vec<(Option<&FileInfo>, Option<&str>)> = if result.media_urls.is_empty() {
+        result.files.iter().map(|file| (Some(file), None)).collect()
+    } else {
+        result
+            .media_urls
+            .iter()
+            .enumerate()
+            .map(|(index, url)| (result.files.get(index), Some(url.as_str())))
+            .collect()
+    };
+    sources
+        .into_iter()
+        .filter(|(file, _)| file.is_none_or(|file| ImageFormat::from_path(&file.name).is_ok()))
+        .filter_map(|(file, url)| {
+            let title = file
+                .map(|file| file.name.clone())
+                .or(url.map(str::to_string))?;
+            match thumbnail_bytes(file, url, client).and_then(|bytes| thumbnail_data_uri(&bytes)) {
+                Ok(uri) => Some((title, uri)),
+                Err(e) => {
+                    log::debug!("Нет миниатюры {} для отчёта: {}", title, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// Клиент для скачивания опубликованных фото; в демо-режиме — встроенный макет
+fn thumbnail_client() -> Option<Arc<dyn HttpClient>> {
+    if let Some(client) = crate::demo::client() {
+        return Some(client);
+    }
+    let client = crate::network::client_builder()
+        .and_then(|builder| Ok(builder.timeout(THUMBNAIL_TIMEOUT).build()?))
+        .map_err(|e| log::warn!("Миниатюры по ссылкам не будут встроены в отчёт: {}", e))
+        .ok()?;
+    Some(Arc::new(ReqwestClient::new(client)))
+}
+
+pub fn save_html(path: &Path, results: &[ItemResult]) -> Result<()> {
+    log::info!("Сохранение HTML-отчёта в {}", path.display());
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"ru\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Отчёт о загрузке</title>\n<style>\n");
+    html.push_str("body { font-family: sans-serif; margin: 20px; }\n");
+    html.push_str("table { border-collapse: collapse; }\n");
+    html.push_str(
+        "th, td { border: 1px solid #ccc; padding: 6px; vertical-align: top; text-align: left; }\n",
+    );
+    html.push_str("th { background: #d9e1f2; }\n");
+    html.push_str(".success { background: #c6efce; }\n");
+    html.push_str(".failed { background: #ffc7ce; }\n");
+    html.push_str(".in_progress { background: #ffeb9c; }\n");
+    html.push_str(&format!(
+        "img {{ width: {0}px; height: {0}px; object-fit: cover; margin: 2px; border: 1px solid #ddd; }}\n",
+        THUMBNAIL_SIZE
+    ));
+    html.push_str("</style>\n</head>\n<body>\n");
+    html.push_str(&format!(
+        "<h1>Отчёт о загрузке от {}</h1>\n",
+        Local::now().format("%Y-%m-%d %H:%M:%S")
+    ));
+    html.push_str("<table>\n<tr><th>vendorCode</th><th>nmId</th><th>Фото</th><th>Результат</th><th>Ошибка</th><th>Тип ошибки</th><th>Ошибки WB</th></tr>\n");
+    let client = thumbnail_client();
+    for result in results {
+        let thumbnails: String = thumbnails(result, client.as_deref())
+            .iter()
+            .map(|(title, src)| format!("<img src=\"{}\" title=\"{}\">", src, html_escape(title)))
+            .collect();
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(&result.vendor_code),
+            result.nm_id.map(|id| id.to_string()).unwrap_or_default(),
+            thumbnails,
+            result.status.code(),
+            result.status.label(),
+            html_escape(result.error.as_deref().unwrap_or_default()),
+            error_class(result),
+            html_escape(&result.wb_errors.join("; ")),
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    std::fs::write(path, html)
+        .map_err(|e| anyhow::anyhow!("Ошибка записи отчёта {}: {}", path.display(), e))?;
+    log::info!("HTML-отчёт сохранён: {} строк", results.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fake::{ScriptedClient, response};
+    use image::{Rgb, RgbImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbImage::from_pixel(width, height, Rgb([200, 40, 40]))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn csv_opens_in_russian_excel() {
+        let path = std::env::temp_dir().join(format!("wb_report_{}.csv", std::process::id()));
+        save_csv(&path, &[]).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        // Импорт таблиц читает такой файл обратно по тем же столбцам
+        let rows = crate::import::read_table(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let header = text.strip_prefix(UTF8_BOM).unwrap().lines().next().unwrap();
+        assert_eq!(header, HEADER.join(";"));
+        assert_eq!(rows, vec![HEADER.map(str::to_string).to_vec()]);
+    }
+
+    #[test]
+    fn thumbnail_is_downscaled_jpeg_data_uri() {
+        let uri = thumbnail_data_uri(&png(1200, 900)).unwrap();
+        let encoded = uri.strip_prefix("data:image/jpeg;base64,").unwrap();
+        let image = image::load_from_memory(&BASE64.decode(encoded).unwrap()).unwrap();
+        assert_eq!(
+            (image.width(), image.height()),
+            (THUMBNAIL_SIZE * 2, THUMBNAIL_SIZE * 3 / 2)
+        );
+        assert!(thumbnail_data_uri(b"not an image").is_err());
+    }
+
+    #[test]
+    fn published_photo_is_downloaded_when_no_local_file() {
+        let client = ScriptedClient::new(vec![response(200, &[], "jpeg")]);
+        let file = FileInfo {
+            name: "ABC_1.jpg".to_string(),
+            path: "/Съёмка/ABC_1.jpg".to_string(),
+            articul: "ABC".to_string(),
+            photo_number: 1,
+            hash: None,
+            size: None,
+        };
+        let bytes = thumbnail_bytes(
+            Some(&file),
+            Some("https://example.com/1.jpg"),
+            Some(client.as_ref()),
+        );
+        assert_eq!(bytes.unwrap(), b"jpeg");
+        assert_eq!(client.requests()[0].0, "https://example.com/1.jpg");
+        assert!(thumbnail_bytes(Some(&file), None, None).is_err());
+    }
+}
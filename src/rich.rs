@@ -0,0 +1,70 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+// Рич-контент из подготовленных JSON-шаблонов. Шаблон — тело запроса целиком,
+// плейсхолдеры {vendorCode} и {nmId} подставляются для каждой карточки.
+// Путь — файл шаблона для всех карточек или папка с «<vendorCode>.json» и «default.json»
+#[derive(Clone)]
+pub struct RichContent {
+    path: PathBuf,
+}
+
+impl RichContent {
+    // Пустой путь — рич-контент не загружается
+    pub fn from_settings(path: &str) -> Result<Option<Self>> {
+        let path = path.trim();
+        if path.is_empty() {
+            return Ok(None);
+        }
+        let path = PathBuf::from(path);
+        if !path.exists() {
+            return Err(anyhow::anyhow!(
+                "Шаблон рич-контента {} не найден",
+                path.display()
+            ));
+        }
+        Ok(Some(Self { path }))
+    }
+
+    fn template_path(&self, vendor_code: &str) -> Option<PathBuf> {
+        if !self.path.is_dir() {
+            return Some(self.path.clone());
+        }
+        [format!("{}.json", vendor_code), "default.json".to_string()]
+            .into_iter()
+            .map(|name| self.path.join(name))
+            .find(|path| path.is_file())
+    }
+
+    // None — для vendorCode нет шаблона в папке
+    pub fn render(&self, vendor_code: &str, nm_id: i64) -> Result<Option<serde_json::Value>> {
+        let Some(path) = self.template_path(vendor_code) else {
+            return Ok(None);
+        };
+        let template = std::fs::read_to_string(&path).map_err(|e| {
+            anyhow::anyhow!("Не удалось прочитать шаблон {}: {}", path.display(), e)
+        })?;
+        render_template(&template, vendor_code, nm_id, &path).map(Some)
+    }
+}
+
+fn render_template(
+    template: &str,
+    vendor_code: &str,
+    nm_id: i64,
+    path: &Path,
+) -> Result<serde_json::Value> {
+    // vendorCode экранируется как строка JSON, кавычки вокруг плейсхолдера остаются из шаблона
+    let escaped = serde_json::to_string(vendor_code)?;
+    let rendered = template
+        .replace("{vendorCode}", &escaped[1..escaped.len() - 1])
+        .replace("{nmId}", &nm_id.to_string());
+    serde_json::from_str(&rendered).map_err(|e| {
+        anyhow::anyhow!(
+            "Шаблон {} после подстановки для vendorCode {} — неверный JSON: {}",
+            path.display(),
+            vendor_code,
+            e
+        )
+    })
+}
@@ -0,0 +1,157 @@
+use crate::config::Config;
+use crate::job::{self, JobSource, JobSpec, JobState};
+use crate::logs::{LogEvent, Stage};
+use crate::profile::ProfileManager;
+use crate::settings::Settings;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(20);
+
+// Сохранённое задание с расписанием в формате cron
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedJob {
+    pub name: String,
+    pub profile: String,
+    pub vendor_codes: Vec<String>,
+    pub source: JobSource,
+    pub schedule: String,
+    pub enabled: bool,
+    pub only_new: bool,
+    pub last_run: Option<DateTime<Local>>,
+}
+
+impl SavedJob {
+    pub fn next_run(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        parse_schedule(&self.schedule).ok()?.after(&after).next()
+    }
+}
+
+// Принимает классические 5 полей ("0 3 * * *") и формат с секундами
+pub fn parse_schedule(expression: &str) -> Result<Schedule> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    Schedule::from_str(&expression)
+        .map_err(|e| anyhow::anyhow!("Неверное расписание {}: {}", expression, e))
+}
+
+pub fn load_jobs(config: &Config) -> Vec<SavedJob> {
+    let path = config.get_jobs_file_path();
+    if !path.exists() {
+        return Vec::new();
+    }
+    match std::fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            log::warn!("Ошибка парсинга сохранённых заданий: {}", e);
+            Vec::new()
+        }),
+        Err(e) => {
+            log::warn!(
+                "Не удалось прочитать файл заданий {}: {}",
+                path.display(),
+                e
+            );
+            Vec::new()
+        }
+    }
+}
+
+pub fn save_jobs(config: &Config, jobs: &[SavedJob]) -> Result<()> {
+    let path = config.get_jobs_file_path();
+    let data = serde_json::to_string_pretty(jobs)
+        .map_err(|e| anyhow::anyhow!("Ошибка сериализации заданий: {}", e))?;
+    std::fs::write(&path, data).map_err(|e| {
+        anyhow::anyhow!("Не удалось записать файл заданий {}: {}", path.display(), e)
+    })?;
+    log::info!("Задания сохранены в {}", path.display());
+    Ok(())
+}
+
+pub fn run_saved_job(saved: &SavedJob, state: &JobState) -> Result<()> {
+    if *state.is_processing.lock().unwrap() {
+        return Err(anyhow::anyhow!("Обработка уже выполняется"));
+    }
+    let manager = ProfileManager::new()?;
+    let profile = manager
+        .profiles
+        .iter()
+        .find(|p| p.name == saved.profile)
+        .ok_or_else(|| anyhow::anyhow!("Профиль не найден: {}", saved.profile))?;
+    if profile.api_key.is_empty() {
+        return Err(anyhow::anyhow!(
+            "API ключ не указан для профиля {}",
+            profile.name
+        ));
+    }
+    state.log(LogEvent::info(
+        Stage::General,
+        format!("Запуск сохранённого задания: {}", saved.name),
+    ));
+    state.results.lock().unwrap().clear();
+    job::start(
+        JobSpec {
+            profile: profile.name.clone(),
+            api_key: profile.api_key.clone(),
+            wb_base_url: profile.wb_base_url.clone(),
+            timeouts: profile.timeouts,
+            vendor_codes: saved.vendor_codes.clone(),
+            source: saved.source.clone(),
+            settings: Settings::load(&manager.config),
+            report_dir: manager.config.get_reports_dir(),
+            started_at: Local::now(),
+            only_new: saved.only_new,
+            confirm_validation: false,
+            force_reupload: false,
+        },
+        state.clone(),
+    );
+    Ok(())
+}
+
+// Фоновая проверка расписаний; пропущенный из-за идущей обработки запуск выполняется, когда она закончится
+pub fn spawn(config: Config, jobs: Arc<Mutex<Vec<SavedJob>>>, state: JobState) {
+    std::thread::spawn(move || {
+        let started = Local::now();
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+            let now = Local::now();
+            let mut jobs = jobs.lock().unwrap();
+            let due = jobs.iter().position(|saved| {
+                saved.enabled
+                    && saved
+                        .next_run(saved.last_run.unwrap_or(started))
+                        .is_some_and(|next| next <= now)
+            });
+            let Some(index) = due else {
+                continue;
+            };
+            if *state.is_processing.lock().unwrap() {
+                continue;
+            }
+            match run_saved_job(&jobs[index], &state) {
+                Ok(()) => {
+                    jobs[index].last_run = Some(now);
+                    if let Err(e) = save_jobs(&config, &jobs) {
+                        state.log(LogEvent::warn(Stage::General, e.to_string()));
+                    }
+                }
+                Err(e) => {
+                    jobs[index].last_run = Some(now);
+                    state.log(LogEvent::error(
+                        Stage::General,
+                        format!("Ошибка запуска задания {}: {}", jobs[index].name, e),
+                    ));
+                }
+            }
+        }
+    });
+}
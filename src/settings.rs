@@ -0,0 +1,248 @@
+use crate::config::Config;
+use crate::logs::DEFAULT_LOG_CAPACITY;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub log_capacity: usize,
+    pub max_upload_kbps: u32,
+    // Сколько файлов vendorCode загружать одновременно; при ответах 429 меньше, 1 — по одному
+    pub upload_concurrency: usize,
+    // Сколько МБ должно оставаться свободными на диске для отчётов, истории и копий фото;
+    // предел памяти на один файл, читаемый или скачиваемый перед загрузкой; 0 — без проверки
+    pub min_free_disk_mb: u64,
+    pub max_file_memory_mb: u64,
+    // Предел размера папки staging, МБ, и срок хранения файлов в ней, дней; 0 — без ограничения
+    pub staging_max_mb: u64,
+    pub staging_max_age_days: u64,
+    // Резервный режим для WB, который не скачивает файлы по ссылкам Яндекс.Диска: приложение
+    // само скачивает их в staging и загружает файлами через media/file
+    pub download_yandex_files: bool,
+    // Удалять скачанный файл из staging сразу после загрузки в WB
+    pub delete_after_upload: bool,
+    // Записывать все HTTP-запросы запуска с ответами в файл captures/http_<время>.jsonl
+    pub capture_http: bool,
+    pub json_report_dir: String,
+    // Папка ZIP-архивов загруженных запуском файлов; пусто — не архивировать
+    pub archive_dir: String,
+    // Локальные файлы отдаются WB ссылками через встроенный HTTP-сервер вместо media/file;
+    // внешний адрес — туннель на порт сервера; без него сервер не запускается
+    pub file_server_enabled: bool,
+    pub file_server_port: u16,
+    pub file_server_url: String,
+    pub network: NetworkOptions,
+    pub telegram_token: String,
+    pub telegram_chat_id: String,
+    pub desktop_notifications: bool,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: String,
+    pub smtp_password: String,
+    pub smtp_from: String,
+    pub smtp_to: String,
+    pub webhook_urls: Vec<String>,
+    pub api_enabled: bool,
+    pub api_port: u16,
+    // Без токена API управления не запускается
+    pub api_token: String,
+    pub vendor_codes: VendorCodeOptions,
+    pub mapping_file: String,
+    pub matching: MatchOptions,
+    pub exclude_patterns: Vec<String>,
+    pub scan_folders: Vec<String>,
+    pub max_scan_depth: usize,
+    pub cache_scans: bool,
+    pub validate_before_upload: bool,
+    // Перенумеровывать фото каждого vendorCode подряд с 1 перед загрузкой
+    pub auto_renumber_photos: bool,
+    pub skip_uploaded: bool,
+    // Сохранять фото карточки перед заменой, чтобы запуск можно было откатить
+    pub snapshot_media: bool,
+    // Файл или папка шаблонов рич-контента; пусто — не загружать
+    pub rich_content_template: String,
+    // Через сколько секунд после загрузки проверять список ошибок карточек WB; 0 — не проверять
+    pub card_errors_delay_secs: u64,
+    // Сколько раз после основного прохода повторять ошибочные vendor codes и пауза перед повтором
+    pub auto_retry_count: u32,
+    pub auto_retry_delay_secs: u64,
+    // Восстанавливать из корзины WB карточки, найденные только там
+    pub restore_trashed_cards: bool,
+    // Проверять при запуске, нет ли новой версии на GitHub
+    pub check_updates: bool,
+    // Спрашивать подтверждение перед запуском, заменяющим фото, и перед удалением профиля
+    pub confirm_media_replace: bool,
+    pub confirm_profile_delete: bool,
+    pub theme: Theme,
+    // Цвет рамки окна в тёмной теме, RGB; в светлой он осветляется
+    pub accent_color: [u8; 3],
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    // Как в системе
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "Как в системе",
+            Theme::Light => "Светлая",
+            Theme::Dark => "Тёмная",
+        }
+    }
+}
+
+pub const DEFAULT_ACCENT_COLOR: [u8; 3] = [60, 80, 180];
+
+// Сравнение имён файлов с vendorCode; по умолчанию без учёта регистра
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MatchOptions {
+    pub case_sensitive: bool,
+    // «-» и «_» считаются одним символом
+    pub ignore_separators: bool,
+}
+
+// Параметры соединений с WB и Яндекс.Диском для корпоративных сетей, где ломается IPv6,
+// нужен свой DNS или прокси подменяет сертификаты
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct NetworkOptions {
+    // Подключаться только по IPv4
+    pub force_ipv4: bool,
+    // IP сетевой карты для исходящих соединений; пусто — выбирает система
+    pub local_address: String,
+    // Адреса хостов в обход DNS, строки «host=IP»
+    pub host_overrides: Vec<String>,
+    // PEM-файл корневых сертификатов прокси с подменой TLS; пусто — только системные
+    pub ca_certificate: String,
+}
+
+// Правила разбора списка vendor codes из поля ввода
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct VendorCodeOptions {
+    pub trim: bool,
+    pub strip_bom: bool,
+    pub dedupe: bool,
+    pub strip_comments: bool,
+    pub uppercase: bool,
+}
+
+impl Default for VendorCodeOptions {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            strip_bom: true,
+            dedupe: true,
+            strip_comments: true,
+            uppercase: false,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            log_capacity: DEFAULT_LOG_CAPACITY,
+            max_upload_kbps: 0,
+            upload_concurrency: 1,
+            min_free_disk_mb: 500,
+            max_file_memory_mb: 512,
+            staging_max_mb: 2048,
+            staging_max_age_days: 7,
+            download_yandex_files: false,
+            delete_after_upload: true,
+            capture_http: false,
+            json_report_dir: String::new(),
+            archive_dir: String::new(),
+            file_server_enabled: false,
+            file_server_port: 8766,
+            file_server_url: String::new(),
+            network: NetworkOptions::default(),
+            telegram_token: String::new(),
+            telegram_chat_id: String::new(),
+            desktop_notifications: true,
+            smtp_host: String::new(),
+            smtp_port: 587,
+            smtp_username: String::new(),
+            smtp_password: String::new(),
+            smtp_from: String::new(),
+            smtp_to: String::new(),
+            webhook_urls: Vec::new(),
+            api_enabled: false,
+            api_port: 8765,
+            api_token: String::new(),
+            vendor_codes: VendorCodeOptions::default(),
+            mapping_file: String::new(),
+            matching: MatchOptions::default(),
+            exclude_patterns: Vec::new(),
+            scan_folders: Vec::new(),
+            max_scan_depth: 0,
+            cache_scans: true,
+            validate_before_upload: true,
+            auto_renumber_photos: false,
+            skip_uploaded: true,
+            snapshot_media: true,
+            rich_content_template: String::new(),
+            card_errors_delay_secs: 30,
+            auto_retry_count: 0,
+            auto_retry_delay_secs: 60,
+            restore_trashed_cards: false,
+            check_updates: true,
+            confirm_media_replace: true,
+            confirm_profile_delete: true,
+            theme: Theme::System,
+            accent_color: DEFAULT_ACCENT_COLOR,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(config: &Config) -> Self {
+        let settings_file = config.get_settings_file_path();
+        if !settings_file.exists() {
+            log::info!("Файл настроек не найден, используются настройки по умолчанию");
+            return Self::default();
+        }
+        match std::fs::read_to_string(&settings_file) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+                log::warn!(
+                    "Ошибка парсинга настроек, используются настройки по умолчанию: {}",
+                    e
+                );
+                Self::default()
+            }),
+            Err(e) => {
+                log::warn!(
+                    "Не удалось прочитать файл настроек {}: {}",
+                    settings_file.display(),
+                    e
+                );
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        log::info!("Сохранение настроек");
+        let settings_file = config.get_settings_file_path();
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| anyhow::anyhow!("Ошибка сериализации настроек: {}", e))?;
+        std::fs::write(&settings_file, data).map_err(|e| {
+            anyhow::anyhow!(
+                "Не удалось записать файл настроек {}: {}",
+                settings_file.display(),
+                e
+            )
+        })?;
+        log::info!("Настройки сохранены в {}", settings_file.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+const MB: u64 = 1024 * 1024;
+
+// Файлы моложе часа могут принадлежать идущему запуску (в том числе второму экземпляру
+// приложения) — очистка по сроку и размеру их не трогает, только кнопка «Очистить кэш»
+const IN_USE_AGE: Duration = Duration::from_secs(60 * 60);
+
+// Управляемая папка в директории конфигурации: сюда скачиваются файлы Яндекс.Диска перед
+// загрузкой в WB файлами и для архива. Приложение удаляет файлы только внутри неё,
+// пользовательские папки не трогает
+#[derive(Clone)]
+pub struct Staging {
+    dir: PathBuf,
+}
+
+// Сколько места занимает папка
+#[derive(Clone, Copy, Default)]
+pub struct StagingUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl StagingUsage {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} файлов, {:.1} МБ",
+            self.files,
+            self.bytes as f64 / MB as f64
+        )
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl Staging {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Уникальный путь для нового файла; папка создаётся при необходимости
+    pub fn new_file_path(&self) -> Result<PathBuf> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        std::fs::create_dir_all(&self.dir).map_err(|e| {
+            anyhow::anyhow!("Не удалось создать папку {}: {}", self.dir.display(), e)
+        })?;
+        Ok(self.dir.join(format!(
+            "{}_{}_{}.part",
+            std::process::id(),
+            chrono::Local::now().format("%Y%m%d%H%M%S"),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+
+    // Файл лежит прямо в папке staging. Пути сравниваются после разрешения ссылок и «..»,
+    // поэтому ссылка из папки наружу или путь вида «staging/../фото» сюда не проходят
+    pub fn contains(&self, path: &Path) -> bool {
+        let (Ok(path), Ok(dir)) = (path.canonicalize(), self.dir.canonicalize()) else {
+            return false;
+        };
+        path.is_file() && path.parent() == Some(dir.as_path())
+    }
+
+    pub fn usage(&self) -> StagingUsage {
+        let entries = self.entries();
+        StagingUsage {
+            files: entries.len(),
+            bytes: entries.iter().map(|entry| entry.size).sum(),
+        }
+    }
+
+    // Удаляет файлы старше max_age_days, затем самые старые, пока папка больше max_size_mb;
+    // 0 — без ограничения. Возвращает, сколько файлов удалено и сколько байт освобождено
+    pub fn cleanup(&self, max_size_mb: u64, max_age_days: u64) -> StagingUsage {
+        let mut entries: Vec<Entry> = self
+            .entries()
+            .into_iter()
+            .filter(|entry| age(entry) > IN_USE_AGE)
+            .collect();
+        entries.sort_by_key(|entry| entry.modified);
+        let max_age = Duration::from_secs(max_age_days.saturating_mul(24 * 60 * 60));
+        let mut total = self.usage().bytes;
+        let mut removed = StagingUsage::default();
+        for entry in entries {
+            let expired = max_age_days > 0 && age(&entry) > max_age;
+            let oversized = max_size_mb > 0 && total > max_size_mb * MB;
+            if !expired && !oversized {
+                continue;
+            }
+            if remove(&entry) {
+                total = total.saturating_sub(entry.size);
+                removed.files += 1;
+                removed.bytes += entry.size;
+            }
+        }
+        removed
+    }
+
+    // Кнопка «Очистить кэш»: удаляет всё; вызывается, когда обработка не идёт
+    pub fn clear(&self) -> StagingUsage {
+        let mut removed = StagingUsage::default();
+        for entry in self.entries() {
+            if remove(&entry) {
+                removed.files += 1;
+                removed.bytes += entry.size;
+            }
+        }
+        removed
+    }
+
+    // Файлы верхнего уровня папки; вложенных папок приложение не создаёт
+    fn entries(&self) -> Vec<Entry> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+                Some(Entry {
+                    path: entry.path(),
+                    size: metadata.len(),
+                    modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                })
+            })
+            .collect()
+    }
+}
+
+fn remove(entry: &Entry) -> bool {
+    match std::fs::remove_file(&entry.path) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!(
+                "Не удалось удалить временный файл {}: {}",
+                entry.path.display(),
+                e
+            );
+            false
+        }
+    }
+}
+
+fn age(entry: &Entry) -> Duration {
+    entry.modified.elapsed().unwrap_or_default()
+}
@@ -0,0 +1,87 @@
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+// Сколько уведомление держится на экране и сколько их видно одновременно
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const MAX_TOASTS: usize = 5;
+
+#[derive(Clone, Copy)]
+pub enum ToastKind {
+    Info,
+    Success,
+    Error,
+}
+
+struct Toast {
+    kind: ToastKind,
+    text: String,
+    shown_at: Instant,
+}
+
+// Короткие уведомления в правом нижнем углу поверх интерфейса: не требуют открывать логи
+// и не блокируют окно; исчезают сами или по щелчку
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(ToastKind::Info, text.into());
+    }
+
+    pub fn success(&mut self, text: impl Into<String>) {
+        self.push(ToastKind::Success, text.into());
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(ToastKind::Error, text.into());
+    }
+
+    fn push(&mut self, kind: ToastKind, text: String) {
+        self.toasts.push(Toast {
+            kind,
+            text,
+            shown_at: Instant::now(),
+        });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.toasts.retain(|toast| toast.shown_at.elapsed() < TOAST_DURATION);
+        if self.toasts.is_empty() {
+            return;
+        }
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (i, toast) in self.toasts.iter().enumerate() {
+                    let (icon, color) = match toast.kind {
+                        ToastKind::Info => ("ℹ", egui::Color32::from_rgb(120, 170, 255)),
+                        ToastKind::Success => ("✅", egui::Color32::from_rgb(120, 220, 120)),
+                        ToastKind::Error => ("❌", egui::Color32::from_rgb(255, 120, 120)),
+                    };
+                    let response = egui::Frame::popup(ui.style())
+                        .stroke(egui::Stroke::new(1.0, color))
+                        .show(ui, |ui| {
+                            ui.set_max_width(320.0);
+                            ui.label(egui::RichText::new(format!("{} {}", icon, toast.text)).color(color));
+                        })
+                        .response
+                        .interact(egui::Sense::click())
+                        .on_hover_text("Щёлкните, чтобы закрыть");
+                    if response.clicked() {
+                        dismissed = Some(i);
+                    }
+                }
+            });
+        if let Some(i) = dismissed {
+            self.toasts.remove(i);
+        }
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+}
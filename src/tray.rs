@@ -0,0 +1,144 @@
+use eframe::egui;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Значок в области уведомлений Windows: подсказка показывает ход запуска, щелчок или пункт
+// «Открыть окно» возвращают спрятанное окно. На других системах значка нет, окно сворачивается
+#[derive(Default)]
+pub struct Tray {
+    #[cfg(windows)]
+    icon: Option<tray_icon::TrayIcon>,
+    // Создать значок не удалось — повторно не пытаемся
+    #[cfg(windows)]
+    unavailable: bool,
+    tooltip: String,
+    // «Выход» из меню значка: обрабатывается в update, чтобы остановить запуск перед закрытием
+    quit_requested: Arc<AtomicBool>,
+}
+
+impl Tray {
+    // Значок создаётся при первом вызове, дальше меняется только подсказка
+    pub fn update(&mut self, ctx: &egui::Context, tooltip: String) {
+        if self.tooltip == tooltip {
+            return;
+        }
+        self.tooltip = tooltip;
+        #[cfg(windows)]
+        self.update_icon(ctx);
+        #[cfg(not(windows))]
+        let _ = ctx;
+    }
+
+    #[cfg(windows)]
+    pub fn is_available(&self) -> bool {
+        self.icon.is_some()
+    }
+
+    #[cfg(not(windows))]
+    pub fn is_available(&self) -> bool {
+        false
+    }
+
+    // Прячет окно в трей, а без значка сворачивает его
+    pub fn hide_window(&self, ctx: &egui::Context) {
+        if self.is_available() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        } else {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+        }
+    }
+
+    pub fn take_quit_request(&self) -> bool {
+        self.quit_requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[cfg(windows)]
+impl Tray {
+    fn update_icon(&mut self, ctx: &egui::Context) {
+        if let Some(icon) = &self.icon {
+            if let Err(e) = icon.set_tooltip(Some(&self.tooltip)) {
+                log::warn!("Не удалось обновить подсказку значка в трее: {}", e);
+            }
+            return;
+        }
+        if self.unavailable {
+            return;
+        }
+        match self.build(ctx) {
+            Ok(icon) => self.icon = Some(icon),
+            Err(e) => {
+                log::warn!("Не удалось создать значок в трее: {}", e);
+                self.unavailable = true;
+            }
+        }
+    }
+
+    fn build(&self, ctx: &egui::Context) -> anyhow::Result<tray_icon::TrayIcon> {
+        use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+        use tray_icon::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+
+        let open = MenuItem::new("Открыть окно", true, None);
+        let quit = MenuItem::new("Выход", true, None);
+        let menu = Menu::new();
+        menu.append_items(&[&open, &PredefinedMenuItem::separator(), &quit])?;
+
+        let open_id = open.id().clone();
+        let quit_id = quit.id().clone();
+        let menu_ctx = ctx.clone();
+        let quit_requested = self.quit_requested.clone();
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            if event.id == quit_id {
+                quit_requested.store(true, Ordering::Relaxed);
+                show_window(&menu_ctx);
+            } else if event.id == open_id {
+                show_window(&menu_ctx);
+            }
+        }));
+        let click_ctx = ctx.clone();
+        TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+            if matches!(
+                event,
+                TrayIconEvent::Click {
+                    button: MouseButton::Left,
+                    button_state: MouseButtonState::Up,
+                    ..
+                } | TrayIconEvent::DoubleClick { .. }
+            ) {
+                show_window(&click_ctx);
+            }
+        }));
+
+        Ok(TrayIconBuilder::new()
+            .with_icon(icon()?)
+            .with_tooltip(&self.tooltip)
+            .with_menu(Box::new(menu))
+            .with_menu_on_left_click(false)
+            .build()?)
+    }
+}
+
+// Окно могло быть спрятано или свёрнуто; без перерисовки команды не применятся
+#[cfg(windows)]
+fn show_window(ctx: &egui::Context) {
+    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    ctx.request_repaint();
+}
+
+// Своего файла иконки у приложения нет: рисуем круг фирменного цвета WB
+#[cfg(windows)]
+fn icon() -> anyhow::Result<tray_icon::Icon> {
+    const SIZE: u32 = 32;
+    let center = (SIZE as f32 - 1.0) / 2.0;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            let alpha = (center + 0.5 - distance).clamp(0.0, 1.0);
+            rgba.extend_from_slice(&[203, 17, 171, (alpha * 255.0) as u8]);
+        }
+    }
+    Ok(tray_icon::Icon::from_rgba(rgba, SIZE, SIZE)?)
+}
@@ -0,0 +1,49 @@
+use crate::config::Config;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// Поля главной вкладки, сохраняемые при выходе, чтобы случайное закрытие
+// не теряло подготовленный запуск
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct UiState {
+    pub urls: String,
+    pub file_names: String,
+    pub local_source_path: String,
+    pub single_file_path: String,
+    pub use_local_path: bool,
+    pub force_reupload: bool,
+    // Имя выбранного профиля: индекс меняется при удалении профилей
+    pub profile: String,
+}
+
+impl UiState {
+    // None, если состояние ещё не сохранялось или файл повреждён
+    pub fn load(config: &Config) -> Option<Self> {
+        let path = config.get_ui_state_file_path();
+        if !path.exists() {
+            return None;
+        }
+        let data = std::fs::read_to_string(&path)
+            .map_err(|e| log::warn!("Не удалось прочитать состояние интерфейса {}: {}", path.display(), e))
+            .ok()?;
+        serde_json::from_str(&data)
+            .map_err(|e| log::warn!("Ошибка парсинга состояния интерфейса: {}", e))
+            .ok()
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let path = config.get_ui_state_file_path();
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| anyhow::anyhow!("Ошибка сериализации состояния интерфейса: {}", e))?;
+        std::fs::write(&path, data).map_err(|e| {
+            anyhow::anyhow!(
+                "Не удалось записать состояние интерфейса {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+        log::info!("Состояние интерфейса сохранено в {}", path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use crate::http::{HttpClient, ReqwestClient, Request};
+use anyhow::Result;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Последний релиз публикуется на GitHub; сборки на складских ПК обновляются вручную
+const RELEASES_URL: &str = "https://api.github.com/repos/Lagbag/WBUploadManager/releases/latest";
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Новая версия и адрес, по которому её скачать
+#[derive(Clone)]
+pub struct Release {
+    pub version: String,
+    pub download_url: String,
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+// Результат проверки для баннера в интерфейсе; dismissed — баннер закрыт до перезапуска
+#[derive(Clone, Default)]
+pub struct UpdateState {
+    pub available: Arc<Mutex<Option<Release>>>,
+    pub dismissed: Arc<Mutex<bool>>,
+}
+
+pub fn spawn(state: UpdateState) {
+    std::thread::spawn(move || {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(format!("WBUploadManager/{}", CURRENT_VERSION))
+            .build();
+        let release = client
+            .map_err(anyhow::Error::from)
+            .and_then(|client| check(&ReqwestClient::new(client), CURRENT_VERSION));
+        match release {
+            Ok(Some(release)) => {
+                log::info!("Доступна новая версия {}", release.version);
+                *state.available.lock().unwrap() = Some(release);
+            }
+            Ok(None) => log::info!("Установлена последняя версия {}", CURRENT_VERSION),
+            // Без сети или при ограничении GitHub просто работаем дальше
+            Err(e) => log::warn!("Не удалось проверить обновления: {}", e),
+        }
+    });
+}
+
+// Some, если последний релиз новее current
+pub fn check(client: &dyn HttpClient, current: &str) -> Result<Option<Release>> {
+    let response = client
+        .send(Request::get(RELEASES_URL).header("Accept", "application/vnd.github+json"))?
+        .error_for_status()?;
+    let release: GithubRelease = response
+        .json()
+        .map_err(|e| anyhow::anyhow!("Ошибка парсинга ответа GitHub: {}", e))?;
+    if !is_newer(&release.tag_name, current) {
+        return Ok(None);
+    }
+    // Установщик для Windows, если он приложен к релизу, иначе страница релиза
+    let download_url = release
+        .assets
+        .iter()
+        .find(|asset| cfg!(windows) && asset.name.to_lowercase().ends_with(".exe"))
+        .map(|asset| asset.browser_download_url.clone())
+        .unwrap_or(release.html_url);
+    Ok(Some(Release {
+        version: release.tag_name.trim_start_matches(['v', 'V']).to_string(),
+        download_url,
+    }))
+}
+
+// Версии сравниваются по числовым частям: «v1.10.0» новее «1.9.3»
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        let mut parts: Vec<u64> = version
+            .trim()
+            .trim_start_matches(['v', 'V'])
+            .split(['.', '-', '+'])
+            .map_while(|part| part.parse().ok())
+            .collect();
+        // «1.2» и «1.2.0» — одна версия
+        while parts.last() == Some(&0) {
+            parts.pop();
+        }
+        parts
+    };
+    parts(latest) > parts(current)
+}
@@ -1,17 +1,235 @@
+use crate::capture::{CapturingClient, ExchangeSink};
+use crate::concurrency::AdaptiveLimit;
+use crate::http::{FilePart, HttpClient, ReqwestClient, Request, Response};
+use crate::metrics::Metrics;
+use crate::profile::{HttpTimeouts, Profile};
 use anyhow::Result;
-use reqwest::blocking::{Client, ClientBuilder};
+use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+// Адрес Content API по умолчанию; в профиле можно указать песочницу или прокси-шлюз
+pub const WB_API_URL: &str = "https://content-api.wildberries.ru";
+pub const WB_SANDBOX_API_URL: &str = "https://content-api-sandbox.wildberries.ru";
+
+// Метод Content API для рич-контента карточки
+const RICH_CONTENT_PATH: &str = "/content/v2/cards/rich-content";
+
+// Класс ошибки запроса к WB: по нему задание решает, повторять vendorCode, пропустить его
+// или остановить весь запуск, а отчёт группирует ошибки
+#[derive(Debug, Clone, PartialEq)]
+pub enum WbError {
+    // 429 не прошёл и после повторов
+    RateLimited(String),
+    // 401/403: ключ неверный, просрочен или без доступа к Content API
+    AuthFailed(String),
+    // Карточка, файл или метод не найдены
+    NotFound(String),
+    // WB отклонил данные (400, 422 и другие 4xx) или они некорректны ещё до отправки
+    Validation(String),
+    // Запрос не дошёл до WB или ответ не получен
+    Network(String),
+    // 5xx или ответ, который не удалось разобрать
+    ServerError(String),
+}
+
+impl WbError {
+    fn from_status(status: u16, message: String) -> Self {
+        match status {
+            429 => Self::RateLimited(message),
+            401 | 403 => Self::AuthFailed(message),
+            404 => Self::NotFound(message),
+            500.. => Self::ServerError(message),
+            _ => Self::Validation(message),
+        }
+    }
+
+    fn parse(context: &str, e: serde_json::Error) -> Self {
+        Self::ServerError(format!("Ошибка парсинга ответа {}: {}", context, e))
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::RateLimited(message)
+            | Self::AuthFailed(message)
+            | Self::NotFound(message)
+            | Self::Validation(message)
+            | Self::Network(message)
+            | Self::ServerError(message) => message,
+        }
+    }
+
+    // Та же ошибка с дополненным сообщением, класс сохраняется
+    pub fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            Self::RateLimited(message) => Self::RateLimited(f(message)),
+            Self::AuthFailed(message) => Self::AuthFailed(f(message)),
+            Self::NotFound(message) => Self::NotFound(f(message)),
+            Self::Validation(message) => Self::Validation(f(message)),
+            Self::Network(message) => Self::Network(f(message)),
+            Self::ServerError(message) => Self::ServerError(f(message)),
+        }
+    }
+
+    // Название класса для отчёта и сводки
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::RateLimited(_) => "Лимит запросов",
+            Self::AuthFailed(_) => "Авторизация",
+            Self::NotFound(_) => "Не найдено",
+            Self::Validation(_) => "Ошибка данных",
+            Self::Network(_) => "Сеть",
+            Self::ServerError(_) => "Сбой WB",
+        }
+    }
+
+    // Временные ошибки: повтор позже может пройти
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::RateLimited(_) | Self::Network(_) | Self::ServerError(_))
+    }
+
+    // С этой ошибкой не пройдут и остальные vendor codes запуска
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::AuthFailed(_))
+    }
+}
+
+impl std::fmt::Display for WbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for WbError {}
+
+// Запрос к WB и ответ на него; status None — ответ не получен, в response текст ошибки
+#[derive(Clone)]
+pub struct Exchange {
+    pub request: String,
+    pub status: Option<u16>,
+    pub response: String,
+    pub timestamp: DateTime<Local>,
+}
 
 pub struct WbUploader {
-    client: Client,
-    #[allow(dead_code)]
-    api_key: String,
+    client: Arc<dyn HttpClient>,
+    base_url: String,
+    upload_limit: Option<Arc<RateLimit>>,
+    memory_limit_mb: u64,
+    metrics: Option<Arc<Metrics>>,
+    recorder: Option<Arc<dyn Fn(Exchange) + Send + Sync>>,
+    concurrency: Option<Arc<AdaptiveLimit>>,
+}
+
+// Байтовый прогресс передачи текущего файла, читается из UI
+pub struct TransferProgress {
+    pub sent: AtomicU64,
+    pub total: AtomicU64,
+    started: Mutex<Instant>,
+}
+
+impl Default for TransferProgress {
+    fn default() -> Self {
+        Self {
+            sent: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            started: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl TransferProgress {
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    // Время текущей попытки передачи
+    pub fn elapsed(&self) -> Duration {
+        self.started.lock().unwrap().elapsed()
+    }
+
+    pub fn bytes_per_second(&self) -> f64 {
+        let secs = self.elapsed().as_secs_f64();
+        if secs > 0.0 {
+            self.sent() as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    fn restart(&self) {
+        self.sent.store(0, Ordering::Relaxed);
+        *self.started.lock().unwrap() = Instant::now();
+    }
+}
+
+// Общий лимит скорости (байт/с) на все файлы, загружаемые параллельно:
+// каждый прочитанный блок занимает своё время в общем расписании
+struct RateLimit {
+    bytes_per_second: u64,
+    next: Mutex<Instant>,
+}
+
+impl RateLimit {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    // Не больше ~1/10 секундного лимита за раз, чтобы скорость была ровной
+    fn chunk(&self) -> usize {
+        (self.bytes_per_second / 10).max(1024) as usize
+    }
+
+    fn consume(&self, bytes: u64) {
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            // Простой не копится в запас: после паузы отсчёт начинается заново
+            *next = (*next).max(now)
+                + Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64);
+            *next - now
+        };
+        thread::sleep(wait);
+    }
+}
+
+// Обёртка над телом запроса, отмечающая каждый прочитанный клиентом блок
+// и при заданном лимите притормаживающая чтение
+struct ProgressReader<R> {
+    inner: R,
+    progress: Arc<TransferProgress>,
+    limit: Option<Arc<RateLimit>>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let buf = match &self.limit {
+            Some(limit) => {
+                let len = buf.len().min(limit.chunk());
+                &mut buf[..len]
+            }
+            None => buf,
+        };
+        let n = self.inner.read(buf)?;
+        self.progress.sent.fetch_add(n as u64, Ordering::Relaxed);
+        if let Some(limit) = &self.limit {
+            limit.consume(n as u64);
+        }
+        Ok(n)
+    }
 }
 
 #[derive(Serialize)]
@@ -26,9 +244,16 @@ struct CardSettings {
     sort: Sort,
 }
 
+// Размер страницы cards/list; больше WB не отдаёт
+const CARDS_PAGE: i32 = 100;
+
 #[derive(Serialize)]
 struct Cursor {
     limit: i32,
+    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+    updated_at: Option<String>,
+    #[serde(rename = "nmID", skip_serializing_if = "Option::is_none")]
+    nm_id: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -37,6 +262,8 @@ struct Filter {
     with_photo: i32,
     #[serde(rename = "textSearch")]
     text_search: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    brands: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -47,16 +274,191 @@ struct Sort {
 #[derive(Deserialize)]
 struct CardResponse {
     cards: Vec<Card>,
+    #[serde(default)]
+    cursor: Option<ResponseCursor>,
+}
+
+// Позиция следующей страницы каталога
+#[derive(Deserialize)]
+struct ResponseCursor {
+    #[serde(rename = "updatedAt", default)]
+    updated_at: Option<String>,
+    #[serde(rename = "nmID", default)]
+    nm_id: Option<i64>,
+    #[serde(default)]
+    total: i64,
 }
 
 #[derive(Deserialize)]
 struct Card {
     #[serde(rename = "nmID")]
     nm_id: i64,
+    #[serde(rename = "vendorCode", default)]
+    vendor_code: String,
+    #[serde(default)]
+    brand: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    photos: Vec<CardPhoto>,
+}
+
+#[derive(Deserialize)]
+struct CardPhoto {
+    #[serde(default)]
+    big: String,
+}
+
+// Слот карточки, в который загружается файл
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MediaSlot {
+    Photo(u32),
+    Video,
+}
+
+impl MediaSlot {
+    pub fn describe(&self) -> String {
+        match self {
+            MediaSlot::Photo(number) => format!("фото №{}", number),
+            MediaSlot::Video => "видео".to_string(),
+        }
+    }
+}
+
+// Карточка с текущими фото в порядке галереи
+pub struct CardMedia {
+    pub nm_id: i64,
+    pub photos: Vec<String>,
+}
+
+// Карточка каталога продавца
+#[derive(Clone)]
+pub struct CardSummary {
+    pub nm_id: i64,
+    pub vendor_code: String,
+    pub brand: String,
+    pub title: String,
+    pub photos: usize,
+}
+
+// Характеристика из справочника категории WB
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Characteristic {
+    #[serde(rename = "charcID")]
+    pub id: i64,
+    pub name: String,
+    #[serde(rename = "charcType", default)]
+    pub charc_type: i32,
+    #[serde(rename = "maxCount", default)]
+    pub max_count: usize,
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl Characteristic {
+    // charcType 4 — числовое значение, остальные — список строк
+    pub fn is_numeric(&self) -> bool {
+        self.charc_type == 4
+    }
+}
+
+#[derive(Deserialize)]
+struct CharacteristicsResponse {
+    #[serde(default)]
+    data: Vec<Characteristic>,
+}
+
+// Ошибка обработки карточки на стороне WB из cards/error/list
+#[derive(Deserialize)]
+pub struct CardError {
+    #[serde(rename = "vendorCode", default)]
+    pub vendor_code: String,
+    #[serde(rename = "updateAt", default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CardErrorsResponse {
+    #[serde(default)]
+    data: Vec<CardError>,
+}
+
+// Сколько карточек продавец ещё может создать: бесплатный и оплаченный лимиты
+#[derive(Deserialize, Clone, Copy, Default)]
+pub struct CardLimits {
+    #[serde(rename = "freeLimits", default)]
+    pub free: u64,
+    #[serde(rename = "paidLimits", default)]
+    pub paid: u64,
+}
+
+impl CardLimits {
+    pub fn total(&self) -> u64 {
+        self.free + self.paid
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} (бесплатных {}, оплаченных {})",
+            self.total(),
+            self.free,
+            self.paid
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct CardLimitsResponse {
+    data: CardLimits,
+}
+
+// Предмет (категория) WB: subjectID указывается при создании карточки
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Subject {
+    #[serde(rename = "subjectID")]
+    pub id: i64,
+    #[serde(rename = "subjectName", default)]
+    pub name: String,
+    #[serde(rename = "parentName", default)]
+    pub parent_name: String,
+}
+
+#[derive(Deserialize)]
+struct SubjectsResponse {
+    #[serde(default)]
+    data: Vec<Subject>,
+}
+
+const SUBJECTS_PAGE: usize = 1000;
+
+// Поиск по каталогу; with_photo: -1 — все карточки, 0 — без фото, 1 — с фото
+#[derive(Clone)]
+pub struct CardQuery {
+    pub text: String,
+    pub brands: Vec<String>,
+    pub with_photo: i32,
+    // 0 — весь каталог
+    pub limit: usize,
+}
+
+impl Default for CardQuery {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            brands: Vec::new(),
+            with_photo: -1,
+            limit: 0,
+        }
+    }
 }
 
 impl WbUploader {
-    pub fn new(api_key: String) -> Result<Self, anyhow::Error> {
+    pub fn new(api_key: String, timeouts: &HttpTimeouts) -> Result<Self, anyhow::Error> {
+        if let Some(client) = crate::demo::client() {
+            return Ok(Self::with_client(client));
+        }
         if api_key.is_empty() {
             log::error!("API ключ пустой");
             return Err(anyhow::anyhow!("API ключ пустой"));
@@ -65,9 +467,10 @@ impl WbUploader {
             "Инициализация WbUploader с API ключом длиной: {}",
             api_key.len()
         );
-        let client = ClientBuilder::new()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10))
+        let (connect_timeout, timeout) = timeouts.wb();
+        let client = crate::network::client_builder()?
+            .timeout(timeout)
+            .connect_timeout(connect_timeout)
             .default_headers({
                 let mut headers = reqwest::header::HeaderMap::new();
                 headers.insert(
@@ -86,75 +489,485 @@ impl WbUploader {
             })
             .build()
             .map_err(|e| anyhow::anyhow!("Не удалось создать HTTP-клиент: {}", e))?;
-        Ok(Self { client, api_key })
+        Ok(Self::with_client(Arc::new(ReqwestClient::new(client))))
     }
 
-    pub fn get_nm_id_by_vendor_code(&self, vendor_code: &str) -> Result<i64, anyhow::Error> {
+    // Клиент с ключом и адресом API профиля
+    pub fn for_profile(profile: &Profile) -> Result<Self, anyhow::Error> {
+        let mut uploader = Self::new(profile.api_key.clone(), &profile.timeouts)?;
+        uploader.set_base_url(&profile.wb_base_url);
+        Ok(uploader)
+    }
+
+    // Клиент с уже настроенной авторизацией, например подменный в тестах
+    pub fn with_client(client: Arc<dyn HttpClient>) -> Self {
+        Self {
+            client,
+            base_url: WB_API_URL.to_string(),
+            upload_limit: None,
+            memory_limit_mb: 0,
+            metrics: None,
+            recorder: None,
+            concurrency: None,
+        }
+    }
+
+    // Адрес API из профиля; пустой — основной API WB
+    pub fn set_base_url(&mut self, base_url: &str) {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if base_url.is_empty() {
+            return;
+        }
+        log::info!("Адрес API WB: {}", base_url);
+        self.base_url = base_url.to_string();
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn set_metrics(&mut self, metrics: Arc<Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    // Получает каждый запрос к WB вместе с ответом, например для подробностей ошибки в отчёте
+    pub fn set_recorder(&mut self, recorder: Arc<dyn Fn(Exchange) + Send + Sync>) {
+        self.recorder = Some(recorder);
+    }
+
+    // Дальнейшие запросы к WB с ответами передаются sink: в файл записи или подробный журнал
+    pub fn set_capture(&mut self, sink: Arc<dyn ExchangeSink>) {
+        self.client = Arc::new(CapturingClient::new(self.client.clone(), sink));
+    }
+
+    // Параллельные загрузки и их предел, который уменьшается при ответах 429
+    pub fn set_concurrency(&mut self, concurrency: Arc<AdaptiveLimit>) {
+        self.concurrency = Some(concurrency);
+    }
+
+    pub fn concurrency(&self) -> Option<&AdaptiveLimit> {
+        self.concurrency.as_deref()
+    }
+
+    fn send(&self, request: Request) -> Result<Response> {
+        let Some(recorder) = &self.recorder else {
+            let response = self.client.send(request);
+            self.track_rate_limit(&response);
+            return response;
+        };
+        let description = request.describe();
+        let response = self.client.send(request);
+        self.track_rate_limit(&response);
+        recorder(Exchange {
+            request: description,
+            status: response.as_ref().ok().map(|r| r.status().as_u16()),
+            response: match &response {
+                Ok(response) => response.text(),
+                Err(e) => e.to_string(),
+            },
+            timestamp: Local::now(),
+        });
+        response
+    }
+
+    fn track_rate_limit(&self, response: &Result<Response>) {
+        let (Some(concurrency), Ok(response)) = (&self.concurrency, response) else {
+            return;
+        };
+        if response.status().as_u16() == 429 {
+            concurrency.on_rate_limited();
+        } else if response.status().is_success() {
+            concurrency.on_success();
+        }
+    }
+
+    // Ограничение скорости загрузки файлов, КБ/с, общее для всех параллельных загрузок;
+    // 0 — без ограничения
+    pub fn set_upload_limit_kbps(&mut self, kbps: u32) {
+        self.upload_limit = (kbps > 0).then(|| Arc::new(RateLimit::new(kbps as u64 * 1024)));
+        if kbps > 0 {
+            log::info!("Ограничение скорости загрузки: {} КБ/с", kbps);
+        }
+    }
+
+    // Предел памяти на один файл, МБ; 0 — без ограничения
+    pub fn set_memory_limit_mb(&mut self, mb: u64) {
+        self.memory_limit_mb = mb;
+    }
+
+    // Размер файла известен по метаданным — проверяем до чтения
+    fn check_file_size(&self, file_path: &str, size: u64) -> Result<(), WbError> {
+        crate::limits::check_file_memory(file_path, size, self.memory_limit_mb)
+            .map_err(|e| WbError::Validation(e.to_string()))
+    }
+
+    pub fn get_nm_id_by_vendor_code(&self, vendor_code: &str) -> Result<i64, WbError> {
+        self.get_card(vendor_code).map(|card| card.nm_id)
+    }
+
+    pub fn get_card(&self, vendor_code: &str) -> Result<CardMedia, WbError> {
         log::info!("Запрос nmId для vendorCode: {}", vendor_code);
         let request_body = CardRequest {
             settings: CardSettings {
-                cursor: Cursor { limit: 100 },
+                cursor: Cursor {
+                    limit: CARDS_PAGE,
+                    updated_at: None,
+                    nm_id: None,
+                },
                 filter: Filter {
                     with_photo: -1,
                     text_search: vendor_code.to_string(),
+                    brands: Vec::new(),
                 },
                 sort: Sort { ascending: false },
             },
         };
-        log::debug!(
-            "HTTP Request: POST https://content-api.wildberries.ru/content/v2/get/cards/list\nBody: {}",
-            serde_json::to_string_pretty(&request_body)?
-        );
-        let response = self
-            .client
-            .post("https://content-api.wildberries.ru/content/v2/get/cards/list")
-            .json(&request_body)
-            .send()
-            .map_err(|e| {
-                anyhow::anyhow!(
-                    "Не удалось отправить запрос для vendorCode {}: {}",
-                    vendor_code,
-                    e
-                )
-            })?;
-        let status = response.status();
-        let body = response.text().map_err(|e| {
-            anyhow::anyhow!(
-                "Не удалось прочитать ответ для vendorCode {}: {}",
-                vendor_code,
-                e
-            )
-        })?;
-        log::debug!("HTTP Response: Status: {}, Body: {}", status, body);
-
-        if !status.is_success() {
-            log::error!("Ошибка API Wildberries: Статус {}, Тело: {}", status, body);
-            return Err(anyhow::anyhow!(
-                "Ошибка API Wildberries: Статус {}, Тело: {}",
-                status,
-                body
-            ));
-        }
-        let card_response: CardResponse = serde_json::from_str(&body).map_err(|e| {
-            anyhow::anyhow!(
-                "Ошибка парсинга ответа для vendorCode {}: {}",
-                vendor_code,
-                e
-            )
-        })?;
-        if let Some(card) = card_response.cards.first() {
+        let card_response =
+            self.request_cards(&request_body, &format!("для vendorCode {}", vendor_code))?;
+        // textSearch ищет и по вхождению: «ABC1» находит «ABC12», поэтому нужно точное совпадение
+        if let Some(card) = card_response
+            .cards
+            .into_iter()
+            .find(|card| card.vendor_code == vendor_code)
+        {
             log::info!(
                 "Найден nmId: {} для vendorCode: {}",
                 card.nm_id,
                 vendor_code
             );
-            Ok(card.nm_id)
+            Ok(CardMedia {
+                nm_id: card.nm_id,
+                photos: card
+                    .photos
+                    .into_iter()
+                    .map(|p| p.big)
+                    .filter(|url| !url.is_empty())
+                    .collect(),
+            })
         } else {
             log::error!("nmId не найден для vendorCode: {}", vendor_code);
-            Err(anyhow::anyhow!(
+            Err(WbError::NotFound(format!(
                 "nmId не найден для vendorCode: {}",
                 vendor_code
-            ))
+            )))
+        }
+    }
+
+    // nmId карточки в корзине WB с точно таким vendorCode
+    pub fn find_in_trash(&self, vendor_code: &str) -> Result<Option<i64>, WbError> {
+        let request_body = serde_json::json!({
+            "settings": {
+                "cursor": { "limit": CARDS_PAGE },
+                "filter": { "textSearch": vendor_code },
+            }
+        });
+        let context = format!("корзины для vendorCode {}", vendor_code);
+        let body = self.post_with_retry(
+            &self.url("/content/v2/get/cards/trash"),
+            &request_body,
+            &context,
+        )?;
+        let response: CardResponse = serde_json::from_str(&body)
+            .map_err(|e| WbError::parse(&context, e))?;
+        Ok(response
+            .cards
+            .into_iter()
+            .find(|card| card.vendor_code == vendor_code)
+            .map(|card| card.nm_id))
+    }
+
+    pub fn recover_cards(&self, nm_ids: &[i64]) -> Result<String, WbError> {
+        log::info!("Восстановление карточек из корзины: {:?}", nm_ids);
+        self.post_with_retry(
+            &self.url("/content/v2/cards/recover"),
+            &serde_json::json!({ "nmIDs": nm_ids }),
+            "восстановления карточек из корзины",
+        )
+    }
+
+    // Весь каталог продавца постранично; on_page получает число уже полученных карточек
+    pub fn list_cards(
+        &self,
+        with_photo: i32,
+        on_page: impl FnMut(usize),
+    ) -> Result<Vec<CardSummary>, WbError> {
+        let query = CardQuery {
+            with_photo,
+            ..Default::default()
+        };
+        self.search_cards(&query, on_page)
+    }
+
+    pub fn search_cards(
+        &self,
+        query: &CardQuery,
+        mut on_page: impl FnMut(usize),
+    ) -> Result<Vec<CardSummary>, WbError> {
+        log::info!("Запрос каталога карточек");
+        let mut cards = Vec::new();
+        let mut cursor = Cursor {
+            limit: CARDS_PAGE,
+            updated_at: None,
+            nm_id: None,
+        };
+        loop {
+            let request_body = CardRequest {
+                settings: CardSettings {
+                    cursor,
+                    filter: Filter {
+                        with_photo: query.with_photo,
+                        text_search: query.text.trim().to_string(),
+                        brands: query.brands.clone(),
+                    },
+                    sort: Sort { ascending: false },
+                },
+            };
+            let response = self.request_cards(&request_body, "каталога карточек")?;
+            cards.extend(response.cards.into_iter().map(|card| CardSummary {
+                nm_id: card.nm_id,
+                vendor_code: card.vendor_code,
+                brand: card.brand,
+                title: card.title,
+                photos: card.photos.len(),
+            }));
+            on_page(cards.len());
+            if query.limit > 0 && cards.len() >= query.limit {
+                cards.truncate(query.limit);
+                break;
+            }
+            match response.cursor {
+                Some(next) if next.total >= CARDS_PAGE as i64 => {
+                    cursor = Cursor {
+                        limit: CARDS_PAGE,
+                        updated_at: next.updated_at,
+                        nm_id: next.nm_id,
+                    };
+                }
+                _ => break,
+            }
+        }
+        log::info!("Получено карточек каталога: {}", cards.len());
+        Ok(cards)
+    }
+
+    // Все пары vendorCode/nmId каталога в CSV, который можно открыть через импорт vendor codes
+    pub fn export_vendor_codes(
+        &self,
+        path: &Path,
+        on_page: impl FnMut(usize),
+    ) -> Result<usize, anyhow::Error> {
+        let mut cards = self.list_cards(-1, on_page)?;
+        cards.sort_by(|a, b| a.vendor_code.cmp(&b.vendor_code));
+        let mut writer = csv::Writer::from_path(path)
+            .map_err(|e| anyhow::anyhow!("Не удалось создать файл {}: {}", path.display(), e))?;
+        writer.write_record(["vendorCode", "nmId"])?;
+        for card in &cards {
+            writer.write_record([card.vendor_code.as_str(), &card.nm_id.to_string()])?;
+        }
+        writer
+            .flush()
+            .map_err(|e| anyhow::anyhow!("Ошибка записи файла {}: {}", path.display(), e))?;
+        log::info!("Выгружено vendor codes: {} в {}", cards.len(), path.display());
+        Ok(cards.len())
+    }
+
+    fn request_cards(
+        &self,
+        request_body: &CardRequest,
+        context: &str,
+    ) -> Result<CardResponse, WbError> {
+        let body = self.post_with_retry(
+            &self.url("/content/v2/get/cards/list"),
+            request_body,
+            context,
+        )?;
+        serde_json::from_str(&body)
+            .map_err(|e| WbError::parse(context, e))
+    }
+
+    // Карточка целиком, как её отдаёт cards/list, — основа для cards/update
+    pub fn get_card_json(&self, vendor_code: &str) -> Result<serde_json::Value, WbError> {
+        let request_body = CardRequest {
+            settings: CardSettings {
+                cursor: Cursor {
+                    limit: CARDS_PAGE,
+                    updated_at: None,
+                    nm_id: None,
+                },
+                filter: Filter {
+                    with_photo: -1,
+                    text_search: vendor_code.to_string(),
+                    brands: Vec::new(),
+                },
+                sort: Sort { ascending: false },
+            },
+        };
+        let context = format!("для vendorCode {}", vendor_code);
+        let body = self.post_with_retry(
+            &self.url("/content/v2/get/cards/list"),
+            &request_body,
+            &context,
+        )?;
+        let mut response: serde_json::Value = serde_json::from_str(&body)
+            .map_err(|e| WbError::parse(&context, e))?;
+        let cards = response["cards"].as_array_mut().map(std::mem::take).unwrap_or_default();
+        cards
+            .into_iter()
+            .find(|card| card["vendorCode"].as_str() == Some(vendor_code))
+            .ok_or_else(|| {
+                WbError::NotFound(format!("nmId не найден для vendorCode: {}", vendor_code))
+            })
+    }
+
+    // Карточки отправляются целиком: WB заменяет все поля карточки переданными
+    pub fn update_cards(&self, cards: &[serde_json::Value]) -> Result<String, WbError> {
+        log::info!("Обновление карточек: {}", cards.len());
+        self.post_with_retry(
+            &self.url("/content/v2/cards/update"),
+            &cards,
+            "обновления карточек",
+        )
+    }
+
+    // WB создаёт карточки асинхронно: nmId появляется в cards/list не сразу
+    pub fn create_cards(&self, cards: &[serde_json::Value]) -> Result<String, WbError> {
+        log::info!("Создание карточек: {}", cards.len());
+        self.post_with_retry(
+            &self.url("/content/v2/cards/upload"),
+            &cards,
+            "создания карточек",
+        )
+    }
+
+    // Справочник характеристик категории (subjectID карточки)
+    pub fn get_characteristics(
+        &self,
+        subject_id: i64,
+    ) -> Result<Vec<Characteristic>, WbError> {
+        let url = self.url(&format!("/content/v2/object/charcs/{}", subject_id));
+        let context = format!("справочника характеристик категории {}", subject_id);
+        let body = self.send_with_retry(&context, || {
+            log::debug!("HTTP Request: GET {}", url);
+            Ok(Request::get(&url))
+        })?;
+        let response: CharacteristicsResponse = serde_json::from_str(&body)
+            .map_err(|e| WbError::parse(&context, e))?;
+        log::info!(
+            "Справочник характеристик категории {}: {}",
+            subject_id,
+            response.data.len()
+        );
+        Ok(response.data)
+    }
+
+    // Карточки, которые WB не смог создать или обновить, с причинами
+    pub fn get_card_errors(&self) -> Result<Vec<CardError>, WbError> {
+        let url = self.url("/content/v2/cards/error/list");
+        let body = self.send_with_retry("списка ошибок карточек", || {
+            log::debug!("HTTP Request: GET {}", url);
+            Ok(Request::get(&url))
+        })?;
+        let response: CardErrorsResponse = serde_json::from_str(&body)
+            .map_err(|e| WbError::parse("списка ошибок карточек", e))?;
+        Ok(response.data)
+    }
+
+    // Оставшийся лимит создания карточек
+    pub fn get_card_limits(&self) -> Result<CardLimits, WbError> {
+        let url = self.url("/content/v2/cards/limits");
+        let body = self.send_with_retry("лимитов карточек", || {
+            log::debug!("HTTP Request: GET {}", url);
+            Ok(Request::get(&url))
+        })?;
+        let response: CardLimitsResponse = serde_json::from_str(&body)
+            .map_err(|e| WbError::parse("лимитов карточек", e))?;
+        log::info!("Лимит создания карточек: {}", response.data.describe());
+        Ok(response.data)
+    }
+
+    // Все предметы WB постранично; on_page получает число уже полученных
+    pub fn get_subjects(
+        &self,
+        mut on_page: impl FnMut(usize),
+    ) -> Result<Vec<Subject>, WbError> {
+        let mut subjects: Vec<Subject> = Vec::new();
+        loop {
+            let url = self.url(&format!(
+                "/content/v2/object/all?limit={}&offset={}",
+                SUBJECTS_PAGE,
+                subjects.len()
+            ));
+            let body = self.send_with_retry("справочника предметов", || {
+                log::debug!("HTTP Request: GET {}", url);
+                Ok(Request::get(&url))
+            })?;
+            let response: SubjectsResponse = serde_json::from_str(&body)
+                .map_err(|e| WbError::parse("справочника предметов", e))?;
+            let page = response.data.len();
+            subjects.extend(response.data);
+            on_page(subjects.len());
+            if page < SUBJECTS_PAGE {
+                break;
+            }
+        }
+        log::info!("Справочник предметов WB: {}", subjects.len());
+        Ok(subjects)
+    }
+
+    // POST с повтором при 429; context — «для vendorCode …» в сообщениях об ошибках
+    fn post_with_retry(
+        &self,
+        url: &str,
+        request_body: &impl Serialize,
+        context: &str,
+    ) -> Result<String, WbError> {
+        let pretty = serde_json::to_string_pretty(request_body)
+            .map_err(|e| WbError::Validation(format!("Некорректный запрос {}: {}", context, e)))?;
+        self.send_with_retry(context, || {
+            log::debug!("HTTP Request: POST {}\nBody: {}", url, pretty);
+            Request::post(url).json(request_body)
+        })
+    }
+
+    fn send_with_retry(
+        &self,
+        context: &str,
+        request: impl Fn() -> Result<Request>,
+    ) -> Result<String, WbError> {
+        let max_attempts = 3;
+        let mut attempts = 0;
+        loop {
+            let request = request()
+                .map_err(|e| WbError::Validation(format!("Некорректный запрос {}: {}", context, e)))?;
+            let response = self.send(request).map_err(|e| {
+                WbError::Network(format!("Не удалось отправить запрос {}: {}", context, e))
+            })?;
+            let status = response.status();
+            let body = response.text();
+            log::debug!("HTTP Response: Status: {}, Body: {}", status, body);
+
+            if status.as_u16() == 429 && attempts < max_attempts {
+                if let Some(metrics) = &self.metrics {
+                    metrics.add_rate_limited();
+                }
+                attempts += 1;
+                log::warn!(
+                    "Ошибка 429: Слишком много запросов {}, повторная попытка через 60 секунд (попытка {}/{})",
+                    context,
+                    attempts,
+                    max_attempts
+                );
+                thread::sleep(Duration::from_secs(60));
+                continue;
+            }
+            if !status.is_success() {
+                log::error!("Ошибка API Wildberries: Статус {}, Тело: {}", status, body);
+                return Err(WbError::from_status(
+                    status.as_u16(),
+                    format!("Ошибка API Wildberries: Статус {}, Тело: {}", status, body),
+                ));
+            }
+            return Ok(body);
         }
     }
 
@@ -162,19 +975,23 @@ impl WbUploader {
         &self,
         nm_id: i64,
         urls: &[String],
-        processed_files: &Arc<Mutex<usize>>,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<String, WbError> {
         log::info!("Начало загрузки ссылок для nmId {}", nm_id);
         for url in urls {
-            if !url.starts_with("http://")
-                && !url.starts_with("https://")
-                && !url.starts_with("file://")
-            {
+            if url.starts_with("file://") {
+                log::error!("{} — локальный путь, WB не может его скачать", url);
+                return Err(WbError::Validation(format!(
+                    "Локальный путь вместо ссылки: {} — такие файлы загружаются через media/file",
+                    url
+                )));
+            }
+            if !url.starts_with("http://") && !url.starts_with("https://") {
                 log::error!("{} не является валидным URL", url);
-                return Err(anyhow::anyhow!("Передан невалидный URL: {}", url));
+                return Err(WbError::Validation(format!("Передан невалидный URL: {}", url)));
             }
         }
 
+        let url = self.url("/content/v3/media/save");
         let mut attempts = 0;
         let max_attempts = 3;
         loop {
@@ -182,30 +999,23 @@ impl WbUploader {
                 "nmId": nm_id,
                 "data": urls
             });
-            log::debug!(
-                "HTTP Request: POST https://content-api.wildberries.ru/content/v3/media/save\nBody: {}",
-                serde_json::to_string_pretty(&body)?
-            );
-            let response = self
-                .client
-                .post("https://content-api.wildberries.ru/content/v3/media/save")
-                .json(&body)
-                .send();
+            log::debug!("HTTP Request: POST {}\nBody: {}", url, body);
+            let request = Request::post(&url).json(&body).map_err(|e| {
+                WbError::Validation(format!("Некорректный запрос для nmId {}: {}", nm_id, e))
+            })?;
+            let response = self.send(request);
             match response {
                 Ok(response) => {
                     let status = response.status();
-                    let response_body = response.text().map_err(|e| {
-                        anyhow::anyhow!("Не удалось прочитать ответ для nmId {}: {}", nm_id, e)
-                    })?;
+                    let response_body = response.text();
                     log::debug!("HTTP Response: Status: {}, Body: {}", status, response_body);
                     if status.is_success() {
                         log::info!("Загружены ссылки на WB для nmId {}: {:?}", nm_id, urls);
-                        {
-                            let mut processed = processed_files.lock().unwrap();
-                            *processed += 1;
-                        }
-                        return Ok(());
+                        return Ok(response_body);
                     } else if status.as_u16() == 429 {
+                        if let Some(metrics) = &self.metrics {
+                            metrics.add_rate_limited();
+                        }
                         log::warn!(
                             "Ошибка 429: Слишком много запросов для nmId {}, повторная попытка через 60 секунд (попытка {}/{})",
                             nm_id,
@@ -218,10 +1028,10 @@ impl WbUploader {
                                 nm_id,
                                 max_attempts
                             );
-                            return Err(anyhow::anyhow!(
+                            return Err(WbError::RateLimited(format!(
                                 "Не удалось загрузить ссылки после {} попыток",
                                 max_attempts
-                            ));
+                            )));
                         }
                         thread::sleep(Duration::from_secs(60));
                     } else {
@@ -230,10 +1040,12 @@ impl WbUploader {
                             nm_id,
                             response_body
                         );
-                        return Err(anyhow::anyhow!(
-                            "Ошибка загрузки ссылок: Статус {}, Тело: {}",
-                            status,
-                            response_body
+                        return Err(WbError::from_status(
+                            status.as_u16(),
+                            format!(
+                                "Ошибка загрузки ссылок: Статус {}, Тело: {}",
+                                status, response_body
+                            ),
                         ));
                     }
                 }
@@ -245,10 +1057,10 @@ impl WbUploader {
                             nm_id,
                             max_attempts
                         );
-                        return Err(anyhow::anyhow!(
-                            "Не удалось загрузить ссылки после {} попыток",
-                            max_attempts
-                        ));
+                        return Err(WbError::Network(format!(
+                            "Не удалось загрузить ссылки после {} попыток: {}",
+                            max_attempts, e
+                        )));
                     }
                     log::warn!(
                         "Ошибка HTTP запроса, повторная попытка через 60 секунд (попытка {}/{})",
@@ -262,84 +1074,170 @@ impl WbUploader {
         }
     }
 
+    // Тело запроса готовится из шаблона рич-контента; запрос уходит только на адрес API WB из профиля
+    pub fn upload_rich_content(
+        &self,
+        nm_id: i64,
+        body: &serde_json::Value,
+    ) -> Result<String, WbError> {
+        log::info!("Загрузка рич-контента для nmId {}", nm_id);
+        let response = self.post_with_retry(
+            &self.url(RICH_CONTENT_PATH),
+            body,
+            &format!("для nmId {}", nm_id),
+        )?;
+        log::info!("Рич-контент загружен для nmId {}", nm_id);
+        Ok(response)
+    }
+
     pub fn upload_local_file(
         &self,
         nm_id: i64,
         file_path: &str,
-        photo_number: u32,
-        processed_files: &Arc<Mutex<usize>>,
-    ) -> Result<(), anyhow::Error> {
-        log::info!(
-            "Начало загрузки файла {} для nmId {} с номером фото {}",
-            file_path,
-            nm_id,
-            photo_number
-        );
-
+        slot: MediaSlot,
+        transfer: &Arc<TransferProgress>,
+    ) -> Result<String, WbError> {
         // Проверка существования файла
         if !Path::new(file_path).exists() {
             log::error!("Файл {} не существует", file_path);
-            return Err(anyhow::anyhow!("Файл {} не существует", file_path));
+            return Err(WbError::NotFound(format!("Файл {} не существует", file_path)));
         }
 
         // Чтение файла в память
         let mut file = File::open(file_path).map_err(|e| {
-            anyhow::anyhow!("Не удалось открыть файл {}: {}", file_path, e)
+            WbError::Validation(format!("Не удалось открыть файл {}: {}", file_path, e))
         })?;
+        if let Ok(metadata) = file.metadata() {
+            self.check_file_size(file_path, metadata.len())?;
+        }
         let mut file_content = Vec::new();
         file.read_to_end(&mut file_content).map_err(|e| {
-            anyhow::anyhow!("Не удалось прочитать файл {}: {}", file_path, e)
+            WbError::Validation(format!("Не удалось прочитать файл {}: {}", file_path, e))
         })?;
+        self.upload_bytes(nm_id, file_path, file_content, slot, transfer)
+    }
+
+    // file_path — путь или имя файла для multipart и сообщений
+    pub fn upload_bytes(
+        &self,
+        nm_id: i64,
+        file_path: &str,
+        file_content: Vec<u8>,
+        slot: MediaSlot,
+        transfer: &Arc<TransferProgress>,
+    ) -> Result<String, WbError> {
+        let file_size = file_content.len() as u64;
+        let content: Arc<[u8]> = file_content.into();
+        self.upload_from(nm_id, file_path, file_size, slot, transfer, &|| {
+            Ok(Box::new(std::io::Cursor::new(Arc::clone(&content))))
+        })
+    }
+
+    // Скачанный файл (local_path) отправляется с диска потоком, не занимая память;
+    // file_path — исходный путь для имени в multipart и сообщений
+    pub fn upload_downloaded_file(
+        &self,
+        nm_id: i64,
+        file_path: &str,
+        local_path: &Path,
+        slot: MediaSlot,
+        transfer: &Arc<TransferProgress>,
+    ) -> Result<String, WbError> {
+        let open_error = |e: std::io::Error| {
+            WbError::Validation(format!(
+                "Не удалось открыть скачанный файл {}: {}",
+                local_path.display(),
+                e
+            ))
+        };
+        let file_size = std::fs::metadata(local_path).map_err(open_error)?.len();
+        self.upload_from(nm_id, file_path, file_size, slot, transfer, &|| {
+            Ok(Box::new(File::open(local_path).map_err(open_error)?))
+        })
+    }
+
+    // Тело запроса открывается заново на каждую попытку
+    fn upload_from(
+        &self,
+        nm_id: i64,
+        file_path: &str,
+        file_size: u64,
+        slot: MediaSlot,
+        transfer: &Arc<TransferProgress>,
+        open: &dyn Fn() -> Result<Box<dyn Read + Send>, WbError>,
+    ) -> Result<String, WbError> {
+        log::info!(
+            "Начало загрузки файла {} для nmId {} в {}",
+            file_path,
+            nm_id,
+            slot.describe()
+        );
+        transfer.total.store(file_size, Ordering::Relaxed);
+
+        // Видео занимает отдельный слот карточки: WB ждёт для него номер 1 и видео-MIME
+        let (photo_number, mime) = match slot {
+            MediaSlot::Photo(number) => (number, "application/octet-stream"),
+            MediaSlot::Video => (
+                1,
+                match Path::new(file_path)
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .as_deref()
+                {
+                    Some("mov") => "video/quicktime",
+                    _ => "video/mp4",
+                },
+            ),
+        };
 
         let mut attempts = 0;
         let max_attempts = 3;
         loop {
             // Формирование multipart формы внутри цикла
-            let form = reqwest::blocking::multipart::Form::new().part(
-                "uploadfile",
-                reqwest::blocking::multipart::Part::bytes(file_content.clone())
-                    .file_name(Path::new(file_path).file_name().unwrap().to_string_lossy().to_string())
-                    .mime_str("application/octet-stream")?,
-            );
+            transfer.restart();
+            let reader = ProgressReader {
+                inner: open()?,
+                progress: Arc::clone(transfer),
+                limit: self.upload_limit.clone(),
+            };
+            let part = FilePart {
+                field: "uploadfile".to_string(),
+                file_name: Path::new(file_path).file_name().unwrap().to_string_lossy().to_string(),
+                mime: mime.to_string(),
+                reader: Box::new(reader),
+                length: file_size,
+            };
 
+            let url = self.url("/content/v3/media/file");
             log::debug!(
-                "HTTP Request: POST https://content-api.wildberries.ru/content/v3/media/file\nX-Nm-Id: {}, X-Photo-Number: {}",
+                "HTTP Request: POST {}\nX-Nm-Id: {}, X-Photo-Number: {}",
+                url,
                 nm_id,
                 photo_number
             );
-            let response = self
-                .client
-                .post("https://content-api.wildberries.ru/content/v3/media/file")
+            let request = Request::post(&url)
                 .header("X-Nm-Id", nm_id.to_string())
-                .header("X-Photo-Number", photo_number.to_string())
-                .multipart(form)
-                .send();
+                .header("X-Photo-Number", photo_number.to_string());
+            let response = self.send(request.multipart(part));
 
             match response {
                 Ok(response) => {
                     let status = response.status();
-                    let response_body = response.text().map_err(|e| {
-                        anyhow::anyhow!(
-                            "Не удалось прочитать ответ для файла {}: {}",
-                            file_path,
-                            e
-                        )
-                    })?;
+                    let response_body = response.text();
                     log::debug!("HTTP Response: Status: {}, Body: {}", status, response_body);
 
                     if status.is_success() {
                         log::info!(
-                            "Файл {} загружен для nmId {} с номером фото {}",
+                            "Файл {} загружен для nmId {} в {}",
                             file_path,
                             nm_id,
-                            photo_number
+                            slot.describe()
                         );
-                        {
-                            let mut processed = processed_files.lock().unwrap();
-                            *processed += 1;
-                        }
-                        return Ok(());
+                        return Ok(response_body);
                     } else if status.as_u16() == 429 {
+                        if let Some(metrics) = &self.metrics {
+                            metrics.add_rate_limited();
+                        }
                         log::warn!(
                             "Ошибка 429: Слишком много запросов для файла {}, повторная попытка через 60 секунд (попытка {}/{})",
                             file_path,
@@ -352,10 +1250,10 @@ impl WbUploader {
                                 file_path,
                                 max_attempts
                             );
-                            return Err(anyhow::anyhow!(
+                            return Err(WbError::RateLimited(format!(
                                 "Не удалось загрузить файл после {} попыток",
                                 max_attempts
-                            ));
+                            )));
                         }
                         thread::sleep(Duration::from_secs(60));
                     } else {
@@ -366,10 +1264,12 @@ impl WbUploader {
                             status,
                             response_body
                         );
-                        return Err(anyhow::anyhow!(
-                            "Ошибка загрузки файла: Статус {}, Тело: {}",
-                            status,
-                            response_body
+                        return Err(WbError::from_status(
+                            status.as_u16(),
+                            format!(
+                                "Ошибка загрузки файла: Статус {}, Тело: {}",
+                                status, response_body
+                            ),
                         ));
                     }
                 }
@@ -381,10 +1281,10 @@ impl WbUploader {
                             file_path,
                             max_attempts
                         );
-                        return Err(anyhow::anyhow!(
-                            "Не удалось загрузить файл после {} попыток",
-                            max_attempts
-                        ));
+                        return Err(WbError::Network(format!(
+                            "Не удалось загрузить файл после {} попыток: {}",
+                            max_attempts, e
+                        )));
                     }
                     log::warn!(
                         "Ошибка HTTP запроса, повторная попытка через 60 секунд (попытка {}/{})",
@@ -397,4 +1297,119 @@ impl WbUploader {
             attempts += 1;
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::fake::{ScriptedClient, response};
+
+    #[test]
+    fn status_codes_map_to_error_classes() {
+        let cases = [
+            (429, WbError::RateLimited(String::new())),
+            (401, WbError::AuthFailed(String::new())),
+            (403, WbError::AuthFailed(String::new())),
+            (404, WbError::NotFound(String::new())),
+            (400, WbError::Validation(String::new())),
+            (409, WbError::Validation(String::new())),
+            (500, WbError::ServerError(String::new())),
+            (502, WbError::ServerError(String::new())),
+        ];
+        for (status, expected) in cases {
+            assert_eq!(WbError::from_status(status, String::new()), expected, "{}", status);
+        }
+    }
+
+    fn cards_page(from: i64, count: i64, cursor_total: i64) -> Result<Response> {
+        let cards: Vec<serde_json::Value> = (from..from + count)
+            .map(|nm_id| serde_json::json!({ "nmID": nm_id, "vendorCode": format!("VC-{}", nm_id) }))
+            .collect();
+        let body = serde_json::json!({
+            "cards": cards,
+            "cursor": {
+                "updatedAt": format!("2024-05-01T00:00:{:02}Z", from % 60),
+                "nmID": from + count - 1,
+                "total": cursor_total,
+            },
+        });
+        response(200, &[], &body.to_string())
+    }
+
+    #[test]
+    fn list_cards_follows_cursor_until_short_page() {
+        let client = ScriptedClient::new(vec![
+            cards_page(1, 100, 100),
+            cards_page(101, 100, 100),
+            cards_page(201, 30, 30),
+        ]);
+        let uploader = WbUploader::with_client(client.clone());
+        let mut pages = Vec::new();
+        let cards = uploader.list_cards(-1, |count| pages.push(count)).unwrap();
+        assert_eq!(cards.len(), 230);
+        assert_eq!(cards[229].vendor_code, "VC-230");
+        assert_eq!(pages, vec![100, 200, 230]);
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].0.ends_with("/content/v2/get/cards/list"));
+        let cursor = |index: usize| requests[index].1.as_ref().unwrap()["settings"]["cursor"].clone();
+        assert_eq!(cursor(0), serde_json::json!({ "limit": 100 }));
+        assert_eq!(
+            cursor(1),
+            serde_json::json!({ "limit": 100, "updatedAt": "2024-05-01T00:00:01Z", "nmID": 100 })
+        );
+        assert_eq!(cursor(2)["nmID"], 200);
+    }
+
+    #[test]
+    fn get_card_takes_exact_vendor_code_only() {
+        let cards = |vendor_codes: &[(i64, &str)]| {
+            let cards: Vec<serde_json::Value> = vendor_codes
+                .iter()
+                .map(|(nm_id, vendor_code)| serde_json::json!({ "nmID": nm_id, "vendorCode": vendor_code }))
+                .collect();
+            response(200, &[], &serde_json::json!({ "cards": cards }).to_string())
+        };
+        let client = ScriptedClient::new(vec![
+            cards(&[(12, "ABC12"), (1, "ABC1")]),
+            cards(&[(12, "ABC12")]),
+        ]);
+        let uploader = WbUploader::with_client(client);
+        assert_eq!(uploader.get_card("ABC1").unwrap().nm_id, 1);
+        assert!(matches!(uploader.get_card("ABC1"), Err(WbError::NotFound(_))));
+    }
+
+    #[test]
+    fn list_cards_reports_auth_error() {
+        let client = ScriptedClient::new(vec![response(401, &[], "unauthorized")]);
+        let uploader = WbUploader::with_client(client);
+        let result = uploader.list_cards(-1, |_| {});
+        assert!(matches!(result, Err(WbError::AuthFailed(_))));
+    }
+
+    #[test]
+    fn parallel_readers_share_upload_limit() {
+        const LIMIT: u64 = 64 * 1024;
+        const FILE: usize = 16 * 1024;
+        let limit = Arc::new(RateLimit::new(LIMIT));
+        let started = Instant::now();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                let limit = Arc::clone(&limit);
+                scope.spawn(move || {
+                    let mut reader = ProgressReader {
+                        inner: std::io::repeat(0).take(FILE as u64),
+                        progress: Arc::new(TransferProgress::default()),
+                        limit: Some(limit),
+                    };
+                    let mut sink = Vec::new();
+                    reader.read_to_end(&mut sink).unwrap();
+                    assert_eq!(sink.len(), FILE);
+                });
+            }
+        });
+        // 64 КБ на общем лимите 64 КБ/с — около секунды, а не четверть секунды на каждый поток
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+}
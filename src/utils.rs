@@ -1,4 +1,14 @@
+use crate::settings::VendorCodeOptions;
+use anyhow::Result;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::LazyLock;
+
+// Имя без номера фото и приписок после него: «ABC-12_2_final» → «ABC-12»
+static VENDOR_CODE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+)(?:[ _-]*\(\d+\)|[ _-]+\d{1,2})(?:[ _-][^.]*)?$").unwrap());
 
 pub fn is_media_file(file_name: &str) -> bool {
     let ext = Path::new(file_name)
@@ -18,3 +28,97 @@ pub fn is_media_file(file_name: &str) -> bool {
     );
     is_media
 }
+
+// Видео у карточки WB одно и загружается отдельно от фото
+pub fn is_video_file(file_name: &str) -> bool {
+    matches!(
+        Path::new(file_name)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .as_deref(),
+        Some("mov" | "mp4")
+    )
+}
+
+pub fn file_sha256(path: &str) -> Result<String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Не удалось прочитать файл {}: {}", path, e))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["Б", "КБ", "МБ", "ГБ"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn format_duration(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if hours > 0 {
+        format!("{} ч {} мин", hours, minutes)
+    } else if minutes > 0 {
+        format!("{} мин {} сек", minutes, seconds)
+    } else {
+        format!("{} сек", seconds)
+    }
+}
+
+pub struct ParsedVendorCodes {
+    pub codes: Vec<String>,
+    pub duplicates: usize,
+}
+
+// Одна строка — один vendor code; комментарий начинается с '#'
+pub fn parse_vendor_codes(text: &str, options: &VendorCodeOptions) -> ParsedVendorCodes {
+    let mut seen = HashSet::new();
+    let mut codes = Vec::new();
+    let mut duplicates = 0;
+    for line in text.lines() {
+        let mut code = line;
+        if options.strip_bom {
+            code = code.trim_start_matches('\u{feff}');
+        }
+        if options.strip_comments
+            && let Some(index) = code.find('#')
+        {
+            code = &code[..index];
+        }
+        if options.trim {
+            code = code.trim();
+        }
+        if code.trim().is_empty() {
+            continue;
+        }
+        let code = if options.uppercase {
+            code.to_uppercase()
+        } else {
+            code.to_string()
+        };
+        if options.dedupe && !seen.insert(code.clone()) {
+            duplicates += 1;
+            continue;
+        }
+        codes.push(code);
+    }
+    ParsedVendorCodes { codes, duplicates }
+}
+
+// Предполагаемый vendor code по имени файла, например перетащенного в окно
+pub fn guess_vendor_code(file_name: &str) -> Option<String> {
+    let stem = Path::new(file_name).file_stem()?.to_string_lossy();
+    let code = VENDOR_CODE_RE
+        .captures(&stem)
+        .map(|caps| caps[1].to_string())
+        .unwrap_or_else(|| stem.to_string());
+    let code = code.trim();
+    (!code.is_empty()).then(|| code.to_string())
+}
@@ -0,0 +1,232 @@
+use crate::downloader::FileInfo;
+use crate::utils::is_video_file;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// Ограничения Wildberries на размер медиафайлов
+const MAX_PHOTO_BYTES: u64 = 32 * 1024 * 1024;
+const MAX_VIDEO_BYTES: u64 = 50 * 1024 * 1024;
+
+// Итог проверки перед загрузкой; nmId найденных vendorCode переиспользуются при загрузке
+#[derive(Clone, Default)]
+pub struct ValidationReport {
+    pub codes_without_files: Vec<String>,
+    pub unmatched_files: Vec<String>,
+    pub codes_not_in_wb: Vec<String>,
+    pub oversized_files: Vec<(String, u64)>,
+    // vendorCode и его номера фото с пропусками: 1, 2, 4
+    pub number_gaps: Vec<(String, Vec<u32>)>,
+    // vendorCode, номер фото и файлы с этим номером: на WB останется только последний
+    pub number_conflicts: Vec<(String, u32, Vec<String>)>,
+    // vendorCode и его видеофайлы, если их больше одного: WB принимает одно видео на карточку
+    pub extra_videos: Vec<(String, Vec<String>)>,
+    pub nm_ids: HashMap<String, i64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ValidationDecision {
+    Continue,
+    // Продолжить, перенумеровав фото каждого vendorCode подряд с 1
+    Renumber,
+    Cancel,
+}
+
+impl ValidationReport {
+    pub fn is_clean(&self) -> bool {
+        self.codes_without_files.is_empty()
+            && self.unmatched_files.is_empty()
+            && self.codes_not_in_wb.is_empty()
+            && self.oversized_files.is_empty()
+            && self.extra_videos.is_empty()
+            && !self.has_number_issues()
+    }
+
+    pub fn has_number_issues(&self) -> bool {
+        !self.number_gaps.is_empty() || !self.number_conflicts.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "vendor codes без файлов: {}, файлов без vendor code: {}, vendor codes не найдено в WB: {}, слишком больших файлов: {}, vendor codes с несколькими видео: {}, пропусков в номерах фото: {}, повторов номеров фото: {}",
+            self.codes_without_files.len(),
+            self.unmatched_files.len(),
+            self.codes_not_in_wb.len(),
+            self.oversized_files.len(),
+            self.extra_videos.len(),
+            self.number_gaps.len(),
+            self.number_conflicts.len()
+        )
+    }
+
+    pub fn save_csv(&self, path: &Path) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path).map_err(|e| {
+            anyhow::anyhow!("Не удалось создать файл проверки {}: {}", path.display(), e)
+        })?;
+        writer.write_record(["Проблема", "Значение", "Подробности"])?;
+        for code in &self.codes_without_files {
+            writer.write_record(["Нет файлов", code, ""])?;
+        }
+        for file in &self.unmatched_files {
+            writer.write_record(["Файл без vendor code", file, ""])?;
+        }
+        for code in &self.codes_not_in_wb {
+            writer.write_record(["Не найден в WB", code, ""])?;
+        }
+        for (file, size) in &self.oversized_files {
+            writer.write_record(["Слишком большой файл", file, &size.to_string()])?;
+        }
+        for (code, files) in &self.extra_videos {
+            writer.write_record(["Несколько видео", code, &files.join(", ")])?;
+        }
+        for (code, numbers) in &self.number_gaps {
+            writer.write_record(["Пропуск в номерах фото", code, &join_numbers(numbers)])?;
+        }
+        for (code, number, files) in &self.number_conflicts {
+            writer.write_record([
+                "Повтор номера фото",
+                code,
+                &format!("{}: {}", number, files.join(", ")),
+            ])?;
+        }
+        writer.flush().map_err(|e| {
+            anyhow::anyhow!("Ошибка записи файла проверки {}: {}", path.display(), e)
+        })?;
+        log::info!("Результат проверки сохранён в {}", path.display());
+        Ok(())
+    }
+}
+
+// nm_id_lookup — поиск nmId в WB; размер известен только у локальных файлов
+pub fn validate(
+    vendor_codes: &[String],
+    files: &[FileInfo],
+    unmatched_files: Vec<String>,
+    check_sizes: bool,
+    nm_id_lookup: impl Fn(&str) -> Result<i64>,
+) -> ValidationReport {
+    let (number_gaps, number_conflicts) = photo_number_issues(files);
+    let mut report = ValidationReport {
+        unmatched_files,
+        number_gaps,
+        number_conflicts,
+        extra_videos: extra_videos(files),
+        ..Default::default()
+    };
+    for vendor_code in vendor_codes {
+        if !files.iter().any(|f| &f.articul == vendor_code) {
+            report.codes_without_files.push(vendor_code.clone());
+        }
+        match nm_id_lookup(vendor_code) {
+            Ok(nm_id) => {
+                report.nm_ids.insert(vendor_code.clone(), nm_id);
+            }
+            Err(e) => {
+                log::warn!("Проверка: vendorCode {} не найден в WB: {}", vendor_code, e);
+                report.codes_not_in_wb.push(vendor_code.clone());
+            }
+        }
+    }
+    for file in files.iter().filter(|_| check_sizes) {
+        let Ok(metadata) = std::fs::metadata(&file.path) else {
+            continue;
+        };
+        let limit = if is_video_file(&file.name) {
+            MAX_VIDEO_BYTES
+        } else {
+            MAX_PHOTO_BYTES
+        };
+        if metadata.len() > limit {
+            report
+                .oversized_files
+                .push((file.path.clone(), metadata.len()));
+        }
+    }
+    report
+}
+
+pub fn join_numbers(numbers: &[u32]) -> String {
+    numbers
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Видеофайлы каждого vendorCode, у которых их больше одного
+pub fn extra_videos(files: &[FileInfo]) -> Vec<(String, Vec<String>)> {
+    let mut videos: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for file in files.iter().filter(|f| is_video_file(&f.name)) {
+        videos
+            .entry(file.articul.as_str())
+            .or_default()
+            .push(file.name.clone());
+    }
+    videos
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|(code, names)| (code.to_string(), names))
+        .collect()
+}
+
+// Номера фото без видео: у видео отдельный слот карточки
+fn photo_numbers(files: &[FileInfo]) -> BTreeMap<&str, BTreeMap<u32, Vec<&FileInfo>>> {
+    let mut numbers: BTreeMap<&str, BTreeMap<u32, Vec<&FileInfo>>> = BTreeMap::new();
+    for file in files.iter().filter(|f| !is_video_file(&f.name)) {
+        numbers
+            .entry(file.articul.as_str())
+            .or_default()
+            .entry(file.photo_number)
+            .or_default()
+            .push(file);
+    }
+    numbers
+}
+
+type NumberIssues = (Vec<(String, Vec<u32>)>, Vec<(String, u32, Vec<String>)>);
+
+pub fn photo_number_issues(files: &[FileInfo]) -> NumberIssues {
+    let mut gaps = Vec::new();
+    let mut conflicts = Vec::new();
+    for (code, numbers) in photo_numbers(files) {
+        let sorted: Vec<u32> = numbers.keys().copied().collect();
+        if sorted.iter().enumerate().any(|(i, n)| *n != i as u32 + 1) {
+            gaps.push((code.to_string(), sorted));
+        }
+        for (number, files) in numbers.iter().filter(|(_, files)| files.len() > 1) {
+            conflicts.push((
+                code.to_string(),
+                *number,
+                files.iter().map(|f| f.name.clone()).collect(),
+            ));
+        }
+    }
+    (gaps, conflicts)
+}
+
+// Номера фото каждого vendorCode подряд с 1 в порядке прежних номеров, при повторе — по имени;
+// видео не перенумеровываются
+pub fn renumber(files: &mut [FileInfo]) {
+    let mut order: Vec<usize> = (0..files.len())
+        .filter(|i| !is_video_file(&files[*i].name))
+        .collect();
+    order.sort_by(|a, b| {
+        let (a, b) = (&files[*a], &files[*b]);
+        (&a.articul, a.photo_number, &a.name).cmp(&(&b.articul, b.photo_number, &b.name))
+    });
+    let mut next: HashMap<String, u32> = HashMap::new();
+    for index in order {
+        let number = next.entry(files[index].articul.clone()).or_insert(0);
+        *number += 1;
+        if files[index].photo_number != *number {
+            log::info!(
+                "Файл {} (vendorCode {}): номер фото {} → {}",
+                files[index].name,
+                files[index].articul,
+                files[index].photo_number,
+                number
+            );
+            files[index].photo_number = *number;
+        }
+    }
+}
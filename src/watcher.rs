@@ -0,0 +1,139 @@
+use crate::downloader::{Downloader, Matching};
+use crate::job::{self, JobSource, JobSpec, JobState};
+use crate::logs::{LogEvent, Stage};
+use crate::profile::HttpTimeouts;
+use ::notify::event::ModifyKind;
+use ::notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+
+// Пауза без новых событий, после которой файлы считаются дописанными
+const QUIET_PERIOD: Duration = Duration::from_secs(3);
+
+// Отслеживание локальной папки: новые файлы известных vendor codes загружаются автоматически.
+// Остановка — удаление FolderWatcher
+pub struct FolderWatcher {
+    _watcher: RecommendedWatcher,
+    folder: String,
+}
+
+impl FolderWatcher {
+    pub fn start(
+        folder: &str,
+        vendor_codes: Vec<String>,
+        matching: Matching,
+        state: JobState,
+        make_spec: impl Fn(Vec<String>, JobSource) -> JobSpec + Send + 'static,
+    ) -> Result<Self> {
+        // Папка локальная, сеть наблюдателю не нужна
+        let mut downloader =
+            Downloader::new(Vec::new(), vendor_codes.clone(), &HttpTimeouts::default())?;
+        downloader.set_matching(matching);
+        let (sender, receiver) = mpsc::channel::<::notify::Result<Event>>();
+        let mut watcher = ::notify::recommended_watcher(sender)
+            .map_err(|e| anyhow::anyhow!("Не удалось создать наблюдатель: {}", e))?;
+        watcher
+            .watch(Path::new(folder), RecursiveMode::Recursive)
+            .map_err(|e| anyhow::anyhow!("Не удалось отслеживать папку {}: {}", folder, e))?;
+        state.log(LogEvent::info(
+            Stage::Scan,
+            format!("Отслеживание папки {} запущено", folder),
+        ));
+
+        std::thread::spawn(move || {
+            let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+            let mut last_event = Instant::now();
+            let mut processed: HashMap<PathBuf, SystemTime> = HashMap::new();
+            loop {
+                match receiver.recv_timeout(Duration::from_secs(1)) {
+                    Ok(Ok(event)) => {
+                        if matches!(
+                            event.kind,
+                            EventKind::Create(_)
+                                | EventKind::Modify(ModifyKind::Data(_))
+                                | EventKind::Modify(ModifyKind::Name(_))
+                        ) {
+                            pending.extend(event.paths);
+                            last_event = Instant::now();
+                        }
+                    }
+                    Ok(Err(e)) => state.log(LogEvent::warn(
+                        Stage::Scan,
+                        format!("Ошибка отслеживания папки: {}", e),
+                    )),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                if pending.is_empty()
+                    || last_event.elapsed() < QUIET_PERIOD
+                    || *state.is_processing.lock().unwrap()
+                {
+                    continue;
+                }
+
+                // Файл с тем же временем изменения повторно не загружается
+                let paths: Vec<String> = std::mem::take(&mut pending)
+                    .into_iter()
+                    .filter(|path| {
+                        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+                        modified.is_some() && processed.get(path) != modified.as_ref()
+                    })
+                    .map(|path| path.to_string_lossy().to_string())
+                    .collect();
+                let files = match downloader.match_local_paths(&paths) {
+                    Ok(files) => files,
+                    Err(e) => {
+                        state.log(LogEvent::error(
+                            Stage::Scan,
+                            format!("Ошибка сопоставления файлов: {}", e),
+                        ));
+                        continue;
+                    }
+                };
+                if files.is_empty() {
+                    continue;
+                }
+                for file in &files {
+                    let path = PathBuf::from(&file.path);
+                    if let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) {
+                        processed.insert(path, modified);
+                    }
+                }
+                let matched: Vec<String> = vendor_codes
+                    .iter()
+                    .filter(|code| files.iter().any(|f| &f.articul == *code))
+                    .cloned()
+                    .collect();
+                state.log(LogEvent::info(
+                    Stage::Scan,
+                    format!(
+                        "Новые файлы в отслеживаемой папке: {}, vendor codes: {}",
+                        files.len(),
+                        matched.join(", ")
+                    ),
+                ));
+                let paths = files.into_iter().map(|f| f.path).collect();
+                job::start(
+                    make_spec(matched, JobSource::Files { paths }),
+                    state.clone(),
+                );
+            }
+            state.log(LogEvent::info(
+                Stage::Scan,
+                "Отслеживание папки остановлено",
+            ));
+        });
+
+        Ok(Self {
+            _watcher: watcher,
+            folder: folder.to_string(),
+        })
+    }
+
+    pub fn folder(&self) -> &str {
+        &self.folder
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::Local;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use wb_upload_core::demo;
+use wb_upload_core::history::History;
+use wb_upload_core::job::{self, ItemStatus, JobSource, JobSpec, JobState};
+use wb_upload_core::logs::{LogBuffer, LogFilter};
+use wb_upload_core::settings::Settings;
+use wb_upload_core::uploader::WbUploader;
+
+// Резервный режим: файлы демо-папки скачиваются во временные файлы и загружаются через media/file
+#[test]
+fn yandex_run_downloads_files_before_upload() {
+    demo::enable();
+    let report_dir =
+        std::env::temp_dir().join(format!("wb_demo_download_run_{}", std::process::id()));
+    std::fs::create_dir_all(&report_dir).unwrap();
+    let history = History::open(Path::new(":memory:")).unwrap();
+    let settings = Settings {
+        card_errors_delay_secs: 0,
+        download_yandex_files: true,
+        ..Default::default()
+    };
+    let state = JobState::new(LogBuffer::new(1000), Some(history));
+    job::start(
+        JobSpec {
+            profile: "Демо".to_string(),
+            api_key: demo::API_KEY.to_string(),
+            wb_base_url: String::new(),
+            timeouts: Default::default(),
+            vendor_codes: vec!["DEMO-001".to_string(), "DEMO-002".to_string()],
+            source: JobSource::Yandex {
+                public_keys: vec![demo::PUBLIC_LINK.to_string()],
+            },
+            settings,
+            report_dir: report_dir.clone(),
+            started_at: Local::now(),
+            only_new: false,
+            confirm_validation: false,
+            force_reupload: false,
+        },
+        state.clone(),
+    );
+
+    let started = Instant::now();
+    while *state.is_processing.lock().unwrap() {
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "запуск не завершился"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let results = state.results.lock().unwrap().clone();
+    for vendor_code in ["DEMO-001", "DEMO-002"] {
+        let r = results
+            .iter()
+            .find(|r| r.vendor_code == vendor_code)
+            .unwrap_or_else(|| panic!("нет результата для {}", vendor_code));
+        assert_eq!(r.status, ItemStatus::Success, "{}: {:?}", vendor_code, r.error);
+        assert_eq!(r.files_uploaded, 3, "{}", vendor_code);
+    }
+
+    // Файлы легли в фото со своими номерами, а не заменили галерею: у DEMO-002 фото 1, 2 и 4
+    let uploader = WbUploader::new(demo::API_KEY.to_string(), &Default::default()).unwrap();
+    assert_eq!(uploader.get_card("DEMO-001").unwrap().photos.len(), 3);
+    assert_eq!(uploader.get_card("DEMO-002").unwrap().photos.len(), 4);
+
+    // Временные файлы этого процесса после загрузки удалены, каждое удаление — в журнале
+    let logs = state.logs.lock().unwrap().filtered(&LogFilter {
+        min_level: None,
+        vendor_code: String::new(),
+    });
+    let deleted = logs
+        .iter()
+        .filter(|event| event.message.starts_with("Скачанный файл") && event.message.contains("удалён"))
+        .count();
+    assert_eq!(deleted, 6);
+    let prefix = format!("{}_", std::process::id());
+    let left: Vec<_> = std::fs::read_dir(state.staging().dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                .collect()
+        })
+        .unwrap_or_default();
+    assert!(left.is_empty(), "остались временные файлы: {}", left.len());
+
+    let _ = std::fs::remove_dir_all(&report_dir);
+}
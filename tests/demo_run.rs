@@ -0,0 +1,91 @@
+use chrono::Local;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use wb_upload_core::demo;
+use wb_upload_core::history::History;
+use wb_upload_core::job::{self, ItemStatus, JobSource, JobSpec, JobState};
+use wb_upload_core::logs::LogBuffer;
+use wb_upload_core::settings::Settings;
+use wb_upload_core::uploader::WbUploader;
+
+// Полный запуск из публичной папки Яндекс.Диска против встроенного макета WB и Диска
+#[test]
+fn yandex_run_uploads_demo_folder() {
+    demo::enable();
+    let report_dir = std::env::temp_dir().join(format!("wb_demo_run_{}", std::process::id()));
+    std::fs::create_dir_all(&report_dir).unwrap();
+    let history = History::open(Path::new(":memory:")).unwrap();
+    // Проверка ошибок карточек через 30 секунд только затянула бы тест
+    let settings = Settings {
+        card_errors_delay_secs: 0,
+        ..Default::default()
+    };
+    let state = JobState::new(LogBuffer::new(1000), Some(history));
+    job::start(
+        JobSpec {
+            profile: "Демо".to_string(),
+            api_key: demo::API_KEY.to_string(),
+            wb_base_url: String::new(),
+            timeouts: Default::default(),
+            vendor_codes: demo::VENDOR_CODES.iter().map(|v| v.to_string()).collect(),
+            source: JobSource::Yandex {
+                public_keys: vec![demo::PUBLIC_LINK.to_string()],
+            },
+            settings,
+            report_dir: report_dir.clone(),
+            started_at: Local::now(),
+            only_new: false,
+            confirm_validation: false,
+            force_reupload: false,
+        },
+        state.clone(),
+    );
+
+    let started = Instant::now();
+    while *state.is_processing.lock().unwrap() {
+        assert!(
+            started.elapsed() < Duration::from_secs(60),
+            "запуск не завершился"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let results = state.results.lock().unwrap().clone();
+    let result = |vendor_code: &str| {
+        results
+            .iter()
+            .find(|r| r.vendor_code == vendor_code)
+            .unwrap_or_else(|| panic!("нет результата для {}", vendor_code))
+    };
+    assert_eq!(results.len(), demo::VENDOR_CODES.len());
+    for (vendor_code, nm_id, uploaded) in [
+        ("DEMO-001", 100001, 3),
+        ("DEMO-002", 100002, 3),
+        ("DEMO-003", 100003, 1),
+    ] {
+        let r = result(vendor_code);
+        assert_eq!(
+            r.status,
+            ItemStatus::Success,
+            "{}: {:?}",
+            vendor_code,
+            r.error
+        );
+        assert_eq!(r.nm_id, Some(nm_id));
+        assert_eq!(r.files_uploaded, uploaded, "{}", vendor_code);
+    }
+    // Карточка в корзине без восстановления и несуществующий vendorCode — ошибки
+    assert_eq!(result("DEMO-004").status, ItemStatus::Failed);
+    assert_eq!(result("DEMO-005").status, ItemStatus::Failed);
+    assert_eq!(
+        *state.failed_vendor_codes.lock().unwrap(),
+        vec!["DEMO-004".to_string(), "DEMO-005".to_string()]
+    );
+
+    // Фото попали в карточки макета WB
+    let uploader = WbUploader::new(demo::API_KEY.to_string(), &Default::default()).unwrap();
+    assert_eq!(uploader.get_card("DEMO-001").unwrap().photos.len(), 3);
+    assert_eq!(uploader.get_card("DEMO-003").unwrap().photos.len(), 1);
+
+    let _ = std::fs::remove_dir_all(&report_dir);
+}